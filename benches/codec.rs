@@ -0,0 +1,79 @@
+//! What the codecs add to every change with state in a web worker: encoding the action, decoding
+//! it in the worker, then the same for the state sent back. A change to state shared in the page
+//! is timed alongside for scale.
+//!
+//! This isn't what a change through a worker costs: workers can't run outside the browser, so
+//! posting messages between threads (and waiting on the worker) isn't measured.
+//!
+//! ```sh
+//! cargo bench --bench codec
+//! ```
+use std::hint::black_box;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use yew_state::worker::{Bincode, Codec, Json};
+use yew_state::{Dispatch, SharedHandle};
+
+const ITERATIONS: u32 = 10_000;
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Todos {
+    items: Vec<Todo>,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Todo {
+    title: String,
+    done: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Toggle {
+    Item(usize),
+}
+
+fn todos(len: usize) -> Todos {
+    Todos {
+        items: (0..len)
+            .map(|index| Todo {
+                title: format!("todo number {}", index),
+                done: index % 2 == 0,
+            })
+            .collect(),
+    }
+}
+
+/// Run `f` `ITERATIONS` times, printing the time each took on average.
+fn bench(name: &str, mut f: impl FnMut()) {
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let each = started.elapsed() / ITERATIONS;
+    println!("{:<32} {:>10.2?}", name, each);
+}
+
+/// Encode and decode an action, then the state it changed, as a change through a worker does.
+fn crossing<C: Codec>(state: &Todos) {
+    let action = C::encode(&Toggle::Item(0)).unwrap();
+    black_box(C::decode::<Toggle>(&action));
+    let reply = C::encode(state).unwrap();
+    black_box(C::decode::<Todos>(&reply));
+}
+
+fn main() {
+    for &len in &[10, 100, 1_000] {
+        println!("{} todos:", len);
+        let state = todos(len);
+
+        let mut dispatch = Dispatch::<SharedHandle<Todos>>::connect();
+        let seed = state.clone();
+        dispatch.reduce(move |todos| *todos = seed.clone());
+        bench("  context reduce", || {
+            dispatch.reduce(|todos| todos.items[0].done = !todos.items[0].done)
+        });
+        bench("  bincode encoding", || crossing::<Bincode>(&state));
+        bench("  json encoding", || crossing::<Json>(&state));
+    }
+}
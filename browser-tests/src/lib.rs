@@ -0,0 +1 @@
+//! Nothing but tests: see `tests/`.
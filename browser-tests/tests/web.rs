@@ -0,0 +1,385 @@
+//! The wrapper, both handlers, scopes and storage, end to end in a headless browser.
+//!
+//! Tests share one page, and so agents, config and web storage. Each uses models and keys of its
+//! own, and clears storage first.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+use web_sys::{Element, Storage};
+use yew::prelude::*;
+use yew::Callback;
+use yew_state::component::provider::Props as ProviderProps;
+use yew_state::component::{self, Recovery};
+use yew_state::notify::{self, NotifyPolicy};
+use yew_state::sync::{self, memory::MemoryHub, SyncTransport};
+use yew_state::{
+    Dispatch, Error, Machine, SharedHandle, SharedState, SharedStateComponent, StateErrorBoundary,
+    Storable, StorageHandle, TestProvider,
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Count(u32);
+
+/// Renders its count.
+struct Counter {
+    handle: SharedHandle<Count>,
+}
+
+impl Component for Counter {
+    type Message = ();
+    type Properties = SharedHandle<Count>;
+
+    fn create(handle: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { handle }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        self.handle = handle;
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! { <span>{ self.handle.state().0 }</span> }
+    }
+}
+
+fn local_storage() -> Storage {
+    let storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+    storage.clear().unwrap();
+    storage
+}
+
+/// Element of the page to mount a component into.
+fn mount_point() -> Element {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let element = document.create_element("div").unwrap();
+    document.body().unwrap().append_child(&element).unwrap();
+    element
+}
+
+/// Let spawned futures (and the ones they wake) run.
+async fn settle() {
+    for _ in 0..3 {
+        JsFuture::from(js_sys::Promise::resolve(&JsValue::NULL))
+            .await
+            .unwrap();
+    }
+}
+
+/// Callback keeping every count it receives in `log`.
+fn record(log: &Rc<RefCell<Vec<u32>>>) -> Callback<Rc<Count>> {
+    let log = log.clone();
+    Callback::from(move |count: Rc<Count>| log.borrow_mut().push(count.0))
+}
+
+#[wasm_bindgen_test]
+fn wrapped_components_render_shared_state_as_it_changes() {
+    struct Rendered;
+
+    let element = mount_point();
+    yew::App::<SharedStateComponent<Counter, Rendered>>::new().mount(element.clone());
+    assert_eq!(element.text_content().as_deref(), Some("0"));
+
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Rendered>::connect();
+    dispatch.reduce(|count| count.0 += 2);
+    assert_eq!(element.text_content().as_deref(), Some("2"));
+}
+
+#[derive(Clone, Default, PartialEq, Properties)]
+struct LazyProps {
+    #[prop_or_default]
+    handle: SharedHandle<Count>,
+}
+
+impl SharedState for LazyProps {
+    type Handle = SharedHandle<Count>;
+
+    fn handle(&mut self) -> &mut Self::Handle {
+        &mut self.handle
+    }
+
+    fn lazy(&self) -> bool {
+        true
+    }
+}
+
+/// Renders its count, connecting lazily.
+struct LazyCounter {
+    props: LazyProps,
+}
+
+impl Component for LazyCounter {
+    type Message = ();
+    type Properties = LazyProps;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! { <span>{ self.props.handle.state().0 }</span> }
+    }
+}
+
+#[wasm_bindgen_test]
+fn lazy_components_that_only_read_render_shared_state() {
+    struct Lazy;
+
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Lazy>::connect();
+    dispatch.reduce(|count| count.0 = 3);
+
+    let element = mount_point();
+    yew::App::<SharedStateComponent<LazyCounter, Lazy>>::new().mount(element.clone());
+    assert_eq!(element.text_content().as_deref(), Some("3"));
+
+    dispatch.reduce(|count| count.0 += 1);
+    assert_eq!(element.text_content().as_deref(), Some("4"));
+}
+
+#[wasm_bindgen_test]
+fn scopes_keep_state_apart() {
+    struct Left;
+    struct Right;
+
+    let (left, right) = (Rc::default(), Rc::default());
+    let mut left_dispatch = Dispatch::<SharedHandle<Count>, Left>::new(record(&left));
+    let _right_dispatch = Dispatch::<SharedHandle<Count>, Right>::new(record(&right));
+    left_dispatch.reduce(|count| count.0 = 5);
+
+    assert_eq!(*left.borrow(), vec![0, 5]);
+    assert_eq!(*right.borrow(), vec![0]);
+}
+
+#[wasm_bindgen_test]
+fn test_providers_force_state_outside_of_their_children_too() {
+    struct Forced;
+    type Wrapped = SharedStateComponent<Counter, Forced>;
+
+    let inside = mount_point();
+    let props = ProviderProps {
+        state: Rc::new(Count(9)),
+        children: Children::new(vec![html! { <Wrapped /> }]),
+    };
+    yew::App::<TestProvider<SharedHandle<Count>, Forced>>::new()
+        .mount_with_props(inside.clone(), props);
+    assert_eq!(inside.text_content().as_deref(), Some("9"));
+
+    // Forced state is global, so a sibling tree mounted meanwhile sees it as well.
+    let sibling = mount_point();
+    yew::App::<Wrapped>::new().mount(sibling.clone());
+    assert_eq!(sibling.text_content().as_deref(), Some("9"));
+}
+
+struct Synced;
+
+type SyncBoundary = StateErrorBoundary<SharedHandle<Count>, Synced>;
+
+/// Renders "fine", or the error shared state in `Synced` reports.
+struct SyncStatus;
+
+impl Component for SyncStatus {
+    type Message = ();
+    type Properties = ();
+
+    fn create(_props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        let fallback = component::fallback(
+            |error: &Error, _recovery: &Recovery| html! { <p>{ error.to_string() }</p> },
+        );
+        html! {
+            <SyncBoundary fallback=fallback>
+                <span>{ "fine" }</span>
+            </SyncBoundary>
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn error_boundaries_render_sync_failures() {
+    let hub = MemoryHub::new();
+    let _sync = sync::connect_in::<SharedHandle<Count>, Synced>(hub.connect());
+    let element = mount_point();
+    yew::App::<SyncStatus>::new().mount(element.clone());
+    assert_eq!(element.text_content().as_deref(), Some("fine"));
+
+    hub.connect().send(b"not a message".to_vec());
+    let text = element.text_content().unwrap();
+    assert!(text.starts_with("invalid sync message"), "{}", text);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Door {
+    open: bool,
+    knocks: u32,
+}
+
+#[derive(Debug)]
+enum DoorEvent {
+    Open,
+    Knock,
+}
+
+impl Machine for Door {
+    type Event = DoorEvent;
+
+    fn transition(&self, event: &DoorEvent) -> Option<Self> {
+        match event {
+            DoorEvent::Open if !self.open => Some(Door {
+                open: true,
+                ..self.clone()
+            }),
+            DoorEvent::Open => None,
+            DoorEvent::Knock => Some(Door {
+                knocks: self.knocks + 1,
+                ..self.clone()
+            }),
+        }
+    }
+}
+
+thread_local! {
+    /// Handle of the mounted `DoorView`.
+    static DOOR: RefCell<Option<SharedHandle<Door>>> = RefCell::new(None);
+}
+
+/// Keeps its handle where the test can send events with it.
+struct DoorView;
+
+impl Component for DoorView {
+    type Message = ();
+    type Properties = SharedHandle<Door>;
+
+    fn create(handle: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        DOOR.with(|door| *door.borrow_mut() = Some(handle));
+        Self
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        DOOR.with(|door| *door.borrow_mut() = Some(handle));
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {}
+    }
+}
+
+#[wasm_bindgen_test]
+async fn guarded_transitions_see_changes_the_notify_policy_holds_back() {
+    struct Guarded;
+
+    notify::policy_in::<SharedHandle<Door>, Guarded>(NotifyPolicy::Custom(|_, door| {
+        door.knocks == 0
+    }));
+    yew::App::<SharedStateComponent<DoorView, Guarded>>::new().mount(mount_point());
+    let handle = DOOR.with(|door| door.borrow().clone()).unwrap();
+
+    let (allow, allowed) = oneshot::channel();
+    handle.transition_guarded(DoorEvent::Open, async move { allowed.await.unwrap_or(false) });
+    // Held back from subscribers, but it still supersedes the pending transition.
+    handle.transition(DoorEvent::Knock);
+    allow.send(true).unwrap();
+    settle().await;
+
+    let doors: Rc<RefCell<Vec<Door>>> = Rc::default();
+    let recorded = doors.clone();
+    let _dispatch = Dispatch::<SharedHandle<Door>, Guarded>::new(Callback::from(
+        move |door: Rc<Door>| recorded.borrow_mut().push((*door).clone()),
+    ));
+    assert_eq!(
+        *doors.borrow(),
+        vec![Door {
+            open: true,
+            knocks: 1
+        }]
+    );
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Volume(u8);
+
+impl Storable for Volume {
+    fn key() -> &'static str {
+        "browser-tests:volume"
+    }
+}
+
+#[wasm_bindgen_test]
+fn storage_handles_persist_to_local_storage() {
+    let storage = local_storage();
+    let mut dispatch = Dispatch::<StorageHandle<Volume>>::connect();
+    dispatch.reduce(|volume| volume.0 = 7);
+
+    let stored = storage.get_item("browser-tests:volume").unwrap();
+    assert!(stored.is_some());
+    assert_eq!(
+        storage.get_item("browser-tests:volume.backup").unwrap(),
+        None
+    );
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Theme(String);
+
+impl Storable for Theme {
+    fn key() -> &'static str {
+        "browser-tests:theme"
+    }
+}
+
+#[wasm_bindgen_test]
+fn corrupt_storage_is_backed_up_and_starts_from_default() {
+    let storage = local_storage();
+    storage.set_item("browser-tests:theme", "not json").unwrap();
+
+    let themes: Rc<RefCell<Vec<String>>> = Rc::default();
+    let recorded = themes.clone();
+    let _dispatch =
+        Dispatch::<StorageHandle<Theme>>::new(Callback::from(move |theme: Rc<Theme>| {
+            recorded.borrow_mut().push(theme.0.clone())
+        }));
+
+    assert_eq!(*themes.borrow(), vec![String::new()]);
+    assert_eq!(
+        storage
+            .get_item("browser-tests:theme.backup")
+            .unwrap()
+            .as_deref(),
+        Some("not json")
+    );
+}
@@ -0,0 +1,506 @@
+//! Derive macros for [yew-state](https://github.com/intendednull/yew-state). Use them through
+//! `yew-state`'s `derive` feature.
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Path,
+    Result,
+};
+
+/// Derive `Storable`, deciding where each field is stored with `#[storable(...)]`.
+///
+/// On the struct:
+/// - `key = "..."`: the storage key.
+/// - `area = "local"` or `area = "session"`: where state is stored.
+/// - `compressed`: compress stored state with lz-string.
+///
+/// On fields:
+/// - `skip`: only kept in memory. Restored state has the field's default.
+/// - `session`: stored in session storage, regardless of `area`.
+/// - `encrypt`: stored encrypted with the cipher set by `config().cipher()`.
+///
+/// Fields are restored one by one, so a field that no longer deserializes keeps its default
+/// instead of discarding the rest of stored state.
+#[proc_macro_derive(Storable, attributes(storable))]
+pub fn derive_storable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    storable(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Where a field is stored.
+enum Placement {
+    Skip,
+    Part(TokenStream2),
+}
+
+/// Arguments of every `#[<name>(...)]` attribute.
+fn arguments(attrs: &[Attribute], name: &str) -> Result<Vec<NestedMeta>> {
+    let mut arguments = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(name)) {
+        match attr.parse_meta()? {
+            Meta::List(list) => arguments.extend(list.nested),
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    format!("expected `{}(...)`", name),
+                ))
+            }
+        }
+    }
+    Ok(arguments)
+}
+
+fn is(path: &Path, name: &str) -> bool {
+    path.is_ident(name)
+}
+
+fn storable(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "`Storable` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`Storable` can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut settings = Vec::new();
+    for argument in arguments(&input.attrs, "storable")? {
+        match argument {
+            NestedMeta::Meta(Meta::NameValue(pair)) if is(&pair.path, "key") => match pair.lit {
+                Lit::Str(key) => settings.push(quote! {
+                    fn key() -> &'static str {
+                        #key
+                    }
+                }),
+                lit => return Err(Error::new_spanned(lit, "expected a string")),
+            },
+            NestedMeta::Meta(Meta::NameValue(pair)) if is(&pair.path, "area") => {
+                let area = match &pair.lit {
+                    Lit::Str(area) if area.value() == "local" => quote!(Local),
+                    Lit::Str(area) if area.value() == "session" => quote!(Session),
+                    lit => {
+                        return Err(Error::new_spanned(
+                            lit,
+                            "expected `\"local\"` or `\"session\"`",
+                        ))
+                    }
+                };
+                settings.push(quote! {
+                    fn area() -> ::yew_state::Area {
+                        ::yew_state::Area::#area
+                    }
+                });
+            }
+            NestedMeta::Meta(Meta::Path(path)) if is(&path, "compressed") => {
+                settings.push(quote! {
+                    fn compressed() -> bool {
+                        true
+                    }
+                })
+            }
+            argument => {
+                return Err(Error::new_spanned(
+                    argument,
+                    "expected `key = \"...\"`, `area = \"...\"` or `compressed`",
+                ))
+            }
+        }
+    }
+
+    let mut inserts = Vec::new();
+    let mut gets = Vec::new();
+    for field in fields {
+        let mut placement = Placement::Part(quote!(Main));
+        for argument in arguments(&field.attrs, "storable")? {
+            placement = match argument {
+                NestedMeta::Meta(Meta::Path(path)) if is(&path, "skip") => Placement::Skip,
+                NestedMeta::Meta(Meta::Path(path)) if is(&path, "session") => {
+                    Placement::Part(quote!(Session))
+                }
+                NestedMeta::Meta(Meta::Path(path)) if is(&path, "encrypt") => {
+                    Placement::Part(quote!(Encrypted))
+                }
+                argument => {
+                    return Err(Error::new_spanned(
+                        argument,
+                        "expected `skip`, `session` or `encrypt`",
+                    ))
+                }
+            };
+        }
+
+        if let Placement::Part(part) = placement {
+            let ident = field.ident.as_ref().expect("fields are named");
+            let key = syn::LitStr::new(&ident.to_string(), Span::call_site());
+            inserts.push(quote! {
+                parts.insert(::yew_state::storage::Part::#part, #key, &self.#ident);
+            });
+            gets.push(quote! {
+                if let Some(value) = parts.get(::yew_state::storage::Part::#part, #key) {
+                    state.#ident = value;
+                }
+            });
+        }
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::yew_state::Storable for #name #ty_generics #where_clause {
+            #(#settings)*
+
+            fn to_parts(&self) -> ::yew_state::storage::Parts {
+                let mut parts = ::yew_state::storage::Parts::default();
+                #(#inserts)*
+                parts
+            }
+
+            fn from_parts(parts: &::yew_state::storage::Parts) -> ::std::option::Option<Self>
+            where
+                Self: ::std::default::Default,
+            {
+                let mut state = <Self as ::std::default::Default>::default();
+                #(#gets)*
+                ::std::option::Option::Some(state)
+            }
+        }
+    })
+}
+
+/// Derive `Store` for a struct whose fields are models, each shared in a scope of its own.
+///
+/// Generates a module named after the store in snake case (`AppStore` gives `app_store`), with a
+/// scope type per field named after it in camel case (`cart_items` gives `CartItems`), and a
+/// `<Store>Dispatch` struct with a `Dispatch` per field.
+///
+/// On fields:
+/// - `storage`: shared with `StorageHandle` rather than `SharedHandle`.
+#[proc_macro_derive(Store, attributes(store))]
+pub fn derive_store(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    store(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// `AppStore` to `app_store`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// `cart_items` to `CartItems`.
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn store(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`Store` can't be derived for generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "`Store` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`Store` can only be derived for structs",
+            ))
+        }
+    };
+
+    let module = syn::Ident::new(&snake_case(&name.to_string()), name.span());
+    let dispatch = syn::Ident::new(&format!("{}Dispatch", name), name.span());
+    let mut markers = Vec::new();
+    let mut dispatches = Vec::new();
+    let mut connects = Vec::new();
+    let mut subscribes = Vec::new();
+    for field in fields {
+        let mut handle = quote!(::yew_state::SharedHandle);
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("store"))
+        {
+            let nested = match attr.parse_meta()? {
+                Meta::List(list) => list.nested,
+                meta => return Err(Error::new_spanned(meta, "expected `store(...)`")),
+            };
+            for argument in nested {
+                match argument {
+                    NestedMeta::Meta(Meta::Path(path)) if is(&path, "storage") => {
+                        handle = quote!(::yew_state::StorageHandle);
+                    }
+                    argument => return Err(Error::new_spanned(argument, "expected `storage`")),
+                }
+            }
+        }
+
+        let ident = field.ident.as_ref().expect("fields are named");
+        let ty = &field.ty;
+        let marker = syn::Ident::new(&camel_case(&ident.to_string()), ident.span());
+        let doc = format!("Scope of `{}::{}`.", name, ident);
+        markers.push(quote! {
+            #[doc = #doc]
+            pub struct #marker;
+        });
+        dispatches.push(quote! {
+            pub #ident: ::yew_state::Dispatch<#handle<#ty>, #module::#marker>,
+        });
+        connects.push(quote! {
+            #ident: ::yew_state::Dispatch::connect(),
+        });
+        subscribes.push(quote! {
+            #ident: {
+                let store = store.clone();
+                let callback = callback.clone();
+                ::yew_state::Dispatch::new(::yew::Callback::from(
+                    move |state: ::std::rc::Rc<#ty>| {
+                        let current = {
+                            let mut store = store.borrow_mut();
+                            store.#ident = (*state).clone();
+                            ::std::rc::Rc::new(store.clone())
+                        };
+                        callback.emit(current);
+                    },
+                ))
+            },
+        });
+    }
+
+    let module_doc = format!("Scopes of the fields of `{}`.", name);
+    let dispatch_doc = format!("A `Dispatch` per field of `{}`.", name);
+    Ok(quote! {
+        #[doc = #module_doc]
+        #vis mod #module {
+            #(#markers)*
+        }
+
+        #[doc = #dispatch_doc]
+        #vis struct #dispatch {
+            #(#dispatches)*
+        }
+
+        impl ::yew_state::Store for #name {
+            type Dispatch = #dispatch;
+
+            fn dispatch() -> Self::Dispatch {
+                #dispatch {
+                    #(#connects)*
+                }
+            }
+
+            fn subscribe(
+                callback: ::yew::Callback<::std::rc::Rc<Self>>,
+            ) -> Self::Dispatch {
+                let store = ::std::rc::Rc::new(::std::cell::RefCell::new(
+                    <Self as ::std::default::Default>::default(),
+                ));
+                #dispatch {
+                    #(#subscribes)*
+                }
+            }
+        }
+    })
+}
+
+/// Derive `Redact`, deciding how each field is shown with `#[redact(...)]`.
+///
+/// On fields:
+/// - `hide`: shown as `"<redacted>"`.
+/// - `mask`: shown with only its first character, and the domain of emails.
+/// - `truncate = N`: strings and arrays cut down to `N` characters or items.
+/// - `nested`: redacted by the field type's own `Redact`.
+///
+/// Fields are found by name, so fields renamed with serde aren't redacted.
+#[proc_macro_derive(Redact, attributes(redact))]
+pub fn derive_redact(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    redact(input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+fn redact(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "`Redact` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "`Redact` can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut redactions = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("fields are named");
+        let key = syn::LitStr::new(&ident.to_string(), Span::call_site());
+        let ty = &field.ty;
+        for argument in arguments(&field.attrs, "redact")? {
+            let redaction = match argument {
+                NestedMeta::Meta(Meta::Path(path)) if is(&path, "hide") => {
+                    quote!(::yew_state::redact::hide(value))
+                }
+                NestedMeta::Meta(Meta::Path(path)) if is(&path, "mask") => {
+                    quote!(::yew_state::redact::mask(value))
+                }
+                NestedMeta::Meta(Meta::Path(path)) if is(&path, "nested") => {
+                    quote!(<#ty as ::yew_state::Redact>::redact(value))
+                }
+                NestedMeta::Meta(Meta::NameValue(pair)) if is(&pair.path, "truncate") => {
+                    match pair.lit {
+                        Lit::Int(max) => quote!(::yew_state::redact::truncate(value, #max)),
+                        lit => return Err(Error::new_spanned(lit, "expected a number")),
+                    }
+                }
+                argument => {
+                    return Err(Error::new_spanned(
+                        argument,
+                        "expected `hide`, `mask`, `truncate = N` or `nested`",
+                    ))
+                }
+            };
+            redactions.push(quote! {
+                if let ::std::option::Option::Some(value) = fields.get_mut(#key) {
+                    #redaction;
+                }
+            });
+        }
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::yew_state::Redact for #name #ty_generics #where_clause {
+            fn redact(json: &mut ::yew_state::redact::Json) {
+                if let ::std::option::Option::Some(fields) = json.as_object_mut() {
+                    #(#redactions)*
+                }
+            }
+        }
+    })
+}
+
+/// Derive `machine::Chart` for an enum of unit variants, listing every variant as a state. Events
+/// are listed by the event type, which derives `Events`.
+#[proc_macro_derive(Chart)]
+pub fn derive_chart(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    variants(&input, "Chart")
+        .map(|variants| {
+            let name = &input.ident;
+            let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+            quote! {
+                impl #impl_generics ::yew_state::machine::Chart for #name #ty_generics #where_clause {
+                    fn states() -> ::std::vec::Vec<Self> {
+                        ::std::vec![#(#name::#variants),*]
+                    }
+
+                    fn events() -> ::std::vec::Vec<<Self as ::yew_state::Machine>::Event> {
+                        <<Self as ::yew_state::Machine>::Event as ::yew_state::machine::Events>::all()
+                    }
+                }
+            }
+        })
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Derive `machine::Events` for an enum of unit variants, listing every variant.
+#[proc_macro_derive(Events)]
+pub fn derive_events(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    variants(&input, "Events")
+        .map(|variants| {
+            let name = &input.ident;
+            let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+            quote! {
+                impl #impl_generics ::yew_state::machine::Events for #name #ty_generics #where_clause {
+                    fn all() -> ::std::vec::Vec<Self> {
+                        ::std::vec![#(#name::#variants),*]
+                    }
+                }
+            }
+        })
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Variants of an enum made only of unit variants.
+fn variants<'a>(input: &'a DeriveInput, derive: &str) -> Result<Vec<&'a syn::Ident>> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                format!("`{}` can only be derived for enums", derive),
+            ))
+        }
+    };
+    data.variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(&variant.ident),
+            _ => Err(Error::new_spanned(
+                variant,
+                format!(
+                    "`{}` can only be derived for enums of unit variants",
+                    derive
+                ),
+            )),
+        })
+        .collect()
+}
@@ -0,0 +1,70 @@
+use yew::prelude::*;
+use yew_state::{component, SharedHandle, StateView, StorageHandle};
+
+use crate::entry::Entry;
+use crate::footer::Footer;
+use crate::header::Header;
+use crate::state::{Filter, Todos};
+
+pub struct App;
+
+impl Component for App {
+    type Message = ();
+    type Properties = ();
+
+    fn create(_props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        // The filter is needed to list todos, so its handle is given to the view of todos.
+        let view_filter = component::view(|filter: &SharedHandle<Filter>| {
+            let filter = *filter.state();
+            let view_todos = component::view(move |todos: &StorageHandle<Todos>| {
+                let state = todos.state();
+                let entries = state
+                    .entries
+                    .iter()
+                    .filter(|entry| filter.matches(entry))
+                    .map(|entry| html! { <Entry entry=entry.clone() /> });
+
+                html! {
+                    <>
+                        <section class="main">
+                            <input
+                                type="checkbox"
+                                class="toggle-all"
+                                checked=!state.entries.is_empty() && state.all_completed()
+                                onclick=todos.reduce_callback(|todos| todos.toggle_all())
+                                />
+                            <ul class="todo-list">{ for entries }</ul>
+                        </section>
+                        <Footer
+                            remaining=state.remaining()
+                            on_clear_completed=todos.reduce_callback(|todos| todos.clear_completed())
+                            />
+                    </>
+                }
+            });
+
+            html! {
+                <StateView<StorageHandle<Todos>> view=view_todos />
+            }
+        });
+
+        html! {
+            <section class="todoapp">
+                <Header />
+                <StateView<SharedHandle<Filter>> view=view_filter />
+            </section>
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use yew::prelude::*;
+use yew_state::{SharedState, SharedStateComponent, StorageHandle};
+
+use crate::state::Entry as EntryState;
+use crate::state::Todos;
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    #[prop_or_default]
+    pub handle: StorageHandle<Todos>,
+    pub entry: EntryState,
+}
+
+impl SharedState for Props {
+    type Handle = StorageHandle<Todos>;
+
+    fn handle(&mut self) -> &mut Self::Handle {
+        &mut self.handle
+    }
+}
+
+pub struct Model {
+    props: Props,
+}
+
+impl Component for Model {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let changed = self.props.entry != props.entry;
+        self.props = props;
+        changed
+    }
+
+    fn view(&self) -> Html {
+        let id = self.props.entry.id;
+        let handle = &self.props.handle;
+        let class = if self.props.entry.completed {
+            "completed"
+        } else {
+            ""
+        };
+
+        html! {
+            <li class=class>
+                <div class="view">
+                    <input
+                        type="checkbox"
+                        class="toggle"
+                        checked=self.props.entry.completed
+                        onclick=handle.reduce_callback(move |todos| todos.toggle(id))
+                        />
+                    <label>{ &self.props.entry.description }</label>
+                    <button
+                        class="destroy"
+                        onclick=handle.reduce_callback(move |todos| todos.remove(id))
+                        />
+                </div>
+            </li>
+        }
+    }
+}
+
+pub type Entry = SharedStateComponent<Model>;
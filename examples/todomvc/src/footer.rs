@@ -0,0 +1,86 @@
+use yew::prelude::*;
+use yew_state::{SharedHandle, SharedStateComponent};
+
+use crate::state::Filter;
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    #[prop_or_default]
+    pub handle: SharedHandle<Filter>,
+    pub remaining: usize,
+    pub on_clear_completed: Callback<MouseEvent>,
+}
+
+impl yew_state::SharedState for Props {
+    type Handle = SharedHandle<Filter>;
+
+    fn handle(&mut self) -> &mut Self::Handle {
+        &mut self.handle
+    }
+}
+
+pub struct Model {
+    props: Props,
+}
+
+impl Model {
+    fn view_filter(&self, filter: Filter, label: &str) -> Html {
+        let class = if *self.props.handle.state() == filter {
+            "selected"
+        } else {
+            ""
+        };
+
+        html! {
+            <li>
+                <a
+                    class=class
+                    onclick=self.props.handle.reduce_callback(move |current| *current = filter)
+                    >
+                    { label }
+                </a>
+            </li>
+        }
+    }
+}
+
+impl Component for Model {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let remaining = self.props.remaining;
+        let items = if remaining == 1 { "item" } else { "items" };
+
+        html! {
+            <footer class="footer">
+                <span class="todo-count">
+                    <strong>{ remaining }</strong>{ format!(" {} left", items) }
+                </span>
+                <ul class="filters">
+                    { self.view_filter(Filter::All, "All") }
+                    { self.view_filter(Filter::Active, "Active") }
+                    { self.view_filter(Filter::Completed, "Completed") }
+                </ul>
+                <button class="clear-completed" onclick=self.props.on_clear_completed.clone()>
+                    { "Clear completed" }
+                </button>
+            </footer>
+        }
+    }
+}
+
+pub type Footer = SharedStateComponent<Model>;
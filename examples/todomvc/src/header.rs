@@ -0,0 +1,71 @@
+use yew::prelude::*;
+use yew_state::{SharedStateComponent, StorageHandle};
+
+use crate::state::Todos;
+
+pub enum Msg {
+    Edit(String),
+    Add,
+    Nothing,
+}
+
+pub struct Model {
+    handle: StorageHandle<Todos>,
+    value: String,
+    link: ComponentLink<Self>,
+}
+
+impl Component for Model {
+    type Message = Msg;
+    type Properties = StorageHandle<Todos>;
+
+    fn create(handle: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            handle,
+            value: String::new(),
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Edit(value) => {
+                self.value = value;
+                true
+            }
+            Msg::Add => {
+                let description = self.value.trim().to_string();
+                if !description.is_empty() {
+                    self.handle.reduce(move |todos| todos.add(description));
+                }
+                self.value.clear();
+                true
+            }
+            Msg::Nothing => false,
+        }
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        self.handle = handle;
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <header class="header">
+                <h1>{ "todos" }</h1>
+                <input
+                    class="new-todo"
+                    placeholder="What needs to be done?"
+                    value=&self.value
+                    oninput=self.link.callback(|e: InputData| Msg::Edit(e.value))
+                    onkeypress=self.link.callback(|e: KeyboardEvent| {
+                        if e.key() == "Enter" { Msg::Add } else { Msg::Nothing }
+                    })
+                    />
+            </header>
+        }
+    }
+}
+
+pub type Header = SharedStateComponent<Model>;
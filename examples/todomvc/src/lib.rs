@@ -0,0 +1,12 @@
+use wasm_bindgen::prelude::*;
+
+mod app;
+mod entry;
+mod footer;
+mod header;
+mod state;
+
+#[wasm_bindgen]
+pub fn run_app() {
+    yew::start_app::<app::App>();
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use yew_state::Storable;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    pub description: String,
+    pub completed: bool,
+}
+
+/// Every todo, saved to local storage.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Todos {
+    pub entries: Vec<Entry>,
+    next_id: u64,
+}
+
+impl Todos {
+    pub fn add(&mut self, description: String) {
+        self.entries.push(Entry {
+            id: self.next_id,
+            description,
+            completed: false,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.entries.retain(|entry| entry.id != id);
+    }
+
+    pub fn toggle(&mut self, id: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            entry.completed = !entry.completed;
+        }
+    }
+
+    pub fn toggle_all(&mut self) {
+        let completed = !self.all_completed();
+        for entry in &mut self.entries {
+            entry.completed = completed;
+        }
+    }
+
+    pub fn clear_completed(&mut self) {
+        self.entries.retain(|entry| !entry.completed);
+    }
+
+    pub fn all_completed(&self) -> bool {
+        self.entries.iter().all(|entry| entry.completed)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.entries.iter().filter(|entry| !entry.completed).count()
+    }
+}
+
+impl Storable for Todos {
+    fn key() -> &'static str {
+        "todomvc.todos"
+    }
+}
+
+/// Which todos are shown. Not persisted.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Filter {
+    #[default]
+    All,
+    Active,
+    Completed,
+}
+
+impl Filter {
+    pub fn matches(self, entry: &Entry) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Active => !entry.completed,
+            Filter::Completed => entry.completed,
+        }
+    }
+}
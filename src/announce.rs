@@ -0,0 +1,144 @@
+//! Screen reader announcements as shared state.
+//!
+//! Render `LiveRegion` once, and queue announcements from anywhere: reducers, callbacks, or code
+//! outside of components. The region reads them out through ARIA live regions, so nothing has to
+//! touch the DOM by hand:
+//!
+//! ```ignore
+//! html! {
+//!     <>
+//!         <App />
+//!         <LiveRegion />
+//!     </>
+//! }
+//!
+//! // After adding an item to the cart.
+//! announce::polite("Item added to cart");
+//! ```
+use std::rc::Rc;
+
+use crate::dispatch::Dispatch;
+use crate::handle::AnnouncerHandle;
+use crate::handler::{Handler, Reduction, ReductionOnce};
+
+/// Announcements kept for each politeness. Older ones are dropped, having been read out already.
+const LIMIT: usize = 5;
+
+/// How urgently an announcement is read out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Politeness {
+    /// Read out when the user is idle.
+    Polite,
+    /// Read out right away, interrupting. Keep it for errors and such.
+    Assertive,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Announcement {
+    pub message: String,
+    pub politeness: Politeness,
+    /// Tells repeated messages apart, so each is read out.
+    pub id: u64,
+}
+
+/// Announcements queued for screen readers, oldest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Announcements {
+    queued: Vec<Announcement>,
+    next_id: u64,
+}
+
+impl Announcements {
+    /// Queue an announcement read out when the user is idle.
+    pub fn polite(&mut self, message: impl Into<String>) {
+        self.announce(message, Politeness::Polite);
+    }
+
+    /// Queue an announcement read out right away.
+    pub fn assertive(&mut self, message: impl Into<String>) {
+        self.announce(message, Politeness::Assertive);
+    }
+
+    pub fn announce(&mut self, message: impl Into<String>, politeness: Politeness) {
+        self.next_id += 1;
+        self.queued.push(Announcement {
+            message: message.into(),
+            politeness,
+            id: self.next_id,
+        });
+    }
+
+    /// Announcements of a politeness, oldest first.
+    pub fn iter(&self, politeness: Politeness) -> impl Iterator<Item = &Announcement> {
+        self.queued
+            .iter()
+            .filter(move |announcement| announcement.politeness == politeness)
+    }
+
+    /// The last announcement, if any.
+    pub fn last(&self) -> Option<&Announcement> {
+        self.queued.last()
+    }
+
+    fn trim(&mut self) {
+        for politeness in [Politeness::Polite, Politeness::Assertive] {
+            let excess = self.iter(politeness).count().saturating_sub(LIMIT);
+            let mut dropped = 0;
+            self.queued.retain(|announcement| {
+                let keep = announcement.politeness != politeness || dropped >= excess;
+                if !keep {
+                    dropped += 1;
+                }
+                keep
+            });
+        }
+    }
+}
+
+/// Handler for announcements. Keeps the last few of each politeness.
+#[derive(Clone)]
+pub struct AnnouncerHandler {
+    state: Rc<Announcements>,
+}
+
+impl Handler for AnnouncerHandler {
+    type Model = Announcements;
+
+    fn new() -> Self {
+        Self {
+            state: Default::default(),
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        let state = Rc::make_mut(&mut self.state);
+        f(state);
+        state.trim();
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        let state = Rc::make_mut(&mut self.state);
+        f(state);
+        state.trim();
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+/// Queue an announcement read out when the user is idle.
+pub fn polite(message: impl Into<String>) {
+    announce(message, Politeness::Polite);
+}
+
+/// Queue an announcement read out right away.
+pub fn assertive(message: impl Into<String>) {
+    announce(message, Politeness::Assertive);
+}
+
+pub fn announce(message: impl Into<String>, politeness: Politeness) {
+    let message = message.into();
+    Dispatch::<AnnouncerHandle>::connect()
+        .reduce(move |announcements| announcements.announce(message, politeness));
+}
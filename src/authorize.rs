@@ -0,0 +1,85 @@
+//! Authorizing changes to shared state, as defense in depth for UI rendered read-only.
+//!
+//! Once an authorizer is set for a scope, it's asked about every change made with `reduce` and
+//! such, given current state and the change's label (see `reduce_labeled`), and about restarting
+//! the handler (with no label). Rejected changes aren't applied, and `Error::Unauthorized` is
+//! reported instead.
+//!
+//! ```ignore
+//! authorize::authorize::<SharedHandle<Document>>(|document, label| match label {
+//!     Some("delete") => document.role == Role::Owner,
+//!     _ => document.role != Role::Viewer,
+//! });
+//! ```
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::handle::Handle;
+use crate::handler::Handler;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+type Authorizer<T> = Rc<dyn Fn(&T, Option<&str>) -> bool>;
+
+thread_local! {
+    /// Authorizer of every scope that has one, keyed by handler and scope.
+    static AUTHORIZERS: RefCell<HashMap<(TypeId, TypeId), Rc<dyn Any>>> = Default::default();
+}
+
+/// Authorize changes to shared state of handle type `H` with `f`, replacing any authorizer set
+/// before. `f` gets current state and the change's label, and returns whether it's allowed.
+pub fn authorize<H>(f: impl Fn(&Model<H>, Option<&str>) -> bool + 'static)
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+{
+    authorize_in::<H, StateHandler<H>>(f)
+}
+
+/// Like `authorize`, for shared state in `SCOPE`.
+pub fn authorize_in<H, SCOPE>(f: impl Fn(&Model<H>, Option<&str>) -> bool + 'static)
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    let authorizer: Authorizer<Model<H>> = Rc::new(f);
+    AUTHORIZERS.with(|authorizers| {
+        authorizers
+            .borrow_mut()
+            .insert(key, Rc::new(authorizer) as Rc<dyn Any>)
+    });
+}
+
+/// Allow every change to shared state of handle type `H` in `SCOPE` again.
+pub fn revoke<H, SCOPE>()
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    AUTHORIZERS.with(|authorizers| authorizers.borrow_mut().remove(&key));
+}
+
+/// Whether a change labeled `label` to `state` is allowed.
+pub(crate) fn allowed<H, SCOPE>(state: &H::Model, label: Option<&str>) -> bool
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    // Cloned out, so the authorizer may set authorizers itself.
+    let authorizer = AUTHORIZERS.with(|authorizers| authorizers.borrow().get(&key).cloned());
+    match authorizer {
+        Some(authorizer) => match authorizer.downcast_ref::<Authorizer<H::Model>>() {
+            Some(authorizer) => authorizer(state, label),
+            None => true,
+        },
+        None => true,
+    }
+}
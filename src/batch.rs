@@ -0,0 +1,63 @@
+//! Changing several scopes at once.
+use std::rc::Rc;
+
+use crate::dispatch::Dispatch;
+use crate::handle::{Handle, StateHandle};
+use crate::handler::{Barrier, Handler, Request, Ticket};
+
+/// Changes to apply together, see `atomic`.
+pub struct Batch {
+    barrier: Rc<Barrier>,
+}
+
+impl Batch {
+    /// Apply a function that may mutate the shared state of `handle`, as part of this batch.
+    pub fn reduce<T, H>(&self, handle: &StateHandle<T, H>, f: impl FnOnce(&mut T) + 'static)
+    where
+        T: Default + Clone + 'static,
+        H: Handler<Model = T>,
+    {
+        if let Some(callback) = handle.local_callback() {
+            callback.emit(self.request(Request::ApplyOnce(Box::new(f))));
+        }
+    }
+
+    /// Apply a function that may mutate the shared state of `dispatch`, as part of this batch.
+    pub fn dispatch<H, SCOPE>(
+        &self,
+        dispatch: &mut Dispatch<H, SCOPE>,
+        f: impl FnOnce(&mut <H::Handler as Handler>::Model) + 'static,
+    ) where
+        H: Handle,
+        H::Handler: Clone + 'static,
+        SCOPE: 'static,
+    {
+        dispatch.send(self.request(Request::ApplyOnce(Box::new(f))));
+    }
+
+    fn request<T>(&self, request: Request<T>) -> Request<T> {
+        Request::Batched(Box::new(request), Ticket::new(&self.barrier))
+    }
+}
+
+/// Apply changes to several scopes together. Subscribers of every scope are notified at the same
+/// time, once all changes are applied, so related state is never seen in an inconsistent
+/// combination. A change that's never applied (say, because it isn't authorized) doesn't hold
+/// back the others, nor does one held back by a rate limit or coalescing, which is applied on its
+/// own later.
+///
+/// ```ignore
+/// batch::atomic(|batch| {
+///     batch.reduce(&self.cart, move |cart| cart.items.push(item));
+///     batch.reduce(&self.totals, move |totals| totals.count += 1);
+/// });
+/// ```
+pub fn atomic(f: impl FnOnce(&Batch)) {
+    let batch = Batch {
+        barrier: Default::default(),
+    };
+    // Hold the barrier until every change is sent, in case some are applied right away.
+    let held = Ticket::new(&batch.barrier);
+    f(&batch);
+    held.arrive(Box::new(|| {}));
+}
@@ -0,0 +1,207 @@
+//! Bounded collections for state that grows over time.
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use serde::de::{SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Entries per chunk. Changing a shared log copies at most one chunk.
+const CHUNK: usize = 64;
+
+/// Log of the newest `N` entries, for chat messages, event feeds, and such. Pushing past `N`
+/// entries drops the oldest.
+///
+/// Entries are kept in shared chunks, so cloning a log (as happens to shared state whenever it
+/// changes) doesn't copy entries. Serializes as a sequence of entries, oldest first, and only the
+/// newest `N` are kept when deserializing.
+///
+/// ```ignore
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// struct Chat {
+///     messages: BoundedLog<Message, 500>,
+/// }
+///
+/// handle.reduce(move |chat| chat.messages.push(message));
+/// ```
+pub struct BoundedLog<T, const N: usize> {
+    chunks: VecDeque<Rc<Vec<T>>>,
+    /// Entries of the first chunk that were dropped.
+    offset: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> BoundedLog<T, N> {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            offset: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Entry at `index`, counting from the oldest.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let index = index + self.offset;
+        self.chunks[index / CHUNK].get(index % CHUNK)
+    }
+
+    /// Oldest entry.
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// Newest entry.
+    pub fn last(&self) -> Option<&T> {
+        self.chunks.back().and_then(|chunk| chunk.last())
+    }
+
+    /// Entries, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .skip(self.offset)
+    }
+
+    /// Remove every entry.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Drop the oldest entry.
+    fn drop_first(&mut self) {
+        self.offset += 1;
+        self.len -= 1;
+        if self.offset == CHUNK || self.len == 0 {
+            self.chunks.pop_front();
+            self.offset = 0;
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> BoundedLog<T, N> {
+    /// Add an entry, dropping the oldest if the log is full.
+    pub fn push(&mut self, entry: T) {
+        if N == 0 {
+            return;
+        }
+        match self.chunks.back_mut() {
+            Some(chunk) if chunk.len() < CHUNK => Rc::make_mut(chunk).push(entry),
+            _ => {
+                let mut chunk = Vec::with_capacity(CHUNK.min(N));
+                chunk.push(entry);
+                self.chunks.push_back(Rc::new(chunk));
+            }
+        }
+        self.len += 1;
+        if self.len > N {
+            self.drop_first();
+        }
+    }
+}
+
+impl<T, const N: usize> Default for BoundedLog<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Clone for BoundedLog<T, N> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            offset: self.offset,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for BoundedLog<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for BoundedLog<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone, const N: usize> Extend<T> for BoundedLog<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, entries: I) {
+        for entry in entries {
+            self.push(entry);
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> std::iter::FromIterator<T> for BoundedLog<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(entries: I) -> Self {
+        let mut log = Self::new();
+        log.extend(entries);
+        log
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for BoundedLog<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len))?;
+        for entry in self.iter() {
+            seq.serialize_element(entry)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T, const N: usize> Deserialize<'de> for BoundedLog<T, N>
+where
+    T: Clone + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LogVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T, const N: usize> Visitor<'de> for LogVisitor<T, N>
+        where
+            T: Clone + Deserialize<'de>,
+        {
+            type Value = BoundedLog<T, N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut log = BoundedLog::new();
+                while let Some(entry) = seq.next_element()? {
+                    log.push(entry);
+                }
+                Ok(log)
+            }
+        }
+
+        deserializer.deserialize_seq(LogVisitor(PhantomData))
+    }
+}
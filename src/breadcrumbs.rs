@@ -0,0 +1,80 @@
+//! Navigation trail as shared state.
+//!
+//! Pages push a crumb when they're shown, and `Breadcrumbs` renders the trail. Pushing a crumb
+//! that's already on the trail goes back to it, so navigating up doesn't grow the trail:
+//!
+//! ```ignore
+//! // When a page is shown, with `handle: BreadcrumbHandle`.
+//! self.handle.reduce(|trail| trail.push("Orders", "/orders"));
+//!
+//! // Anywhere in the layout.
+//! html! { <Breadcrumbs /> }
+//! ```
+use crate::handler::SharedHandler;
+
+/// Handler for the navigation trail.
+pub type BreadcrumbHandler = SharedHandler<Trail>;
+
+/// One step of the trail.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crumb {
+    pub label: String,
+    pub href: String,
+}
+
+/// Navigation trail, from the root to the current page.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trail {
+    crumbs: Vec<Crumb>,
+}
+
+impl Trail {
+    /// Push the current page. If it's on the trail already, crumbs after it are removed instead.
+    pub fn push(&mut self, label: impl Into<String>, href: impl Into<String>) {
+        let href = href.into();
+        match self.crumbs.iter().position(|crumb| crumb.href == href) {
+            Some(index) => {
+                self.crumbs.truncate(index + 1);
+                self.crumbs[index].label = label.into();
+            }
+            None => self.crumbs.push(Crumb {
+                label: label.into(),
+                href,
+            }),
+        }
+    }
+
+    /// Remove the current page, returning it.
+    pub fn pop(&mut self) -> Option<Crumb> {
+        self.crumbs.pop()
+    }
+
+    /// Go back to the crumb at `index`, removing the ones after it.
+    pub fn back_to(&mut self, index: usize) {
+        self.crumbs.truncate(index + 1);
+    }
+
+    /// Start over from `label` at `href`.
+    pub fn reset(&mut self, label: impl Into<String>, href: impl Into<String>) {
+        self.crumbs.clear();
+        self.push(label, href);
+    }
+
+    /// The current page, if any.
+    pub fn current(&self) -> Option<&Crumb> {
+        self.crumbs.last()
+    }
+
+    /// Crumbs from the root to the current page.
+    pub fn iter(&self) -> impl Iterator<Item = &Crumb> {
+        self.crumbs.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.crumbs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crumbs.is_empty()
+    }
+}
@@ -0,0 +1,177 @@
+//! Spreading large changes across animation frames, so importing thousands of items doesn't freeze
+//! the page.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::{closure::Closure, JsCast};
+use yew::Callback;
+
+use crate::handle::StateHandle;
+use crate::handler::Handler;
+use crate::progress::Progress;
+
+/// How far along a `reduce_chunked` is, in items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl ChunkProgress {
+    /// Fraction of items applied, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+
+    /// Whether every item is applied.
+    pub fn is_finished(&self) -> bool {
+        self.done == self.total
+    }
+}
+
+type ApplyChunk<T, I> = Rc<dyn Fn(&mut T, Vec<I>)>;
+
+struct Job<I> {
+    chunks: RefCell<std::vec::IntoIter<Vec<I>>>,
+    progress: Cell<ChunkProgress>,
+    watcher: RefCell<Option<Callback<ChunkProgress>>>,
+    cancelled: Cell<bool>,
+}
+
+/// Status of the shared parts of a job, independent of its items.
+trait Status {
+    fn progress(&self) -> ChunkProgress;
+    fn set_watcher(&self, callback: Callback<ChunkProgress>);
+    fn cancel(&self);
+}
+
+impl<I> Status for Job<I> {
+    fn progress(&self) -> ChunkProgress {
+        self.progress.get()
+    }
+
+    fn set_watcher(&self, callback: Callback<ChunkProgress>) {
+        *self.watcher.borrow_mut() = Some(callback);
+    }
+
+    fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+}
+
+/// Changes being applied by `reduce_chunked`. They keep being applied whether or not this is kept.
+pub struct Chunked {
+    job: Rc<dyn Status>,
+}
+
+impl Chunked {
+    /// Items applied so far.
+    pub fn progress(&self) -> ChunkProgress {
+        self.job.progress()
+    }
+
+    /// Be notified with `callback` after every chunk is applied.
+    pub fn on_progress(&self, callback: Callback<ChunkProgress>) {
+        self.job.set_watcher(callback);
+    }
+
+    /// Stop before the next chunk. Chunks already applied stay applied.
+    pub fn cancel(&self) {
+        self.job.cancel();
+    }
+}
+
+/// Run `f` on the next animation frame, or right away without a window.
+fn next_frame(f: impl FnOnce() + 'static) {
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return f(),
+    };
+    let callback = Closure::once_into_js(f);
+    let _ = window.request_animation_frame(callback.unchecked_ref());
+}
+
+impl<T, H> StateHandle<T, H>
+where
+    T: Default + Clone + 'static,
+    H: Handler<Model = T>,
+{
+    /// Apply `f` to `items`, `chunk_size` at a time, one chunk per animation frame. Subscribers
+    /// are notified after every chunk, so the page stays responsive and can show the items as
+    /// they arrive. Progress is also reported to every handle (see `handle.progress()`), and
+    /// cleared once every chunk is applied or the changes are cancelled.
+    ///
+    /// ```ignore
+    /// let import = handle.reduce_chunked(rows, 500, |library, rows| library.books.extend(rows));
+    /// import.on_progress(link.callback(Msg::Imported));
+    /// ```
+    pub fn reduce_chunked<I>(
+        &self,
+        items: impl IntoIterator<Item = I>,
+        chunk_size: usize,
+        f: impl Fn(&mut T, Vec<I>) + 'static,
+    ) -> Chunked
+    where
+        I: 'static,
+        H: 'static,
+    {
+        let chunk_size = chunk_size.max(1);
+        let mut chunks = Vec::new();
+        let mut total = 0;
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            let chunk: Vec<I> = items.by_ref().take(chunk_size).collect();
+            total += chunk.len();
+            chunks.push(chunk);
+        }
+
+        let job = Rc::new(Job {
+            chunks: RefCell::new(chunks.into_iter()),
+            progress: Cell::new(ChunkProgress { done: 0, total }),
+            watcher: RefCell::new(None),
+            cancelled: Cell::new(false),
+        });
+        step(self.clone(), job.clone(), Rc::new(f));
+        Chunked { job }
+    }
+}
+
+/// Apply the next chunk of `job` on the next frame.
+fn step<T, H, I>(handle: StateHandle<T, H>, job: Rc<Job<I>>, f: ApplyChunk<T, I>)
+where
+    T: Default + Clone + 'static,
+    H: Handler<Model = T> + 'static,
+    I: 'static,
+{
+    next_frame(move || {
+        if job.cancelled.get() {
+            handle.clear_progress();
+            return;
+        }
+        let chunk = match job.chunks.borrow_mut().next() {
+            Some(chunk) => chunk,
+            None => return,
+        };
+
+        let mut progress = job.progress.get();
+        progress.done += chunk.len();
+        job.progress.set(progress);
+        let apply = f.clone();
+        handle.reduce(move |state| apply(state, chunk));
+
+        let watcher = job.watcher.borrow().clone();
+        if let Some(watcher) = watcher {
+            watcher.emit(progress);
+        }
+        if progress.is_finished() {
+            handle.clear_progress();
+        } else {
+            handle.report_progress(Progress::new(progress.fraction() * 100.0, ""));
+            step(handle, job, f);
+        }
+    });
+}
@@ -0,0 +1,93 @@
+//! A stable, anonymous id for this client.
+//!
+//! The id is a random UUID, generated on first use and kept in local storage (at
+//! `<prefix>client-id`), so it's the same across visits until it's rotated or cleared. Without
+//! local storage it only lasts as long as the page.
+use std::cell::RefCell;
+
+use yew::{format::Json, services::storage::Area};
+
+use crate::config;
+use crate::storage::WebStorage;
+
+thread_local! {
+    static ID: RefCell<Option<String>> = Default::default();
+}
+
+fn key() -> String {
+    format!("{}client-id", config::key_prefix())
+}
+
+fn storage() -> Option<WebStorage> {
+    WebStorage::new(Area::Local).ok()
+}
+
+/// Random 32 bits.
+fn random() -> u32 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Math::random() * f64::from(u32::MAX)) as u32
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::hash::{BuildHasher, Hasher};
+        std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish() as u32
+    }
+}
+
+/// Random (version 4) UUID.
+fn uuid() -> String {
+    let mut bytes = [0; 16];
+    for chunk in bytes.chunks_mut(4) {
+        chunk.copy_from_slice(&random().to_be_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Id of this client, generating one if there's none yet.
+pub fn client_id() -> String {
+    if let Some(id) = ID.with(|id| id.borrow().clone()) {
+        return id;
+    }
+    let stored: Option<String> = storage().and_then(|storage| match storage.restore(&key()) {
+        Json(Ok(id)) => Some(id),
+        _ => None,
+    });
+    let id = match stored {
+        Some(id) => id,
+        None => return rotate_client_id(),
+    };
+    ID.with(|cached| *cached.borrow_mut() = Some(id.clone()));
+    id
+}
+
+/// Replace the id of this client with a new one, returning it.
+pub fn rotate_client_id() -> String {
+    let id = uuid();
+    if let Some(mut storage) = storage() {
+        storage.store(&key(), Json(&id));
+    }
+    ID.with(|cached| *cached.borrow_mut() = Some(id.clone()));
+    id
+}
+
+/// Forget the id of this client, such as when the user opts out of tracking. A new one is
+/// generated the next time `client_id` is called.
+pub fn clear_client_id() {
+    if let Some(mut storage) = storage() {
+        storage.remove(&key());
+    }
+    ID.with(|cached| *cached.borrow_mut() = None);
+}
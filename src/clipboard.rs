@@ -0,0 +1,46 @@
+//! Copying shared state to the clipboard.
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::handle::Handle;
+use crate::handler::Handler;
+use crate::scope;
+use crate::sensitive::sealed;
+
+/// Copy current state of the default scope of handle `H` to the clipboard, as JSON.
+///
+/// ```ignore
+/// spawn_local(async {
+///     let _ = clipboard::copy_state_to_clipboard::<SharedHandle<AppState>>().await;
+/// });
+/// ```
+pub async fn copy_state_to_clipboard<H>() -> Result<(), JsValue>
+where
+    H: Handle,
+    H::Handler: 'static,
+    <H::Handler as Handler>::Model: Serialize + 'static,
+{
+    copy_scope_to_clipboard::<H, H::Handler>().await
+}
+
+/// Copy current state of `SCOPE` to the clipboard, as JSON. Fails if the scope isn't running.
+pub async fn copy_scope_to_clipboard<H, SCOPE>() -> Result<(), JsValue>
+where
+    H: Handle,
+    H::Handler: 'static,
+    <H::Handler as Handler>::Model: Serialize + 'static,
+    SCOPE: 'static,
+{
+    let state = scope::current::<H::Handler, SCOPE>()
+        .ok_or_else(|| JsValue::from_str("scope isn't running"))?;
+    let json = sealed(|| serde_json::to_string_pretty(&*state))
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let clipboard = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .navigator()
+        .clipboard();
+
+    JsFuture::from(clipboard.write_text(&json)).await?;
+    Ok(())
+}
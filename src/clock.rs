@@ -0,0 +1,103 @@
+//! Time as seen by shared state.
+//!
+//! Everything in this crate that reads the time (rate limits, sync, presence) goes through
+//! [`now`], so tests can install a [`TestClock`] and move time by hand:
+//!
+//! ```ignore
+//! let clock = TestClock::default();
+//! yew_state::config().clock(clock.clone());
+//! dispatch.reduce(|cache| cache.fetched_at = clock::now());
+//! clock.advance(Duration::from_secs(60));
+//! ```
+//!
+//! Handlers of your own should read the time with [`now`] too.
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::config;
+
+/// Source of the current time.
+pub trait Clock {
+    /// Milliseconds since the Unix epoch.
+    fn now(&self) -> f64;
+}
+
+/// Wall-clock time. Used unless another clock is set with `config().clock()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        #[cfg(target_arch = "wasm32")]
+        {
+            js_sys::Date::now()
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0.0, |elapsed| elapsed.as_millis() as f64)
+        }
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time, so keep one to move the clock
+/// after setting it.
+#[derive(Clone, Debug, Default)]
+pub struct TestClock {
+    now: Rc<Cell<f64>>,
+}
+
+impl TestClock {
+    /// Clock starting at `now`, in milliseconds since the Unix epoch.
+    pub fn new(now: f64) -> Self {
+        Self {
+            now: Rc::new(Cell::new(now)),
+        }
+    }
+
+    /// Move the clock forward by `elapsed`.
+    pub fn advance(&self, elapsed: Duration) {
+        self.now
+            .set(self.now.get() + elapsed.as_secs_f64() * 1_000.0);
+    }
+
+    /// Set the clock to `now`, in milliseconds since the Unix epoch.
+    pub fn set(&self, now: f64) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+}
+
+/// Milliseconds since the Unix epoch, according to the configured clock.
+pub fn now() -> f64 {
+    match config::clock() {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+/// Milliseconds since an arbitrary point, with sub-millisecond precision where available. For
+/// measuring how long things take, so it doesn't follow the configured clock.
+#[cfg(any(feature = "profiler", feature = "tracing"))]
+pub(crate) fn monotonic() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|window| window.performance())
+            .map_or_else(js_sys::Date::now, |performance| performance.now())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        thread_local! {
+            static START: std::time::Instant = std::time::Instant::now();
+        }
+        START.with(|start| start.elapsed().as_secs_f64() * 1_000.0)
+    }
+}
@@ -0,0 +1,85 @@
+//! Coalescing bursts of labeled changes.
+//!
+//! Some changes fire many times in a row (on every `resize` or `scroll` event) where only the last
+//! one matters. Changes with coalesced labels are held until the next animation frame, and only
+//! the last change of each label is applied:
+//!
+//! ```ignore
+//! coalesce::labels::<SharedHandle<Layout>>(&["resize", "scroll"]);
+//!
+//! // On every resize event.
+//! handle.reduce_labeled("resize", move |layout| layout.width = width);
+//! ```
+//!
+//! Unlike a debounce, nothing waits on a timer, and order is kept with other changes: a change
+//! with another label applies any held changes first.
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::handle::Handle;
+use crate::handler::Handler;
+
+type StateHandler<H> = <H as Handle>::Handler;
+
+thread_local! {
+    /// Coalesced labels of every scope that has some, keyed by handler and scope.
+    static LABELS: RefCell<HashMap<(TypeId, TypeId), HashSet<&'static str>>> = Default::default();
+}
+
+/// Coalesce changes to shared state of handle type `H` labeled with any of `labels`, along with
+/// labels coalesced already.
+pub fn labels<H>(labels: &[&'static str])
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+{
+    labels_in::<H, StateHandler<H>>(labels)
+}
+
+/// Like `labels`, for shared state in `SCOPE`.
+pub fn labels_in<H, SCOPE>(labels: &[&'static str])
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let scope = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    LABELS.with(|coalesced| {
+        coalesced
+            .borrow_mut()
+            .entry(scope)
+            .or_default()
+            .extend(labels)
+    });
+}
+
+/// Stop coalescing changes to shared state of handle type `H` in `SCOPE`.
+pub fn reset<H, SCOPE>()
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let scope = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    LABELS.with(|coalesced| coalesced.borrow_mut().remove(&scope));
+}
+
+/// Whether changes with `label` are coalesced in a scope.
+pub(crate) fn coalesced<H, SCOPE>(label: Option<&str>) -> bool
+where
+    H: Handler + 'static,
+    SCOPE: 'static,
+{
+    let label = match label {
+        Some(label) => label,
+        None => return false,
+    };
+    let scope = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    LABELS.with(|coalesced| {
+        coalesced
+            .borrow()
+            .get(&scope)
+            .is_some_and(|labels| labels.contains(label))
+    })
+}
@@ -0,0 +1,229 @@
+//! Bridging shared state with other store crates, for migrating one component at a time.
+//!
+//! Components written against another crate (such as yewdux or yewtil) reach their store through a
+//! `Store`, which is small enough to implement over any of them. A `ScopeStore` is a `Store`
+//! backed by a yew-state scope, for components still written against the other crate once their
+//! state moved here:
+//!
+//! ```ignore
+//! let store = ScopeStore::<SharedHandle<Cart>>::new();
+//! legacy::CartBadge::mount(Rc::new(store));
+//! ```
+//!
+//! And the other way around, `mirror` keeps a scope in step with another crate's store, while
+//! that store is still where state lives:
+//!
+//! ```ignore
+//! struct YewduxCart(yewdux::Dispatch<BasicStore<Cart>>);
+//!
+//! impl compat::Store for YewduxCart {
+//!     type Model = Cart;
+//!     // Delegating to the dispatch.
+//! }
+//!
+//! let _mirror = compat::mirror::<SharedHandle<Cart>, _>(Rc::new(YewduxCart(dispatch)));
+//! ```
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use yew::Callback;
+
+use crate::dispatch::Dispatch;
+use crate::handle::Handle;
+use crate::handler::Handler;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+/// A change to state of a `Store`.
+pub type Reducer<T> = Box<dyn FnOnce(&mut T)>;
+
+/// State kept by a store crate, in the shape most of them share: state to read, changes to
+/// make, and subscribers to notify.
+pub trait Store {
+    type Model: Clone + 'static;
+
+    /// Current state.
+    fn state(&self) -> Rc<Self::Model>;
+
+    /// Change state, notifying subscribers.
+    fn reduce(&self, f: Reducer<Self::Model>);
+
+    /// Call `callback` with state on subscribing and after every change, until the returned guard
+    /// is dropped.
+    fn subscribe(&self, callback: Callback<Rc<Self::Model>>) -> StoreGuard;
+}
+
+/// Subscription made by `Store::subscribe`. Dropping it unsubscribes.
+pub struct StoreGuard {
+    _subscription: Box<dyn Any>,
+}
+
+impl StoreGuard {
+    /// Guard keeping `subscription` (whatever a store crate unsubscribes on drop) alive.
+    pub fn new(subscription: impl Any) -> Self {
+        Self {
+            _subscription: Box::new(subscription),
+        }
+    }
+}
+
+impl fmt::Debug for StoreGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreGuard").finish()
+    }
+}
+
+/// A `Store` backed by shared state of handle type `H` in `SCOPE`. State stays alive as long as
+/// the store does.
+pub struct ScopeStore<H, SCOPE = StateHandler<H>>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    dispatch: RefCell<Dispatch<H, SCOPE>>,
+    state: Rc<RefCell<Rc<Model<H>>>>,
+}
+
+impl<H, SCOPE> ScopeStore<H, SCOPE>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: Default,
+    SCOPE: 'static,
+{
+    pub fn new() -> Self {
+        let state: Rc<RefCell<Rc<Model<H>>>> = Default::default();
+        let dispatch = {
+            let state = state.clone();
+            Dispatch::new(Callback::from(move |new| *state.borrow_mut() = new))
+        };
+        Self {
+            dispatch: RefCell::new(dispatch),
+            state,
+        }
+    }
+}
+
+impl<H, SCOPE> Default for ScopeStore<H, SCOPE>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: Default,
+    SCOPE: 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H, SCOPE> Store for ScopeStore<H, SCOPE>
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: 'static,
+    SCOPE: 'static,
+{
+    type Model = Model<H>;
+
+    fn state(&self) -> Rc<Self::Model> {
+        self.state.borrow().clone()
+    }
+
+    fn reduce(&self, f: Reducer<Self::Model>) {
+        reduce(&self.dispatch, f)
+    }
+
+    fn subscribe(&self, callback: Callback<Rc<Self::Model>>) -> StoreGuard {
+        StoreGuard::new(Dispatch::<H, SCOPE>::new(callback))
+    }
+}
+
+impl<H, SCOPE> fmt::Debug for ScopeStore<H, SCOPE>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeStore").finish()
+    }
+}
+
+/// Keeps shared state of handle type `H` in step with `store` until dropped. Made by `mirror`.
+pub struct MirrorGuard {
+    _to_store: Box<dyn Any>,
+    _from_store: StoreGuard,
+}
+
+impl fmt::Debug for MirrorGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorGuard").finish()
+    }
+}
+
+/// Keep shared state of handle type `H` in step with `store`, both ways, until the returned guard
+/// is dropped. State starts from the store's.
+pub fn mirror<H, S>(store: Rc<S>) -> MirrorGuard
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: PartialEq + 'static,
+    S: Store<Model = Model<H>> + 'static,
+{
+    mirror_in::<H, StateHandler<H>, S>(store)
+}
+
+/// Like `mirror`, for shared state in `SCOPE`.
+pub fn mirror_in<H, SCOPE, S>(store: Rc<S>) -> MirrorGuard
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: PartialEq + 'static,
+    SCOPE: 'static,
+    S: Store<Model = Model<H>> + 'static,
+{
+    // Changes to the scope before the store's state is taken would overwrite it.
+    let seeded = Rc::new(Cell::new(false));
+    let from_store = {
+        let seeded = seeded.clone();
+        let dispatch = RefCell::new(Dispatch::<H, SCOPE>::connect());
+        store.subscribe(Callback::from(move |state: Rc<Model<H>>| {
+            let seeded = seeded.clone();
+            reduce(
+                &dispatch,
+                Box::new(move |local| {
+                    seeded.set(true);
+                    if *local != *state {
+                        *local = (*state).clone();
+                    }
+                }),
+            );
+        }))
+    };
+    let to_store = Dispatch::<H, SCOPE>::new(Callback::from(move |state: Rc<Model<H>>| {
+        if seeded.get() && *store.state() != *state {
+            store.reduce(Box::new(move |other| *other = (*state).clone()));
+        }
+    }));
+    MirrorGuard {
+        _to_store: Box::new(to_store),
+        _from_store: from_store,
+    }
+}
+
+/// Change state through `dispatch`, or a dispatch of its own if it's busy changing state already.
+fn reduce<H, SCOPE>(dispatch: &RefCell<Dispatch<H, SCOPE>>, f: Reducer<Model<H>>)
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    match dispatch.try_borrow_mut() {
+        Ok(mut dispatch) => dispatch.reduce(f),
+        Err(_) => Dispatch::<H, SCOPE>::connect().reduce(f),
+    }
+}
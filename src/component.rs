@@ -1,5 +1,40 @@
+pub mod boundary;
+#[cfg(feature = "breadcrumbs")]
+pub mod breadcrumbs;
+#[cfg(feature = "test-utils")]
+pub mod fixtures;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+#[cfg(feature = "announcer")]
+pub mod live_region;
+#[cfg(feature = "profiler")]
+pub mod profiler;
+#[cfg(feature = "test-utils")]
+pub mod provider;
+pub mod state_config;
+mod subscriptions;
+pub mod suspense;
+#[cfg(feature = "undo")]
+pub mod undo;
 pub mod view;
 pub mod wrapper;
 
+pub use boundary::{fallback, Recovery, StateErrorBoundary};
+#[cfg(feature = "breadcrumbs")]
+pub use breadcrumbs::Breadcrumbs;
+#[cfg(feature = "test-utils")]
+pub use fixtures::FixtureHarness;
+#[cfg(feature = "inspector")]
+pub use inspector::StateInspector;
+#[cfg(feature = "announcer")]
+pub use live_region::LiveRegion;
+#[cfg(feature = "profiler")]
+pub use profiler::StateProfiler;
+#[cfg(feature = "test-utils")]
+pub use provider::TestProvider;
+pub use state_config::StateConfig;
+pub use suspense::Suspense;
+#[cfg(feature = "undo")]
+pub use undo::UndoToasts;
 pub use view::{change, rendered, view, StateView};
 pub use wrapper::SharedStateComponent;
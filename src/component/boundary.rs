@@ -0,0 +1,154 @@
+//! Error boundary for shared state, rendering a fallback in place of children while an error is
+//! unrecovered.
+use std::rc::Rc;
+
+use yew::{html, Callback, Children, Component, ComponentLink, Html, Properties, ShouldRender};
+
+use crate::error::Error;
+use crate::handle::Handle;
+use crate::handler::{Handler, Request};
+use crate::{SharedState, SharedStateComponent};
+
+pub type Fallback = Rc<dyn Fn(&Error, &Recovery) -> Html>;
+
+/// Callbacks for recovering from an error.
+pub struct Recovery {
+    /// Render children again, keeping current state.
+    pub retry: Callback<()>,
+    /// Reset state to default, then render children again.
+    pub reset: Callback<()>,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props<H>
+where
+    H: Handle + Clone + Default,
+{
+    #[prop_or_default]
+    handle: H,
+    pub fallback: Fallback,
+    #[prop_or_default]
+    pub children: Children,
+}
+
+impl<H> SharedState for Props<H>
+where
+    H: Handle + Clone + Default,
+{
+    type Handle = H;
+
+    fn handle(&mut self) -> &mut Self::Handle {
+        &mut self.handle
+    }
+}
+
+pub enum Msg {
+    Retry,
+    Reset,
+}
+
+pub struct Model<H>
+where
+    H: Handle + Clone + Default + 'static,
+    <H::Handler as Handler>::Model: Default,
+{
+    props: Props<H>,
+    link: ComponentLink<Self>,
+    /// Error the user has already recovered from.
+    dismissed: Option<Rc<Error>>,
+}
+
+impl<H> Model<H>
+where
+    H: Handle + Clone + Default + 'static,
+    <H::Handler as Handler>::Model: Default,
+{
+    /// Current error, unless it was dismissed.
+    fn error(&self) -> Option<Rc<Error>> {
+        let error = self.props.handle.local_error()?;
+        match &self.dismissed {
+            Some(dismissed) if Rc::ptr_eq(dismissed, &error) => None,
+            _ => Some(error),
+        }
+    }
+}
+
+impl<H> Component for Model<H>
+where
+    H: Handle + Default + Clone + 'static,
+    <H::Handler as Handler>::Model: Default,
+{
+    type Message = Msg;
+    type Properties = Props<H>;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            dismissed: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Retry => {}
+            Msg::Reset => {
+                if let Some(callback) = self.props.handle.local_callback() {
+                    callback.emit(Request::ApplyOnce(Box::new(|state| {
+                        *state = Default::default()
+                    })));
+                }
+            }
+        }
+        self.dismissed = self.props.handle.local_error();
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        match self.error() {
+            Some(error) => {
+                let recovery = Recovery {
+                    retry: self.link.callback(|_| Msg::Retry),
+                    reset: self.link.callback(|_| Msg::Reset),
+                };
+                (self.props.fallback)(&error, &recovery)
+            }
+            None => html! { <>{ self.props.children.clone() }</> },
+        }
+    }
+}
+
+/// Renders `fallback` in place of its children when shared state reports an error.
+///
+/// ```ignore
+/// let fallback = component::fallback(|error: &Error, recovery: &Recovery| {
+///     html! {
+///         <>
+///         <p>{ error }</p>
+///         <button onclick=recovery.retry.reform(|_| ())>{"Retry"}</button>
+///         <button onclick=recovery.reset.reform(|_| ())>{"Reset"}</button>
+///         </>
+///     }
+/// });
+///
+/// html! {
+///     <StateErrorBoundary<SharedHandle<AppState>> fallback=fallback>
+///         <App />
+///     </StateErrorBoundary<SharedHandle<AppState>>>
+/// }
+/// ```
+pub type StateErrorBoundary<H, SCOPE = <H as Handle>::Handler> =
+    SharedStateComponent<Model<H>, SCOPE>;
+
+/// Wraps `f` in `Rc`. Helps with resolving type needed for fallback property.
+pub fn fallback<F>(f: F) -> Fallback
+where
+    F: Fn(&Error, &Recovery) -> Html + 'static,
+{
+    Rc::new(f)
+}
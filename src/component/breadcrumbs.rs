@@ -0,0 +1,66 @@
+use yew::{html, Component, ComponentLink, Html, ShouldRender};
+
+use crate::breadcrumbs::Crumb;
+use crate::component::SharedStateComponent;
+use crate::handle::BreadcrumbHandle;
+
+/// Navigation trail, as links to every page but the current one. Following a link goes back to
+/// it on the trail.
+///
+/// ```ignore
+/// html! {
+///     <header>
+///         <Breadcrumbs />
+///     </header>
+/// }
+/// ```
+pub type Breadcrumbs = SharedStateComponent<Trail>;
+
+pub struct Trail {
+    handle: BreadcrumbHandle,
+}
+
+impl Trail {
+    fn crumb(&self, index: usize, crumb: &Crumb) -> Html {
+        if index + 1 == self.handle.state().len() {
+            return html! {
+                <li aria-current="page">{ &crumb.label }</li>
+            };
+        }
+        let onclick = self
+            .handle
+            .reduce_callback(move |trail| trail.back_to(index));
+        html! {
+            <li><a href=crumb.href.clone() onclick=onclick>{ &crumb.label }</a></li>
+        }
+    }
+}
+
+impl Component for Trail {
+    type Message = ();
+    type Properties = BreadcrumbHandle;
+
+    fn create(handle: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { handle }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        self.handle = handle;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let state = self.handle.state();
+        html! {
+            <nav aria-label="Breadcrumb">
+                <ol>
+                    { for state.iter().enumerate().map(|(index, crumb)| self.crumb(index, crumb)) }
+                </ol>
+            </nav>
+        }
+    }
+}
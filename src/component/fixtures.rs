@@ -0,0 +1,130 @@
+use yew::virtual_dom::{VChild, VComp};
+use yew::{html, Children, Component, ComponentLink, Html, NodeRef, Properties, ShouldRender};
+
+use crate::component::provider::{self, TestProvider};
+use crate::fixtures;
+use crate::handle::Handle;
+use crate::handler::Handler;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+const BAR: &str = "display: flex; gap: 4px; padding: 4px; font: 12px monospace;";
+const SELECTED: &str = "font-weight: bold;";
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    /// Fixture shown first. Defaults to the first registered.
+    #[prop_or_default]
+    pub initial: Option<String>,
+    #[prop_or_default]
+    pub children: Children,
+}
+
+pub enum Msg {
+    Select(String),
+}
+
+/// Dev harness rendering its children against one registered fixture of `H`'s model at a time,
+/// with a button per fixture to switch between them. Children are created again on every switch,
+/// so they start from the fixture. Fixtures are forced with a `TestProvider`, see its caveats.
+///
+/// ```ignore
+/// html! {
+///     <FixtureHarness<SharedHandle<Cart>>>
+///         <CartBadge />
+///     </FixtureHarness<SharedHandle<Cart>>>
+/// }
+/// ```
+pub struct FixtureHarness<H, SCOPE = StateHandler<H>>
+where
+    H: Handle + Clone + 'static,
+    StateHandler<H>: 'static,
+    Model<H>: Clone,
+    SCOPE: 'static,
+{
+    props: Props,
+    selected: Option<String>,
+    link: ComponentLink<Self>,
+    _marker: std::marker::PhantomData<(H, SCOPE)>,
+}
+
+impl<H, SCOPE> Component for FixtureHarness<H, SCOPE>
+where
+    H: Handle + Clone + 'static,
+    StateHandler<H>: 'static,
+    Model<H>: Clone,
+    SCOPE: 'static,
+{
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let selected = props
+            .initial
+            .clone()
+            .or_else(|| fixtures::names::<Model<H>>().into_iter().next());
+        Self {
+            props,
+            selected,
+            link,
+            _marker: Default::default(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Select(name) => {
+                let changed = self.selected.as_ref() != Some(&name);
+                self.selected = Some(name);
+                changed
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let buttons = fixtures::names::<Model<H>>()
+            .into_iter()
+            .map(|name| {
+                let style = if self.selected.as_ref() == Some(&name) {
+                    SELECTED
+                } else {
+                    ""
+                };
+                let select = name.clone();
+                let onclick = self.link.callback(move |_| Msg::Select(select.clone()));
+                html! { <button style=style onclick=onclick>{ name }</button> }
+            })
+            .collect::<Html>();
+        let fixture = self
+            .selected
+            .as_ref()
+            .and_then(|name| Some((name, fixtures::get::<Model<H>>(name)?)));
+        let content = match fixture {
+            Some((name, state)) => {
+                // html! doesn't take components with more than one type parameter.
+                let provider = VChild::<TestProvider<H, SCOPE>>::new(
+                    provider::Props {
+                        state,
+                        children: self.props.children.clone(),
+                    },
+                    NodeRef::default(),
+                    None,
+                );
+                html! { <div key=name.clone()>{ VComp::from(provider) }</div> }
+            }
+            None => html! { <p>{ "No fixture registered." }</p> },
+        };
+        html! {
+            <>
+                <div style=BAR>{ buttons }</div>
+                { content }
+            </>
+        }
+    }
+}
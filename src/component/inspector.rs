@@ -0,0 +1,230 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use yew::{html, ChangeData, Component, ComponentLink, Html, ShouldRender};
+
+use crate::component::SharedStateComponent;
+use crate::config;
+use crate::handle::StateHandle;
+use crate::handler::{Handler, SharedHandler};
+use crate::redact;
+use crate::sensitive::sealed;
+
+const PANEL: &str =
+    "position: fixed; top: 8px; right: 8px; z-index: 2147483647; max-height: 90vh; \
+                     overflow: auto; padding: 6px 8px; background: rgba(20, 20, 20, 0.9); \
+                     color: #eee; font: 11px monospace; border-radius: 4px;";
+const NODE: &str = "margin-left: 12px;";
+const TOGGLE: &str = "cursor: pointer; user-select: none;";
+const INPUT: &str = "font: inherit; color: inherit; background: transparent; border: none; \
+                     border-bottom: 1px dotted #888;";
+const ERROR: &str = "color: #f88;";
+
+/// Dev-only panel showing state of `T` as a tree, for the scope of `SCOPE`. Objects and arrays
+/// collapse when their key is clicked, and other values are edited inline: an edit is parsed as
+/// JSON (falling back to a string), and replaces state if the result still deserializes to `T`.
+/// Arrays and objects show their first `config().debug_items(..)` items, and more when the row
+/// counting the rest is clicked.
+///
+/// ```ignore
+/// html! {
+///     <>
+///         <App />
+///         <StateInspector<AppState> />
+///     </>
+/// }
+/// ```
+pub type StateInspector<T, H = SharedHandler<T>, SCOPE = H> =
+    SharedStateComponent<Inspector<T, H>, SCOPE>;
+
+pub enum Msg {
+    /// Collapse or expand the node at a JSON pointer.
+    Toggle(String),
+    /// Replace the value at a JSON pointer with the given text.
+    Edit(String, String),
+    /// Show more items of the node at a JSON pointer.
+    More(String),
+}
+
+pub struct Inspector<T, H>
+where
+    T: Default + Clone + Serialize + DeserializeOwned + 'static,
+    H: Handler<Model = T> + 'static,
+{
+    handle: StateHandle<T, H>,
+    collapsed: HashSet<String>,
+    /// Items shown of nodes showing more than the default.
+    shown: HashMap<String, usize>,
+    /// Why the last edit wasn't applied.
+    error: Option<String>,
+    link: ComponentLink<Self>,
+}
+
+impl<T, H> Inspector<T, H>
+where
+    T: Default + Clone + Serialize + DeserializeOwned + 'static,
+    H: Handler<Model = T> + 'static,
+{
+    fn edit(&self, pointer: &str, text: String) -> Result<(), String> {
+        let mut tree = serde_json::to_value(self.handle.state()).map_err(|e| e.to_string())?;
+        let value = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        match tree.pointer_mut(pointer) {
+            Some(node) => *node = value,
+            None => return Err(format!("nothing at {}", pointer)),
+        }
+        let state: T = serde_json::from_value(tree).map_err(|e| e.to_string())?;
+        self.handle.reduce(move |current| *current = state);
+        Ok(())
+    }
+
+    /// Items shown of the node at a JSON pointer.
+    fn shown(&self, pointer: &str) -> usize {
+        match config::debug_items() {
+            0 => usize::MAX,
+            items => self.shown.get(pointer).copied().unwrap_or(items),
+        }
+    }
+
+    fn node(&self, key: Html, pointer: String, value: &Value) -> Html {
+        let children: Vec<(String, &Value)> = match value {
+            Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect(),
+            leaf => return self.leaf(key, pointer, leaf),
+        };
+        let collapsed = self.collapsed.contains(&pointer);
+        let (open, close) = match value {
+            Value::Array(_) => ("[", "]"),
+            _ => ("{", "}"),
+        };
+        let summary = if collapsed {
+            format!("{}{} {}", open, children.len(), close)
+        } else {
+            open.to_string()
+        };
+        let toggle = {
+            let pointer = pointer.clone();
+            self.link.callback(move |_| Msg::Toggle(pointer.clone()))
+        };
+        let body = if collapsed {
+            html! {}
+        } else {
+            let len = children.len();
+            let shown = self.shown(&pointer);
+            let more = if len > shown {
+                let more = {
+                    let pointer = pointer.clone();
+                    self.link.callback(move |_| Msg::More(pointer.clone()))
+                };
+                html! {
+                    <div style=NODE>
+                        <span style=TOGGLE onclick=more>{ format!("… {} more", len - shown) }</span>
+                    </div>
+                }
+            } else {
+                html! {}
+            };
+            let children: Html = children
+                .into_iter()
+                .take(shown)
+                .map(|(child, value)| {
+                    let pointer = format!("{}/{}", pointer, escape(&child));
+                    self.node(html! { <>{ child }{ ": " }</> }, pointer, value)
+                })
+                .collect();
+            html! { <>{ children }{ more }<div>{ close }</div></> }
+        };
+        let arrow = if collapsed { "▸ " } else { "▾ " };
+        html! {
+            <div style=NODE>
+                <span style=TOGGLE onclick=toggle>{ arrow }{ key }{ summary }</span>
+                { body }
+            </div>
+        }
+    }
+
+    fn leaf(&self, key: Html, pointer: String, value: &Value) -> Html {
+        let text = match value {
+            Value::String(text) => text.clone(),
+            value => value.to_string(),
+        };
+        let onchange = self.link.batch_callback(move |change| match change {
+            ChangeData::Value(text) => vec![Msg::Edit(pointer.clone(), text)],
+            _ => vec![],
+        });
+        html! {
+            <div style=NODE>
+                { key }<input style=INPUT value=text onchange=onchange />
+            </div>
+        }
+    }
+}
+
+/// Escape a key for use in a JSON pointer.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+impl<T, H> Component for Inspector<T, H>
+where
+    T: Default + Clone + Serialize + DeserializeOwned + 'static,
+    H: Handler<Model = T> + 'static,
+{
+    type Message = Msg;
+    type Properties = StateHandle<T, H>;
+
+    fn create(handle: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            handle,
+            collapsed: HashSet::new(),
+            shown: HashMap::new(),
+            error: None,
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Toggle(pointer) => {
+                if !self.collapsed.remove(&pointer) {
+                    self.collapsed.insert(pointer);
+                }
+            }
+            Msg::Edit(pointer, text) => self.error = self.edit(&pointer, text).err(),
+            Msg::More(pointer) => {
+                let shown = self.shown(&pointer);
+                self.shown.insert(pointer, shown + config::debug_items());
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        self.handle = handle;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let tree = match redact::show(self.handle.state())
+            .map_or_else(|| sealed(|| serde_json::to_value(self.handle.state())), Ok)
+        {
+            Ok(tree) => self.node(html! { std::any::type_name::<T>() }, String::new(), &tree),
+            Err(error) => html! { <div style=ERROR>{ error.to_string() }</div> },
+        };
+        html! {
+            <div style=PANEL>
+                { tree }
+                {
+                    match &self.error {
+                        Some(error) => html! { <div style=ERROR>{ error }</div> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use yew::{html, Component, ComponentLink, Html, ShouldRender};
+
+use crate::announce::{Announcement, Politeness};
+use crate::component::SharedStateComponent;
+use crate::handle::AnnouncerHandle;
+
+/// Visually hidden, but still read by screen readers.
+const HIDDEN: &str = "position: absolute; width: 1px; height: 1px; margin: -1px; padding: 0; \
+                      overflow: hidden; clip: rect(0, 0, 0, 0); white-space: nowrap; border: 0;";
+
+/// ARIA live regions reading out announcements queued with `announce`. Render it anywhere, once.
+///
+/// ```ignore
+/// html! {
+///     <>
+///         <App />
+///         <LiveRegion />
+///     </>
+/// }
+/// ```
+pub type LiveRegion = SharedStateComponent<Region>;
+
+pub struct Region {
+    handle: AnnouncerHandle,
+}
+
+impl Region {
+    fn region(&self, politeness: Politeness) -> Html {
+        let (live, role) = match politeness {
+            Politeness::Polite => ("polite", "status"),
+            Politeness::Assertive => ("assertive", "alert"),
+        };
+        let state = self.handle.state();
+        html! {
+            <div style=HIDDEN aria-live=live role=role>
+                { for state.iter(politeness).map(message) }
+            </div>
+        }
+    }
+}
+
+impl Component for Region {
+    type Message = ();
+    type Properties = AnnouncerHandle;
+
+    fn create(handle: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { handle }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        self.handle = handle;
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <>
+                { self.region(Politeness::Polite) }
+                { self.region(Politeness::Assertive) }
+            </>
+        }
+    }
+}
+
+/// Keyed by id, so a repeated message is a new node and read out again.
+fn message(announcement: &Announcement) -> Html {
+    html! { <p key=announcement.id.to_string()>{ &announcement.message }</p> }
+}
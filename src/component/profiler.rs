@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use yew::{
+    html,
+    services::{interval::IntervalTask, IntervalService},
+    Component, ComponentLink, Html, ShouldRender,
+};
+
+use crate::profiler::{self, ScopeStats};
+
+/// Milliseconds between refreshes.
+const REFRESH: u64 = 500;
+
+const PANEL: &str = "position: fixed; bottom: 8px; right: 8px; z-index: 2147483647; \
+                     padding: 6px 8px; background: rgba(20, 20, 20, 0.85); color: #eee; \
+                     font: 11px monospace; border-radius: 4px; pointer-events: none;";
+
+pub enum Msg {
+    Refresh,
+}
+
+/// Floating panel with statistics of every running scope: requests per second, how long the last
+/// request took, subscribers, and state size. Meant for development; render it anywhere, once.
+///
+/// ```ignore
+/// html! {
+///     <>
+///         <App />
+///         <StateProfiler />
+///     </>
+/// }
+/// ```
+pub struct StateProfiler {
+    scopes: Vec<ScopeStats>,
+    _refresh: IntervalTask,
+}
+
+impl Component for StateProfiler {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            scopes: profiler::scopes(),
+            _refresh: IntervalService::spawn(
+                Duration::from_millis(REFRESH),
+                link.callback(|_| Msg::Refresh),
+            ),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Refresh => {
+                let scopes = profiler::scopes();
+                let changed = scopes != self.scopes;
+                self.scopes = scopes;
+                changed
+            }
+        }
+    }
+
+    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div style=PANEL>
+                <table>
+                    <tr>
+                        <th>{ "scope" }</th>
+                        <th>{ "req/s" }</th>
+                        <th>{ "last ms" }</th>
+                        <th>{ "subs" }</th>
+                        <th>{ "bytes" }</th>
+                    </tr>
+                    { for self.scopes.iter().map(row) }
+                </table>
+            </div>
+        }
+    }
+}
+
+fn row(stats: &ScopeStats) -> Html {
+    html! {
+        <tr>
+            <td>{ &stats.name }</td>
+            <td>{ stats.rate }</td>
+            <td>{ format!("{:.2}", stats.last_duration) }</td>
+            <td>{ stats.subscribers }</td>
+            <td>{ stats.size }</td>
+        </tr>
+    }
+}
@@ -0,0 +1,91 @@
+//! Providing fixed state to components, for tests and component galleries.
+use std::rc::Rc;
+
+use yew::{html, Children, Component, ComponentLink, Html, Properties, ShouldRender};
+
+use crate::handle::Handle;
+use crate::handler::Handler;
+use crate::scope;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+#[derive(Properties, Clone)]
+pub struct Props<H>
+where
+    H: Handle + Clone,
+    Model<H>: Clone,
+{
+    /// State children see.
+    pub state: Rc<Model<H>>,
+    #[prop_or_default]
+    pub children: Children,
+}
+
+/// Forces state of handle type `H` in `SCOPE` for components created while it's mounted, such as
+/// its children, so they can be rendered against fixed state in tests and component galleries.
+///
+/// Those components don't connect to shared state: each gets its own copy of `state`, which only
+/// it sees changes to. Providers nest, with the innermost one winning.
+///
+/// State is forced for the whole thread, not just the provider's children: components of the same
+/// handle and scope created elsewhere while it's mounted get `state` too. Mount it on its own, or
+/// give galleries a scope of their own. Needs the `test-utils` feature.
+///
+/// ```ignore
+/// html! {
+///     <TestProvider<SharedHandle<Cart>> state=Rc::new(Cart::with_items(3))>
+///         <CartBadge />
+///     </TestProvider<SharedHandle<Cart>>>
+/// }
+/// ```
+pub struct TestProvider<H, SCOPE = StateHandler<H>>
+where
+    H: Handle + Clone + 'static,
+    StateHandler<H>: 'static,
+    Model<H>: Clone,
+    SCOPE: 'static,
+{
+    props: Props<H>,
+    _scope: std::marker::PhantomData<SCOPE>,
+}
+
+impl<H, SCOPE> Component for TestProvider<H, SCOPE>
+where
+    H: Handle + Clone + 'static,
+    StateHandler<H>: 'static,
+    Model<H>: Clone,
+    SCOPE: 'static,
+{
+    type Message = ();
+    type Properties = Props<H>;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        scope::force::<StateHandler<H>, SCOPE>(props.state.clone());
+        Self {
+            props,
+            _scope: Default::default(),
+        }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if !Rc::ptr_eq(&self.props.state, &props.state) {
+            scope::restore::<StateHandler<H>, SCOPE>(&self.props.state);
+            scope::force::<StateHandler<H>, SCOPE>(props.state.clone());
+        }
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! { <>{ self.props.children.clone() }</> }
+    }
+
+    fn destroy(&mut self) {
+        scope::restore::<StateHandler<H>, SCOPE>(&self.props.state);
+    }
+}
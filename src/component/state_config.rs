@@ -0,0 +1,116 @@
+//! Configuration of a wrapped component, set from its properties.
+//!
+//! Instead of a type alias for every scope, and policies set elsewhere before the component is
+//! created, properties can describe how their wrapper behaves:
+//!
+//! ```ignore
+//! impl SharedState for Props {
+//!     type Handle = SharedHandle<Feed>;
+//!
+//!     fn handle(&mut self) -> &mut Self::Handle {
+//!         &mut self.handle
+//!     }
+//!
+//!     fn config(&self) -> StateConfig<Self> {
+//!         StateConfig::new()
+//!             .scope::<Sidebar>()
+//!             .render_if(|old, new| old.items != new.items)
+//!             .notify(NotifyPolicy::OnChange)
+//!             .fallback(html! { <Spinner /> })
+//!     }
+//! }
+//! ```
+//!
+//! The configuration is read again whenever properties change, so a component may move to another
+//! scope along with its properties.
+use yew::Html;
+
+use super::wrapper::ScopeKey;
+use crate::handle::{Handle, SharedState};
+use crate::handler::Handler;
+use crate::notify::{self, NotifyPolicy, Policy};
+
+type StateHandler<P> = <<P as SharedState>::Handle as Handle>::Handler;
+type Model<P> = <StateHandler<P> as Handler>::Model;
+/// Given state before and after a change, whether to render it.
+type RenderIf<T> = fn(&T, &T) -> bool;
+
+/// Builder for the configuration of a wrapped component with properties `P`. Anything not set is
+/// left to the wrapper's type parameters and the `SharedState` trait.
+pub struct StateConfig<P>
+where
+    P: SharedState,
+{
+    pub(crate) scope: Option<ScopeKey<StateHandler<P>>>,
+    pub(crate) render: Option<RenderIf<Model<P>>>,
+    pub(crate) notify: Option<Policy<Model<P>>>,
+    pub(crate) fallback: Option<Html>,
+}
+
+impl<P> Default for StateConfig<P>
+where
+    P: SharedState,
+{
+    fn default() -> Self {
+        Self {
+            scope: None,
+            render: None,
+            notify: None,
+            fallback: None,
+        }
+    }
+}
+
+impl<P> StateConfig<P>
+where
+    P: SharedState,
+{
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Share state in `SCOPE`, instead of the wrapper's scope type parameter.
+    pub fn scope<SCOPE: 'static>(mut self) -> Self
+    where
+        StateHandler<P>: Clone + 'static,
+    {
+        self.scope = Some(ScopeKey::of::<SCOPE>());
+        self
+    }
+
+    /// Only render when `f` returns `true`, given state before and after a change. Other changes
+    /// are still passed to the component, just not rendered until something else renders.
+    pub fn render_if(mut self, f: RenderIf<Model<P>>) -> Self {
+        self.render = Some(f);
+        self
+    }
+
+    /// Notify subscribers of the scope according to `policy`, as `notify::policy_in` does, once
+    /// the component connects.
+    pub fn notify(mut self, policy: NotifyPolicy<Model<P>>) -> Self
+    where
+        Model<P>: PartialEq + 'static,
+    {
+        self.notify = Some(notify::compile(policy));
+        self
+    }
+
+    /// Rendered until the first state arrives, in place of `SharedState::fallback`.
+    pub fn fallback(mut self, fallback: Html) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// The same configuration, for other properties with the same handle.
+    pub(crate) fn cast<Q>(self) -> StateConfig<Q>
+    where
+        Q: SharedState<Handle = P::Handle>,
+    {
+        StateConfig {
+            scope: self.scope,
+            render: self.render,
+            notify: self.notify,
+            fallback: self.fallback,
+        }
+    }
+}
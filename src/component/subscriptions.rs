@@ -0,0 +1,147 @@
+//! Subscribers of a scope's shared state. Kept apart from the agent and its model, so it's
+//! compiled once rather than for every model and scope.
+use std::any::Any;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use yew::agent::HandlerId;
+
+use crate::handler::Priority;
+
+/// Sends a response of the agent (a `Response` of its model) to a subscriber.
+pub(crate) type Respond = Rc<dyn Fn(HandlerId, &dyn Any)>;
+
+/// A subscriber of shared state.
+#[derive(Default)]
+struct Subscriber {
+    priority: Priority,
+    /// Paused subscribers aren't notified of changes.
+    paused: bool,
+    /// Waiting for initial state, along with others connecting at the same time.
+    bootstrapping: bool,
+}
+
+/// Subscribers of shared state, and how to send them responses.
+pub(crate) struct Subscriptions {
+    subscribers: HashMap<HandlerId, Subscriber>,
+    respond: Respond,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(respond: Respond) -> Self {
+        Self {
+            subscribers: Default::default(),
+            respond,
+        }
+    }
+
+    #[cfg(any(feature = "profiler", feature = "metrics"))]
+    pub(crate) fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    /// Whether any subscriber is notified of changes: one that isn't paused, nor waiting for
+    /// initial state.
+    pub(crate) fn notified(&self) -> bool {
+        self.subscribers
+            .values()
+            .any(|subscriber| !subscriber.paused && !subscriber.bootstrapping)
+    }
+
+    /// Add a subscriber, waiting for initial state if `bootstrapping`.
+    pub(crate) fn connect(&mut self, who: HandlerId, bootstrapping: bool) {
+        self.subscribers.insert(
+            who,
+            Subscriber {
+                bootstrapping,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub(crate) fn disconnect(&mut self, who: HandlerId) {
+        self.subscribers.remove(&who);
+    }
+
+    pub(crate) fn set_priority(&mut self, who: HandlerId, priority: Priority) {
+        if let Some(subscriber) = self.subscribers.get_mut(&who) {
+            subscriber.priority = priority;
+        }
+    }
+
+    pub(crate) fn pause(&mut self, who: HandlerId) {
+        if let Some(subscriber) = self.subscribers.get_mut(&who) {
+            subscriber.paused = true;
+        }
+    }
+
+    /// Resume a subscriber, returning whether it was paused.
+    pub(crate) fn resume(&mut self, who: HandlerId) -> bool {
+        match self.subscribers.get_mut(&who) {
+            Some(subscriber) if subscriber.paused => {
+                subscriber.paused = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Stop a subscriber waiting for initial state, returning whether it's still subscribed.
+    pub(crate) fn bootstrapped(&mut self, who: HandlerId) -> bool {
+        match self.subscribers.get_mut(&who) {
+            Some(subscriber) => {
+                subscriber.bootstrapping = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Subscribers waiting for initial state.
+    pub(crate) fn waiting(&self) -> Vec<HandlerId> {
+        self.subscribers
+            .iter()
+            .filter(|(_, subscriber)| subscriber.bootstrapping)
+            .map(|(who, _)| *who)
+            .collect()
+    }
+
+    pub(crate) fn respond(&self, who: HandlerId, response: &dyn Any) {
+        (self.respond)(who, response)
+    }
+
+    /// Send `response` to subscribers, paused ones too if `paused`.
+    pub(crate) fn broadcast(&self, response: &dyn Any, paused: bool) {
+        for who in self.ordered(paused) {
+            self.respond(who, response);
+        }
+    }
+
+    /// Like `broadcast` to subscribers that aren't paused, returning a function that sends it
+    /// later to those subscribed now.
+    pub(crate) fn broadcast_later(&self, response: Box<dyn Any>) -> Box<dyn FnOnce()> {
+        let respond = self.respond.clone();
+        let subscribers = self.ordered(false);
+        Box::new(move || {
+            for who in subscribers {
+                respond(who, &*response);
+            }
+        })
+    }
+
+    /// Subscribers in the order they should be notified, paused ones too if `paused`. Those
+    /// waiting for initial state are left out, as it will be current when they get it.
+    fn ordered(&self, paused: bool) -> Vec<HandlerId> {
+        let mut subscribers: Vec<_> = self
+            .subscribers
+            .iter()
+            .filter(|(_, subscriber)| !subscriber.bootstrapping && (paused || !subscriber.paused))
+            .collect();
+        subscribers.sort_by_key(|(_, subscriber)| subscriber.priority);
+        subscribers.into_iter().map(|(who, _)| *who).collect()
+    }
+}
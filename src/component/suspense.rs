@@ -0,0 +1,92 @@
+//! Rendering a fallback in place of a wrapped component until its first state arrives. Made by
+//! `SharedStateComponent::with_fallback`.
+use std::rc::Rc;
+
+use yew::{html, Component, ComponentLink, Html, Properties, ShouldRender};
+
+use super::state_config::StateConfig;
+use crate::handle::{Handle, SharedState};
+use crate::handler::{Handler, Priority};
+
+type StateHandler<P> = <<P as SharedState>::Handle as Handle>::Handler;
+type Model<P> = <StateHandler<P> as Handler>::Model;
+
+#[derive(Properties, Clone)]
+pub struct Props<P>
+where
+    P: SharedState + Properties + Clone,
+{
+    /// Properties of the wrapped component.
+    pub props: P,
+    /// Rendered until the first state arrives.
+    pub fallback: Html,
+}
+
+impl<P> SharedState for Props<P>
+where
+    P: SharedState + Properties + Clone,
+{
+    type Handle = P::Handle;
+
+    fn handle(&mut self) -> &mut Self::Handle {
+        self.props.handle()
+    }
+
+    fn fallback(&self) -> Option<Html> {
+        Some(self.fallback.clone())
+    }
+
+    fn priority(&self) -> Priority {
+        self.props.priority()
+    }
+
+    fn initial(&self) -> Option<Rc<Model<Self>>> {
+        self.props.initial()
+    }
+
+    fn lazy(&self) -> bool {
+        self.props.lazy()
+    }
+
+    fn config(&self) -> StateConfig<Self> {
+        self.props.config().cast().fallback(self.fallback.clone())
+    }
+}
+
+/// Renders `C` once the wrapper has state for it.
+pub struct Suspense<C>
+where
+    C: Component,
+    C::Properties: SharedState + Clone,
+{
+    props: Props<C::Properties>,
+}
+
+impl<C> Component for Suspense<C>
+where
+    C: Component,
+    C::Properties: SharedState + Clone,
+{
+    type Message = ();
+    type Properties = Props<C::Properties>;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let props = self.props.props.clone();
+        html! {
+            <C with props />
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use yew::{html, Callback, Component, ComponentLink, Html, ShouldRender};
+
+use crate::component::SharedStateComponent;
+use crate::handle::UndoHandle;
+use crate::undo::{Pending, Undoable};
+
+/// A toast for every change that can still be undone, with a button to undo it. Render it
+/// anywhere, once.
+///
+/// ```ignore
+/// html! {
+///     <>
+///         <App />
+///         <UndoToasts />
+///     </>
+/// }
+/// ```
+pub type UndoToasts = SharedStateComponent<Toasts>;
+
+pub struct Toasts {
+    handle: UndoHandle,
+}
+
+impl Component for Toasts {
+    type Message = ();
+    type Properties = UndoHandle;
+
+    fn create(handle: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Self { handle }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+        self.handle = handle;
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="undo-toasts">
+                { for self.handle.state().iter().map(toast) }
+            </div>
+        }
+    }
+}
+
+fn toast(pending: &Pending) -> Html {
+    let undoable = Undoable::from_id(pending.id);
+    html! {
+        <div class="undo-toast" role="status" key=pending.id.to_string()>
+            <span>{ &pending.message }</span>
+            <button onclick=Callback::from(move |_| undoable.undo())>{ "Undo" }</button>
+        </div>
+    }
+}
@@ -1,37 +1,256 @@
 //! Wrapper for components with shared state.
-use std::collections::HashSet;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
+use std::time::Duration;
 
 use yew::{
     agent::{Agent, AgentLink, Bridge, Bridged, Context, HandlerId},
     prelude::*,
 };
 
+use super::state_config::StateConfig;
+use super::subscriptions::{Respond, Subscriptions};
+use super::suspense::Suspense;
+use crate::authorize;
+use crate::coalesce;
+use crate::config;
+use crate::error::Error;
 use crate::handle::{Handle, SharedState};
-use crate::handler::{Handler, Reduction, ReductionOnce};
+use crate::handler::{Changes, Detached, Handler, Priority, Reduction, Request};
+#[cfg(feature = "metrics")]
+use crate::metrics::{Counter, Gauge};
+use crate::notify;
+use crate::power::{self, PowerGuard};
+use crate::progress::Progress;
+use crate::schedule::{self, Control, Timing};
+use crate::scope;
+use crate::telemetry;
+use crate::watch::Watcher;
 
-enum Request<T> {
-    /// Apply a state change.
-    Apply(Reduction<T>),
-    /// Apply a state change once.
-    ApplyOnce(ReductionOnce<T>),
-}
-
-enum Response<T> {
+pub(crate) enum Response<T> {
     /// Update subscribers with current state.
     State(Rc<T>),
+    /// Update subscribers with changes to state.
+    Patch(Reduction<T>),
+    /// Notify subscribers of an error.
+    Error(Rc<Error>),
+    /// Notify subscribers of progress, or that it's cleared.
+    Progress(Option<Rc<Progress>>),
+}
+
+impl<T> Clone for Response<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Response::State(state) => Response::State(state.clone()),
+            Response::Patch(patch) => Response::Patch(patch.clone()),
+            Response::Error(error) => Response::Error(error.clone()),
+            Response::Progress(progress) => Response::Progress(progress.clone()),
+        }
+    }
+}
+
+/// Idempotency keys remembered per scope. Older keys are forgotten, so a change retried after
+/// this many others may be applied twice.
+const KEYS: usize = 1_024;
+
+/// Idempotency keys of recently applied changes.
+#[derive(Default)]
+struct RecentKeys {
+    keys: HashSet<String>,
+    /// Oldest first.
+    order: VecDeque<String>,
+}
+
+impl RecentKeys {
+    fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    fn insert(&mut self, key: String) {
+        if self.keys.insert(key.clone()) {
+            self.order.push_back(key);
+        }
+        while self.order.len() > KEYS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub(crate) enum Msg {
+    /// Apply changes deferred by the rate limit.
+    #[cfg(feature = "rate-limit")]
+    Wake,
+    /// Send initial state to subscribers waiting for it.
+    Bootstrap,
+    /// Apply coalesced changes.
+    Flush,
+    /// Change when a scheduled change applies.
+    Timing(u64, Timing),
+    /// Apply a scheduled change, its delay having passed.
+    Due(u64),
+    /// Catch up after the page was hidden or asleep.
+    #[cfg(feature = "wake")]
+    Woke,
+    /// Low power mode was switched.
+    Power(bool),
+}
+
+/// A change waiting for its delay to pass, or recurring every so often.
+struct Scheduled<T> {
+    task: Task<T>,
+    timer: Timer,
+}
+
+enum Task<T> {
+    Once(Request<T>),
+    Every(Reduction<T>),
+}
+
+impl<T> Scheduled<T> {
+    fn new(task: Task<T>, delay: Duration, who: HandlerId) -> Self {
+        let recurring = matches!(task, Task::Every(_));
+        Self {
+            task,
+            timer: Timer::new(delay, recurring, who),
+        }
+    }
+}
+
+/// When a scheduled change is due. Kept apart from the change, so it's compiled once rather than
+/// for every scope.
+struct Timer {
+    /// Delay, or interval of a recurring change.
+    delay: Duration,
+    recurring: bool,
+    /// Whether a recurring change is paused.
+    paused: bool,
+    /// Subscriber that scheduled it.
+    who: HandlerId,
+    #[cfg(target_arch = "wasm32")]
+    task: Option<Box<dyn yew::services::Task>>,
+}
+
+/// What to do with a scheduled change once its timing changed.
+enum Due {
+    Later,
+    Now,
+    Never,
+}
+
+impl Timer {
+    fn new(delay: Duration, recurring: bool, who: HandlerId) -> Self {
+        Self {
+            delay,
+            recurring,
+            paused: false,
+            who,
+            #[cfg(target_arch = "wasm32")]
+            task: None,
+        }
+    }
+
+    /// Whether it's recurring and not paused.
+    fn running(&self) -> bool {
+        self.recurring && !self.paused
+    }
+
+    /// Start over, calling `due` when it's due. Timers only run in the browser.
+    #[cfg(target_arch = "wasm32")]
+    fn start(&mut self, due: Callback<()>) {
+        use yew::services::{IntervalService, TimeoutService};
+
+        self.task = Some(if self.recurring {
+            Box::new(IntervalService::spawn(power::stretch(self.delay), due))
+        } else {
+            Box::new(TimeoutService::spawn(self.delay, due))
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start(&mut self, _due: Callback<()>) {}
+
+    fn stop(&mut self) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.task = None;
+        }
+    }
+
+    /// Change when it's due, calling `due` when it is.
+    fn time(&mut self, timing: Timing, due: Callback<()>) -> Due {
+        match timing {
+            Timing::Cancel => return Due::Never,
+            Timing::Now => return Due::Now,
+            Timing::After(delay) if delay == Duration::ZERO && !self.recurring => return Due::Now,
+            Timing::After(delay) => {
+                self.delay = delay;
+                if !self.paused {
+                    self.start(due);
+                }
+            }
+            Timing::Pause => {
+                self.paused = true;
+                self.stop();
+            }
+            Timing::Resume if self.paused => {
+                self.paused = false;
+                self.start(due);
+            }
+            Timing::Resume => {}
+        }
+        Due::Later
+    }
 }
 
 /// Context agent for managing shared state. In charge of applying changes to state then notifying
 /// subscribers of new state.
-struct SharedStateService<T, SCOPE>
+pub(crate) struct SharedStateService<T, SCOPE>
 where
     T: Handler + Clone + 'static,
     SCOPE: 'static,
 {
     handler: T,
-    subscriptions: HashSet<HandlerId>,
+    subscriptions: Subscriptions,
     link: AgentLink<SharedStateService<T, SCOPE>>,
+    /// Last error, sent to subscribers when they connect.
+    error: Option<Rc<Error>>,
+    /// Current progress, sent to subscribers when they connect.
+    progress: Option<Rc<Progress>>,
+    /// Whether state may still be seeded by the first subscriber.
+    seedable: bool,
+    /// Whether the notify policy held back a patch, so subscribers need entire state next time.
+    stale: bool,
+    watchers: Vec<Watcher<<T as Handler>::Model>>,
+    /// Keys of idempotent changes applied recently, which aren't applied again.
+    applied: RecentKeys,
+    /// Whether subscribers are waiting for initial state.
+    bootstrapping: bool,
+    /// Whether a request is being handled.
+    handling: bool,
+    /// Changes waiting for their delay to pass, by id.
+    scheduled: HashMap<u64, Scheduled<<T as Handler>::Model>>,
+    /// Id of the next scheduled change.
+    next_scheduled: u64,
+    /// Coalesced changes waiting for their burst to end, oldest first.
+    held: VecDeque<Request<<T as Handler>::Model>>,
+    /// Applies held changes on the next animation frame.
+    #[cfg(target_arch = "wasm32")]
+    frame: Option<yew::services::render::RenderTask>,
+    /// Requests sent while handling another, oldest first.
+    nested: VecDeque<(Request<<T as Handler>::Model>, HandlerId)>,
+    /// Changes deferred by the rate limit, oldest first.
+    #[cfg(feature = "rate-limit")]
+    deferred: VecDeque<Request<<T as Handler>::Model>>,
+    /// Wakes the service once there's capacity for deferred changes.
+    #[cfg(feature = "rate-limit")]
+    wake: Option<yew::services::timeout::TimeoutTask>,
+    #[cfg(feature = "wake")]
+    _woke: crate::wake::WakeGuard,
+    _power: PowerGuard,
 }
 
 impl<T, SCOPE> Agent for SharedStateService<T, SCOPE>
@@ -39,66 +258,779 @@ where
     T: Handler + Clone + 'static,
     SCOPE: 'static,
 {
-    type Message = ();
+    type Message = Msg;
     type Reach = Context<Self>;
     type Input = Request<<T as Handler>::Model>;
     type Output = Response<<T as Handler>::Model>;
 
     fn create(link: AgentLink<Self>) -> Self {
+        let mut handler = <T as Handler>::new();
+        // Seed state from parent scope.
+        if let Some(state) = scope::inherited::<T, SCOPE>() {
+            handler.apply_once(Box::new(move |local| *local = (*state).clone()));
+            // Subscribers get all of it as they connect.
+            handler.changes();
+        }
+        scope::publish::<T, SCOPE>(handler.state());
+        let error = handler.take_error().map(Rc::new);
+        let power = {
+            let switched = link.callback(Msg::Power);
+            power::watch(move |low| switched.emit(low))
+        };
+        let respond: Respond = {
+            let link = link.clone();
+            Rc::new(move |who, response: &dyn Any| {
+                if let Some(response) = response.downcast_ref::<Response<T::Model>>() {
+                    #[cfg(target_arch = "wasm32")]
+                    crate::loops::notifying(|| link.respond(who, response.clone()));
+                    #[cfg(not(target_arch = "wasm32"))]
+                    link.respond(who, response.clone());
+                }
+            })
+        };
+        #[cfg(feature = "wake")]
+        let woke = {
+            let woke = link.callback(|_| Msg::Woke);
+            crate::wake::on_wake(move |_| woke.emit(()))
+        };
+
         Self {
-            handler: <T as Handler>::new(),
-            subscriptions: Default::default(),
+            handler,
+            subscriptions: Subscriptions::new(respond),
             link,
+            error,
+            progress: None,
+            seedable: true,
+            stale: false,
+            watchers: Vec::new(),
+            applied: Default::default(),
+            bootstrapping: false,
+            scheduled: HashMap::new(),
+            next_scheduled: 0,
+            held: VecDeque::new(),
+            #[cfg(target_arch = "wasm32")]
+            frame: None,
+            handling: false,
+            nested: VecDeque::new(),
+            #[cfg(feature = "rate-limit")]
+            deferred: Default::default(),
+            #[cfg(feature = "rate-limit")]
+            wake: None,
+            #[cfg(feature = "wake")]
+            _woke: woke,
+            _power: power,
         }
     }
 
-    fn update(&mut self, _msg: Self::Message) {}
-
-    fn handle_input(&mut self, msg: Self::Input, _who: HandlerId) {
+    fn update(&mut self, msg: Self::Message) {
         match msg {
-            Request::Apply(reduce) => {
-                self.handler.apply(reduce);
-            }
-            Request::ApplyOnce(reduce) => {
-                self.handler.apply_once(reduce);
+            #[cfg(feature = "rate-limit")]
+            Msg::Wake => self.apply_deferred(),
+            Msg::Flush => self.flush(),
+            Msg::Timing(id, timing) => self.time(id, timing),
+            Msg::Due(id) => self.run_scheduled(id),
+            #[cfg(feature = "wake")]
+            Msg::Woke => self.woke(),
+            Msg::Power(low) => self.power(low),
+            Msg::Bootstrap => {
+                self.bootstrapping = false;
+                for who in self.subscriptions.waiting() {
+                    self.send_initial(who);
+                }
             }
         }
+    }
 
-        // Notify subscribers of change
-        for who in self.subscriptions.iter().cloned() {
-            self.link
-                .respond(who, Response::State(self.handler.state()));
+    fn handle_input(&mut self, msg: Self::Input, who: HandlerId) {
+        // Requests sent while handling another (by reducers, watchers, or subscribers being
+        // notified) wait until it's handled and every subscriber is notified. yew's scheduler
+        // already defers them, but this shouldn't depend on it.
+        if self.handling {
+            self.nested.push_back((msg, who));
+            return;
         }
+        self.handling = true;
+        self.handle(msg, who);
+        while let Some((msg, who)) = self.nested.pop_front() {
+            self.handle(msg, who);
+        }
+        self.handling = false;
     }
 
     fn connected(&mut self, who: HandlerId) {
-        self.subscriptions.insert(who);
-        self.link
-            .respond(who, Response::State(self.handler.state()));
+        if !self.subscriptions.is_empty() {
+            self.seedable = false;
+        }
+        let bootstrapping = config::batch_bootstrap();
+        self.subscriptions.connect(who, bootstrapping);
+        #[cfg(feature = "profiler")]
+        crate::profiler::subscribers::<T, SCOPE>(self.subscriptions.len());
+        #[cfg(feature = "metrics")]
+        crate::metrics::gauge::<T, SCOPE>(Gauge::Subscribers, self.subscriptions.len() as f64);
+        if !bootstrapping {
+            self.send_initial(who);
+        } else if !self.bootstrapping {
+            // Runs once the scheduler is done with what's queued, such as other components
+            // mounting along with this one.
+            self.bootstrapping = true;
+            self.link.send_message(Msg::Bootstrap);
+        }
     }
 
     fn disconnected(&mut self, who: HandlerId) {
-        self.subscriptions.remove(&who);
+        self.subscriptions.disconnect(who);
+        #[cfg(feature = "profiler")]
+        crate::profiler::subscribers::<T, SCOPE>(self.subscriptions.len());
+        #[cfg(feature = "metrics")]
+        crate::metrics::gauge::<T, SCOPE>(Gauge::Subscribers, self.subscriptions.len() as f64);
+    }
+
+    fn destroy(&mut self) {
+        scope::unpublish::<T, SCOPE>();
+        #[cfg(feature = "profiler")]
+        crate::profiler::stopped::<T, SCOPE>();
     }
 }
 
+impl<T, SCOPE> SharedStateService<T, SCOPE>
+where
+    T: Handler + Clone + 'static,
+    SCOPE: 'static,
+{
+    /// Send initial state to a subscriber that just connected, along with any error and progress.
+    fn send_initial(&mut self, who: HandlerId) {
+        if !self.subscriptions.bootstrapped(who) {
+            return;
+        }
+        self.respond(who, Response::State(self.handler.state()));
+        if let Some(error) = &self.error {
+            self.respond(who, Response::Error(error.clone()));
+        }
+        if self.progress.is_some() {
+            self.respond(who, Response::Progress(self.progress.clone()));
+        }
+    }
+
+    fn respond(&self, who: HandlerId, response: Response<T::Model>) {
+        self.subscriptions.respond(who, &response)
+    }
+
+    /// Send a response to every subscriber, paused ones too if `paused`.
+    fn broadcast(&self, response: Response<T::Model>, paused: bool) {
+        self.subscriptions.broadcast(&response, paused)
+    }
+
+    /// Handle a request, notifying subscribers of any change.
+    fn handle(&mut self, msg: Request<T::Model>, who: HandlerId) {
+        match msg {
+            Request::Priority(priority) => self.subscriptions.set_priority(who, priority),
+            Request::Pause => self.subscriptions.pause(who),
+            Request::Resume => {
+                if self.subscriptions.resume(who) {
+                    // Catch up on changes missed while paused.
+                    self.respond(who, Response::State(self.handler.state()));
+                }
+            }
+            Request::Seed(_) if !self.seedable => {}
+            Request::Watch(mut watcher, now) => {
+                if !(now && watcher(&self.handler.state())) {
+                    self.watchers.push(watcher);
+                }
+            }
+            Request::Report(error) => self.report(error),
+            Request::Configure(settings) => {
+                self.handler.configure(&settings);
+                if let Some(error) = self.handler.take_error() {
+                    self.report(error);
+                }
+            }
+            Request::Schedule(delay, control, request) => {
+                self.schedule(&control, Scheduled::new(Task::Once(*request), delay, who))
+            }
+            Request::Every(interval, control, reduce) => {
+                self.schedule(&control, Scheduled::new(Task::Every(reduce), interval, who))
+            }
+            Request::Progress(progress) => {
+                self.progress = progress.map(Rc::new);
+                self.broadcast(Response::Progress(self.progress.clone()), true);
+            }
+            Request::Batched(request, ticket) => {
+                self.seedable = false;
+                // Changes held back (coalesced, or over a rate limit) are applied on their own
+                // later, without holding back the rest of the batch.
+                let notify: Box<dyn FnOnce()> = if self.coalesces(&request) {
+                    self.hold(*request);
+                    Box::new(|| {})
+                } else {
+                    self.flush();
+                    self.commit(*request)
+                };
+                ticket.arrive(notify);
+            }
+            request if self.coalesces(&request) => {
+                self.seedable = false;
+                self.hold(request);
+            }
+            request => {
+                self.seedable = false;
+                self.flush();
+                self.commit(request)();
+            }
+        }
+    }
+
+    /// Catch up after the page was hidden or asleep.
+    #[cfg(feature = "wake")]
+    fn woke(&mut self) {
+        self.handler.wake();
+        if let Some(error) = self.handler.take_error() {
+            self.report(error);
+        }
+        // Held up while away, so upkeep runs now.
+        let recurring: Vec<u64> = self
+            .scheduled
+            .iter()
+            .filter(|(_, scheduled)| scheduled.timer.running())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in recurring {
+            self.run_scheduled(id);
+        }
+    }
+
+    /// Wait longer (or less) between recurring work as low power mode is switched.
+    fn power(&mut self, low: bool) {
+        self.handler.low_power(low);
+        for (id, scheduled) in &mut self.scheduled {
+            if scheduled.timer.running() {
+                let id = *id;
+                scheduled
+                    .timer
+                    .start(self.link.callback(move |_| Msg::Due(id)));
+            }
+        }
+    }
+
+    /// Hold a scheduled or recurring change, connecting its handle, and start its timer.
+    fn schedule(&mut self, control: &Control, scheduled: Scheduled<T::Model>) {
+        let id = self.next_scheduled;
+        self.next_scheduled += 1;
+        let agent = self.link.callback(move |timing| Msg::Timing(id, timing));
+        let early = schedule::connect(control, agent);
+        let delay = scheduled.timer.delay;
+        self.scheduled.insert(id, scheduled);
+        self.time(id, early.unwrap_or(Timing::After(delay)));
+    }
+
+    /// Change when a scheduled or recurring change applies.
+    fn time(&mut self, id: u64, timing: Timing) {
+        let due = self.link.callback(move |_| Msg::Due(id));
+        let due = match self.scheduled.get_mut(&id) {
+            Some(scheduled) => scheduled.timer.time(timing, due),
+            None => return,
+        };
+        match due {
+            Due::Later => {}
+            Due::Now => self.run_scheduled(id),
+            Due::Never => {
+                self.scheduled.remove(&id);
+            }
+        }
+    }
+
+    /// Apply a scheduled or recurring change, unless it's cancelled or applied already.
+    fn run_scheduled(&mut self, id: u64) {
+        let (request, who) = match self.scheduled.get(&id) {
+            Some(Scheduled {
+                task: Task::Every(reduce),
+                timer,
+            }) => (Request::Apply(reduce.clone()), timer.who),
+            Some(_) => match self.scheduled.remove(&id) {
+                Some(Scheduled {
+                    task: Task::Once(request),
+                    timer,
+                }) => (request, timer.who),
+                _ => return,
+            },
+            None => return,
+        };
+        self.handle_input(request, who);
+    }
+
+    /// Whether `request` is a change coalesced with others of its label.
+    fn coalesces(&self, request: &Request<T::Model>) -> bool {
+        request.is_reduction() && coalesce::coalesced::<T, SCOPE>(request.label())
+    }
+
+    /// Hold a coalesced change until the burst is over, in place of any held with its label.
+    fn hold(&mut self, request: Request<T::Model>) {
+        if self.held.is_empty() {
+            self.flush_later();
+        }
+        self.held.retain(|held| held.label() != request.label());
+        self.held.push_back(request);
+    }
+
+    /// Apply held changes on the next animation frame, after the events of this one.
+    #[cfg(target_arch = "wasm32")]
+    fn flush_later(&mut self) {
+        let callback = self.link.callback(|_| Msg::Flush);
+        self.frame = Some(yew::services::RenderService::request_animation_frame(
+            callback,
+        ));
+    }
+
+    /// Apply held changes once the scheduler is done with what's queued.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn flush_later(&mut self) {
+        self.link.send_message(Msg::Flush);
+    }
+
+    /// Apply coalesced changes held so far.
+    fn flush(&mut self) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.frame = None;
+        }
+        for request in std::mem::take(&mut self.held) {
+            self.commit(request)();
+        }
+    }
+
+    /// Apply a change unless it's over the rate limit, returning a function that notifies
+    /// subscribers of it.
+    fn commit(&mut self, request: Request<T::Model>) -> Box<dyn FnOnce()> {
+        #[cfg(feature = "rate-limit")]
+        let request = match self.limit(request) {
+            Some(request) => request,
+            None => return Box::new(|| {}),
+        };
+        self.apply(request)
+    }
+
+    /// Apply a request, returning a function that notifies subscribers of the changes.
+    fn apply(&mut self, request: Request<T::Model>) -> Box<dyn FnOnce()> {
+        #[cfg(feature = "tracing")]
+        let span = telemetry::span::<T, SCOPE>(request.label());
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        // Dropped unless the change is applied, resolving its future with nothing.
+        let (request, completion) = request.take_completion();
+        if request.is_change()
+            && !authorize::allowed::<T, SCOPE>(&self.handler.state(), request.label())
+        {
+            let label = request.label().map(str::to_string);
+            self.report(Error::Unauthorized { label });
+            return Box::new(|| {});
+        }
+        if let Request::Restart = request {
+            self.restart();
+            return Box::new(|| {});
+        }
+
+        // Retried, but already applied.
+        let key = request.key().map(str::to_string);
+        if key.as_deref().is_some_and(|key| self.applied.contains(key)) {
+            return Box::new(|| {});
+        }
+
+        // Kept only to compare with, as it makes the handler copy state to change it.
+        let policy = notify::policy_of::<T, SCOPE>();
+        let previous = policy.as_ref().map(|_| self.handler.state());
+
+        #[cfg(any(feature = "profiler", feature = "tracing"))]
+        let started = crate::clock::monotonic();
+        self.handler.subscribed(self.subscriptions.notified());
+        let result = request.apply(&mut self.handler);
+        #[cfg(feature = "profiler")]
+        crate::profiler::applied::<T, SCOPE>(
+            started,
+            std::mem::size_of_val(&*self.handler.state()),
+        );
+        #[cfg(feature = "metrics")]
+        {
+            crate::metrics::count::<T, SCOPE>(Counter::Dispatches);
+            let size = std::mem::size_of_val(&*self.handler.state()) as f64;
+            crate::metrics::gauge::<T, SCOPE>(Gauge::StateSize, size);
+        }
+        if let Some(error) = self.handler.take_error() {
+            self.report(error);
+        }
+        if let Err(error) = result {
+            self.report(error);
+            return Box::new(|| {});
+        }
+        // Only once applied, so failed changes may be retried.
+        if let Some(key) = key {
+            self.applied.insert(key);
+        }
+
+        scope::publish::<T, SCOPE>(self.handler.state());
+
+        let mut changes = self.handler.changes();
+        // Watchers (such as guarded transitions) act on state as applied, even when subscribers
+        // aren't notified of it.
+        if !matches!(changes, Changes::None) {
+            let state = self.handler.state();
+            self.watchers.retain_mut(|watcher| !watcher(&state));
+        }
+        if let (Some(policy), Some(previous)) = (policy, previous) {
+            let state = self.handler.state();
+            changes = match (policy(&previous, &state), changes) {
+                // Subscribers skipping a patch can't apply the ones after it.
+                (false, Changes::Patch(_)) => {
+                    self.stale = true;
+                    Changes::None
+                }
+                (false, _) => Changes::None,
+                (true, Changes::None) => Changes::State(state),
+                (true, _) if std::mem::take(&mut self.stale) => Changes::State(state),
+                (true, changes) => changes,
+            };
+        }
+        #[cfg(feature = "tracing")]
+        span.record("changed", !matches!(changes, Changes::None))
+            .record("duration_ms", crate::clock::monotonic() - started);
+
+        let notify = match changes {
+            Changes::State(state) => self
+                .subscriptions
+                .broadcast_later(Box::new(Response::State(state))),
+            Changes::Patch(patch) => self
+                .subscriptions
+                .broadcast_later(Box::new(Response::Patch(patch))),
+            Changes::None => Box::new(|| {}),
+        };
+        match completion {
+            Some(completion) => {
+                let state = self.handler.state();
+                Box::new(move || {
+                    notify();
+                    completion.complete(state);
+                })
+            }
+            None => notify,
+        }
+    }
+
+    /// Create the handler again (reloading storage, reconnecting and such), then send its state
+    /// to every subscriber.
+    fn restart(&mut self) {
+        // Saved before the handler is replaced, so pending changes aren't lost.
+        self.handler.flush();
+        if let Some(error) = self.handler.take_error() {
+            self.report(error);
+        }
+        self.handler = T::new();
+        self.error = None;
+        if let Some(error) = self.handler.take_error() {
+            self.report(error);
+        }
+        let state = self.handler.state();
+        scope::publish::<T, SCOPE>(state.clone());
+        self.watchers.retain_mut(|watcher| !watcher(&state));
+        self.stale = false;
+        self.broadcast(Response::State(state), false);
+    }
+
+    /// Hold back a change over the rate limit, returning it if it may be applied now.
+    #[cfg(feature = "rate-limit")]
+    fn limit(&mut self, request: Request<T::Model>) -> Option<Request<T::Model>> {
+        use crate::rate_limit::{self, Admission};
+
+        if !request.is_change() {
+            return Some(request);
+        }
+        // Keep order behind changes deferred already, once those there's capacity for are applied.
+        if !self.deferred.is_empty() {
+            self.apply_deferred();
+        }
+        if !self.deferred.is_empty() {
+            self.deferred.push_back(request);
+            return None;
+        }
+        let label = request.label();
+        let (report, deferred) = match rate_limit::admit::<T, SCOPE>(label) {
+            Admission::Allowed => return Some(request),
+            Admission::Dropped { report } => (report, None),
+            Admission::Deferred { report, wait } => (report, Some(wait)),
+        };
+        if report {
+            self.report(Error::RateLimited {
+                label: label.map(str::to_string),
+            });
+        }
+        if let Some(wait) = deferred {
+            self.deferred.push_back(request);
+            self.wake_after(wait);
+        }
+        None
+    }
+
+    /// Apply deferred changes there's capacity for, waiting again for the rest.
+    #[cfg(feature = "rate-limit")]
+    fn apply_deferred(&mut self) {
+        use crate::rate_limit::{self, Admission};
+
+        self.wake = None;
+        while let Some(request) = self.deferred.pop_front() {
+            match rate_limit::admit::<T, SCOPE>(request.label()) {
+                Admission::Allowed => self.apply(request)(),
+                Admission::Deferred { wait, .. } => {
+                    self.deferred.push_front(request);
+                    self.wake_after(wait);
+                    return;
+                }
+                // Limited differently since it was deferred, dropping changes over the limit.
+                Admission::Dropped { report } => {
+                    if report {
+                        self.report(Error::RateLimited {
+                            label: request.label().map(str::to_string),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "rate-limit")]
+    fn wake_after(&mut self, wait: f64) {
+        if self.wake.is_none() {
+            let wait = std::time::Duration::from_millis(wait.ceil() as u64);
+            self.wake = timeout(wait, self.link.callback(|_| Msg::Wake));
+        }
+    }
+
+    /// Notify subscribers of an error.
+    fn report(&mut self, error: Error) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::count::<T, SCOPE>(Counter::Errors);
+        let error = Rc::new(error);
+        self.broadcast(Response::Error(error.clone()), true);
+        self.error = Some(error);
+    }
+
+    /// Like `bridge`, but returns `None` instead of panicking if the bridge can't be created.
+    ///
+    /// yew keeps agents in thread local state, and panics bridging them once it's torn down (as
+    /// when components are dropped along with the thread), so that's checked up front. Panics
+    /// can't be caught instead, as wasm32-unknown-unknown aborts on them.
+    fn try_bridge(callback: Callback<Response<T::Model>>) -> Option<Box<dyn Bridge<Self>>> {
+        if config::standalone() {
+            return None;
+        }
+        Some(Self::bridge(callback))
+    }
+}
+
+/// Call `due` after `wait`. Timers only run in the browser; elsewhere deferred changes are
+/// applied along with the next change.
+#[cfg(all(feature = "rate-limit", target_arch = "wasm32"))]
+fn timeout(wait: Duration, due: Callback<()>) -> Option<yew::services::timeout::TimeoutTask> {
+    Some(yew::services::TimeoutService::spawn(wait, due))
+}
+
+#[cfg(all(feature = "rate-limit", not(target_arch = "wasm32")))]
+fn timeout(_wait: Duration, _due: Callback<()>) -> Option<yew::services::timeout::TimeoutTask> {
+    None
+}
+
 type StateHandler<T> = <<T as SharedState>::Handle as Handle>::Handler;
 type Model<T> = <StateHandler<T> as Handler>::Model;
 
+/// Bridge to the shared state service of some scope.
+pub(crate) trait ServiceLink<T> {
+    fn send(&mut self, request: Request<T>);
+}
+
+pub(crate) type Link<T> = Box<dyn ServiceLink<T>>;
+/// Bridges to the shared state service of a scope, if it can.
+type Bridger<T> = fn(Callback<Response<T>>) -> Option<Link<T>>;
+
+impl<H, SCOPE> ServiceLink<H::Model> for Box<dyn Bridge<SharedStateService<H, SCOPE>>>
+where
+    H: Handler + Clone + 'static,
+    SCOPE: 'static,
+{
+    fn send(&mut self, request: Request<H::Model>) {
+        #[cfg(target_arch = "wasm32")]
+        if request.is_reduction() {
+            crate::loops::sent::<H::Model, SCOPE>(request.label());
+        }
+        Bridge::send(self.as_mut(), request)
+    }
+}
+
+/// What a wrapper needs from the scope it shares state in, so the scope may be picked by
+/// `StateConfig` instead of a type parameter.
+pub(crate) struct ScopeKey<H: Handler> {
+    id: TypeId,
+    bridge: Bridger<H::Model>,
+    forced: fn() -> Option<Rc<H::Model>>,
+    allowed: fn(&H::Model, Option<&str>) -> bool,
+}
+
+impl<H: Handler> Clone for ScopeKey<H> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<H: Handler> Copy for ScopeKey<H> {}
+
+impl<H> ScopeKey<H>
+where
+    H: Handler + Clone + 'static,
+{
+    pub(crate) fn of<SCOPE: 'static>() -> Self {
+        Self {
+            id: TypeId::of::<SCOPE>(),
+            bridge: |callback| {
+                let bridge = SharedStateService::<H, SCOPE>::try_bridge(callback)?;
+                Some(Box::new(bridge))
+            },
+            forced: scope::forced::<H, SCOPE>,
+            allowed: authorize::allowed::<H, SCOPE>,
+        }
+    }
+
+    /// Bridge to the scope's shared state service, falling back to standalone state (with a
+    /// warning) if it can't be bridged.
+    pub(crate) fn connect(&self, callback: Callback<Response<H::Model>>) -> Link<H::Model> {
+        match (self.bridge)(callback.clone()) {
+            Some(bridge) => bridge,
+            None => {
+                warn_standalone();
+                Box::new(self.standalone(None, callback))
+            }
+        }
+    }
+
+    /// State of the scope's handler kept by one subscriber, starting from `forced` if given.
+    fn standalone(
+        &self,
+        forced: Option<Rc<H::Model>>,
+        respond: Callback<Response<H::Model>>,
+    ) -> Standalone<H> {
+        Standalone::new(forced, self.allowed, respond)
+    }
+}
+
+fn warn_standalone() {
+    telemetry::warn(
+        "yew-state: Failed to bridge shared state service, falling back to standalone state.",
+    );
+}
+
+/// State kept by a single subscriber instead of being shared, when the shared state service
+/// can't be bridged. Requests are answered as the service would answer them.
+pub(crate) struct Standalone<H: Handler> {
+    handler: H,
+    respond: Callback<Response<H::Model>>,
+    allowed: fn(&H::Model, Option<&str>) -> bool,
+    watchers: Vec<Watcher<H::Model>>,
+}
+
+impl<H> Standalone<H>
+where
+    H: Handler,
+    H::Model: Clone + 'static,
+{
+    /// Create state (from `forced` if given), sending it to `respond` along with any error.
+    fn new(
+        forced: Option<Rc<H::Model>>,
+        allowed: fn(&H::Model, Option<&str>) -> bool,
+        respond: Callback<Response<H::Model>>,
+    ) -> Self {
+        let mut handler = H::new();
+        if let Some(state) = forced {
+            handler.apply_once(Box::new(move |local| *local = (*state).clone()));
+            // Sent whole right after.
+            handler.changes();
+        }
+        respond.emit(Response::State(handler.state()));
+        if let Some(error) = handler.take_error() {
+            respond.emit(Response::Error(Rc::new(error)));
+        }
+        Self {
+            handler,
+            respond,
+            allowed,
+            watchers: Vec::new(),
+        }
+    }
+
+    fn report(&self, error: Error) {
+        self.respond.emit(Response::Error(Rc::new(error)));
+    }
+}
+
+impl<H> ServiceLink<H::Model> for Standalone<H>
+where
+    H: Handler,
+    H::Model: Clone + 'static,
+{
+    fn send(&mut self, request: Request<H::Model>) {
+        let request = match request {
+            Request::Progress(progress) => {
+                let progress = progress.map(Rc::new);
+                return self.respond.emit(Response::Progress(progress));
+            }
+            Request::Watch(mut watcher, now) => {
+                if !(now && watcher(&self.handler.state())) {
+                    self.watchers.push(watcher);
+                }
+                return;
+            }
+            request => request,
+        };
+        let (request, completion) = request.take_completion();
+        if request.is_change() && !(self.allowed)(&self.handler.state(), request.label()) {
+            let label = request.label().map(str::to_string);
+            return self.report(Error::Unauthorized { label });
+        }
+        let result = request.apply(&mut self.handler);
+        if let Some(error) = self.handler.take_error() {
+            self.report(error);
+        }
+        if let Err(error) = result {
+            return self.report(error);
+        }
+        let state = self.handler.state();
+        let response = match self.handler.changes() {
+            Changes::State(state) => Some(Response::State(state)),
+            Changes::Patch(patch) => Some(Response::Patch(patch)),
+            Changes::None => None,
+        };
+        if let Some(response) = response {
+            self.watchers.retain_mut(|watcher| !watcher(&state));
+            self.respond.emit(response);
+        }
+        if let Some(completion) = completion {
+            completion.complete(state);
+        }
+    }
+}
+
 /// Component wrapper for managing messages and state handles.
 ///
 /// Wraps any component with properties that implement `SharedState`:
-/// ```
+/// ```ignore
 /// pub type MyComponent = SharedStateComponent<MyComponentModel>;
 /// ```
 ///
 /// A scope may be provided to specify where the state is shared:
-/// ```
+/// ```ignore
 /// // This will only share state with other components using `FooScope`.
 /// pub struct FooScope;
 /// pub type MyComponent = SharedStateComponent<MyComponentModel, FooScope>;
 /// ```
 ///
+/// If the shared state service can't be bridged (possible in some embedding scenarios), a warning is
+/// logged and the component falls back to standalone state that isn't shared with anything. Use
+/// `try_create` to fail instead.
+///
 /// # Important
 /// By default `StorageHandle` and `GlobalHandle` have different scopes. Though not enforced,
 /// components with different handles should not use the same scope.
@@ -106,11 +1038,25 @@ pub struct SharedStateComponent<C, SCOPE = StateHandler<<C as Component>::Proper
 where
     C: Component,
     C::Properties: SharedState + Clone,
+    Model<C::Properties>: Default,
     StateHandler<C::Properties>: Clone,
     SCOPE: 'static,
 {
     props: C::Properties,
-    bridge: Box<dyn Bridge<SharedStateService<StateHandler<C::Properties>, SCOPE>>>,
+    /// Configuration from properties, read again when they change.
+    config: StateConfig<C::Properties>,
+    /// Scope state is shared in, from configuration or `SCOPE`.
+    scope: ScopeKey<StateHandler<C::Properties>>,
+    /// Shared state service, or standalone state if it can't be bridged.
+    connection: Option<Link<Model<C::Properties>>>,
+    link: ComponentLink<Self>,
+    /// Whether state has been received since connecting.
+    loaded: bool,
+    /// Whether state was received since the component last changed it, so the next change
+    /// responds to it.
+    #[cfg(target_arch = "wasm32")]
+    notified: bool,
+    _scope: std::marker::PhantomData<SCOPE>,
 }
 
 #[doc(hidden)]
@@ -118,61 +1064,308 @@ pub enum SharedStateComponentMsg<T> {
     /// Recieve new local state.
     /// IMPORTANT: Changes will **not** be reflected in shared state.
     SetLocal(Rc<T>),
+    /// Patch local state.
+    /// IMPORTANT: Changes will **not** be reflected in shared state.
+    PatchLocal(Reduction<T>),
+    /// Recieve an error from shared state.
+    SetError(Rc<Error>),
+    /// Recieve progress from shared state.
+    SetProgress(Option<Rc<Progress>>),
     /// Update shared state.
-    Apply(Reduction<T>),
-    ApplyOnce(ReductionOnce<T>),
+    Request(Request<T>),
+    /// Connect to shared state, once a lazy handle is first read.
+    Connect,
+    /// Run work finishing later, see `Detached`.
+    Detach(Detached<T>),
 }
 
-impl<C, SCOPE> Component for SharedStateComponent<C, SCOPE>
+impl<C, SCOPE> SharedStateComponent<C, SCOPE>
 where
     C: Component,
     C::Properties: SharedState + Clone,
     Model<C::Properties>: Default,
     StateHandler<C::Properties>: Clone,
 {
-    type Message = SharedStateComponentMsg<Model<C::Properties>>;
-    type Properties = C::Properties;
+    /// `C` with default properties, rendering `fallback` in its place until the first state
+    /// arrives, so it's never mounted with default state. For other properties, render
+    /// `SharedStateComponent<Suspense<C>, SCOPE>` with `props` and `fallback`.
+    ///
+    /// ```ignore
+    /// html! {
+    ///     { SharedStateComponent::<Feed>::with_fallback(html! { <Spinner /> }) }
+    /// }
+    /// ```
+    pub fn with_fallback(fallback: Html) -> Html
+    where
+        C::Properties: Default,
+    {
+        let props = C::Properties::default();
+        html! {
+            <SharedStateComponent<Suspense<C>, SCOPE> props=props fallback=fallback />
+        }
+    }
+
+    /// Like `Component::create`, failing with `Error::Unbridged` instead of falling back to
+    /// standalone state if the shared state service can't be bridged. yew only calls `create`, so
+    /// this is for wrappers created by hand, such as by a component embedding another.
+    pub fn try_create(props: C::Properties, link: ComponentLink<Self>) -> Result<Self, Error> {
+        let mut this = Self::new(props, link);
+        if this.connects_now() {
+            this.try_connect()?;
+        }
+        Ok(this)
+    }
+
+    fn new(props: C::Properties, link: ComponentLink<Self>) -> Self {
+        let config = props.config();
+        let mut this = SharedStateComponent {
+            scope: config.scope.unwrap_or_else(ScopeKey::of::<SCOPE>),
+            config,
+            props,
+            connection: None,
+            link,
+            loaded: false,
+            #[cfg(target_arch = "wasm32")]
+            notified: false,
+            _scope: Default::default(),
+        };
+        if this.props.handle().is_connected() && this.props.lazy() {
+            this.connect_later();
+        }
+        this
+    }
+
+    /// Whether to bridge right away. Handles that aren't connected don't need a bridge, and lazy
+    /// ones wait until they're read or sent a request.
+    fn connects_now(&mut self) -> bool {
+        self.props.handle().is_connected() && !self.props.lazy()
+    }
+
+    /// Bridge to shared state service and give handle access to it. Falls back to standalone
+    /// state (with a warning) if the bridge can't be created.
+    fn connect(&mut self) {
+        if let Err(Error::Unbridged) = self.try_connect() {
+            warn_standalone();
+            let standalone = self.scope.standalone(None, self.responses());
+            self.attach(Box::new(standalone), true);
+        }
+    }
 
-    fn create(mut props: Self::Properties, link: ComponentLink<Self>) -> Self {
+    /// Callback receiving responses of shared state as messages.
+    fn responses(&self) -> Callback<Response<Model<C::Properties>>> {
         use SharedStateComponentMsg::*;
-        // Bridge to receive new state.
-        let callback = link.callback(|msg| match msg {
+        self.link.callback(|msg| match msg {
             Response::State(state) => SetLocal(state),
-        });
-        let bridge = SharedStateService::bridge(callback);
+            Response::Patch(patch) => PatchLocal(patch),
+            Response::Error(error) => SetError(error),
+            Response::Progress(progress) => SetProgress(progress),
+        })
+    }
+
+    /// Bridge to shared state service and give handle access to it, failing with
+    /// `Error::Unbridged` if the bridge can't be created.
+    fn try_connect(&mut self) -> Result<(), Error> {
+        // Bridge to receive new state.
+        let callback = self.responses();
+        self.loaded = false;
+        let forced = (self.scope.forced)();
+        let seeded = forced.is_none();
+        let connection: Link<_> = match forced {
+            // State forced by a `TestProvider` is used standalone, without the shared state
+            // service. It's shown right away.
+            Some(state) => {
+                let standalone = self.scope.standalone(Some(state), callback);
+                self.props
+                    .handle()
+                    .set_local_state(standalone.handler.state());
+                self.loaded = true;
+                Box::new(standalone)
+            }
+            None => (self.scope.bridge)(callback).ok_or(Error::Unbridged)?,
+        };
+        self.attach(connection, seeded);
+        Ok(())
+    }
+
+    /// Send requests of the handle through `connection`, seeding it with initial state if
+    /// `seeded`.
+    fn attach(&mut self, connection: Link<Model<C::Properties>>, seeded: bool) {
+        self.connection = Some(connection);
+
+        if let Some(policy) = self.config.notify.clone() {
+            notify::set::<StateHandler<C::Properties>>(self.scope.id, policy);
+        }
+        if let (Some(initial), true) = (self.props.initial(), seeded) {
+            self.send(crate::handler::Request::Seed(initial));
+        }
+        let priority = self.props.priority();
+        if priority != Priority::default() {
+            self.send(crate::handler::Request::Priority(priority));
+        }
+
+        let detach = self.link.callback(SharedStateComponentMsg::Detach);
+        let handle = self.props.handle();
+        handle.set_local_callback(self.link.callback(SharedStateComponentMsg::Request));
+        handle.set_local_detach(detach);
+    }
+
+    /// Whether to render a change from `previous` state, according to `StateConfig::render_if`.
+    fn renders(&mut self, previous: Option<Rc<Model<C::Properties>>>) -> ShouldRender {
+        match (
+            self.config.render,
+            previous,
+            self.props.handle().local_state(),
+        ) {
+            (Some(render), Some(previous), Some(state)) => render(&previous, &state),
+            _ => true,
+        }
+    }
+
+    /// Give handle access to shared state without bridging to it until it's read or sends a
+    /// request.
+    fn connect_later(&mut self) {
+        let detach = self.link.callback(SharedStateComponentMsg::Detach);
+        let handle = self.props.handle();
+        handle.set_local_callback(self.link.callback(SharedStateComponentMsg::Request));
+        handle.set_local_read(self.link.callback(|_| SharedStateComponentMsg::Connect));
+        handle.set_local_detach(detach);
+    }
+
+    /// Connect a lazy handle on first use.
+    fn connect_lazily(&mut self) {
+        if self.connection.is_none() && self.props.handle().is_connected() {
+            self.connect();
+        }
+    }
+
+    /// Callback sending requests to shared state for as long as it's kept, even once this
+    /// component is destroyed, through a bridge of its own.
+    fn detached(&mut self) -> Callback<Request<Model<C::Properties>>> {
+        let bridge = match (self.scope.forced)() {
+            Some(_) => None,
+            None => (self.scope.bridge)(Callback::noop()),
+        };
+        match bridge {
+            Some(bridge) => {
+                let bridge = RefCell::new(bridge);
+                Callback::from(move |request| bridge.borrow_mut().send(request))
+            }
+            // Standalone state goes away along with the component anyway.
+            None => self.link.callback(SharedStateComponentMsg::Request),
+        }
+    }
 
-        props
-            .handle()
-            .set_local_callback(link.callback(Apply), link.callback(ApplyOnce));
+    /// Send request to shared state, or to standalone state. Changes come back as messages, so
+    /// nothing renders right away.
+    fn send(&mut self, request: Request<Model<C::Properties>>) -> ShouldRender {
+        if let Some(connection) = &mut self.connection {
+            connection.send(request);
+        }
+        false
+    }
+}
+
+impl<C, SCOPE> Component for SharedStateComponent<C, SCOPE>
+where
+    C: Component,
+    C::Properties: SharedState + Clone,
+    Model<C::Properties>: Default,
+    StateHandler<C::Properties>: Clone,
+{
+    type Message = SharedStateComponentMsg<Model<C::Properties>>;
+    type Properties = C::Properties;
 
-        SharedStateComponent { props, bridge }
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut this = Self::new(props, link);
+        if this.connects_now() {
+            this.connect();
+        }
+        this
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         use SharedStateComponentMsg::*;
         match msg {
-            Apply(reduce) => {
-                self.bridge.send(Request::Apply(reduce));
-                false
-            }
-            ApplyOnce(reduce) => {
-                self.bridge.send(Request::ApplyOnce(reduce));
-                false
+            Request(request) => {
+                self.connect_lazily();
+                #[cfg(target_arch = "wasm32")]
+                if request.is_reduction() && std::mem::replace(&mut self.notified, false) {
+                    crate::loops::changed::<Model<C::Properties>, SCOPE>(request.label());
+                }
+                self.send(request)
             }
             SetLocal(state) => {
+                #[cfg(target_arch = "wasm32")]
+                {
+                    self.notified = true;
+                }
+                let previous = self.props.handle().local_state();
                 self.props.handle().set_local_state(state);
+                // Render at least once, in place of any fallback.
+                let loaded = std::mem::replace(&mut self.loaded, true);
+                !loaded || self.renders(previous)
+            }
+            PatchLocal(patch) => {
+                #[cfg(target_arch = "wasm32")]
+                {
+                    self.notified = true;
+                }
+                let previous = self.props.handle().local_state();
+                self.props.handle().patch_local_state(patch);
+                self.renders(previous)
+            }
+            SetError(error) => {
+                self.props.handle().set_local_error(error);
+                true
+            }
+            SetProgress(progress) => {
+                self.props.handle().set_local_progress(progress);
                 true
             }
+            Connect => {
+                self.connect_lazily();
+                false
+            }
+            Detach(task) => {
+                task(self.detached());
+                false
+            }
         }
     }
 
     fn change(&mut self, mut props: Self::Properties) -> ShouldRender {
         props.handle().set_local(self.props.handle());
         self.props = props;
+        self.config = self.props.config();
+        let scope = self.config.scope.unwrap_or_else(ScopeKey::of::<SCOPE>);
+        let moved = std::mem::replace(&mut self.scope, scope).id != scope.id;
+        // Connect or disconnect if handle changed, and connect again if scope did.
+        match (
+            self.connection.is_some(),
+            self.props.handle().is_connected(),
+        ) {
+            (false, true) if self.props.lazy() => self.connect_later(),
+            (false, true) => self.connect(),
+            (true, false) => self.connection = None,
+            (true, true) if moved => self.connect(),
+            _ => {}
+        }
+
         true
     }
 
     fn view(&self) -> Html {
+        if self.connection.is_some() && !self.loaded {
+            if let Some(fallback) = self
+                .config
+                .fallback
+                .clone()
+                .or_else(|| self.props.fallback())
+            {
+                return fallback;
+            }
+        }
+
         let props = self.props.clone();
         html! {
             <C with props />
@@ -0,0 +1,205 @@
+//! Crate-wide configuration.
+//!
+//! Configuration should be set once at startup, before any shared state is created:
+//!
+//! ```ignore
+//! fn main() {
+//!     yew_state::config().key_prefix("myapp:v2:");
+//!     yew::start_app::<App>();
+//! }
+//! ```
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::clock::Clock;
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsSink;
+use crate::storage::Cipher;
+
+thread_local! {
+    static SETTINGS: RefCell<Settings> = Default::default();
+}
+
+struct Settings {
+    key_prefix: String,
+    compress_links: bool,
+    cipher: Option<Rc<dyn Cipher>>,
+    clock: Option<Rc<dyn Clock>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Rc<dyn MetricsSink>>,
+    loop_limit: usize,
+    loop_panics: bool,
+    batch_bootstrap: bool,
+    debug_items: usize,
+    standalone: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            key_prefix: String::new(),
+            compress_links: false,
+            cipher: None,
+            clock: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            loop_limit: 1_000,
+            loop_panics: cfg!(debug_assertions),
+            batch_bootstrap: false,
+            debug_items: 100,
+            standalone: false,
+        }
+    }
+}
+
+/// Builder for crate-wide configuration. Every setting takes effect immediately.
+pub struct Config {
+    _private: (),
+}
+
+/// Configure the crate.
+pub fn config() -> Config {
+    Config { _private: () }
+}
+
+impl Config {
+    /// Prefix prepended to every `Storable::key()`, so apps (or builds of an app) sharing an origin
+    /// don't overwrite each other's storage. Empty by default.
+    pub fn key_prefix(self, prefix: &str) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().key_prefix = prefix.to_string());
+        self
+    }
+
+    /// Compress state shared in links with lz-string, so JS tooling can read it with lz-string's
+    /// `decompressFromEncodedURIComponent`. Links made with a different setting can't be read.
+    /// Off by default.
+    pub fn compress_links(self, compress: bool) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().compress_links = compress);
+        self
+    }
+
+    /// Cipher for state fields marked `#[storable(encrypt)]`. Until it's set, those fields aren't
+    /// stored.
+    pub fn cipher(self, cipher: impl Cipher + 'static) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().cipher = Some(Rc::new(cipher)));
+        self
+    }
+
+    /// Clock read by rate limits, sync, and `clock::now`. Wall-clock time by default; set a
+    /// `TestClock` in tests to control time.
+    pub fn clock(self, clock: impl Clock + 'static) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().clock = Some(Rc::new(clock)));
+        self
+    }
+
+    /// Sink receiving metrics of every scope. Metrics aren't collected until it's set.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(self, sink: impl MetricsSink + 'static) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().metrics = Some(Rc::new(sink)));
+        self
+    }
+
+    /// Changes made in response to other changes (by subscribers being notified, or components
+    /// rendering new state) allowed without the browser getting control back, before it's
+    /// considered a feedback loop. 1000 by default, and 0 turns detection off. Only checked in the
+    /// browser.
+    pub fn loop_limit(self, limit: usize) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().loop_limit = limit);
+        self
+    }
+
+    /// Panic when a feedback loop is detected, rather than logging a warning. On in debug builds,
+    /// off in release builds.
+    pub fn loop_panics(self, panics: bool) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().loop_panics = panics);
+        self
+    }
+
+    /// Switch low power mode, spreading out recurring work so the app goes easy on batteries. See
+    /// `power`. Off by default.
+    pub fn low_power(self, low: bool) -> Self {
+        crate::power::set(low);
+        self
+    }
+
+    /// Send initial state to subscribers connecting at the same time (as when a page mounts many
+    /// components) together, once they've all connected, instead of one at a time as each
+    /// connects. Changes made in the meantime aren't sent separately. Off by default.
+    pub fn batch_bootstrap(self, batch: bool) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().batch_bootstrap = batch);
+        self
+    }
+
+    /// Items of each array or object shown by debug output (`Debug` of handles and handlers, and
+    /// the inspector) before the rest is left out, so large collections don't freeze the page.
+    /// The inspector shows more when asked. 100 by default, and 0 shows everything.
+    pub fn debug_items(self, items: usize) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().debug_items = items);
+        self
+    }
+
+    /// Keep state of every component and `Dispatch` to itself, instead of bridging to shared
+    /// state services. For embeddings where agents can't run (such as components mounted as
+    /// custom elements by another framework), and tests of the fallback. Off by default.
+    pub fn standalone(self, standalone: bool) -> Self {
+        SETTINGS.with(|settings| settings.borrow_mut().standalone = standalone);
+        self
+    }
+}
+
+/// Items of each collection shown by debug output, or 0 for all of them.
+pub(crate) fn debug_items() -> usize {
+    SETTINGS.with(|settings| settings.borrow().debug_items)
+}
+
+/// Whether initial state is sent to subscribers in batches.
+pub(crate) fn batch_bootstrap() -> bool {
+    SETTINGS.with(|settings| settings.borrow().batch_bootstrap)
+}
+
+/// Whether shared state services shouldn't be bridged. Also the case once thread local state
+/// (where yew keeps its agents too) is torn down.
+pub(crate) fn standalone() -> bool {
+    SETTINGS
+        .try_with(|settings| settings.borrow().standalone)
+        .unwrap_or(true)
+}
+
+/// Current storage key prefix.
+pub(crate) fn key_prefix() -> String {
+    SETTINGS.with(|settings| settings.borrow().key_prefix.clone())
+}
+
+/// Cipher for encrypted state fields, if one is set.
+pub(crate) fn cipher() -> Option<Rc<dyn Cipher>> {
+    SETTINGS.with(|settings| settings.borrow().cipher.clone())
+}
+
+/// Clock to read the time from, if one is set.
+pub(crate) fn clock() -> Option<Rc<dyn Clock>> {
+    SETTINGS.with(|settings| settings.borrow().clock.clone())
+}
+
+/// Sink for metrics, if one is set.
+#[cfg(feature = "metrics")]
+pub(crate) fn metrics() -> Option<Rc<dyn MetricsSink>> {
+    SETTINGS.with(|settings| settings.borrow().metrics.clone())
+}
+
+/// Changes allowed in one go, or 0 if loops aren't detected.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn loop_limit() -> usize {
+    SETTINGS.with(|settings| settings.borrow().loop_limit)
+}
+
+/// Whether detected loops panic, rather than warn.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn loop_panics() -> bool {
+    SETTINGS.with(|settings| settings.borrow().loop_panics)
+}
+
+/// Whether links are compressed with lz-string.
+#[cfg(feature = "links")]
+pub(crate) fn compress_links() -> bool {
+    SETTINGS.with(|settings| settings.borrow().compress_links)
+}
@@ -0,0 +1,117 @@
+//! Hydrating shared state from cookies.
+//!
+//! Useful for context set by the server, such as an auth token or experiment bucket.
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use web_sys::HtmlDocument;
+
+use crate::handler::{Handler, Reduction, ReductionOnce};
+
+/// Allows state to be read from cookies when it's created, and optionally written back.
+pub trait Cookies {
+    /// Read cookies into state. `cookies` maps cookie names to their decoded values.
+    fn hydrate(&mut self, cookies: &HashMap<String, String>);
+    /// Cookies to write after every change, as name and value pairs. Nothing is written by
+    /// default.
+    fn write_back(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    /// Attributes of written cookies.
+    fn attributes() -> &'static str {
+        "path=/; SameSite=Lax"
+    }
+}
+
+/// Handler for shared state hydrated from cookies.
+///
+/// Cookies are read once, when state is created. Values returned by `Cookies::write_back` are
+/// written after every change, if they changed.
+#[derive(Default, Clone)]
+pub struct CookieHandler<T> {
+    state: Rc<T>,
+    cookies: HashMap<String, String>,
+}
+
+impl<T> CookieHandler<T>
+where
+    T: Cookies,
+{
+    fn write_cookies(&mut self) {
+        let document = match html_document() {
+            Some(document) => document,
+            None => return,
+        };
+
+        for (name, value) in self.state.write_back() {
+            if self.cookies.get(&name) == Some(&value) {
+                continue;
+            }
+            let cookie = format!(
+                "{}={}; {}",
+                name,
+                String::from(js_sys::encode_uri_component(&value)),
+                T::attributes()
+            );
+            if document.set_cookie(&cookie).is_ok() {
+                self.cookies.insert(name, value);
+            }
+        }
+    }
+}
+
+impl<T> Handler for CookieHandler<T>
+where
+    T: Default + Clone + Cookies,
+{
+    type Model = T;
+
+    fn new() -> Self {
+        let cookies = read_cookies();
+        let mut state = T::default();
+        state.hydrate(&cookies);
+        Self {
+            state: Rc::new(state),
+            cookies,
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.write_cookies();
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.write_cookies();
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+fn html_document() -> Option<HtmlDocument> {
+    web_sys::window()?.document()?.dyn_into().ok()
+}
+
+/// Every cookie visible to the page, by name.
+fn read_cookies() -> HashMap<String, String> {
+    let cookies = html_document()
+        .and_then(|document| document.cookie().ok())
+        .unwrap_or_default();
+
+    cookies
+        .split(';')
+        .filter_map(|cookie| {
+            let mut parts = cookie.splitn(2, '=');
+            let name = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            let value = js_sys::decode_uri_component(value)
+                .map(String::from)
+                .unwrap_or_else(|_| value.to_string());
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
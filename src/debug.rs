@@ -0,0 +1,15 @@
+//! Looking into shared state, gathered from the modules of each tool: while developing
+//! (inspector, profiler, snapshots, session recordings) and in production (metrics, redaction).
+#[cfg(feature = "inspector")]
+pub use crate::component::StateInspector;
+#[cfg(feature = "profiler")]
+pub use crate::component::StateProfiler;
+#[cfg(feature = "metrics")]
+pub use crate::metrics;
+#[cfg(feature = "profiler")]
+pub use crate::profiler;
+pub use crate::redact::{self, Redact};
+#[cfg(feature = "session-recorder")]
+pub use crate::session::{self, Replay, SessionRecorder};
+#[cfg(feature = "test-utils")]
+pub use crate::snapshot;
@@ -0,0 +1,421 @@
+//! Access to shared state outside of `SharedStateComponent`.
+use std::any::{type_name, Any};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::time::Duration;
+
+use yew::{
+    agent::{Agent, AgentLink},
+    Callback,
+};
+
+use crate::component::wrapper::{Link, Response, ScopeKey};
+use crate::error::Error;
+use crate::handle::Handle;
+use crate::handler::{Handler, HandlerSettings, Priority, Request};
+use crate::path::Path;
+use crate::progress::Progress;
+use crate::schedule::{RecurringHandle, ScheduledHandle};
+#[cfg(feature = "stream")]
+use crate::stream::StateStream;
+use crate::watch::{self, Applied, Change};
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+/// Connection to the shared state of handle type `H`, in `SCOPE`.
+///
+/// Useful for services, agents, or any code that isn't a component. State stays alive as long as
+/// the dispatch does. Like components, a dispatch keeps state to itself (with a warning) if the
+/// shared state service can't be bridged.
+///
+/// ```ignore
+/// let mut dispatch = Dispatch::<SharedHandle<Count>>::new(Callback::from(|count: Rc<Count>| {
+///     log::info!("count is now {}", count.0);
+/// }));
+/// dispatch.reduce(|count| count.0 += 1);
+/// ```
+pub struct Dispatch<H, SCOPE = StateHandler<H>>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    link: Link<Model<H>>,
+    _scope: PhantomData<SCOPE>,
+}
+
+impl<H, SCOPE> Dispatch<H, SCOPE>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    /// Connect to shared state. `callback` receives current state on connect, and after every
+    /// change.
+    pub fn new(callback: Callback<Rc<Model<H>>>) -> Self {
+        Self::with_errors(callback, Callback::noop())
+    }
+
+    /// Like `new`, passing errors reported by shared state to `on_error`.
+    pub fn with_errors(callback: Callback<Rc<Model<H>>>, on_error: Callback<Rc<Error>>) -> Self {
+        // Patches are applied to a local copy, so callers always receive entire state.
+        let local: Rc<RefCell<Option<Rc<Model<H>>>>> = Default::default();
+        let callback = Callback::from(move |response| {
+            let state = match response {
+                Response::State(state) => state,
+                Response::Patch(patch) => match &mut *local.borrow_mut() {
+                    Some(state) => {
+                        patch(Rc::make_mut(state));
+                        state.clone()
+                    }
+                    None => return,
+                },
+                Response::Error(error) => return on_error.emit(error),
+                Response::Progress(_) => return,
+            };
+            *local.borrow_mut() = Some(state.clone());
+            callback.emit(state);
+        });
+
+        Self {
+            link: ScopeKey::<StateHandler<H>>::of::<SCOPE>().connect(callback),
+            _scope: PhantomData,
+        }
+    }
+
+    /// Connect to shared state from inside another agent, which is sent the message made by `f`
+    /// after every change. Useful for agents that push into shared state, as for a websocket.
+    ///
+    /// ```ignore
+    /// impl Agent for Socket {
+    ///     fn create(link: AgentLink<Self>) -> Self {
+    ///         let dispatch = Dispatch::from_agent(&link, SocketMsg::Messages);
+    ///         ...
+    ///     }
+    /// }
+    /// ```
+    pub fn from_agent<AGENT, M>(
+        link: &AgentLink<AGENT>,
+        f: impl Fn(Rc<Model<H>>) -> M + 'static,
+    ) -> Self
+    where
+        AGENT: Agent,
+        M: Into<AGENT::Message>,
+    {
+        Self::new(link.callback(f))
+    }
+
+    /// Like `new`, but notified of changes in order of `priority`, relative to other subscribers.
+    pub fn with_priority(priority: Priority, callback: Callback<Rc<Model<H>>>) -> Self {
+        let mut this = Self::new(callback);
+        this.send(Request::Priority(priority));
+        this
+    }
+
+    /// Connect to shared state without receiving changes.
+    pub fn connect() -> Self {
+        Self::new(Callback::noop())
+    }
+
+    /// Apply a function that may mutate shared state.
+    pub fn reduce(&mut self, f: impl FnOnce(&mut Model<H>) + 'static) {
+        self.send(Request::ApplyOnce(Box::new(f)));
+    }
+
+    /// Apply a function that may fail to mutate shared state. See `StateHandle::try_reduce`.
+    pub fn try_reduce(&mut self, f: impl FnOnce(&mut Model<H>) -> Result<(), Error> + 'static) {
+        self.send(Request::TryApplyOnce(Box::new(f)));
+    }
+
+    /// Like `reduce`, naming the change with `label`. Labels are passed to authorizers (see
+    /// `authorize`).
+    pub fn reduce_labeled(&mut self, label: &'static str, f: impl FnOnce(&mut Model<H>) + 'static) {
+        self.send(Request::Labeled(
+            label,
+            Box::new(Request::ApplyOnce(Box::new(f))),
+        ));
+    }
+
+    /// Apply `f` once `delay` has passed. See `StateHandle::reduce_after`.
+    pub fn reduce_after(
+        &mut self,
+        delay: Duration,
+        f: impl FnOnce(&mut Model<H>) + 'static,
+    ) -> ScheduledHandle {
+        let scheduled = ScheduledHandle::default();
+        let request = Request::ApplyOnce(Box::new(f));
+        self.send(Request::Schedule(
+            delay,
+            scheduled.control(),
+            Box::new(request),
+        ));
+        scheduled
+    }
+
+    /// Apply `f` every `interval`. See `StateHandle::every`.
+    pub fn every(
+        &mut self,
+        interval: Duration,
+        f: impl Fn(&mut Model<H>) + 'static,
+    ) -> RecurringHandle {
+        let recurring = RecurringHandle::default();
+        self.send(Request::Every(interval, recurring.control(), Rc::new(f)));
+        recurring
+    }
+
+    /// Set the field at `path` to `value`, labeling the change with the path. See `path`.
+    pub fn set<U: 'static>(&mut self, path: Path<Model<H>, U>, value: U) {
+        self.reduce_labeled(path.name(), move |state| *path.get_mut(state) = value)
+    }
+
+    /// Like `reduce`, skipped if a change with the same `key` was applied recently. See
+    /// `StateHandle::reduce_idempotent`.
+    pub fn reduce_idempotent(
+        &mut self,
+        key: impl Into<String>,
+        f: impl FnOnce(&mut Model<H>) + 'static,
+    ) {
+        self.send(Request::Idempotent(
+            key.into(),
+            Box::new(Request::ApplyOnce(Box::new(f))),
+        ));
+    }
+
+    /// Apply `f` right away, and offer to undo it with the change `f` returns until `window` has
+    /// passed. Only then is `on_commit` called. See `StateHandle::reduce_undoable`.
+    #[cfg(feature = "undo")]
+    pub fn reduce_undoable<U>(
+        &mut self,
+        message: impl Into<String>,
+        f: impl FnOnce(&mut Model<H>) -> U + 'static,
+        window: std::time::Duration,
+        on_commit: impl FnOnce() + 'static,
+    ) -> crate::undo::Undoable
+    where
+        H: 'static,
+        U: FnOnce(&mut Model<H>) + 'static,
+    {
+        let inverse: Rc<RefCell<Option<U>>> = Default::default();
+        let made = inverse.clone();
+        self.reduce(move |state| *made.borrow_mut() = Some(f(state)));
+        let undo = move || {
+            if let Some(inverse) = inverse.borrow_mut().take() {
+                Self::connect().reduce(inverse);
+            }
+        };
+        crate::undo::offer(message.into(), window, undo, on_commit)
+    }
+
+    /// Show `progress` to every handle, until it's replaced or cleared.
+    pub fn report_progress(&mut self, progress: Progress) {
+        self.send(Request::Progress(Some(progress)));
+    }
+
+    /// Stop showing progress, once the operation is done.
+    pub fn clear_progress(&mut self) {
+        self.send(Request::Progress(None));
+    }
+
+    /// Change handler settings at runtime, keeping state.
+    pub fn configure(&mut self, settings: HandlerSettings) {
+        self.send(Request::Configure(settings));
+    }
+
+    /// Create the handler again, as if shared state was created for the first time: storage is
+    /// loaded again, feature flags fetched again, and so on. Subscribers stay connected, and
+    /// receive the fresh state right away. Useful after signing in changes what state should be
+    /// loaded.
+    pub fn restart_handler(&mut self) {
+        self.send(Request::Restart);
+    }
+
+    /// Like `reduce`, returning a future resolving with state once `f` is applied and every
+    /// subscriber notified, or with `None` if it never is. See `StateHandle::reduce_awaitable`.
+    ///
+    /// ```ignore
+    /// let todos = dispatch.reduce_awaitable(|todos| todos.add("Buy milk")).await;
+    /// ```
+    pub fn reduce_awaitable(
+        &mut self,
+        f: impl FnOnce(&mut Model<H>) + 'static,
+    ) -> Applied<Model<H>> {
+        let (request, applied) = watch::awaited(Request::ApplyOnce(Box::new(f)));
+        self.send(request);
+        applied
+    }
+
+    /// Wait for the next change to shared state.
+    pub fn next_state(&mut self) -> Change<Model<H>> {
+        let (request, change) = watch::watch(|_| true, false);
+        self.send(request);
+        change
+    }
+
+    /// Wait until shared state matches `selector`, resolving right away if it already does.
+    ///
+    /// ```ignore
+    /// let session = dispatch.await_change(|session| session.user.is_some()).await;
+    /// ```
+    pub fn await_change(
+        &mut self,
+        selector: impl Fn(&Model<H>) -> bool + 'static,
+    ) -> Change<Model<H>> {
+        let (request, change) = watch::watch(selector, true);
+        self.send(request);
+        change
+    }
+
+    /// Stream of shared state, starting with current state, then state after every change.
+    ///
+    /// ```ignore
+    /// let mut carts = dispatch.stream().filter(|cart| ready(!cart.items.is_empty()));
+    /// while let Some(cart) = carts.next().await {
+    ///     save_draft(&cart).await;
+    /// }
+    /// ```
+    #[cfg(feature = "stream")]
+    pub fn stream(&self) -> StateStream<Model<H>>
+    where
+        H: 'static,
+    {
+        StateStream::new(Self::new)
+    }
+
+    pub(crate) fn send(&mut self, request: Request<Model<H>>) {
+        self.link.send(request);
+    }
+}
+
+impl<H, SCOPE> fmt::Debug for Dispatch<H, SCOPE>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dispatch")
+            .field("handler", &type_name::<StateHandler<H>>())
+            .field("scope", &type_name::<SCOPE>())
+            .finish()
+    }
+}
+
+/// Subscription made by `observe`. Dropping it unsubscribes.
+pub struct ObserverGuard {
+    _dispatch: Box<dyn Any>,
+}
+
+/// Call `f` with shared state of handle type `H`, on subscribing and after every change, until the
+/// returned guard is dropped. For plain services that need to know about changes.
+///
+/// ```ignore
+/// let guard = yew_state::observe::<SharedHandle<Session>>(|session| {
+///     analytics::set_user(session.user.as_ref());
+/// });
+/// ```
+pub fn observe<H>(f: impl Fn(Rc<Model<H>>) + 'static) -> ObserverGuard
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+{
+    observe_in::<H, StateHandler<H>>(f)
+}
+
+/// Like `observe`, for shared state in `SCOPE`.
+pub fn observe_in<H, SCOPE>(f: impl Fn(Rc<Model<H>>) + 'static) -> ObserverGuard
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    ObserverGuard {
+        _dispatch: Box::new(Dispatch::<H, SCOPE>::new(Callback::from(f))),
+    }
+}
+
+/// Shared state of handle type `H` in `SCOPE`, as part of a `Query`.
+pub struct In<H, SCOPE = StateHandler<H>>(PhantomData<(H, SCOPE)>)
+where
+    H: Handle;
+
+/// Several shared states read together by `get_many`. Implemented for tuples of `In`.
+pub trait Query {
+    /// Current state of every scope.
+    type States;
+
+    #[doc(hidden)]
+    fn connect(callback: Callback<Self::States>) -> Vec<Box<dyn Any>>;
+}
+
+/// Pending read started by `get_many`. Dropping it before the callback runs cancels the read.
+pub struct GetMany {
+    _dispatches: Vec<Box<dyn Any>>,
+}
+
+/// Read current state of several scopes at once. `callback` runs once, as soon as every scope has
+/// responded.
+///
+/// ```ignore
+/// type Dashboard = (In<SharedHandle<Cart>>, In<SharedHandle<User>, Account>);
+///
+/// self.pending = Some(dispatch::get_many::<Dashboard>(
+///     self.link.callback(|(cart, user)| Msg::Loaded(cart, user)),
+/// ));
+/// ```
+pub fn get_many<Q: Query>(callback: Callback<Q::States>) -> GetMany {
+    GetMany {
+        _dispatches: Q::connect(callback),
+    }
+}
+
+macro_rules! impl_query {
+    ($($handler:ident $scope:ident $index:tt),+) => {
+        impl<$($handler, $scope),+> Query for ($(In<$handler, $scope>,)+)
+        where
+            $(
+                $handler: Handle + 'static,
+                StateHandler<$handler>: Clone + 'static,
+                $scope: 'static,
+            )+
+        {
+            type States = ($(Rc<Model<$handler>>,)+);
+
+            fn connect(callback: Callback<Self::States>) -> Vec<Box<dyn Any>> {
+                let slots: Rc<RefCell<($(Option<Rc<Model<$handler>>>,)+)>> = Default::default();
+                let done = Rc::new(Cell::new(false));
+                let ready = {
+                    let slots = slots.clone();
+                    Rc::new(move || -> Option<Self::States> {
+                        let slots = slots.borrow();
+                        Some(($(slots.$index.clone()?,)+))
+                    })
+                };
+
+                vec![$({
+                    let slots = slots.clone();
+                    let done = done.clone();
+                    let ready = ready.clone();
+                    let callback = callback.clone();
+                    let dispatch = Dispatch::<$handler, $scope>::new(Callback::from(move |state| {
+                        slots.borrow_mut().$index = Some(state);
+                        if done.get() {
+                            return;
+                        }
+                        if let Some(states) = ready() {
+                            done.set(true);
+                            callback.emit(states);
+                        }
+                    }));
+                    Box::new(dispatch) as Box<dyn Any>
+                }),+]
+            }
+        }
+    };
+}
+
+impl_query!(A SA 0, B SB 1);
+impl_query!(A SA 0, B SB 1, C SC 2);
+impl_query!(A SA 0, B SB 1, C SC 2, D SD 3);
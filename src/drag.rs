@@ -0,0 +1,206 @@
+//! Drag-and-drop across components.
+//!
+//! `DragHandle<T>` tracks what's being dragged (a payload of type `T`, and the name of where it
+//! came from) and which drop target it's over, for the whole app. Helpers produce the callbacks
+//! for draggable elements and drop targets:
+//!
+//! ```ignore
+//! // In a card list, with `handle: DragHandle<CardId>`.
+//! html! {
+//!     <li draggable="true" ondragstart=drag::on_drag_start(&self.handle, "backlog", card.id)
+//!         ondragend=drag::on_drag_end(&self.handle)>
+//!         { &card.title }
+//!     </li>
+//! }
+//!
+//! // In a column.
+//! html! {
+//!     <ul ondragover=drag::on_drag_over(&self.handle, "done")
+//!         ondragleave=drag::on_drag_leave(&self.handle, "done")
+//!         ondrop=drag::on_drop(&self.handle, "done")>
+//!         ...
+//!     </ul>
+//! }
+//!
+//! // When the column receives new state, with `seen` starting at 0.
+//! if let Some(dropped) = self.handle.state().take_dropped("done", &mut self.seen) {
+//!     self.move_card(dropped.payload);
+//! }
+//! ```
+use web_sys::DragEvent;
+use yew::Callback;
+
+use crate::handle::DragHandle;
+
+/// Something being dragged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dragging<T> {
+    pub payload: T,
+    /// Where it's dragged from.
+    pub source: String,
+}
+
+/// Something dropped on a target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dropped<T> {
+    pub payload: T,
+    pub source: String,
+    pub target: String,
+    /// Drops so far, so one drop can be told apart from the next.
+    pub count: u64,
+}
+
+/// Drag-and-drop state of the whole app.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Drag<T> {
+    dragging: Option<Dragging<T>>,
+    hovered: Option<String>,
+    dropped: Option<Dropped<T>>,
+}
+
+impl<T> Default for Drag<T> {
+    fn default() -> Self {
+        Self {
+            dragging: None,
+            hovered: None,
+            dropped: None,
+        }
+    }
+}
+
+impl<T: Clone> Drag<T> {
+    /// What's being dragged, if anything.
+    pub fn dragging(&self) -> Option<&Dragging<T>> {
+        self.dragging.as_ref()
+    }
+
+    /// Drop target the payload is over, if any.
+    pub fn hovered(&self) -> Option<&str> {
+        self.hovered.as_deref()
+    }
+
+    /// Whether the payload is over `target`, say to highlight it.
+    pub fn is_hovered(&self, target: &str) -> bool {
+        self.hovered() == Some(target)
+    }
+
+    /// The last drop, if any.
+    pub fn dropped(&self) -> Option<&Dropped<T>> {
+        self.dropped.as_ref()
+    }
+
+    /// The last drop, if it was on `target` after `seen`, which is then updated. Keep `seen`
+    /// (starting from 0) in each target to handle every drop once.
+    pub fn take_dropped(&self, target: &str, seen: &mut u64) -> Option<&Dropped<T>> {
+        let dropped = self
+            .dropped
+            .as_ref()
+            .filter(|dropped| dropped.target == target && dropped.count > *seen)?;
+        *seen = dropped.count;
+        Some(dropped)
+    }
+
+    pub fn start(&mut self, source: &str, payload: T) {
+        self.dragging = Some(Dragging {
+            payload,
+            source: source.to_string(),
+        });
+        self.hovered = None;
+    }
+
+    pub fn hover(&mut self, target: &str) {
+        if self.dragging.is_some() {
+            self.hovered = Some(target.to_string());
+        }
+    }
+
+    /// Leave `target`, if the payload is over it.
+    pub fn leave(&mut self, target: &str) {
+        if self.is_hovered(target) {
+            self.hovered = None;
+        }
+    }
+
+    /// Drop the payload on `target`. Returns whether anything was being dragged.
+    pub fn drop_on(&mut self, target: &str) -> bool {
+        let dragging = match self.dragging.take() {
+            Some(dragging) => dragging,
+            None => return false,
+        };
+        let count = self.dropped.as_ref().map_or(0, |last| last.count) + 1;
+        self.dropped = Some(Dropped {
+            payload: dragging.payload,
+            source: dragging.source,
+            target: target.to_string(),
+            count,
+        });
+        self.hovered = None;
+        true
+    }
+
+    /// Stop dragging without dropping, as when a drag is cancelled.
+    pub fn end(&mut self) {
+        self.dragging = None;
+        self.hovered = None;
+    }
+}
+
+/// Callback for `ondragstart` of a draggable element, starting to drag `payload` from `source`.
+pub fn on_drag_start<T>(
+    handle: &DragHandle<T>,
+    source: &'static str,
+    payload: T,
+) -> Callback<DragEvent>
+where
+    T: Clone + 'static,
+{
+    handle.reduce_callback_with(move |drag, event: DragEvent| {
+        // Some browsers only start dragging with data set.
+        if let Some(data) = event.data_transfer() {
+            let _ = data.set_data("text/plain", source);
+        }
+        drag.start(source, payload.clone());
+    })
+}
+
+/// Callback for `ondragover` of a drop target, allowing drops on it.
+pub fn on_drag_over<T>(handle: &DragHandle<T>, target: &'static str) -> Callback<DragEvent>
+where
+    T: Clone + 'static,
+{
+    // Fires continuously while dragging, so only change state when entering the target.
+    if handle.state().is_hovered(target) {
+        return Callback::from(|event: DragEvent| event.prevent_default());
+    }
+    handle.reduce_callback_with(move |drag, event: DragEvent| {
+        event.prevent_default();
+        drag.hover(target);
+    })
+}
+
+/// Callback for `ondragleave` of a drop target.
+pub fn on_drag_leave<T>(handle: &DragHandle<T>, target: &'static str) -> Callback<DragEvent>
+where
+    T: Clone + 'static,
+{
+    handle.reduce_callback(move |drag| drag.leave(target))
+}
+
+/// Callback for `ondrop` of a drop target, dropping the payload on it.
+pub fn on_drop<T>(handle: &DragHandle<T>, target: &'static str) -> Callback<DragEvent>
+where
+    T: Clone + 'static,
+{
+    handle.reduce_callback_with(move |drag, event: DragEvent| {
+        event.prevent_default();
+        drag.drop_on(target);
+    })
+}
+
+/// Callback for `ondragend` of a draggable element, clearing the drag if it wasn't dropped.
+pub fn on_drag_end<T>(handle: &DragHandle<T>) -> Callback<DragEvent>
+where
+    T: Clone + 'static,
+{
+    handle.reduce_callback(|drag| drag.end())
+}
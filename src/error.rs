@@ -0,0 +1,72 @@
+//! Errors reported by shared state.
+use std::fmt;
+
+/// Error reported to subscribers of shared state.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A state machine rejected an event in its current state.
+    IllegalTransition { state: String, event: String },
+    /// The guard of a state machine transition didn't allow it.
+    GuardRejected { state: String, event: String },
+    /// Persisted state was corrupt, and has been moved to a backup entry.
+    CorruptStorage { key: String, backup: String },
+    /// State couldn't be serialized for storage (say a map with keys that aren't strings), and
+    /// wasn't stored.
+    Unstorable { key: String, reason: String },
+    /// A file doesn't contain valid state.
+    InvalidFile(String),
+    /// A sync transport couldn't keep up, and outgoing messages were dropped.
+    SyncBackpressure { held: usize, dropped: usize },
+    /// A message from a sync peer couldn't be decoded, and was ignored.
+    InvalidSyncMessage(String),
+    /// A change wasn't authorized, and wasn't applied. Has the change's label, if it had one.
+    Unauthorized { label: Option<String> },
+    /// Changes came faster than the rate limit allows, and some were dropped or deferred. Has the
+    /// label of the first change held back, if it had one.
+    RateLimited { label: Option<String> },
+    /// A message couldn't be encoded (say for a worker), and wasn't sent.
+    Encode(String),
+    /// The shared state service couldn't be bridged. See `SharedStateComponent::try_create`.
+    Unbridged,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IllegalTransition { state, event } => {
+                write!(f, "illegal transition: {} can't handle {}", state, event)
+            }
+            Error::GuardRejected { state, event } => {
+                write!(f, "transition rejected: {} with {}", state, event)
+            }
+            Error::CorruptStorage { key, backup } => {
+                write!(
+                    f,
+                    "corrupt state in storage at {}, moved to {}",
+                    key, backup
+                )
+            }
+            Error::Unstorable { key, reason } => {
+                write!(f, "can't store state at {}: {}", key, reason)
+            }
+            Error::InvalidFile(reason) => write!(f, "invalid file: {}", reason),
+            Error::SyncBackpressure { held, dropped } => write!(
+                f,
+                "sync can't keep up: {} messages held, {} dropped",
+                held, dropped
+            ),
+            Error::InvalidSyncMessage(reason) => write!(f, "invalid sync message: {}", reason),
+            Error::Unauthorized { label: Some(label) } => write!(f, "not authorized: {}", label),
+            Error::Unauthorized { label: None } => write!(f, "not authorized to change state"),
+            Error::RateLimited { label: Some(label) } => {
+                write!(f, "too many changes labeled {}", label)
+            }
+            Error::RateLimited { label: None } => write!(f, "too many changes"),
+            Error::Encode(reason) => write!(f, "can't encode message: {}", reason),
+            Error::Unbridged => write!(f, "can't bridge to the shared state service"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
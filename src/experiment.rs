@@ -0,0 +1,202 @@
+//! A/B experiments, with variants assigned per client and kept across visits.
+//!
+//! Each experiment's variant is picked by hashing the id of this client (see `client_id`) with the
+//! experiment's name, so assignments are stable, and independent between experiments. Assignments
+//! are stored too, so a client keeps its variant even if the experiment's variants are reordered or
+//! added to later.
+//!
+//! ```ignore
+//! struct Experiments;
+//!
+//! impl ExperimentSource for Experiments {
+//!     fn experiments() -> &'static [(&'static str, &'static [&'static str])] {
+//!         &[("checkout", &["control", "one-page"])]
+//!     }
+//! }
+//!
+//! // Once, at startup.
+//! experiment::on_exposure(|experiment, variant| telemetry::track(experiment, variant));
+//!
+//! // In a component with `handle: ExperimentHandle<Experiments>`.
+//! if self.handle.state().is("checkout", "one-page") {
+//!     html! { <OnePageCheckout /> }
+//! }
+//! ```
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use yew::{format::Json, services::storage::Area};
+
+use crate::client::client_id;
+use crate::config;
+use crate::handler::{Handler, Reduction, ReductionOnce};
+use crate::storage::{checksum, WebStorage};
+
+type ExposureHook = Rc<dyn Fn(&str, &str)>;
+
+thread_local! {
+    static ON_EXPOSURE: RefCell<Option<ExposureHook>> = Default::default();
+    /// Experiments already reported as exposed on this page.
+    static EXPOSED: RefCell<HashSet<String>> = Default::default();
+}
+
+/// Experiments to assign variants of. Each source's assignments are shared state of their own.
+pub trait ExperimentSource: 'static {
+    /// Experiments running, as their names and variants. Variants are equally likely.
+    fn experiments() -> &'static [(&'static str, &'static [&'static str])];
+    /// Key assignments are stored at in local storage. The prefix set with
+    /// `config().key_prefix()` is prepended to it.
+    fn key() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Be notified with `f` once per page load for each experiment a client is exposed to, that is,
+/// whose variant is read with `Assignments::variant` or `Assignments::is`. Useful for logging
+/// exposures to analytics.
+pub fn on_exposure(f: impl Fn(&str, &str) + 'static) {
+    ON_EXPOSURE.with(|hook| *hook.borrow_mut() = Some(Rc::new(f)));
+}
+
+fn exposed(experiment: &str, variant: &str) {
+    let first = EXPOSED.with(|exposed| exposed.borrow_mut().insert(experiment.to_string()));
+    if !first {
+        return;
+    }
+    // Cloned out, so the hook may read variants itself.
+    let hook = ON_EXPOSURE.with(|hook| hook.borrow().clone());
+    if let Some(hook) = hook {
+        hook(experiment, variant);
+    }
+}
+
+/// Variants assigned to this client, by experiment.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Assignments {
+    variants: BTreeMap<String, String>,
+}
+
+impl Assignments {
+    /// Variant of `experiment` assigned to this client, if it's running.
+    pub fn variant(&self, experiment: &str) -> Option<&str> {
+        let variant = self.variants.get(experiment)?;
+        exposed(experiment, variant);
+        Some(variant)
+    }
+
+    /// Whether this client is assigned `variant` of `experiment`.
+    pub fn is(&self, experiment: &str, variant: &str) -> bool {
+        self.variant(experiment) == Some(variant)
+    }
+
+    /// Every assignment, without counting as exposure.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.variants
+            .iter()
+            .map(|(experiment, variant)| (experiment.as_str(), variant.as_str()))
+    }
+
+    /// Assign `variant` of `experiment`, overriding the assigned one. Useful for trying out a
+    /// variant during development.
+    pub fn assign(&mut self, experiment: &str, variant: &str) {
+        self.variants
+            .insert(experiment.to_string(), variant.to_string());
+    }
+}
+
+/// Variant of `experiment` for client `id`.
+fn pick<'a>(id: &str, experiment: &str, variants: &[&'a str]) -> Option<&'a str> {
+    if variants.is_empty() {
+        return None;
+    }
+    let hash = checksum(&format!("{}:{}", experiment, id));
+    Some(variants[(hash % variants.len() as u64) as usize])
+}
+
+fn storage() -> Option<WebStorage> {
+    WebStorage::new(Area::Local).ok()
+}
+
+fn key<S: ExperimentSource>() -> String {
+    format!("{}{}", config::key_prefix(), S::key())
+}
+
+/// Handler assigning variants of experiments from `S`.
+///
+/// Stored assignments are kept as long as their experiment runs with that variant; other
+/// experiments are assigned when state is created.
+pub struct ExperimentHandler<S> {
+    state: Rc<Assignments>,
+    _source: PhantomData<S>,
+}
+
+impl<S: ExperimentSource> ExperimentHandler<S> {
+    fn save(&self) {
+        if let Some(mut storage) = storage() {
+            storage.store(&key::<S>(), Json(&*self.state));
+        }
+    }
+}
+
+impl<S: ExperimentSource> Handler for ExperimentHandler<S> {
+    type Model = Assignments;
+
+    fn new() -> Self {
+        crate::storage::reserve(S::key());
+        let stored: Assignments = storage()
+            .and_then(|storage| match storage.restore(&key::<S>()) {
+                Json(Ok(assignments)) => Some(assignments),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let id = client_id();
+        let mut state = Assignments::default();
+        for (experiment, variants) in S::experiments() {
+            let variant = stored
+                .variants
+                .get(*experiment)
+                .map(String::as_str)
+                .filter(|variant| variants.contains(variant))
+                .or_else(|| pick(&id, experiment, variants));
+            if let Some(variant) = variant {
+                state.assign(experiment, variant);
+            }
+        }
+
+        let this = Self {
+            state: Rc::new(state),
+            _source: PhantomData,
+        };
+        if *this.state != stored {
+            this.save();
+        }
+        this
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.save();
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.save();
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+impl<S> Clone for ExperimentHandler<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            _source: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Backing up shared state to files.
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, File, HtmlAnchorElement, Url};
+
+use crate::error::Error;
+use crate::handle::StateHandle;
+use crate::handler::Handler;
+use crate::sensitive::sealed;
+
+/// Download current state as a JSON file named `filename`.
+pub fn export_to_file<T, H>(handle: &StateHandle<T, H>, filename: &str) -> Result<(), JsValue>
+where
+    T: Default + Clone + Serialize + 'static,
+    H: Handler<Model = T>,
+{
+    let json = sealed(|| serde_json::to_string_pretty(handle.state()))
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let parts = js_sys::Array::of1(&JsValue::from_str(&json));
+    let options = BlobPropertyBag::new();
+    options.set_type("application/json");
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+    let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(filename);
+    link.click();
+
+    Url::revoke_object_url(&url)
+}
+
+/// Replace shared state with the contents of a file, previously saved with `export_to_file`.
+///
+/// ```ignore
+/// let onchange = self.link.callback(|data| match data {
+///     ChangeData::Files(files) => Msg::Import(files.get(0)),
+///     _ => Msg::Import(None),
+/// });
+/// // ..
+/// Msg::Import(Some(file)) => {
+///     let handle = self.handle.clone();
+///     spawn_local(async move {
+///         let _ = file::import_from_file(&handle, file).await;
+///     });
+/// }
+/// ```
+pub async fn import_from_file<T, H>(handle: &StateHandle<T, H>, file: File) -> Result<(), Error>
+where
+    T: Default + Clone + for<'a> Deserialize<'a> + 'static,
+    H: Handler<Model = T>,
+{
+    let text = JsFuture::from(file.text())
+        .await
+        .ok()
+        .and_then(|text| text.as_string())
+        .ok_or_else(|| Error::InvalidFile("can't read file".to_string()))?;
+    let backup: T =
+        serde_json::from_str(&text).map_err(|err| Error::InvalidFile(err.to_string()))?;
+
+    handle.reduce(move |state| *state = backup);
+    Ok(())
+}
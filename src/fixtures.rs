@@ -0,0 +1,55 @@
+//! Named fixture states, for reviewing components across state permutations.
+//!
+//! Register fixtures once at startup, then render components inside a
+//! [`FixtureHarness`](crate::component::FixtureHarness) to switch between them:
+//!
+//! ```ignore
+//! fixtures::register("empty_cart", Cart::default());
+//! fixtures::register("full_cart", Cart::with_items(30));
+//! ```
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Named = Vec<(String, Rc<dyn Any>)>;
+
+thread_local! {
+    static FIXTURES: RefCell<HashMap<TypeId, Named>> = Default::default();
+}
+
+/// Register `state` as fixture `name` of its type, replacing any fixture of that name.
+pub fn register<T: 'static>(name: &str, state: T) {
+    let state: Rc<dyn Any> = Rc::new(state);
+    FIXTURES.with(|fixtures| {
+        let mut fixtures = fixtures.borrow_mut();
+        let named = fixtures.entry(TypeId::of::<T>()).or_default();
+        match named.iter_mut().find(|(other, _)| other == name) {
+            Some((_, fixture)) => *fixture = state,
+            None => named.push((name.to_string(), state)),
+        }
+    });
+}
+
+/// Names of fixtures of type `T`, in the order they were first registered.
+pub fn names<T: 'static>() -> Vec<String> {
+    FIXTURES.with(|fixtures| {
+        fixtures
+            .borrow()
+            .get(&TypeId::of::<T>())
+            .map(|named| named.iter().map(|(name, _)| name.clone()).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Fixture `name` of type `T`, if it's registered.
+pub fn get<T: 'static>(name: &str) -> Option<Rc<T>> {
+    FIXTURES.with(|fixtures| {
+        fixtures
+            .borrow()
+            .get(&TypeId::of::<T>())?
+            .iter()
+            .find(|(other, _)| other == name)
+            .and_then(|(_, fixture)| fixture.clone().downcast().ok())
+    })
+}
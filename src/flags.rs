@@ -0,0 +1,239 @@
+//! Feature flags loaded from a server.
+//!
+//! Flags are a map of names to whether they're enabled, fetched as JSON from `FlagSource::url`:
+//!
+//! ```json
+//! { "new-editor": true, "dark-mode": false }
+//! ```
+//!
+//! ```ignore
+//! struct Flags;
+//!
+//! impl FlagSource for Flags {
+//!     fn url() -> &'static str {
+//!         "/api/flags"
+//!     }
+//! }
+//!
+//! // In a component with `handle: FlagHandle<Flags>`.
+//! if self.handle.state().is_enabled("new-editor") {
+//!     html! { <NewEditor /> }
+//! }
+//! ```
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use yew::{
+    format::{Json, Nothing, Text},
+    services::{
+        fetch::{FetchTask, Request, Response},
+        interval::IntervalTask,
+        storage::Area,
+        FetchService, IntervalService,
+    },
+    Callback,
+};
+
+use crate::config;
+use crate::dispatch::Dispatch;
+use crate::handle::FlagHandle;
+use crate::handler::{Handler, HandlerSettings, Reduction, ReductionOnce};
+use crate::power;
+use crate::storage::{self, WebStorage};
+
+/// Where feature flags are loaded from. Each source's flags are shared state of their own.
+pub trait FlagSource: 'static {
+    /// URL returning flags as a JSON object of names to booleans.
+    fn url() -> &'static str;
+    /// How often flags are fetched again. Every 5 minutes by default.
+    fn refresh() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+    /// Key flags are cached at in local storage. The prefix set with `config().key_prefix()` is
+    /// prepended to it.
+    fn key() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Feature flags, by name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Flags {
+    flags: HashMap<String, bool>,
+}
+
+impl Flags {
+    /// Whether flag `name` is enabled. Unknown flags are disabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.flags.get(name).copied().unwrap_or(false)
+    }
+
+    /// Every flag, enabled or not.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.flags
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+    }
+
+    /// Enable or disable flag `name` locally, until flags are next fetched. Useful for trying out
+    /// a feature during development.
+    pub fn set(&mut self, name: &str, enabled: bool) {
+        self.flags.insert(name.to_string(), enabled);
+    }
+}
+
+/// Selector for whether flag `name` is enabled, for use with `Dispatch::await_change` and such.
+pub fn is_enabled(name: &'static str) -> impl Fn(&Flags) -> bool {
+    move |flags| flags.is_enabled(name)
+}
+
+fn key<S: FlagSource>() -> String {
+    format!("{}{}", config::key_prefix(), S::key())
+}
+
+/// Where flags are fetched from, and the fetch in flight.
+struct Source {
+    url: String,
+    task: Option<FetchTask>,
+}
+
+/// Fetch flags of `S`, replacing current flags when they arrive. Flags are kept as they are if
+/// fetching fails.
+fn fetch<S: FlagSource>(source: &Rc<RefCell<Source>>) {
+    let url = source.borrow().url.clone();
+    let request = match Request::get(url).body(Nothing) {
+        Ok(request) => request,
+        Err(_) => return,
+    };
+    let callback = Callback::from(|response: Response<Text>| {
+        let (meta, body) = response.into_parts();
+        if !meta.status.is_success() {
+            return;
+        }
+        let flags = match body.ok().and_then(|body| serde_json::from_str(&body).ok()) {
+            Some(flags) => Flags { flags },
+            None => return,
+        };
+        Dispatch::<FlagHandle<S>>::connect().reduce(move |current| *current = flags);
+    });
+    source.borrow_mut().task = FetchService::fetch(request, callback).ok();
+}
+
+/// Fetch flags of `S` every `interval`, stretched in low power mode.
+fn refresh<S: FlagSource>(source: &Rc<RefCell<Source>>, interval: Duration) -> IntervalTask {
+    let source = source.clone();
+    let interval = power::stretch(interval);
+    IntervalService::spawn(interval, Callback::from(move |_| fetch::<S>(&source)))
+}
+
+/// Handler for feature flags loaded from `S`.
+///
+/// Flags start from those cached by the last visit, and are fetched when state is created, then
+/// every `FlagSource::refresh`. Fetched flags are cached in local storage. The URL and refresh
+/// interval can be changed at runtime with `configure`.
+pub struct FeatureFlagHandler<S> {
+    state: Rc<Flags>,
+    source: Option<Rc<RefCell<Source>>>,
+    /// How often flags are fetched, before low power mode stretches it.
+    interval: Duration,
+    _refresh: Option<IntervalTask>,
+    _source: PhantomData<S>,
+}
+
+impl<S: FlagSource> FeatureFlagHandler<S> {
+    fn save(&self) {
+        if let Ok(mut storage) = WebStorage::new(Area::Local) {
+            storage.store(&key::<S>(), Json(&*self.state));
+        }
+    }
+}
+
+impl<S: FlagSource> Handler for FeatureFlagHandler<S> {
+    type Model = Flags;
+
+    fn new() -> Self {
+        storage::reserve(S::key());
+        let state = WebStorage::new(Area::Local)
+            .ok()
+            .and_then(|storage| match storage.restore(&key::<S>()) {
+                Json(Ok(flags)) => Some(flags),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let source = Rc::new(RefCell::new(Source {
+            url: S::url().to_string(),
+            task: None,
+        }));
+        fetch::<S>(&source);
+
+        Self {
+            state: Rc::new(state),
+            interval: S::refresh(),
+            _refresh: Some(refresh::<S>(&source, S::refresh())),
+            source: Some(source),
+            _source: PhantomData,
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.save();
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.save();
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+
+    fn configure(&mut self, settings: &HandlerSettings) {
+        // Clones don't fetch.
+        let source = match &self.source {
+            Some(source) => source,
+            None => return,
+        };
+        if let Some(url) = &settings.url {
+            source.borrow_mut().url = url.clone();
+            fetch::<S>(source);
+        }
+        if let Some(interval) = settings.refresh {
+            self.interval = interval;
+            self._refresh = Some(refresh::<S>(source, interval));
+        }
+    }
+
+    fn low_power(&mut self, _low: bool) {
+        if let Some(source) = &self.source {
+            self._refresh = Some(refresh::<S>(source, self.interval));
+        }
+    }
+
+    /// Flags may have changed while away, with refreshes held up.
+    #[cfg(feature = "wake")]
+    fn wake(&mut self) {
+        if let Some(source) = &self.source {
+            fetch::<S>(source);
+        }
+    }
+}
+
+impl<S> Clone for FeatureFlagHandler<S> {
+    /// Clones share flags, but not fetching.
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            source: None,
+            interval: self.interval,
+            _refresh: None,
+            _source: PhantomData,
+        }
+    }
+}
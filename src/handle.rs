@@ -1,9 +1,23 @@
 //! Ergonomic interface with shared state.
+use std::any::type_name;
+use std::cell::Cell;
+use std::fmt;
 use std::rc::Rc;
+use std::time::Duration;
 
-use yew::{Callback, Properties};
+use yew::{Callback, Html, Properties};
 
-use super::handler::{Handler, Reduction, ReductionOnce, SharedHandler, StorageHandler};
+use super::component::StateConfig;
+use super::error::Error;
+use super::handler::{
+    Detached, DiffHandler, Handler, Reduction, Request, SharedHandler, StorageHandler, Summary,
+};
+use super::path::Path;
+use super::progress::Progress;
+use super::schedule::{RecurringHandle, ScheduledHandle};
+use super::watch::{self, Applied, Change};
+
+pub use super::handler::{Diff, HandlerSettings, Priority};
 
 type Model<T> = <T as Handler>::Model;
 
@@ -12,18 +26,69 @@ pub trait Handle {
     type Handler: Handler;
 
     fn set_local_state(&mut self, state: Rc<Model<Self::Handler>>);
-    fn set_local_callback(
-        &mut self,
-        callback: Callback<Reduction<Model<Self::Handler>>>,
-        callback_once: Callback<ReductionOnce<Model<Self::Handler>>>,
-    );
+    /// Patches a copy of local state by default, for handles that keep it.
+    fn patch_local_state(&mut self, patch: Reduction<Model<Self::Handler>>) {
+        if let Some(mut state) = self.local_state() {
+            patch(Rc::make_mut(&mut state));
+            self.set_local_state(state);
+        }
+    }
+    fn set_local_error(&mut self, _error: Rc<Error>) {}
+    fn set_local_progress(&mut self, _progress: Option<Rc<Progress>>) {}
+    fn set_local_callback(&mut self, callback: Callback<Request<Model<Self::Handler>>>);
+    /// Called back once state is first read, so lazy components connect then.
+    fn set_local_read(&mut self, _read: Callback<()>) {}
+    /// Where to hand work finishing later, see `StateHandle::detach`.
+    fn set_local_detach(&mut self, _detach: Callback<Detached<Model<Self::Handler>>>) {}
     fn set_local(&mut self, other: &Self);
+    fn local_error(&self) -> Option<Rc<Error>> {
+        None
+    }
+    fn local_state(&self) -> Option<Rc<Model<Self::Handler>>> {
+        None
+    }
+    fn local_callback(&self) -> Option<&Callback<Request<Model<Self::Handler>>>> {
+        None
+    }
+    /// Whether this handle should be connected to shared state.
+    fn is_connected(&self) -> bool {
+        true
+    }
 }
 
 /// Trait that provides access to state handle
 pub trait SharedState {
     type Handle: Handle;
     fn handle(&mut self) -> &mut Self::Handle;
+    /// Rendered by the wrapper component until the first state arrives, instead of rendering with
+    /// default state. Renders with default state when `None`, which is the default.
+    fn fallback(&self) -> Option<Html> {
+        None
+    }
+    /// Order in which the wrapper component is notified of changes, relative to other
+    /// subscribers.
+    fn priority(&self) -> Priority {
+        Priority::Normal
+    }
+    /// State to start from, instead of default. Only used by the first component to connect,
+    /// before state is changed by anything else.
+    fn initial(&self) -> Option<Rc<Model<<Self::Handle as Handle>::Handler>>> {
+        None
+    }
+    /// Whether to connect to shared state only once the handle is first used, to read state or to
+    /// change it, for components that mostly dispatch. Until then the component isn't notified of
+    /// changes made elsewhere. Off by default.
+    fn lazy(&self) -> bool {
+        false
+    }
+    /// Configuration of the wrapper component, for behavior that would otherwise take a scope
+    /// type parameter or a policy set elsewhere. See `StateConfig`.
+    fn config(&self) -> StateConfig<Self>
+    where
+        Self: Sized,
+    {
+        StateConfig::new()
+    }
 }
 
 /// Interface to shared state
@@ -36,9 +101,19 @@ where
     #[prop_or_default]
     state: Rc<T>,
     #[prop_or_default]
-    callback: Callback<Reduction<T>>,
+    error: Option<Rc<Error>>,
+    #[prop_or_default]
+    progress: Option<Rc<Progress>>,
+    #[prop_or_default]
+    callback: Callback<Request<T>>,
+    /// Called back on first read, shared by clones. See `SharedState::lazy`.
+    #[prop_or_default]
+    unread: Rc<Cell<Option<Callback<()>>>>,
     #[prop_or_default]
-    callback_once: Callback<ReductionOnce<T>>,
+    detach: Option<Callback<Detached<T>>>,
+    /// State to start from, see `SharedState::initial`.
+    #[prop_or_default]
+    initial: Option<Rc<T>>,
     #[prop_or_default]
     _mark: std::marker::PhantomData<H>,
 }
@@ -49,13 +124,215 @@ where
     H: Handler<Model = T>,
 {
     pub fn state(&self) -> &T {
+        self.read();
         &self.state
     }
 
+    fn read(&self) {
+        if let Some(read) = self.unread.take() {
+            read.emit(());
+        }
+    }
+
+    /// Last error reported by shared state, if any.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_deref()
+    }
+
+    /// Progress of a long-running operation on shared state, if one is underway.
+    pub fn progress(&self) -> Option<&Progress> {
+        self.progress.as_deref()
+    }
+
+    /// Report `error` to every subscriber, leaving state as is.
+    pub(crate) fn report(&self, error: Error) {
+        self.callback.emit(Request::Report(error))
+    }
+
+    /// Show `progress` to every subscriber, until it's replaced or cleared.
+    pub fn report_progress(&self, progress: Progress) {
+        self.callback.emit(Request::Progress(Some(progress)))
+    }
+
+    /// Stop showing progress, once the operation is done.
+    pub fn clear_progress(&self) {
+        self.callback.emit(Request::Progress(None))
+    }
+
+    /// Change handler settings at runtime, keeping state.
+    pub fn configure(&self, settings: HandlerSettings) {
+        self.callback.emit(Request::Configure(settings))
+    }
+
+    /// Create the handler again, as if shared state was created for the first time (reloading
+    /// storage and such), keeping subscribers. See `Dispatch::restart_handler`.
+    pub fn restart_handler(&self) {
+        self.callback.emit(Request::Restart)
+    }
+
+    /// Derive a read-only handle to part of current state, suitable as a property for child
+    /// components. Mapped handles compare by their mapped value, so children are only updated when
+    /// the part they care about changes.
+    pub fn map<U>(&self, f: impl FnOnce(&T) -> U) -> MappedHandle<U> {
+        MappedHandle::new(f(self.state()))
+    }
+
+    pub(crate) fn send(&self, request: Request<T>) {
+        self.callback.emit(request)
+    }
+
+    /// Run `task`, giving it a callback sending requests to shared state even once this handle's
+    /// component is gone. Handles without a wrapper component give it their own callback.
+    pub(crate) fn detach(&self, task: Detached<T>) {
+        match &self.detach {
+            Some(detach) => detach.emit(task),
+            None => task(self.callback.clone()),
+        }
+    }
+
     /// Apply a function that may mutate shared state.
     /// Changes are not immediate, and must be handled in `Component::change`.
     pub fn reduce(&self, f: impl FnOnce(&mut T) + 'static) {
-        self.callback_once.emit(Box::new(f))
+        self.callback.emit(Request::ApplyOnce(Box::new(f)))
+    }
+
+    /// Like `reduce`, naming the change with `label`. Labels are passed to authorizers (see
+    /// `authorize`).
+    pub fn reduce_labeled(&self, label: &'static str, f: impl FnOnce(&mut T) + 'static) {
+        self.callback.emit(Request::Labeled(
+            label,
+            Box::new(Request::ApplyOnce(Box::new(f))),
+        ))
+    }
+
+    /// Apply `f` once `delay` has passed, even if this component is gone by then. The returned
+    /// handle cancels or reschedules it. See `schedule`.
+    pub fn reduce_after(
+        &self,
+        delay: Duration,
+        f: impl FnOnce(&mut T) + 'static,
+    ) -> ScheduledHandle {
+        let scheduled = ScheduledHandle::default();
+        let request = Request::ApplyOnce(Box::new(f));
+        self.callback.emit(Request::Schedule(
+            delay,
+            scheduled.control(),
+            Box::new(request),
+        ));
+        scheduled
+    }
+
+    /// Apply `f` every `interval`, for as long as the scope's state is alive, even if this
+    /// component is gone. The returned handle pauses, resumes or cancels it. See `schedule`.
+    pub fn every(&self, interval: Duration, f: impl Fn(&mut T) + 'static) -> RecurringHandle {
+        let recurring = RecurringHandle::default();
+        self.callback
+            .emit(Request::Every(interval, recurring.control(), Rc::new(f)));
+        recurring
+    }
+
+    /// Set the field at `path` to `value`, labeling the change with the path. See `path`.
+    pub fn set<U: 'static>(&self, path: Path<T, U>, value: U) {
+        self.reduce_labeled(path.name(), move |state| *path.get_mut(state) = value)
+    }
+
+    /// Apply `f` right away, and offer to undo it (with `UndoToasts`) until `window` has passed.
+    /// `f` returns the change undoing it, which reverses only what `f` did. Only once the window
+    /// has passed is `on_commit` called, for the part that can't be undone. See `undo`.
+    #[cfg(feature = "undo")]
+    pub fn reduce_undoable<U>(
+        &self,
+        message: impl Into<String>,
+        f: impl FnOnce(&mut T) -> U + 'static,
+        window: std::time::Duration,
+        on_commit: impl FnOnce() + 'static,
+    ) -> crate::undo::Undoable
+    where
+        H: 'static,
+        U: FnOnce(&mut T) + 'static,
+    {
+        let inverse: Rc<std::cell::RefCell<Option<U>>> = Default::default();
+        let made = inverse.clone();
+        self.reduce(move |state| *made.borrow_mut() = Some(f(state)));
+        let handle = self.clone();
+        let undo = move || {
+            if let Some(inverse) = inverse.borrow_mut().take() {
+                handle.reduce(inverse);
+            }
+        };
+        crate::undo::offer(message.into(), window, undo, on_commit)
+    }
+
+    /// Like `reduce`, skipped if a change with the same `key` was applied recently, so a change
+    /// that may be retried (from an offline queue, or over a reconnecting transport) is applied at
+    /// most once. Keys are remembered per scope, for the last 1024 keyed changes.
+    pub fn reduce_idempotent(&self, key: impl Into<String>, f: impl FnOnce(&mut T) + 'static) {
+        self.callback.emit(Request::Idempotent(
+            key.into(),
+            Box::new(Request::ApplyOnce(Box::new(f))),
+        ))
+    }
+
+    /// Stop receiving changes to shared state, keeping current state as is. Useful for showing a
+    /// frozen snapshot.
+    pub fn pause(&self) {
+        self.callback.emit(Request::Pause)
+    }
+
+    /// Resume receiving changes to shared state after `pause`, starting with current state.
+    pub fn resume(&self) {
+        self.callback.emit(Request::Resume)
+    }
+
+    /// Like `reduce`, returning a future resolving with state once `f` is applied and every
+    /// subscriber notified. Useful to read what `f` did, such as an id it assigned. If the change
+    /// is held back (coalesced or deferred by a rate limit), it resolves once it's applied, and
+    /// with `None` if it never is (as when it isn't authorized).
+    ///
+    /// ```ignore
+    /// spawn_local(async move {
+    ///     if let Some(todos) = handle.reduce_awaitable(|todos| todos.add("Buy milk")).await {
+    ///         let id = todos.last_id();
+    ///     }
+    /// });
+    /// ```
+    pub fn reduce_awaitable(&self, f: impl FnOnce(&mut T) + 'static) -> Applied<T> {
+        let (request, applied) = watch::awaited(Request::ApplyOnce(Box::new(f)));
+        self.callback.emit(request);
+        applied
+    }
+
+    /// Wait for the next change to shared state.
+    ///
+    /// ```ignore
+    /// spawn_local(async move {
+    ///     let state = handle.next_state().await;
+    /// });
+    /// ```
+    pub fn next_state(&self) -> Change<T> {
+        let (request, change) = watch::watch(|_| true, false);
+        self.callback.emit(request);
+        change
+    }
+
+    /// Wait until shared state matches `selector`, resolving right away if it already does.
+    ///
+    /// ```ignore
+    /// spawn_local(async move {
+    ///     let session = handle.await_change(|session| session.user.is_some()).await;
+    /// });
+    /// ```
+    pub fn await_change(&self, selector: impl Fn(&T) -> bool + 'static) -> Change<T> {
+        let (request, change) = watch::watch(selector, true);
+        self.callback.emit(request);
+        change
+    }
+
+    /// Apply a function that may fail to mutate shared state. `f` changes a copy of state, which
+    /// only replaces state if it succeeds. On failure whatever `f` changed is discarded, and the
+    /// error is reported to all subscribers (see `error`).
+    pub fn try_reduce(&self, f: impl FnOnce(&mut T) -> Result<(), Error> + 'static) {
+        self.callback.emit(Request::TryApplyOnce(Box::new(f)))
     }
 
     /// Convenience method for modifying shared state directly from a `Callback`.
@@ -64,8 +341,8 @@ where
     where
         T: 'static,
     {
-        let f = Rc::new(f);
-        self.callback.reform(move |_| f.clone())
+        let f: Reduction<T> = Rc::new(f);
+        self.callback.reform(move |_| Request::Apply(f.clone()))
     }
 
     /// Convenience method for modifying shared state directly from a `CallbackOnce`.
@@ -75,21 +352,21 @@ where
         T: 'static,
     {
         let f = Box::new(f);
-        let cb = self.callback_once.clone();
-        Callback::once(move |_| cb.emit(f))
+        let cb = self.callback.clone();
+        Callback::once(move |_| cb.emit(Request::ApplyOnce(f)))
     }
 
     /// Convenience method for modifying shared state directly from a `Callback`.
     /// Similar to `reduce_callback` but it also accepts the fired event.
-    pub fn reduce_callback_with<E: 'static>(&self, f: impl Fn(&mut T, E) + 'static) -> Callback<E>
+    pub fn reduce_callback_with<E>(&self, f: impl Fn(&mut T, E) + 'static) -> Callback<E>
     where
         T: 'static,
-        E: Clone,
+        E: Clone + 'static,
     {
         let f = Rc::new(f);
         self.callback.reform(move |e: E| {
             let f = f.clone();
-            Rc::new(move |state| f.clone()(state, e.clone()))
+            Request::Apply(Rc::new(move |state| f.clone()(state, e.clone())))
         })
     }
 
@@ -102,8 +379,8 @@ where
     where
         T: 'static,
     {
-        let cb = self.callback_once.clone();
-        Callback::once(move |e| cb.emit(Box::new(move |state| f(state, e))))
+        let cb = self.callback.clone();
+        Callback::once(move |e| cb.emit(Request::ApplyOnce(Box::new(move |state| f(state, e)))))
     }
 }
 
@@ -115,22 +392,45 @@ where
     fn clone(&self) -> Self {
         Self {
             state: self.state.clone(),
+            error: self.error.clone(),
+            progress: self.progress.clone(),
             callback: self.callback.clone(),
-            callback_once: self.callback_once.clone(),
+            unread: self.unread.clone(),
+            detach: self.detach.clone(),
+            initial: self.initial.clone(),
             _mark: Default::default(),
         }
     }
 }
 
+impl<T, H> fmt::Debug for StateHandle<T, H>
+where
+    T: Default + Clone + fmt::Debug + 'static,
+    H: Handler,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateHandle")
+            .field("handler", &type_name::<H>())
+            .field("state", &Summary(&*self.state))
+            .field("error", &self.error)
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+/// Handles are equal if they hold the same state, error, and progress, so properties holding them
+/// may derive `PartialEq` without state having to. State is compared by pointer, which is enough
+/// as it's copied when changed.
 impl<T, H> PartialEq for StateHandle<T, H>
 where
-    T: Default + PartialEq + Clone + 'static,
+    T: Default + Clone + 'static,
     H: Handler,
 {
     fn eq(&self, other: &Self) -> bool {
-        self.state == other.state
+        Rc::ptr_eq(&self.state, &other.state)
+            && ptr_eq(&self.error, &other.error)
+            && ptr_eq(&self.progress, &other.progress)
             && self.callback == other.callback
-            && self.callback_once == other.callback_once
     }
 }
 
@@ -145,18 +445,45 @@ where
         self.state = state;
     }
 
-    fn set_local_callback(
-        &mut self,
-        callback: Callback<Reduction<Model<Self::Handler>>>,
-        callback_once: Callback<ReductionOnce<Model<Self::Handler>>>,
-    ) {
+    fn patch_local_state(&mut self, patch: Reduction<Model<Self::Handler>>) {
+        patch(Rc::make_mut(&mut self.state));
+    }
+
+    fn set_local_error(&mut self, error: Rc<Error>) {
+        self.error = Some(error);
+    }
+
+    fn set_local_progress(&mut self, progress: Option<Rc<Progress>>) {
+        self.progress = progress;
+    }
+
+    fn set_local_callback(&mut self, callback: Callback<Request<Model<Self::Handler>>>) {
         self.callback = callback;
-        self.callback_once = callback_once;
+    }
+
+    fn set_local_read(&mut self, read: Callback<()>) {
+        self.unread = Rc::new(Cell::new(Some(read)));
+    }
+
+    fn set_local_detach(&mut self, detach: Callback<Detached<T>>) {
+        self.detach = Some(detach);
     }
 
     fn set_local(&mut self, other: &Self) {
         *self = other.clone();
     }
+
+    fn local_error(&self) -> Option<Rc<Error>> {
+        self.error.clone()
+    }
+
+    fn local_state(&self) -> Option<Rc<T>> {
+        Some(self.state.clone())
+    }
+
+    fn local_callback(&self) -> Option<&Callback<Request<Model<Self::Handler>>>> {
+        Some(&self.callback)
+    }
 }
 
 impl<T, H> SharedState for StateHandle<T, H>
@@ -169,9 +496,188 @@ where
     fn handle(&mut self) -> &mut Self::Handle {
         self
     }
+
+    fn initial(&self) -> Option<Rc<T>> {
+        self.initial.clone()
+    }
+}
+
+/// Optional handles are only connected to shared state when `Some`. Useful for components that may
+/// be used standalone.
+impl<H> Handle for Option<H>
+where
+    H: Handle,
+{
+    type Handler = H::Handler;
+
+    fn set_local_state(&mut self, state: Rc<Model<Self::Handler>>) {
+        if let Some(handle) = self {
+            handle.set_local_state(state);
+        }
+    }
+
+    fn patch_local_state(&mut self, patch: Reduction<Model<Self::Handler>>) {
+        if let Some(handle) = self {
+            handle.patch_local_state(patch);
+        }
+    }
+
+    fn set_local_error(&mut self, error: Rc<Error>) {
+        if let Some(handle) = self {
+            handle.set_local_error(error);
+        }
+    }
+
+    fn set_local_progress(&mut self, progress: Option<Rc<Progress>>) {
+        if let Some(handle) = self {
+            handle.set_local_progress(progress);
+        }
+    }
+
+    fn set_local_callback(&mut self, callback: Callback<Request<Model<Self::Handler>>>) {
+        if let Some(handle) = self {
+            handle.set_local_callback(callback);
+        }
+    }
+
+    fn set_local_read(&mut self, read: Callback<()>) {
+        if let Some(handle) = self {
+            handle.set_local_read(read);
+        }
+    }
+
+    fn set_local_detach(&mut self, detach: Callback<Detached<Model<Self::Handler>>>) {
+        if let Some(handle) = self {
+            handle.set_local_detach(detach);
+        }
+    }
+
+    fn set_local(&mut self, other: &Self) {
+        if let (Some(handle), Some(other)) = (self, other) {
+            handle.set_local(other);
+        }
+    }
+
+    fn local_error(&self) -> Option<Rc<Error>> {
+        self.as_ref().and_then(Handle::local_error)
+    }
+
+    fn local_state(&self) -> Option<Rc<Model<Self::Handler>>> {
+        self.as_ref().and_then(Handle::local_state)
+    }
+
+    fn local_callback(&self) -> Option<&Callback<Request<Model<Self::Handler>>>> {
+        self.as_ref().and_then(Handle::local_callback)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_some()
+    }
+}
+
+/// Read-only handle to state derived from another handle.
+pub struct MappedHandle<U> {
+    state: Rc<U>,
+}
+
+impl<U> MappedHandle<U> {
+    fn new(state: U) -> Self {
+        Self {
+            state: Rc::new(state),
+        }
+    }
+
+    pub fn state(&self) -> &U {
+        &self.state
+    }
+
+    /// Derive a read-only handle to part of this handle's state.
+    pub fn map<V>(&self, f: impl FnOnce(&U) -> V) -> MappedHandle<V> {
+        MappedHandle::new(f(&self.state))
+    }
+}
+
+impl<U> Clone for MappedHandle<U> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<U: fmt::Debug + 'static> fmt::Debug for MappedHandle<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedHandle")
+            .field("state", &Summary(&*self.state))
+            .finish()
+    }
+}
+
+impl<U> PartialEq for MappedHandle<U>
+where
+    U: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state) || self.state == other.state
+    }
+}
+
+impl<U> Default for MappedHandle<U>
+where
+    U: Default,
+{
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+fn ptr_eq<T>(a: &Option<Rc<T>>, b: &Option<Rc<T>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
 }
 
+/// Handle for a shared service object, see `service`.
+pub type ServiceHandle<T> =
+    StateHandle<crate::service::Service<T>, crate::service::ServiceHandler<T>>;
 /// Handle for basic shared state.
 pub type SharedHandle<T> = StateHandle<T, SharedHandler<T>>;
 /// Handle for shared state with persistent storage.
 pub type StorageHandle<T> = StateHandle<T, StorageHandler<T>>;
+/// Handle for shared state hydrated from cookies.
+#[cfg(feature = "cookies")]
+pub type CookieHandle<T> = StateHandle<T, crate::cookie::CookieHandler<T>>;
+/// Handle for variants of experiments from `S` assigned to this client.
+#[cfg(feature = "experiments")]
+pub type ExperimentHandle<S> =
+    StateHandle<crate::experiment::Assignments, crate::experiment::ExperimentHandler<S>>;
+/// Handle for feature flags loaded from `S`.
+#[cfg(feature = "flags")]
+pub type FlagHandle<S> = StateHandle<crate::flags::Flags, crate::flags::FeatureFlagHandler<S>>;
+/// Handle for screen reader announcements.
+#[cfg(feature = "announcer")]
+pub type AnnouncerHandle =
+    StateHandle<crate::announce::Announcements, crate::announce::AnnouncerHandler>;
+/// Handle for the navigation trail.
+#[cfg(feature = "breadcrumbs")]
+pub type BreadcrumbHandle = SharedHandle<crate::breadcrumbs::Trail>;
+/// Handle for changes that can still be undone.
+#[cfg(feature = "undo")]
+pub type UndoHandle = SharedHandle<crate::undo::Undoables>;
+/// Handle for drag-and-drop of payloads of type `T`.
+#[cfg(feature = "drag")]
+pub type DragHandle<T> = SharedHandle<crate::drag::Drag<T>>;
+/// Handle for UI state of type `T` kept per route.
+#[cfg(feature = "session-restore")]
+pub type SessionRestoreHandle<T> = StateHandle<T, crate::restore::SessionRestoreHandler<T>>;
+/// Handle for keyboard shortcuts.
+#[cfg(feature = "shortcuts")]
+pub type ShortcutHandle =
+    StateHandle<crate::shortcuts::Shortcuts, crate::shortcuts::ShortcutHandler>;
+/// Handle for scroll offsets, selections and focus of named views.
+#[cfg(feature = "view-sync")]
+pub type ViewSyncHandle = SharedHandle<crate::view_sync::Views>;
+/// Handle for shared state that is updated with diffs.
+pub type DiffHandle<T> = StateHandle<T, DiffHandler<T>>;
@@ -1,19 +1,295 @@
 //! State handlers determine how state should be created, modified, and shared.
 use std::any::type_name;
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Write};
 use std::rc::Rc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 use yew::{
-    format::Json,
-    services::{storage::Area, StorageService},
+    format::Text,
+    services::{interval::IntervalTask, storage::Area},
+    Callback,
 };
 
+use crate::config;
+use crate::error::Error;
+use crate::progress::Progress;
+use crate::redact;
+use crate::schedule::Control;
+use crate::storage::{self, Parts, Restored, WebStorage};
+use crate::watch::{Completion, Watcher};
+
+/// Characters of state shown by `Debug` output, before it's cut off.
+const SUMMARY_LIMIT: usize = 200;
+
+/// `Debug` output of state (redacted if it's registered with `redact`), cut off so large state
+/// doesn't flood logs. Formatting stops once the limit is reached, so large collections aren't
+/// formatted in full.
+pub(crate) struct Summary<'a, T>(pub(crate) &'a T);
+
+impl<T: fmt::Debug + 'static> fmt::Debug for Summary<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = Limited(String::new());
+        // Errors only once the limit is reached.
+        let _ = match redact::show(self.0) {
+            Some(mut redacted) => {
+                if config::debug_items() > 0 {
+                    redact::sample(&mut redacted, config::debug_items());
+                }
+                write!(out, "{}", redacted)
+            }
+            None => write!(out, "{:?}", self.0),
+        };
+        match out.0.char_indices().nth(SUMMARY_LIMIT) {
+            Some((end, _)) => write!(f, "{}…", &out.0[..end]),
+            None => f.write_str(&out.0),
+        }
+    }
+}
+
+/// Writer failing once it holds more than `SUMMARY_LIMIT` characters, to stop formatting.
+struct Limited(String);
+
+impl fmt::Write for Limited {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.push_str(s);
+        match self.0.char_indices().nth(SUMMARY_LIMIT) {
+            Some(_) => Err(fmt::Error),
+            None => Ok(()),
+        }
+    }
+}
+
 pub(crate) type Reduction<T> = Rc<dyn Fn(&mut T)>;
 pub(crate) type ReductionOnce<T> = Box<dyn FnOnce(&mut T)>;
+pub(crate) type TryReductionOnce<T> = Box<dyn FnOnce(&mut T) -> Result<(), Error>>;
+
+/// Request to change shared state.
+#[doc(hidden)]
+pub enum Request<T> {
+    /// Apply a state change.
+    Apply(Reduction<T>),
+    /// Apply a state change once.
+    ApplyOnce(ReductionOnce<T>),
+    /// Apply a state change once, which may fail.
+    TryApplyOnce(TryReductionOnce<T>),
+    /// Apply a state change named by a label, for authorization and diagnostics.
+    Labeled(&'static str, Box<Request<T>>),
+    /// Apply a state change only if no change with the same key was applied recently.
+    Idempotent(String, Box<Request<T>>),
+    /// Apply a state change as part of a batch, notifying subscribers when the whole batch is
+    /// applied.
+    Batched(Box<Request<T>>, Ticket),
+    /// Apply a state change, resolving a `reduce_awaitable` future once subscribers are notified.
+    Awaited(Box<Request<T>>, Completion<T>),
+    /// Set the priority of the subscriber sending it.
+    Priority(Priority),
+    /// Stop notifying the subscriber sending it of changes.
+    Pause,
+    /// Resume notifying the subscriber sending it of changes, starting with current state.
+    Resume,
+    /// Replace state, only if sent by the first subscriber before anything else changed it.
+    Seed(Rc<T>),
+    /// Watch for changes to state, checking current state first if `true`.
+    Watch(Watcher<T>, bool),
+    /// Report an error to subscribers, leaving state as is.
+    Report(Error),
+    /// Set (or clear with `None`) progress shown to subscribers, leaving state as is.
+    Progress(Option<Progress>),
+    /// Change handler settings, leaving state as is.
+    Configure(HandlerSettings),
+    /// Create the handler again, keeping subscribers.
+    Restart,
+    /// Apply a state change after a delay, controlled by a `ScheduledHandle`.
+    Schedule(Duration, Rc<Control>, Box<Request<T>>),
+    /// Apply a state change every so often, controlled by a `RecurringHandle`.
+    Every(Duration, Rc<Control>, Reduction<T>),
+}
+
+/// Work finishing later (such as a guarded transition), given a callback sending requests to
+/// shared state for as long as it's kept, even once the component that started it is gone. Handed
+/// to the wrapper component rather than sent as a request, as only it can keep a bridge of its own.
+pub(crate) type Detached<T> = Box<dyn FnOnce(Callback<Request<T>>)>;
+
+/// Order in which subscribers are notified of changes. Subscribers with the same priority are
+/// notified in no particular order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Notified first. Useful for watchers that update derived state.
+    High,
+    /// The default.
+    #[default]
+    Normal,
+    /// Notified last.
+    Low,
+}
+
+/// Holds back notifications of batched changes until every change in the batch is applied.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct Barrier {
+    remaining: Cell<usize>,
+    pending: RefCell<Vec<Box<dyn FnOnce()>>>,
+}
+
+impl Barrier {
+    /// Expect one more change to arrive.
+    fn expect(&self) {
+        self.remaining.set(self.remaining.get() + 1);
+    }
+
+    /// A change was applied. `notify` runs once every expected change has arrived.
+    fn arrive(&self, notify: Box<dyn FnOnce()>) {
+        self.pending.borrow_mut().push(notify);
+        self.remaining.set(self.remaining.get().saturating_sub(1));
+        if self.remaining.get() == 0 {
+            let pending = self.pending.replace(Vec::new());
+            for notify in pending {
+                notify();
+            }
+        }
+    }
+}
+
+/// Place of a change in a `Barrier`. Dropped without arriving, as when the change is never
+/// handled, it arrives with nothing to notify, so the rest of the batch isn't held back.
+#[doc(hidden)]
+pub struct Ticket {
+    barrier: Option<Rc<Barrier>>,
+}
+
+impl Ticket {
+    /// Expect one more change to arrive at `barrier`.
+    pub(crate) fn new(barrier: &Rc<Barrier>) -> Self {
+        barrier.expect();
+        Self {
+            barrier: Some(barrier.clone()),
+        }
+    }
+
+    /// The change was applied. `notify` runs once every expected change has arrived.
+    pub(crate) fn arrive(mut self, notify: Box<dyn FnOnce()>) {
+        if let Some(barrier) = self.barrier.take() {
+            barrier.arrive(notify);
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if let Some(barrier) = self.barrier.take() {
+            barrier.arrive(Box::new(|| {}));
+        }
+    }
+}
+
+impl<T: Clone + 'static> Request<T> {
+    /// Apply this request to `handler`.
+    pub(crate) fn apply<H: Handler<Model = T>>(self, handler: &mut H) -> Result<(), Error> {
+        match self {
+            Request::Apply(reduce) => handler.apply(reduce),
+            Request::ApplyOnce(reduce) => handler.apply_once(reduce),
+            Request::TryApplyOnce(reduce) => {
+                // Changed on a copy, so a failed change leaves nothing behind: not in state,
+                // storage, or the handler's pending changes.
+                let mut draft = (*handler.state()).clone();
+                reduce(&mut draft)?;
+                handler.apply_once(Box::new(move |state| *state = draft));
+            }
+            // Awaited requests are taken apart before they're applied, so subscribers are notified
+            // before the future resolves.
+            Request::Labeled(_, request)
+            | Request::Idempotent(_, request)
+            | Request::Awaited(request, _) => return request.apply(handler),
+            Request::Batched(request, ticket) => {
+                let result = request.apply(handler);
+                ticket.arrive(Box::new(|| {}));
+                return result;
+            }
+            Request::Seed(seed) => {
+                handler.apply_once(Box::new(move |state| *state = (*seed).clone()))
+            }
+            Request::Report(error) => return Err(error),
+            Request::Configure(settings) => handler.configure(&settings),
+            Request::Restart => {
+                handler.flush();
+                *handler = H::new();
+            }
+            Request::Priority(_)
+            | Request::Pause
+            | Request::Resume
+            | Request::Watch(..)
+            | Request::Progress(_)
+            | Request::Schedule(..)
+            | Request::Every(..) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Label of this request, if it's labeled.
+    pub(crate) fn label(&self) -> Option<&'static str> {
+        match self {
+            Request::Labeled(label, _) => Some(label),
+            Request::Batched(request, _)
+            | Request::Idempotent(_, request)
+            | Request::Awaited(request, _) => request.label(),
+            _ => None,
+        }
+    }
+
+    /// Idempotency key of this request, if it has one.
+    pub(crate) fn key(&self) -> Option<&str> {
+        match self {
+            Request::Idempotent(key, _) => Some(key),
+            Request::Labeled(_, request)
+            | Request::Batched(request, _)
+            | Request::Awaited(request, _) => request.key(),
+            _ => None,
+        }
+    }
+
+    /// Whether this request is a change made with `reduce` and such.
+    pub(crate) fn is_reduction(&self) -> bool {
+        match self {
+            Request::Apply(_) | Request::ApplyOnce(_) | Request::TryApplyOnce(_) => true,
+            Request::Labeled(_, request)
+            | Request::Batched(request, _)
+            | Request::Idempotent(_, request)
+            | Request::Awaited(request, _) => request.is_reduction(),
+            _ => false,
+        }
+    }
+
+    /// This request without the completion of a `reduce_awaitable` future it carries, if any.
+    pub(crate) fn take_completion(self) -> (Self, Option<Completion<T>>) {
+        match self {
+            Request::Awaited(request, completion) => (*request, Some(completion)),
+            request => (request, None),
+        }
+    }
+
+    /// Whether this request changes state, so it's authorized and rate limited: reductions, and
+    /// restarting the handler.
+    pub(crate) fn is_change(&self) -> bool {
+        self.is_reduction() || matches!(self, Request::Restart)
+    }
+}
+
+/// Changes sent to subscribers after state is modified.
+pub enum Changes<T> {
+    /// Replace local state entirely.
+    State(Rc<T>),
+    /// Patch local state in place.
+    Patch(Reduction<T>),
+    /// Nothing changed, subscribers are not notified.
+    None,
+}
 
 /// Determines how state should be created, modified, and shared.
 pub trait Handler {
-    type Model;
+    type Model: Clone;
 
     /// Create new state.
     fn new() -> Self;
@@ -23,6 +299,64 @@ pub trait Handler {
     fn apply_once(&mut self, f: ReductionOnce<Self::Model>);
     /// Return a reference to current state.
     fn state(&self) -> Rc<Self::Model>;
+    /// Changes to send subscribers after state is modified. Sends the entire state by default.
+    fn changes(&mut self) -> Changes<Self::Model> {
+        Changes::State(self.state())
+    }
+    /// Take the last error that occurred while creating or modifying state, if any.
+    fn take_error(&mut self) -> Option<Error> {
+        None
+    }
+    /// Apply settings changed at runtime. Settings the handler doesn't use are ignored.
+    fn configure(&mut self, _settings: &HandlerSettings) {}
+    /// Whether any subscriber is notified of the next change, so handlers may skip working out
+    /// what changed when none is. Assumed by default.
+    fn subscribed(&mut self, _subscribed: bool) {}
+    /// Finish work still pending (such as saving changes), as the handler is about to be replaced.
+    fn flush(&mut self) {}
+    /// Adjust to low power mode being switched, such as by refreshing less often.
+    fn low_power(&mut self, _low: bool) {}
+    /// Catch up after the page was hidden or asleep, as timers and connections may be out of date.
+    #[cfg(feature = "wake")]
+    fn wake(&mut self) {}
+}
+
+/// Handler settings changed at runtime (say from a settings screen) without recreating state.
+/// Settings left unset are kept as they are, and settings a handler doesn't use are ignored.
+///
+/// ```ignore
+/// let every_5s = SavePolicy::Interval(Duration::from_secs(5));
+/// handle.configure(HandlerSettings::new().save_policy(every_5s));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HandlerSettings {
+    pub(crate) save_policy: Option<SavePolicy>,
+    pub(crate) refresh: Option<Duration>,
+    pub(crate) url: Option<String>,
+}
+
+impl HandlerSettings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// When storage handlers save state. Changes not saved yet are saved when it's changed.
+    pub fn save_policy(mut self, policy: SavePolicy) -> Self {
+        self.save_policy = Some(policy);
+        self
+    }
+
+    /// How often state loaded from a server (feature flags) is fetched again.
+    pub fn refresh(mut self, interval: Duration) -> Self {
+        self.refresh = Some(interval);
+        self
+    }
+
+    /// URL state is loaded from (feature flags). State is fetched from it right away.
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
 }
 
 /// Handler for basic shared state.
@@ -56,7 +390,8 @@ where
 
 /// Allows state to be stored persistently in local or session storage.
 pub trait Storable: Serialize + for<'a> Deserialize<'a> {
-    /// The key used to save and load state from storage.
+    /// The key used to save and load state from storage. The prefix set with
+    /// `config().key_prefix()` is prepended to it.
     fn key() -> &'static str {
         type_name::<Self>()
     }
@@ -64,45 +399,214 @@ pub trait Storable: Serialize + for<'a> Deserialize<'a> {
     fn area() -> Area {
         Area::Local
     }
+    /// When state is saved.
+    fn save_policy() -> SavePolicy {
+        SavePolicy::OnChange
+    }
+    /// Whether state is compressed with lz-string before it's stored, so JS tooling can read it
+    /// with lz-string's `decompressFromUTF16`. State stored either way can always be restored.
+    fn compressed() -> bool {
+        false
+    }
+    /// Whether stored state that no longer matches the model is restored as much as possible,
+    /// instead of being treated as corrupt. Fields missing from stored state are filled in from
+    /// the default model, unknown fields are dropped, and fields whose stored value has the wrong
+    /// type keep their default.
+    fn lenient() -> bool {
+        false
+    }
+    /// Split state into the parts that are stored. Generated by `#[derive(Storable)]`.
+    #[doc(hidden)]
+    fn to_parts(&self) -> Parts {
+        Parts::whole(self)
+    }
+    /// Put stored parts back together. Generated by `#[derive(Storable)]`.
+    #[doc(hidden)]
+    fn from_parts(parts: &Parts) -> Option<Self>
+    where
+        Self: Default,
+    {
+        parts.to_whole()
+    }
+}
+
+/// Determines when state is saved to storage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SavePolicy {
+    /// Save after every change. This is the default.
+    OnChange,
+    /// Save periodically, only if state changed since it was last saved. Changes made since the
+    /// last save are lost if the page is closed.
+    Interval(Duration),
+}
+
+/// State waiting to be saved by the autosave interval.
+struct Autosave<T> {
+    state: Rc<T>,
+    dirty: bool,
+    /// Parts of state as last saved.
+    saved: Parts,
+    /// Why state couldn't be saved last, until it's reported.
+    error: Option<Error>,
+}
+
+impl<T: Storable> Autosave<T> {
+    /// Save state if it changed since it was last saved.
+    fn save(&mut self, storage: &mut WebStorage) {
+        if !std::mem::replace(&mut self.dirty, false) {
+            return;
+        }
+        let parts = self.state.to_parts();
+        if parts == self.saved {
+            return;
+        }
+        match storage::store_parts::<T>(storage, &storage::key::<T>(), &parts) {
+            Ok(()) => self.saved = parts,
+            Err(error) => self.error = Some(error),
+        }
+    }
+}
+
+/// Call `tick` every `interval`. Timers only run in the browser; elsewhere pending changes are
+/// saved when the handler is flushed.
+#[cfg(target_arch = "wasm32")]
+fn interval_task(interval: Duration, tick: Callback<()>) -> Option<IntervalTask> {
+    Some(yew::services::IntervalService::spawn(interval, tick))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn interval_task(_interval: Duration, _tick: Callback<()>) -> Option<IntervalTask> {
+    None
 }
 
 /// Handler for shared state with persistent storage.
 ///
 /// If persistent storage is disabled it just behaves like a `SharedHandler`.
+///
+/// State is stored with a checksum. If stored state is corrupt it's moved to a `<key>.backup` entry
+/// for manual recovery, and state starts from default instead.
 #[derive(Default)]
 pub struct StorageHandler<T> {
     state: Rc<T>,
-    storage: Option<StorageService>,
+    storage: Option<WebStorage>,
+    error: Option<Error>,
+    autosave: Option<Rc<RefCell<Autosave<T>>>>,
+    _autosave_task: Option<IntervalTask>,
 }
 
 impl<T> StorageHandler<T>
 where
-    T: Storable,
+    T: Default + Storable,
 {
     fn load_state(&mut self) {
-        let result = self.storage.as_mut().map(|s| s.restore(T::key()));
-        if let Some(Json(Ok(state))) = result {
-            self.state = state;
+        let storage = match &mut self.storage {
+            Some(storage) => storage,
+            None => return,
+        };
+
+        let key = storage::key::<T>();
+        match storage::restore(storage, &key) {
+            Restored::Found(state) => self.state = Rc::new(state),
+            Restored::Missing => {}
+            Restored::Corrupt(data) => {
+                let backup = format!("{}.backup", key);
+                storage.store(&backup, Text::Ok(data));
+                self.error = Some(Error::CorruptStorage { key, backup });
+            }
         }
     }
 
     fn save_state(&mut self) {
+        if let Some(autosave) = &self.autosave {
+            let mut autosave = autosave.borrow_mut();
+            autosave.state = Rc::clone(&self.state);
+            autosave.dirty = true;
+        } else if let Some(storage) = &mut self.storage {
+            if let Err(error) = storage::store(storage, &storage::key::<T>(), &*self.state) {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    /// Save changes the autosave interval hasn't saved yet.
+    fn flush_autosave(&mut self) {
+        let autosave = match &self.autosave {
+            Some(autosave) => autosave,
+            None => return,
+        };
         if let Some(storage) = &mut self.storage {
-            storage.store(T::key(), Json(&self.state));
+            let mut autosave = autosave.borrow_mut();
+            autosave.save(storage);
+            if let Some(error) = autosave.error.take() {
+                self.error = Some(error);
+            }
         }
     }
+
+    fn start_autosave(&mut self, interval: Duration)
+    where
+        T: 'static,
+    {
+        let autosave = Rc::new(RefCell::new(Autosave {
+            state: Rc::clone(&self.state),
+            dirty: false,
+            saved: self.state.to_parts(),
+            error: None,
+        }));
+        let pending = autosave.clone();
+        let callback = Callback::from(move |_| {
+            let mut pending = pending.borrow_mut();
+            if !pending.dirty {
+                return;
+            }
+            if let Ok(mut storage) = WebStorage::new(T::area()) {
+                pending.save(&mut storage);
+            }
+        });
+
+        self.autosave = Some(autosave);
+        self._autosave_task = interval_task(interval, callback);
+    }
+}
+
+impl<T: fmt::Debug + 'static> fmt::Debug for SharedHandler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedHandler")
+            .field("state", &Summary(&*self.state))
+            .finish()
+    }
+}
+
+impl<T> fmt::Debug for StorageHandler<T>
+where
+    T: fmt::Debug + Storable + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StorageHandler")
+            .field("key", &storage::key::<T>())
+            .field("area", &T::area())
+            .field("persistent", &self.storage.is_some())
+            .field("error", &self.error)
+            .field("state", &Summary(&*self.state))
+            .finish()
+    }
 }
 
 impl<T> Handler for StorageHandler<T>
 where
-    T: Default + Clone + Storable,
+    T: Default + Clone + Storable + 'static,
 {
     type Model = T;
 
     fn new() -> Self {
-        let mut this: Self = Default::default();
-        this.storage = StorageService::new(T::area()).ok();
+        let mut this = Self {
+            storage: WebStorage::new(T::area()).ok(),
+            ..Default::default()
+        };
         this.load_state();
+        if let SavePolicy::Interval(interval) = T::save_policy() {
+            this.start_autosave(interval);
+        }
         this
     }
 
@@ -119,11 +623,36 @@ where
     fn state(&self) -> Rc<Self::Model> {
         Rc::clone(&self.state)
     }
+
+    fn take_error(&mut self) -> Option<Error> {
+        // Saving in the background is reported with the next change.
+        let autosave = self.autosave.as_ref();
+        self.error
+            .take()
+            .or_else(|| autosave.and_then(|autosave| autosave.borrow_mut().error.take()))
+    }
+
+    fn flush(&mut self) {
+        self.flush_autosave();
+    }
+
+    fn configure(&mut self, settings: &HandlerSettings) {
+        let policy = match settings.save_policy {
+            Some(policy) => policy,
+            None => return,
+        };
+        self.flush_autosave();
+        self.autosave = None;
+        self._autosave_task = None;
+        if let SavePolicy::Interval(interval) = policy {
+            self.start_autosave(interval);
+        }
+    }
 }
 
 impl<T> Clone for StorageHandler<T>
 where
-    T: Default + Clone + Storable,
+    T: Default + Clone + Storable + 'static,
 {
     fn clone(&self) -> Self {
         let mut new = Self::new();
@@ -133,3 +662,127 @@ where
         new
     }
 }
+
+/// Allows state to be updated with structural diffs instead of being replaced entirely.
+pub trait Diff: Clone {
+    /// Description of changes between two states.
+    type Patch;
+
+    /// Compute changes from `self` to `new`. Returns `None` if nothing changed.
+    fn diff(&self, new: &Self) -> Option<Self::Patch>;
+    /// Apply changes to `self`.
+    fn patch(&mut self, patch: &Self::Patch);
+}
+
+/// Handler for shared state that notifies subscribers with patches.
+///
+/// Subscribers receive the entire state once when connecting, afterwards only the changes computed by
+/// `Diff` are sent, which they apply to their local copy. Patches can't be composed, so when state
+/// changes again before a patch is sent (or with nobody subscribed to it), the entire state is sent
+/// instead.
+pub struct DiffHandler<T>
+where
+    T: Diff,
+{
+    state: Rc<T>,
+    patch: Option<T::Patch>,
+    /// Send the entire state, in place of a patch.
+    whole: bool,
+    subscribed: bool,
+}
+
+impl<T> Default for DiffHandler<T>
+where
+    T: Diff + Default,
+{
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            patch: None,
+            whole: false,
+            subscribed: true,
+        }
+    }
+}
+
+impl<T> DiffHandler<T>
+where
+    T: Diff,
+{
+    fn apply_diff(&mut self, f: impl FnOnce(&mut T)) {
+        if self.subscribed && !self.whole && self.patch.is_none() {
+            let old = Rc::clone(&self.state);
+            f(Rc::make_mut(&mut self.state));
+            self.patch = old.diff(&self.state);
+        } else {
+            // Previous state is only kept to diff with, when a patch can be sent.
+            f(Rc::make_mut(&mut self.state));
+            self.patch = None;
+            self.whole = true;
+        }
+    }
+}
+
+impl<T> fmt::Debug for DiffHandler<T>
+where
+    T: Diff + fmt::Debug + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiffHandler")
+            .field("state", &Summary(&*self.state))
+            .field("pending_patch", &self.patch.is_some())
+            .finish()
+    }
+}
+
+impl<T> Handler for DiffHandler<T>
+where
+    T: Diff + Default + 'static,
+    T::Patch: 'static,
+{
+    type Model = T;
+
+    fn new() -> Self {
+        Default::default()
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        self.apply_diff(|state| f(state));
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        self.apply_diff(f);
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+
+    fn changes(&mut self) -> Changes<Self::Model> {
+        if std::mem::take(&mut self.whole) {
+            return Changes::State(self.state());
+        }
+        match self.patch.take() {
+            Some(patch) => Changes::Patch(Rc::new(move |state: &mut T| state.patch(&patch))),
+            None => Changes::None,
+        }
+    }
+
+    fn subscribed(&mut self, subscribed: bool) {
+        self.subscribed = subscribed;
+    }
+}
+
+impl<T> Clone for DiffHandler<T>
+where
+    T: Diff,
+{
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            patch: None,
+            whole: false,
+            subscribed: self.subscribed,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Keeping state across reloads during development, so iterating on UI with `trunk serve` or
+//! similar doesn't mean recreating app state every time.
+//!
+//! State of every preserved scope is saved to session storage when the page unloads, and restored
+//! by `preserve` on startup. Storable settings are ignored, and every preserved model is saved in
+//! full. Meant for development builds only.
+//!
+//! ```ignore
+//! fn main() {
+//!     #[cfg(debug_assertions)]
+//!     {
+//!         hot_reload::preserve::<SharedHandle<App>>();
+//!         hot_reload::preserve::<StorageHandle<Settings>>();
+//!     }
+//!     yew::start_app::<Root>();
+//! }
+//! ```
+use std::any::{type_name, TypeId};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast};
+use yew::{format::Json, services::storage::Area, Callback};
+
+use crate::config;
+use crate::dispatch::Dispatch;
+use crate::handle::Handle;
+use crate::handler::Handler;
+use crate::storage::WebStorage;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+/// A preserved scope.
+struct Preserved {
+    /// Save current state.
+    save: Box<dyn Fn(&mut WebStorage)>,
+    _dispatch: Box<dyn std::any::Any>,
+}
+
+thread_local! {
+    static PRESERVED: RefCell<Vec<Preserved>> = Default::default();
+    static LISTENING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn storage() -> Option<WebStorage> {
+    WebStorage::new(Area::Session).ok()
+}
+
+fn key<H, SCOPE>() -> String
+where
+    H: Handle,
+    SCOPE: 'static,
+    StateHandler<H>: 'static,
+{
+    let scope = if TypeId::of::<SCOPE>() == TypeId::of::<StateHandler<H>>() {
+        String::new()
+    } else {
+        format!(".{}", type_name::<SCOPE>())
+    };
+    format!(
+        "{}hot-reload.{}{}",
+        config::key_prefix(),
+        type_name::<Model<H>>(),
+        scope
+    )
+}
+
+/// Save every preserved scope when the page unloads. Listening starts with the first preserved
+/// scope, and lasts as long as the page.
+fn listen() {
+    if LISTENING.with(|listening| listening.replace(true)) {
+        return;
+    }
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return,
+    };
+    let onpagehide = Closure::wrap(Box::new(|| {
+        if let Some(mut storage) = storage() {
+            PRESERVED.with(|preserved| {
+                for scope in preserved.borrow().iter() {
+                    (scope.save)(&mut storage);
+                }
+            });
+        }
+    }) as Box<dyn FnMut()>);
+    let _ =
+        window.add_event_listener_with_callback("pagehide", onpagehide.as_ref().unchecked_ref());
+    onpagehide.forget();
+}
+
+/// Keep state of handle type `H` across reloads: restore it now if it was saved by the last
+/// page, and save it whenever the page unloads from now on.
+pub fn preserve<H>()
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: Serialize + DeserializeOwned,
+{
+    preserve_in::<H, StateHandler<H>>()
+}
+
+/// Like `preserve`, for shared state in `SCOPE`.
+pub fn preserve_in<H, SCOPE>()
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: Serialize + DeserializeOwned,
+    SCOPE: 'static,
+{
+    let key = key::<H, SCOPE>();
+    let latest: Rc<RefCell<Option<Rc<Model<H>>>>> = Default::default();
+    let mut dispatch = {
+        let latest = latest.clone();
+        Dispatch::<H, SCOPE>::new(Callback::from(move |state| {
+            *latest.borrow_mut() = Some(state);
+        }))
+    };
+
+    let restored = storage().and_then(|storage| match storage.restore(&key) {
+        Json(Ok(state)) => Some(state),
+        _ => None,
+    });
+    if let Some(restored) = restored {
+        dispatch.reduce(move |state: &mut Model<H>| *state = restored);
+    }
+
+    let save = Box::new(move |storage: &mut WebStorage| {
+        if let Some(state) = &*latest.borrow() {
+            storage.store(&key, Json(&**state));
+        }
+    });
+    PRESERVED.with(|preserved| {
+        preserved.borrow_mut().push(Preserved {
+            save,
+            _dispatch: Box::new(dispatch),
+        })
+    });
+    listen();
+}
@@ -0,0 +1,69 @@
+//! Deferring low-priority changes until the browser is idle.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::IdleRequestOptions;
+
+use crate::handle::StateHandle;
+use crate::handler::Handler;
+
+/// Milliseconds after which deferred work runs even if the browser never goes idle.
+const TIMEOUT: u32 = 1_000;
+/// Milliseconds to wait in browsers without `requestIdleCallback`.
+const FALLBACK: i32 = 50;
+
+/// Run `f` once the browser is idle. Runs right away if it can't be scheduled.
+pub(crate) fn when_idle(f: impl FnOnce() + 'static) {
+    let pending = Rc::new(RefCell::new(Some(f)));
+    let run = pending.clone();
+    let callback = Closure::once_into_js(move || {
+        if let Some(f) = run.borrow_mut().take() {
+            f();
+        }
+    });
+
+    let scheduled = web_sys::window().is_some_and(|window| {
+        let idle = js_sys::Reflect::has(&window, &JsValue::from_str("requestIdleCallback"))
+            .unwrap_or(false);
+        let function = callback.unchecked_ref();
+        if idle {
+            let options = IdleRequestOptions::new();
+            options.set_timeout(TIMEOUT);
+            window
+                .request_idle_callback_with_options(function, &options)
+                .is_ok()
+        } else {
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(function, FALLBACK)
+                .is_ok()
+        }
+    });
+    if !scheduled {
+        let f = pending.borrow_mut().take();
+        if let Some(f) = f {
+            f();
+        }
+    }
+}
+
+impl<T, H> StateHandle<T, H>
+where
+    T: Default + Clone + 'static,
+    H: Handler<Model = T>,
+{
+    /// Like `reduce`, but only once the browser is idle (within a second either way), so heavy,
+    /// low-priority changes like rebuilding an index or pruning a cache don't hold up user
+    /// interactions.
+    ///
+    /// ```ignore
+    /// handle.reduce_idle(|library| library.rebuild_index());
+    /// ```
+    pub fn reduce_idle(&self, f: impl FnOnce(&mut T) + 'static)
+    where
+        H: 'static,
+    {
+        let handle = self.clone();
+        when_idle(move || handle.reduce(f));
+    }
+}
@@ -0,0 +1,164 @@
+//! Deduplication of repeated values in state.
+//!
+//! State with many copies of the same values (tags, labels, and such) can intern them instead, so
+//! every copy shares one allocation. Cloning state, which happens whenever shared state is changed,
+//! then only bumps reference counts.
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+thread_local! {
+    /// Interned values of every type, keyed by type.
+    static POOLS: RefCell<HashMap<TypeId, Box<dyn Any>>> = Default::default();
+}
+
+/// Run `f` with the pool of interned `T` values.
+fn with_pool<T: Eq + Hash + 'static, R>(f: impl FnOnce(&mut HashSet<Rc<T>>) -> R) -> R {
+    POOLS.with(|pools| {
+        let mut pools = pools.borrow_mut();
+        let pool = pools
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(HashSet::<Rc<T>>::new()))
+            .downcast_mut()
+            .expect("pool has the wrong type");
+        f(pool)
+    })
+}
+
+/// An interned value. Equal values interned on the same thread share one allocation.
+///
+/// Serializes as the value itself, and values are interned again when deserialized.
+///
+/// ```ignore
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// struct Post {
+///     tags: Vec<Intern<String>>,
+/// }
+///
+/// post.tags.push(Intern::from("rust"));
+/// ```
+pub struct Intern<T: 'static>(Rc<T>);
+
+impl<T: Eq + Hash + 'static> Intern<T> {
+    /// Intern `value`, reusing an equal value if one is already interned.
+    pub fn new(value: T) -> Self {
+        with_pool(|pool| match pool.get(&value) {
+            Some(interned) => Self(Rc::clone(interned)),
+            None => {
+                let interned = Rc::new(value);
+                pool.insert(Rc::clone(&interned));
+                Self(interned)
+            }
+        })
+    }
+
+    /// Forget interned values that are no longer used anywhere. Values are kept until this is
+    /// called, so call it occasionally if many distinct values come and go.
+    pub fn collect() {
+        with_pool::<T, _>(|pool| pool.retain(|interned| Rc::strong_count(interned) > 1));
+    }
+}
+
+impl<T> Deref for Intern<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> AsRef<T> for Intern<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Borrow<T> for Intern<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Clone for Intern<T> {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl<T: PartialEq> PartialEq for Intern<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for Intern<T> {}
+
+impl<T: PartialOrd> PartialOrd for Intern<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl<T: Ord> Ord for Intern<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T: Hash> Hash for Intern<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T: Eq + Hash + Default + 'static> Default for Intern<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Eq + Hash + 'static> From<T> for Intern<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for Intern<String> {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Intern<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Intern<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Serialize> Serialize for Intern<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Intern<T>
+where
+    T: Eq + Hash + Deserialize<'de> + 'static,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
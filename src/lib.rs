@@ -1,9 +1,135 @@
+//! Ergonomic shared state for Yew.
+//!
+//! Most apps only need the [`prelude`]. Beyond it, items are grouped by what they're for:
+//!
+//! - [`component`]: the wrapper and other components.
+//! - [`handle`]: handles components hold, and their settings.
+//! - [`dispatch`]: changing and observing state from outside of components.
+//! - [`storage`]: persistence, along with cookies, files, and state restored per route.
+//! - `sync`: syncing state with peers over transports (with the `sync` feature).
+//! - [`debug`]: inspecting, profiling, recording, and redacting state.
+//!
+//! Commonly used items are also kept at the crate root, where they've always been.
+#[cfg(feature = "announcer")]
+pub mod announce;
+pub mod authorize;
+pub mod batch;
+pub mod bounded;
+#[cfg(feature = "breadcrumbs")]
+pub mod breadcrumbs;
+#[cfg(feature = "chunked")]
+pub mod chunked;
+#[cfg(feature = "client-id")]
+pub mod client;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod clock;
+pub mod coalesce;
+#[cfg(feature = "compat")]
+pub mod compat;
 pub mod component;
+pub mod config;
+#[cfg(feature = "cookies")]
+pub mod cookie;
+pub mod debug;
+pub mod dispatch;
+#[cfg(feature = "drag")]
+pub mod drag;
+pub mod error;
+#[cfg(feature = "experiments")]
+pub mod experiment;
+#[cfg(feature = "files")]
+pub mod file;
+#[cfg(feature = "test-utils")]
+pub mod fixtures;
+#[cfg(feature = "flags")]
+pub mod flags;
 pub mod handle;
 mod handler;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+#[cfg(feature = "idle")]
+mod idle;
+pub mod intern;
+#[cfg(feature = "links")]
+pub mod link;
+#[cfg(target_arch = "wasm32")]
+mod loops;
+pub mod lz;
+pub mod machine;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod notify;
+pub mod path;
+pub mod power;
+pub mod prelude;
+#[cfg(feature = "profiler")]
+pub mod profiler;
+pub mod progress;
+#[cfg(feature = "rate-limit")]
+pub mod rate_limit;
+pub mod redact;
+#[cfg(feature = "session-restore")]
+pub mod restore;
+pub mod schedule;
+pub mod scope;
+pub mod sensitive;
+pub mod service;
+#[cfg(feature = "service-worker")]
+pub mod service_worker;
+#[cfg(feature = "session-recorder")]
+pub mod session;
+#[cfg(feature = "shortcuts")]
+pub mod shortcuts;
+#[cfg(feature = "test-utils")]
+pub mod snapshot;
+pub mod storage;
+pub mod store;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "sync")]
+pub mod sync;
+mod telemetry;
+#[cfg(feature = "undo")]
+pub mod undo;
+#[cfg(feature = "view-sync")]
+pub mod view_sync;
+#[cfg(feature = "wake")]
+pub mod wake;
+pub mod watch;
+pub mod worker;
 
 pub use yew::services::storage::Area;
 
-pub use component::{SharedStateComponent, StateView};
-pub use handle::{SharedHandle, SharedState, StorageHandle};
-pub use handler::Storable;
+pub use bounded::BoundedLog;
+#[cfg(feature = "client-id")]
+pub use client::client_id;
+#[cfg(feature = "breadcrumbs")]
+pub use component::Breadcrumbs;
+#[cfg(feature = "announcer")]
+pub use component::LiveRegion;
+#[cfg(feature = "inspector")]
+pub use component::StateInspector;
+#[cfg(feature = "profiler")]
+pub use component::StateProfiler;
+#[cfg(feature = "test-utils")]
+pub use component::TestProvider;
+#[cfg(feature = "undo")]
+pub use component::UndoToasts;
+pub use component::{SharedStateComponent, StateConfig, StateErrorBoundary, StateView};
+pub use config::config;
+pub use dispatch::{observe, Dispatch};
+pub use error::Error;
+pub use handle::{
+    DiffHandle, MappedHandle, ServiceHandle, SharedHandle, SharedState, StorageHandle,
+};
+pub use handler::{Diff, HandlerSettings, Priority, SavePolicy, Storable};
+pub use intern::Intern;
+pub use machine::Machine;
+pub use path::Path;
+pub use progress::Progress;
+pub use redact::Redact;
+pub use sensitive::Sensitive;
+pub use store::Store;
+#[cfg(feature = "derive")]
+pub use yew_state_derive::{Redact, Storable, Store};
@@ -0,0 +1,121 @@
+//! Sharing state in links.
+//!
+//! Selected state is serialized to JSON, then encoded as URL safe base64 in the `state` query
+//! parameter of the current page's URL. With `config().compress_links(true)` it's compressed with
+//! lz-string instead (see `lz`).
+use serde::{Deserialize, Serialize};
+use web_sys::Url;
+
+use crate::config;
+use crate::handle::StateHandle;
+use crate::handler::Handler;
+use crate::lz;
+use crate::sensitive::sealed;
+
+/// Query parameter holding shared state.
+pub const PARAM: &str = "state";
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode bytes as URL safe base64, without padding.
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decode URL safe base64, with or without padding. Returns `None` if it's invalid.
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let digits = encoded
+        .trim_end_matches('=')
+        .bytes()
+        .map(|c| ALPHABET.iter().position(|a| *a == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()?;
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, digit)| group | digit << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Encode shared state for a link.
+fn encode_state<U: Serialize>(state: &U) -> Option<String> {
+    let json = sealed(|| serde_json::to_string(state)).ok()?;
+    if config::compress_links() {
+        Some(lz::compress_to_encoded_uri_component(&json))
+    } else {
+        Some(encode(json.as_bytes()))
+    }
+}
+
+/// Decode shared state from a link.
+fn decode_state<U: for<'a> Deserialize<'a>>(encoded: &str) -> Option<U> {
+    if config::compress_links() {
+        serde_json::from_str(&lz::decompress_from_encoded_uri_component(encoded)?).ok()
+    } else {
+        serde_json::from_slice(&decode(encoded)?).ok()
+    }
+}
+
+/// URL of the current page.
+fn current_url() -> Option<Url> {
+    let href = web_sys::window()?.location().href().ok()?;
+    Url::new(&href).ok()
+}
+
+/// Read state shared in the current page's URL, if any.
+///
+/// ```ignore
+/// html! {
+///     <SearchPage initial=link::read::<Search>().map(Rc::new) />
+/// }
+/// ```
+pub fn read<U: for<'a> Deserialize<'a>>() -> Option<U> {
+    let encoded = current_url()?.search_params().get(PARAM)?;
+    decode_state(&encoded)
+}
+
+impl<T, H> StateHandle<T, H>
+where
+    T: Default + Clone + 'static,
+    H: Handler<Model = T>,
+{
+    /// Link to the current page that shares the part of state returned by `selector`.
+    pub fn share_link<U: Serialize>(&self, selector: impl FnOnce(&T) -> U) -> Option<String> {
+        let encoded = encode_state(&selector(self.state()))?;
+        let url = current_url()?;
+        url.search_params().set(PARAM, &encoded);
+        Some(url.href())
+    }
+
+    /// Apply state shared in the current page's URL, if any. Returns whether any was found.
+    pub fn hydrate_from_link<U>(&self, f: impl FnOnce(&mut T, U) + 'static) -> bool
+    where
+        U: for<'a> Deserialize<'a> + 'static,
+    {
+        match read::<U>() {
+            Some(shared) => {
+                self.reduce(move |state| f(state, shared));
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Detecting feedback loops, where notifying subscribers of a change makes them change state again,
+//! on and on without the browser getting to render.
+//!
+//! Only changes made in response to a change are counted: those sent while subscribers are being
+//! notified (by `Dispatch` callbacks, say), and those a component sends after being notified, as
+//! it renders new state. Changes made in bulk, like a thousand in a loop from one click handler,
+//! aren't a feedback loop, and aren't counted.
+//!
+//! Changes are counted until the current task ends (when a microtask resets the count). Once more
+//! than `config().loop_limit()` are made in one go, this panics or warns (see
+//! `config().loop_panics()`), listing the last changes made.
+use std::any::type_name;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use crate::config;
+
+/// Changes listed when a loop is detected.
+const LISTED: usize = 10;
+
+#[derive(Default)]
+struct Tick {
+    changes: usize,
+    /// Last changes made, as scope and label.
+    recent: VecDeque<(&'static str, Option<&'static str>)>,
+}
+
+thread_local! {
+    static TICK: RefCell<Tick> = Default::default();
+    /// How many scopes are notifying subscribers at the moment.
+    static NOTIFYING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Notify subscribers with `notify`, so changes they send meanwhile are counted by `sent`.
+pub(crate) fn notifying(notify: impl FnOnce()) {
+    NOTIFYING.with(|notifying| notifying.set(notifying.get() + 1));
+    notify();
+    NOTIFYING.with(|notifying| notifying.set(notifying.get() - 1));
+}
+
+/// Count a change to state of `T` in `SCOPE` being sent, if it's sent while subscribers of some
+/// scope are being notified.
+pub(crate) fn sent<T: 'static, SCOPE: 'static>(label: Option<&'static str>) {
+    if NOTIFYING.with(Cell::get) > 0 {
+        changed::<T, SCOPE>(label);
+    }
+}
+
+/// Count a change to state of `T` in `SCOPE`, labeled `label`, made in response to a change.
+pub(crate) fn changed<T: 'static, SCOPE: 'static>(label: Option<&'static str>) {
+    let limit = config::loop_limit();
+    let report = TICK.with(|tick| {
+        let mut tick = tick.borrow_mut();
+        if tick.changes == 0 {
+            wasm_bindgen_futures::spawn_local(async {
+                TICK.with(|tick| *tick.borrow_mut() = Tick::default());
+            });
+        }
+        tick.changes += 1;
+        if tick.recent.len() == LISTED {
+            tick.recent.pop_front();
+        }
+        let scope = if type_name::<SCOPE>() == type_name::<T>() {
+            type_name::<T>()
+        } else {
+            type_name::<SCOPE>()
+        };
+        tick.recent.push_back((scope, label));
+        if limit == 0 || tick.changes != limit + 1 {
+            return None;
+        }
+        let recent: Vec<String> = tick
+            .recent
+            .iter()
+            .map(|(scope, label)| match label {
+                Some(label) => format!("{} ({})", scope, label),
+                None => scope.to_string(),
+            })
+            .collect();
+        Some(format!(
+            "yew-state: more than {} changes without yielding, likely a feedback loop between \
+             subscribers and state. Last changes: {}",
+            limit,
+            recent.join(", ")
+        ))
+    });
+    if let Some(report) = report {
+        if config::loop_panics() {
+            panic!("{}", report);
+        }
+        crate::telemetry::warn(&report);
+    }
+}
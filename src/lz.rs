@@ -0,0 +1,285 @@
+//! Compression compatible with the JavaScript [lz-string](https://github.com/pieroxy/lz-string)
+//! library.
+//!
+//! Payloads compressed here can be decompressed by lz-string's function of the same name, and vice
+//! versa. Like lz-string, strings are compressed as UTF-16 code units.
+use std::collections::{HashMap, HashSet};
+
+// Like lz-string, alphabets have a 65th character that is never written.
+const URI_SAFE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+-$";
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+/// Packs bits into characters of `bits_per_char` bits each.
+struct Writer<F> {
+    data: String,
+    value: u32,
+    position: usize,
+    bits_per_char: usize,
+    to_char: F,
+}
+
+impl<F: Fn(u32) -> char> Writer<F> {
+    fn write_bit(&mut self, bit: u32) {
+        self.value = (self.value << 1) | bit;
+        if self.position == self.bits_per_char - 1 {
+            self.position = 0;
+            self.data.push((self.to_char)(self.value));
+            self.value = 0;
+        } else {
+            self.position += 1;
+        }
+    }
+
+    /// Write the lowest `count` bits of `value`, least significant first.
+    fn write_bits(&mut self, count: usize, mut value: u32) {
+        for _ in 0..count {
+            self.write_bit(value & 1);
+            value >>= 1;
+        }
+    }
+
+    fn finish(mut self) -> String {
+        loop {
+            self.value <<= 1;
+            if self.position == self.bits_per_char - 1 {
+                self.data.push((self.to_char)(self.value));
+                return self.data;
+            }
+            self.position += 1;
+        }
+    }
+}
+
+/// Compression state.
+struct Compressor<F> {
+    dictionary: HashMap<Vec<u16>, u32>,
+    /// Single characters added to the dictionary but not yet written out.
+    to_create: HashSet<Vec<u16>>,
+    num_bits: usize,
+    enlarge_in: u32,
+    out: Writer<F>,
+}
+
+impl<F: Fn(u32) -> char> Compressor<F> {
+    fn add(&mut self, entry: Vec<u16>) {
+        // The first three codes are reserved.
+        let code = self.dictionary.len() as u32 + 3;
+        self.dictionary.insert(entry, code);
+    }
+
+    fn enlarge(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.num_bits;
+            self.num_bits += 1;
+        }
+    }
+
+    /// Write the code of `w`.
+    fn emit(&mut self, w: &[u16]) {
+        if self.to_create.remove(w) {
+            let unit = u32::from(w[0]);
+            if unit < 256 {
+                self.out.write_bits(self.num_bits, 0);
+                self.out.write_bits(8, unit);
+            } else {
+                self.out.write_bits(self.num_bits, 1);
+                self.out.write_bits(16, unit);
+            }
+            self.enlarge();
+        } else {
+            let code = self.dictionary[w];
+            self.out.write_bits(self.num_bits, code);
+        }
+        self.enlarge();
+    }
+}
+
+fn compress(input: &str, bits_per_char: usize, to_char: impl Fn(u32) -> char) -> String {
+    let mut compressor = Compressor {
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        num_bits: 2,
+        // Compensates for the first entry, which shouldn't count.
+        enlarge_in: 2,
+        out: Writer {
+            data: String::new(),
+            value: 0,
+            position: 0,
+            bits_per_char,
+            to_char,
+        },
+    };
+
+    let mut w: Vec<u16> = Vec::new();
+    for unit in input.encode_utf16() {
+        let c = vec![unit];
+        if !compressor.dictionary.contains_key(&c) {
+            compressor.add(c.clone());
+            compressor.to_create.insert(c.clone());
+        }
+
+        let mut wc = w.clone();
+        wc.push(unit);
+        if compressor.dictionary.contains_key(&wc) {
+            w = wc;
+        } else {
+            compressor.emit(&w);
+            compressor.add(wc);
+            w = c;
+        }
+    }
+    if !w.is_empty() {
+        compressor.emit(&w);
+    }
+
+    // End of stream.
+    compressor.out.write_bits(compressor.num_bits, 2);
+    compressor.out.finish()
+}
+
+/// Reads bits from characters, most significant first. `reset` is the highest bit of a character.
+struct Reader<'a> {
+    values: &'a [u32],
+    value: u32,
+    position: u32,
+    index: usize,
+    reset: u32,
+}
+
+impl Reader<'_> {
+    fn read_bits(&mut self, count: usize) -> u32 {
+        let mut bits = 0;
+        for power in 0..count {
+            let bit = self.value & self.position;
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = self.reset;
+                self.value = self.values.get(self.index).cloned().unwrap_or(0);
+                self.index += 1;
+            }
+            if bit > 0 {
+                bits |= 1 << power;
+            }
+        }
+        bits
+    }
+}
+
+fn decompress(values: &[u32], reset: u32) -> Option<String> {
+    let mut input = Reader {
+        values,
+        value: values.first().cloned().unwrap_or(0),
+        position: reset,
+        index: 1,
+        reset,
+    };
+    // The first three codes are reserved.
+    let mut dictionary: Vec<Vec<u16>> = vec![Vec::new(); 3];
+    let mut enlarge_in: u32 = 4;
+    let mut num_bits = 3;
+
+    let first = match input.read_bits(2) {
+        0 => input.read_bits(8),
+        1 => input.read_bits(16),
+        2 => return Some(String::new()),
+        _ => return None,
+    };
+    let mut w = vec![first as u16];
+    dictionary.push(w.clone());
+    let mut result = w.clone();
+
+    loop {
+        if input.index > values.len() {
+            return None;
+        }
+        let mut code = input.read_bits(num_bits) as usize;
+        match code {
+            0 | 1 => {
+                let unit = input.read_bits(if code == 0 { 8 } else { 16 });
+                dictionary.push(vec![unit as u16]);
+                code = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return String::from_utf16(&result).ok(),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+
+        let entry = if code < dictionary.len() {
+            dictionary[code].clone()
+        } else if code == dictionary.len() {
+            let mut entry = w.clone();
+            entry.push(w[0]);
+            entry
+        } else {
+            return None;
+        };
+        result.extend_from_slice(&entry);
+
+        let mut next = w;
+        next.push(entry[0]);
+        dictionary.push(next);
+        enlarge_in -= 1;
+        w = entry;
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
+
+/// Values of characters in `alphabet`. Returns `None` if any character isn't in it.
+fn digits(input: &str, alphabet: &[u8]) -> Option<Vec<u32>> {
+    input
+        .bytes()
+        .map(|c| alphabet.iter().position(|a| *a == c).map(|d| d as u32))
+        .collect()
+}
+
+/// Like lz-string's `compressToEncodedURIComponent`. Output is safe to use in URLs as is.
+pub fn compress_to_encoded_uri_component(input: &str) -> String {
+    compress(input, 6, |value| URI_SAFE[value as usize] as char)
+}
+
+/// Like lz-string's `decompressFromEncodedURIComponent`. Returns `None` if `input` is invalid.
+pub fn decompress_from_encoded_uri_component(input: &str) -> Option<String> {
+    // Spaces are what `+` becomes when a URL is decoded carelessly.
+    let input = input.replace(' ', "+");
+    decompress(&digits(&input, URI_SAFE)?, 32)
+}
+
+/// Like lz-string's `compressToBase64`.
+pub fn compress_to_base64(input: &str) -> String {
+    let mut output = compress(input, 6, |value| BASE64[value as usize] as char);
+    while output.len() % 4 != 0 {
+        output.push('=');
+    }
+    output
+}
+
+/// Like lz-string's `decompressFromBase64`. Returns `None` if `input` is invalid.
+pub fn decompress_from_base64(input: &str) -> Option<String> {
+    decompress(&digits(input, BASE64)?, 32)
+}
+
+/// Like lz-string's `compressToUTF16`. Output is valid UTF-16, suitable for `localStorage`.
+pub fn compress_to_utf16(input: &str) -> String {
+    let mut output = compress(input, 15, |value| {
+        std::char::from_u32(value + 32).expect("15 bit values are valid characters")
+    });
+    output.push(' ');
+    output
+}
+
+/// Like lz-string's `decompressFromUTF16`. Returns `None` if `input` is invalid.
+pub fn decompress_from_utf16(input: &str) -> Option<String> {
+    let values: Vec<u32> = input
+        .encode_utf16()
+        .map(|unit| u32::from(unit).wrapping_sub(32))
+        .collect();
+    decompress(&values, 16384)
+}
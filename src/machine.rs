@@ -0,0 +1,307 @@
+//! State machines as shared state.
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::future::Future;
+use std::rc::Rc;
+
+use serde_json::{json, Map, Value};
+use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
+#[cfg(feature = "derive")]
+pub use yew_state_derive::{Chart, Events};
+
+use crate::error::Error;
+use crate::handle::StateHandle;
+use crate::handler::{Handler, Request};
+use crate::telemetry;
+
+/// State that can only change through typed transitions.
+///
+/// ```ignore
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Door {
+///     Open,
+///     Closed,
+/// }
+///
+/// #[derive(Debug)]
+/// enum DoorEvent {
+///     Push,
+///     Pull,
+/// }
+///
+/// impl Machine for Door {
+///     type Event = DoorEvent;
+///
+///     fn transition(&self, event: &Self::Event) -> Option<Self> {
+///         match (self, event) {
+///             (Door::Closed, DoorEvent::Push) => Some(Door::Open),
+///             (Door::Open, DoorEvent::Pull) => Some(Door::Closed),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait Machine: Debug + Sized {
+    type Event: Debug;
+
+    /// Next state after `event`, or `None` if `event` isn't allowed in the current state.
+    fn transition(&self, event: &Self::Event) -> Option<Self>;
+
+    /// State shown while a guarded transition with `event` is pending. Returns `None` by default,
+    /// leaving state unchanged until the guard resolves.
+    fn pending(&self, _event: &Self::Event) -> Option<Self> {
+        None
+    }
+}
+
+/// Move `state` to its next state, or report an illegal transition.
+pub(crate) fn transition<M: Machine>(state: &mut M, event: &M::Event) -> Result<(), Error> {
+    match state.transition(event) {
+        Some(next) => {
+            *state = next;
+            Ok(())
+        }
+        None => Err(Error::IllegalTransition {
+            state: format!("{:?}", state),
+            event: format!("{:?}", event),
+        }),
+    }
+}
+
+/// A guarded transition waiting on its guard.
+struct Pending<M> {
+    /// State before the transition, once it's entered.
+    origin: Option<M>,
+    /// Whether something else changed state since.
+    superseded: bool,
+}
+
+/// Enter pending state of a guarded transition (or its next state, if `optimistic`), remembering
+/// where it started.
+fn enter_pending<M: Machine + Clone>(
+    state: &mut M,
+    event: &M::Event,
+    pending: &RefCell<Pending<M>>,
+    optimistic: bool,
+) -> Result<(), Error> {
+    // Don't bother waiting for guards on illegal transitions.
+    let mut next = state.clone();
+    transition(&mut next, event)?;
+
+    pending.borrow_mut().origin = Some(state.clone());
+    if optimistic {
+        *state = next;
+    } else if let Some(pending) = state.pending(event) {
+        *state = pending;
+    }
+
+    Ok(())
+}
+
+/// Finish a guarded transition once its guard resolved. Only the transition is rolled back (or
+/// committed): if something else changed state while it was pending, that change stays, and an
+/// allowed transition applies to it instead.
+fn leave_pending<M: Machine>(
+    state: &mut M,
+    event: &M::Event,
+    pending: &RefCell<Pending<M>>,
+    allowed: bool,
+) -> Result<(), Error> {
+    let Pending { origin, superseded } = &mut *pending.borrow_mut();
+    // Transition was never entered.
+    let origin = match origin.take() {
+        Some(origin) => origin,
+        None => return Ok(()),
+    };
+    if !*superseded {
+        *state = origin;
+    }
+    if allowed {
+        transition(state, event)
+    } else {
+        Ok(())
+    }
+}
+
+impl<T, H> StateHandle<T, H>
+where
+    T: Machine + Default + Clone + 'static,
+    T::Event: 'static,
+    H: Handler<Model = T> + 'static,
+{
+    /// Send an event to the state machine. Illegal transitions leave state unchanged, and are
+    /// reported as an error.
+    pub fn transition(&self, event: T::Event) {
+        self.try_reduce(move |state| transition(state, &event))
+    }
+
+    /// Send an event that only takes effect once `guard` resolves to `true`, e.g. after server
+    /// confirmation. Until then state shows `Machine::pending`, if provided.
+    ///
+    /// If the guard resolves to `false` the transition is abandoned, returning to state from
+    /// before it, and reported as an error. Changes made by anything else while it was pending
+    /// are kept: state only returns if nothing else changed it, and an allowed transition applies
+    /// to whatever state is current by then.
+    ///
+    /// The guard is seen through even if the component unmounts before it resolves.
+    pub fn transition_guarded(&self, event: T::Event, guard: impl Future<Output = bool> + 'static) {
+        self.guarded(event, guard, false)
+    }
+
+    /// Like `transition_guarded`, but optimistic: state moves to its next state right away, and
+    /// is rolled back if `guard` resolves to `false`.
+    pub fn transition_optimistic(
+        &self,
+        event: T::Event,
+        guard: impl Future<Output = bool> + 'static,
+    ) {
+        self.guarded(event, guard, true)
+    }
+
+    fn guarded(
+        &self,
+        event: T::Event,
+        guard: impl Future<Output = bool> + 'static,
+        optimistic: bool,
+    ) {
+        let event = Rc::new(event);
+        let pending = Rc::new(RefCell::new(Pending {
+            origin: None,
+            superseded: false,
+        }));
+        {
+            let event = event.clone();
+            let pending = pending.clone();
+            self.try_reduce(move |state| enter_pending(state, &event, &pending, optimistic));
+        }
+        // Any change after entering supersedes it, until it's finished.
+        let watching = Rc::downgrade(&pending);
+        self.send(Request::Watch(
+            Box::new(move |_| match watching.upgrade() {
+                Some(pending) => {
+                    pending.borrow_mut().superseded = true;
+                    false
+                }
+                None => true,
+            }),
+            false,
+        ));
+
+        self.detach(Box::new(move |send| {
+            spawn_local(async move {
+                let allowed = guard.await;
+                let rejected =
+                    pending
+                        .borrow()
+                        .origin
+                        .as_ref()
+                        .map(|origin| Error::GuardRejected {
+                            state: format!("{:?}", origin),
+                            event: format!("{:?}", event),
+                        });
+                send.emit(Request::TryApplyOnce(Box::new(move |state| {
+                    leave_pending(state, &*event, &pending, allowed)
+                })));
+                if let (false, Some(rejected)) = (allowed, rejected) {
+                    send.emit(Request::Report(rejected));
+                }
+            });
+        }));
+    }
+
+    /// Convenience method for sending events to the state machine from a `Callback`.
+    pub fn transition_callback<E: 'static>(
+        &self,
+        f: impl Fn(E) -> T::Event + 'static,
+    ) -> Callback<E> {
+        let this = self.clone();
+        Callback::from(move |e| this.transition(f(e)))
+    }
+}
+
+/// State machine whose states and events can be listed, allowing its transitions to be exported
+/// for visualization. With the `derive` feature, `#[derive(Chart)]` lists the variants of a state
+/// enum, and its events are listed by `#[derive(Events)]` on the event enum.
+pub trait Chart: Machine + Default {
+    /// Every state of the machine.
+    fn states() -> Vec<Self>;
+    /// Every event the machine may receive.
+    fn events() -> Vec<Self::Event>;
+}
+
+/// Event type whose every event can be listed.
+pub trait Events: Sized {
+    /// Every event.
+    fn all() -> Vec<Self>;
+}
+
+/// Every legal transition of `M`, as (from, event, to) names.
+fn transitions<M: Chart>() -> Vec<(String, String, String)> {
+    let events = M::events();
+    M::states()
+        .iter()
+        .flat_map(|state| {
+            events.iter().filter_map(move |event| {
+                state.transition(event).map(|next| {
+                    (
+                        format!("{:?}", state),
+                        format!("{:?}", event),
+                        format!("{:?}", next),
+                    )
+                })
+            })
+        })
+        .collect()
+}
+
+/// Describe the transitions of `M` in Graphviz DOT format.
+pub fn to_dot<M: Chart>() -> String {
+    let mut dot = String::from("digraph {\n");
+    dot.push_str(&format!(
+        "    {:?} [shape=doublecircle];\n",
+        format!("{:?}", M::default())
+    ));
+    for state in M::states() {
+        dot.push_str(&format!("    {:?};\n", format!("{:?}", state)));
+    }
+    for (from, event, to) in transitions::<M>() {
+        dot.push_str(&format!(
+            "    {:?} -> {:?} [label={:?}];\n",
+            from, to, event
+        ));
+    }
+    dot.push('}');
+    dot
+}
+
+/// Describe the transitions of `M` as an XState machine config (JSON). Transitions to states
+/// missing from `Chart::states` are left out (with a warning), as XState rejects them.
+pub fn to_xstate<M: Chart>(id: &str) -> String {
+    let mut states = Map::new();
+    for state in M::states() {
+        states.insert(format!("{:?}", state), json!({ "on": {} }));
+    }
+    for (from, event, to) in transitions::<M>() {
+        if !states.contains_key(&to) {
+            telemetry::warn(&format!(
+                "yew-state: Left out transition from {} on {} to {}, as it isn't in `Chart::states`.",
+                from, event, to
+            ));
+            continue;
+        }
+        let on = states
+            .get_mut(&from)
+            .and_then(|state| state["on"].as_object_mut());
+        if let Some(on) = on {
+            on.insert(event, Value::String(to));
+        }
+    }
+
+    json!({
+        "id": id,
+        "initial": format!("{:?}", M::default()),
+        "states": states,
+    })
+    .to_string()
+}
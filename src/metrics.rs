@@ -0,0 +1,90 @@
+//! Metrics of the state layer, for monitoring apps in production.
+//!
+//! Metrics are sent to a sink of your own, set with `config().metrics()`, which forwards them to
+//! whatever collects your app's metrics:
+//!
+//! ```ignore
+//! struct Beacon;
+//!
+//! impl MetricsSink for Beacon {
+//!     fn increment(&self, counter: Counter, scope: &str) {
+//!         telemetry::count(counter.name(), &[("scope", scope)]);
+//!     }
+//!
+//!     fn gauge(&self, gauge: Gauge, scope: &str, value: f64) {
+//!         telemetry::gauge(gauge.name(), value, &[("scope", scope)]);
+//!     }
+//! }
+//!
+//! yew_state::config().metrics(Beacon);
+//! ```
+use crate::config;
+use crate::handler::Handler;
+use crate::scope;
+
+/// Events counted per scope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// Requests applied to state.
+    Dispatches,
+    /// Errors reported to subscribers.
+    Errors,
+    /// Times state was written to storage.
+    StorageWrites,
+}
+
+impl Counter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Counter::Dispatches => "yew_state.dispatches",
+            Counter::Errors => "yew_state.errors",
+            Counter::StorageWrites => "yew_state.storage_writes",
+        }
+    }
+}
+
+/// Values measured per scope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gauge {
+    /// Components and dispatches connected.
+    Subscribers,
+    /// Size of state in bytes, not counting what it points to.
+    StateSize,
+}
+
+impl Gauge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Gauge::Subscribers => "yew_state.subscribers",
+            Gauge::StateSize => "yew_state.state_size",
+        }
+    }
+}
+
+/// Receives metrics. `scope` names the model, and the scope type unless it's the default. Storage
+/// writes are counted by model only.
+pub trait MetricsSink {
+    fn increment(&self, counter: Counter, scope: &str);
+    fn gauge(&self, gauge: Gauge, scope: &str, value: f64);
+}
+
+/// Count an event of a scope.
+pub(crate) fn count<H: Handler + 'static, SCOPE: 'static>(counter: Counter) {
+    if let Some(sink) = config::metrics() {
+        sink.increment(counter, &scope::name::<H, SCOPE>());
+    }
+}
+
+/// Measure a value of a scope.
+pub(crate) fn gauge<H: Handler + 'static, SCOPE: 'static>(gauge: Gauge, value: f64) {
+    if let Some(sink) = config::metrics() {
+        sink.gauge(gauge, &scope::name::<H, SCOPE>(), value);
+    }
+}
+
+/// Count a write of `T` to storage.
+pub(crate) fn stored<T>() {
+    if let Some(sink) = config::metrics() {
+        sink.increment(Counter::StorageWrites, std::any::type_name::<T>());
+    }
+}
@@ -0,0 +1,114 @@
+//! When subscribers are notified of changes.
+//!
+//! Without a policy, it's up to the handler: `SharedHandle` and `StorageHandle` notify after every
+//! change, even if state ended up the same, while `DiffHandle` only notifies when state differs.
+//! Set a policy to make it explicit for a scope. When a policy holds back a patch (of `DiffHandle`),
+//! subscribers are sent entire state the next time they're notified, as later patches wouldn't
+//! apply:
+//!
+//! ```ignore
+//! // Every click retriggers the animation, even when the count didn't move.
+//! notify::policy::<SharedHandle<Pulse>>(NotifyPolicy::Always);
+//! // Only notify when the visible part changed.
+//! notify::policy::<SharedHandle<Feed>>(NotifyPolicy::Custom(|old, new| old.items != new.items));
+//! ```
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::handle::Handle;
+use crate::handler::Handler;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+/// Given state before and after a change, whether to notify subscribers.
+pub(crate) type Policy<T> = Rc<dyn Fn(&T, &T) -> bool>;
+
+thread_local! {
+    /// Policy of every scope that has one, keyed by handler and scope.
+    static POLICIES: RefCell<HashMap<(TypeId, TypeId), Rc<dyn Any>>> = Default::default();
+}
+
+/// When subscribers are notified of a change.
+pub enum NotifyPolicy<T> {
+    /// After every change, even if state is the same.
+    Always,
+    /// Only if state isn't equal to what it was.
+    OnChange,
+    /// When the function returns `true`, given state before and after the change.
+    Custom(fn(&T, &T) -> bool),
+}
+
+/// Notify subscribers of shared state of handle type `H` according to `policy`, replacing any
+/// policy set before. Policies other than `Always` keep a copy of state from before each change to
+/// compare with.
+pub fn policy<H>(policy: NotifyPolicy<Model<H>>)
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    Model<H>: PartialEq,
+{
+    policy_in::<H, StateHandler<H>>(policy)
+}
+
+/// Like `policy`, for shared state in `SCOPE`.
+pub fn policy_in<H, SCOPE>(policy: NotifyPolicy<Model<H>>)
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    Model<H>: PartialEq,
+    SCOPE: 'static,
+{
+    set::<StateHandler<H>>(TypeId::of::<SCOPE>(), compile(policy));
+}
+
+/// `policy` as a function of state before and after a change.
+pub(crate) fn compile<T>(policy: NotifyPolicy<T>) -> Policy<T>
+where
+    T: PartialEq + 'static,
+{
+    match policy {
+        NotifyPolicy::Always => Rc::new(|_, _| true),
+        NotifyPolicy::OnChange => Rc::new(|old, new| old != new),
+        NotifyPolicy::Custom(f) => Rc::new(f),
+    }
+}
+
+/// Set the policy of shared state of handler `H` in the scope with type id `scope`.
+pub(crate) fn set<H>(scope: TypeId, policy: Policy<H::Model>)
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+{
+    let key = (TypeId::of::<H>(), scope);
+    POLICIES.with(|policies| {
+        policies
+            .borrow_mut()
+            .insert(key, Rc::new(policy) as Rc<dyn Any>)
+    });
+}
+
+/// Leave notifying subscribers of shared state of handle type `H` in `SCOPE` to its handler
+/// again.
+pub fn reset<H, SCOPE>()
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    POLICIES.with(|policies| policies.borrow_mut().remove(&key));
+}
+
+/// Policy of a scope, if one is set.
+pub(crate) fn policy_of<H, SCOPE>() -> Option<Policy<H::Model>>
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    let policy = POLICIES.with(|policies| policies.borrow().get(&key).cloned())?;
+    policy.downcast_ref::<Policy<H::Model>>().cloned()
+}
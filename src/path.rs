@@ -0,0 +1,152 @@
+//! Typed paths to fields deep in state, made with `path!`.
+//!
+//! A path reads and writes one field, so deeply nested updates don't need hand-written closures,
+//! and components can tell whether the field they show changed:
+//!
+//! ```ignore
+//! let email = path!(AppState.settings.notifications.email);
+//!
+//! handle.set(email, true);
+//!
+//! fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+//!     let changed = email.changed(self.handle.state(), handle.state());
+//!     self.handle = handle;
+//!     changed
+//! }
+//! ```
+//!
+//! Changes made with `set` are labeled with the path (as in `"settings.notifications.email"`), so
+//! authorizers and tracing see which field changed.
+//!
+//! Paths to booleans, options and vectors also make the usual changes to them, for `reduce` and
+//! `reduce_callback`:
+//!
+//! ```ignore
+//! let todos = path!(AppState.todos);
+//!
+//! let onclick = handle.reduce_callback(todos.push(Todo::new("Buy milk")));
+//! handle.reduce(todos.retain(|todo| !todo.done));
+//! handle.reduce(todos.update_where(|todo| todo.id == id, |todo| todo.done = true));
+//! handle.reduce(path!(AppState.dark_mode).toggle());
+//! ```
+use std::fmt;
+
+/// Path from `T` to one of its fields, of type `U`. Made with `path!`.
+pub struct Path<T, U> {
+    get: fn(&T) -> &U,
+    get_mut: fn(&mut T) -> &mut U,
+    name: &'static str,
+}
+
+impl<T, U> Path<T, U> {
+    #[doc(hidden)]
+    pub fn new(get: fn(&T) -> &U, get_mut: fn(&mut T) -> &mut U, name: &'static str) -> Self {
+        Self { get, get_mut, name }
+    }
+
+    /// The field in `state`.
+    pub fn get<'a>(&self, state: &'a T) -> &'a U {
+        (self.get)(state)
+    }
+
+    /// The field in `state`, to change.
+    pub fn get_mut<'a>(&self, state: &'a mut T) -> &'a mut U {
+        (self.get_mut)(state)
+    }
+
+    /// The fields followed from `T`, separated by dots.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Whether the field differs between `old` and `new`.
+    pub fn changed(&self, old: &T, new: &T) -> bool
+    where
+        U: PartialEq,
+    {
+        self.get(old) != self.get(new)
+    }
+}
+
+impl<T> Path<T, bool> {
+    /// Flip the field.
+    pub fn toggle(self) -> impl Fn(&mut T) {
+        move |state| {
+            let field = self.get_mut(state);
+            *field = !*field;
+        }
+    }
+}
+
+impl<T, V: Clone> Path<T, Option<V>> {
+    /// Set the field to `Some(value)`.
+    pub fn set_some(self, value: V) -> impl Fn(&mut T) {
+        move |state| *self.get_mut(state) = Some(value.clone())
+    }
+
+    /// Set the field to `None`.
+    pub fn take(self) -> impl Fn(&mut T) {
+        move |state| *self.get_mut(state) = None
+    }
+}
+
+impl<T, V: Clone> Path<T, Vec<V>> {
+    /// Push `item` to the end.
+    pub fn push(self, item: V) -> impl Fn(&mut T) {
+        move |state| self.get_mut(state).push(item.clone())
+    }
+
+    /// Keep only items matching `keep`.
+    pub fn retain(self, keep: impl Fn(&V) -> bool) -> impl Fn(&mut T) {
+        move |state| self.get_mut(state).retain(&keep)
+    }
+
+    /// Change every item matching `matches` with `f`.
+    pub fn update_where(self, matches: impl Fn(&V) -> bool, f: impl Fn(&mut V)) -> impl Fn(&mut T) {
+        move |state| {
+            self.get_mut(state)
+                .iter_mut()
+                .filter(|item| matches(item))
+                .for_each(&f)
+        }
+    }
+}
+
+impl<T, U> Clone for Path<T, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, U> Copy for Path<T, U> {}
+
+impl<T, U> fmt::Debug for Path<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Path").field(&self.name).finish()
+    }
+}
+
+/// Typed path to a field of state, starting with the name of the state's type. See the
+/// [`path`](mod@crate::path) module.
+///
+/// ```ignore
+/// handle.set(path!(AppState.settings.notifications.email), true);
+/// ```
+#[macro_export]
+macro_rules! path {
+    ($state:ident $(. $field:tt)+) => {
+        $crate::path::Path::new(
+            |state: &$state| &state $(.$field)+,
+            |state: &mut $state| &mut state $(.$field)+,
+            $crate::__path_name!($($field)+),
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __path_name {
+    ($first:tt $($field:tt)*) => {
+        concat!(stringify!($first) $(, ".", stringify!($field))*)
+    };
+}
@@ -0,0 +1,114 @@
+//! Low power mode, for going easy on batteries.
+//!
+//! In low power mode, recurring work runs a quarter as often: recurring changes made with `every`,
+//! and feature flag refreshes. Switch it from a user setting, taking effect right away:
+//!
+//! ```ignore
+//! yew_state::config().low_power(settings.battery_saver);
+//! ```
+//!
+//! Or, with the `battery` feature, follow the battery where the browser has the Battery Status
+//! API, switching to low power while discharging below a level:
+//!
+//! ```ignore
+//! power::follow_battery(0.2);
+//! ```
+//!
+//! Presence heartbeats and leader leases keep their timing, as peers time out by them.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+#[cfg(any(target_arch = "wasm32", feature = "flags"))]
+use std::time::Duration;
+
+/// How many times longer recurring work waits in low power mode.
+#[cfg(any(target_arch = "wasm32", feature = "flags"))]
+const STRETCH: u32 = 4;
+
+type Watcher = Rc<dyn Fn(bool)>;
+
+thread_local! {
+    static LOW_POWER: Cell<bool> = const { Cell::new(false) };
+    static WATCHERS: RefCell<Vec<(u64, Watcher)>> = Default::default();
+    static NEXT: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Whether low power mode is on.
+pub fn is_low_power() -> bool {
+    LOW_POWER.with(Cell::get)
+}
+
+/// Switch low power mode, telling watchers if it changed.
+pub(crate) fn set(low: bool) {
+    if LOW_POWER.with(|current| current.replace(low)) == low {
+        return;
+    }
+    // Watchers may watch or stop watching while told.
+    let watchers: Vec<Watcher> =
+        WATCHERS.with(|watchers| watchers.borrow().iter().map(|(_, f)| f.clone()).collect());
+    for f in watchers {
+        f(low);
+    }
+}
+
+/// `interval`, stretched in low power mode.
+#[cfg(any(target_arch = "wasm32", feature = "flags"))]
+pub(crate) fn stretch(interval: Duration) -> Duration {
+    if is_low_power() {
+        interval * STRETCH
+    } else {
+        interval
+    }
+}
+
+/// Subscription made by `watch`. Dropping it unsubscribes.
+pub(crate) struct PowerGuard {
+    id: u64,
+}
+
+impl Drop for PowerGuard {
+    fn drop(&mut self) {
+        WATCHERS.with(|watchers| watchers.borrow_mut().retain(|(id, _)| *id != self.id));
+    }
+}
+
+/// Call `f` whenever low power mode is switched, until the returned guard is dropped.
+pub(crate) fn watch(f: impl Fn(bool) + 'static) -> PowerGuard {
+    let id = NEXT.with(|next| next.replace(next.get() + 1));
+    WATCHERS.with(|watchers| watchers.borrow_mut().push((id, Rc::new(f))));
+    PowerGuard { id }
+}
+
+/// Be in low power mode while the battery is discharging below `level` (0 to 1), for as long as
+/// the page lives. Does nothing where the browser doesn't have the Battery Status API.
+#[cfg(feature = "battery")]
+pub fn follow_battery(level: f64) {
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::BatteryManager;
+
+    let navigator = match web_sys::window() {
+        Some(window) => window.navigator(),
+        None => return,
+    };
+    let get_battery = js_sys::Reflect::get(&navigator, &JsValue::from_str("getBattery"))
+        .ok()
+        .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+    let promise = match get_battery.and_then(|f| f.call0(&navigator).ok()) {
+        Some(promise) => js_sys::Promise::from(promise),
+        None => return,
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        let battery: BatteryManager = match wasm_bindgen_futures::JsFuture::from(promise).await {
+            Ok(battery) => battery.unchecked_into(),
+            Err(_) => return,
+        };
+        let update = {
+            let battery = battery.clone();
+            move || set(!battery.charging() && battery.level() < level)
+        };
+        update();
+        let onchange = Closure::wrap(Box::new(update) as Box<dyn FnMut()>);
+        battery.set_onchargingchange(Some(onchange.as_ref().unchecked_ref()));
+        battery.set_onlevelchange(Some(onchange.as_ref().unchecked_ref()));
+        onchange.forget();
+    });
+}
@@ -0,0 +1,20 @@
+//! Commonly used items, for glob importing:
+//!
+//! ```ignore
+//! use yew_state::prelude::*;
+//!
+//! pub type App = SharedStateComponent<Model>;
+//! ```
+pub use yew::services::storage::Area;
+
+pub use crate::component::{SharedStateComponent, StateConfig, StateView};
+pub use crate::dispatch::Dispatch;
+pub use crate::error::Error;
+pub use crate::handle::{
+    DiffHandle, MappedHandle, ServiceHandle, SharedHandle, SharedState, StorageHandle,
+};
+pub use crate::handler::{Diff, HandlerSettings, Priority, SavePolicy, Storable};
+pub use crate::notify::NotifyPolicy;
+pub use crate::path::Path;
+#[cfg(feature = "derive")]
+pub use yew_state_derive::Storable;
@@ -0,0 +1,102 @@
+//! Statistics of running shared state services, for profiling during development.
+//!
+//! Every service records its statistics here while the `profiler` feature is on. Render
+//! `StateProfiler` to show them in a floating panel, or read them with `scopes`.
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+
+use crate::clock;
+use crate::handler::Handler;
+use crate::scope;
+
+/// Milliseconds over which the dispatch rate is measured.
+const WINDOW: f64 = 1_000.0;
+
+/// Statistics of one running scope.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScopeStats {
+    /// Model and scope of the service.
+    pub name: String,
+    /// Requests applied since the service started.
+    pub dispatches: u64,
+    /// Requests applied in the last second.
+    pub rate: usize,
+    /// Milliseconds the last request took to apply, notifications excluded.
+    pub last_duration: f64,
+    pub subscribers: usize,
+    /// Size of state in bytes, not counting what it points to (the contents of `Vec`s, `String`s
+    /// and such).
+    pub size: usize,
+}
+
+struct Entry {
+    stats: ScopeStats,
+    /// When recent requests were applied.
+    recent: VecDeque<f64>,
+}
+
+thread_local! {
+    static SCOPES: RefCell<HashMap<(TypeId, TypeId), Entry>> = Default::default();
+}
+
+fn with_entry<H: Handler + 'static, SCOPE: 'static>(f: impl FnOnce(&mut Entry)) {
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let entry = scopes.entry(key).or_insert_with(|| Entry {
+            stats: ScopeStats {
+                name: scope::name::<H, SCOPE>(),
+                dispatches: 0,
+                rate: 0,
+                last_duration: 0.0,
+                subscribers: 0,
+                size: 0,
+            },
+            recent: VecDeque::new(),
+        });
+        f(entry)
+    });
+}
+
+/// Record a request applied by a service.
+pub(crate) fn applied<H: Handler + 'static, SCOPE: 'static>(started: f64, size: usize) {
+    let now = clock::monotonic();
+    with_entry::<H, SCOPE>(|entry| {
+        entry.stats.dispatches += 1;
+        entry.stats.last_duration = now - started;
+        entry.stats.size = size;
+        entry.recent.push_back(now);
+    });
+}
+
+/// Record the number of subscribers of a service.
+pub(crate) fn subscribers<H: Handler + 'static, SCOPE: 'static>(count: usize) {
+    with_entry::<H, SCOPE>(|entry| entry.stats.subscribers = count);
+}
+
+/// Forget a service that stopped.
+pub(crate) fn stopped<H: Handler + 'static, SCOPE: 'static>() {
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    SCOPES.with(|scopes| scopes.borrow_mut().remove(&key));
+}
+
+/// Statistics of every running scope, ordered by name.
+pub fn scopes() -> Vec<ScopeStats> {
+    let now = clock::monotonic();
+    let mut scopes: Vec<ScopeStats> = SCOPES.with(|scopes| {
+        scopes
+            .borrow_mut()
+            .values_mut()
+            .map(|entry| {
+                while entry.recent.front().is_some_and(|&at| now - at > WINDOW) {
+                    entry.recent.pop_front();
+                }
+                entry.stats.rate = entry.recent.len();
+                entry.stats.clone()
+            })
+            .collect()
+    });
+    scopes.sort_by(|a, b| a.name.cmp(&b.name));
+    scopes
+}
@@ -0,0 +1,39 @@
+//! Progress of long-running operations on shared state, kept beside state instead of in it.
+//!
+//! Any subscriber can report progress, and every handle sees it with `handle.progress()`, so a
+//! progress bar can render it without the model having to carry it:
+//!
+//! ```ignore
+//! handle.report_progress(Progress::new(40.0, "Importing books"));
+//! // Later, once done.
+//! handle.clear_progress();
+//!
+//! // In a view.
+//! if let Some(progress) = handle.progress() {
+//!     html! { <progress max="100" value=progress.percent.to_string()>{ &progress.message }</progress> }
+//! }
+//! ```
+
+/// How far along an operation is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress {
+    /// From 0 to 100.
+    pub percent: f64,
+    /// What's being done, for showing next to the progress bar. May be empty.
+    pub message: String,
+}
+
+impl Progress {
+    /// Progress at `percent`, clamped from 0 to 100.
+    pub fn new(percent: f64, message: impl Into<String>) -> Self {
+        Self {
+            percent: percent.clamp(0.0, 100.0),
+            message: message.into(),
+        }
+    }
+
+    /// Whether the operation is done.
+    pub fn is_finished(&self) -> bool {
+        self.percent >= 100.0
+    }
+}
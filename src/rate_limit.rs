@@ -0,0 +1,175 @@
+//! Rate limiting changes to shared state, to keep runaway loops (like a component changing state
+//! every time it renders) from freezing the page.
+//!
+//! Limits are token buckets: a scope (or each label in it) may make `burst` changes at once, and
+//! regains capacity at `per_second`. Changes over the limit are dropped, or deferred until there's
+//! capacity again. Either way `Error::RateLimited` is reported, once each time changes start being
+//! throttled. Restarting the handler counts as a change, with no label.
+//!
+//! ```ignore
+//! rate_limit::limit::<SharedHandle<Search>>(RateLimit::new(10, 5.0).per_label().defer());
+//! ```
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::clock::now;
+use crate::handle::Handle;
+use crate::handler::Handler;
+
+type StateHandler<H> = <H as Handle>::Handler;
+
+/// Longest wait for capacity before checking again, in milliseconds.
+const MAX_WAIT: f64 = 60_000.0;
+
+/// What happens to changes over the limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Overflow {
+    Drop,
+    Defer,
+}
+
+/// Limit of a scope.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    burst: f64,
+    per_second: f64,
+    per_label: bool,
+    overflow: Overflow,
+}
+
+impl RateLimit {
+    /// Allow `burst` changes at once, regaining capacity for `per_second` changes every second.
+    /// Changes over the limit are dropped.
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self {
+            burst: f64::from(burst.max(1)),
+            per_second,
+            per_label: false,
+            overflow: Overflow::Drop,
+        }
+    }
+
+    /// Limit each label separately (see `reduce_labeled`), rather than the scope as a whole.
+    /// Unlabeled changes share a limit.
+    pub fn per_label(mut self) -> Self {
+        self.per_label = true;
+        self
+    }
+
+    /// Defer changes over the limit until there's capacity, rather than dropping them. Deferred
+    /// changes are applied in order, before any made after them. If the limit is replaced with one
+    /// that drops changes, deferred changes over it are dropped too.
+    pub fn defer(mut self) -> Self {
+        self.overflow = Overflow::Defer;
+        self
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    /// When tokens were last counted, in milliseconds.
+    updated: f64,
+}
+
+struct Limiter {
+    limit: RateLimit,
+    buckets: HashMap<Option<&'static str>, Bucket>,
+    throttled: bool,
+}
+
+thread_local! {
+    /// Limiter of every scope that has one, keyed by handler and scope.
+    static LIMITERS: RefCell<HashMap<(TypeId, TypeId), Limiter>> = Default::default();
+}
+
+/// Limit the rate of changes to handle type `H`, replacing any limit set before.
+pub fn limit<H>(limit: RateLimit)
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+{
+    limit_in::<H, StateHandler<H>>(limit)
+}
+
+/// Like `limit`, for shared state in `SCOPE`.
+pub fn limit_in<H, SCOPE>(limit: RateLimit)
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    let limiter = Limiter {
+        limit,
+        buckets: HashMap::new(),
+        throttled: false,
+    };
+    LIMITERS.with(|limiters| limiters.borrow_mut().insert(key, limiter));
+}
+
+/// Stop limiting changes to handle type `H` in `SCOPE`. Changes deferred already are still applied.
+pub fn unlimit<H, SCOPE>()
+where
+    H: Handle,
+    StateHandler<H>: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<StateHandler<H>>(), TypeId::of::<SCOPE>());
+    LIMITERS.with(|limiters| limiters.borrow_mut().remove(&key));
+}
+
+/// Whether a change may be applied.
+pub(crate) enum Admission {
+    Allowed,
+    Dropped {
+        /// Whether this starts a throttled episode, which should be reported.
+        report: bool,
+    },
+    Deferred {
+        report: bool,
+        /// Milliseconds until there's capacity.
+        wait: f64,
+    },
+}
+
+/// Take capacity for a change labeled `label`, if the scope has it.
+pub(crate) fn admit<H, SCOPE>(label: Option<&'static str>) -> Admission
+where
+    H: Handler + 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    LIMITERS.with(|limiters| {
+        let mut limiters = limiters.borrow_mut();
+        let limiter = match limiters.get_mut(&key) {
+            Some(limiter) => limiter,
+            None => return Admission::Allowed,
+        };
+        let limit = &limiter.limit;
+        let label = if limit.per_label { label } else { None };
+        let now = now();
+        let bucket = limiter.buckets.entry(label).or_insert(Bucket {
+            tokens: limit.burst,
+            updated: now,
+        });
+        let regained = (now - bucket.updated) / 1_000.0 * limit.per_second;
+        bucket.tokens = (bucket.tokens + regained).min(limit.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            limiter.throttled = false;
+            return Admission::Allowed;
+        }
+        let report = !limiter.throttled;
+        limiter.throttled = true;
+        match limit.overflow {
+            Overflow::Drop => Admission::Dropped { report },
+            Overflow::Defer => Admission::Deferred {
+                report,
+                wait: ((1.0 - bucket.tokens) / limit.per_second * 1_000.0).min(MAX_WAIT),
+            },
+        }
+    })
+}
@@ -0,0 +1,125 @@
+//! Redacting state shown by the crate's observability features.
+//!
+//! Models registered with `register` are redacted wherever the crate renders them: `Debug` output
+//! of handles and handlers (and so logs), and the inspector. Derive `Redact` (with the `derive`
+//! feature) to pick how each field is redacted:
+//!
+//! ```ignore
+//! #[derive(Clone, Debug, Default, Serialize, Deserialize, Redact)]
+//! struct Account {
+//!     #[redact(mask)]
+//!     email: String,
+//!     #[redact(hide)]
+//!     api_key: String,
+//!     #[redact(truncate = 32)]
+//!     avatar: String,
+//! }
+//!
+//! redact::register::<Account>();
+//! ```
+//!
+//! Redaction only changes how state is shown, never state itself. Use `Sensitive` for values that
+//! mustn't be written out of the app.
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::sensitive::{sealed, REDACTED};
+
+/// JSON, for implementing `Redact` without depending on `serde_json`.
+pub type Json = Value;
+
+/// How a model is redacted when shown.
+pub trait Redact {
+    /// Redact `json`, the model serialized.
+    fn redact(json: &mut Value);
+}
+
+/// Serializes a registered model, given as `&dyn Any`, then redacts it.
+type Redactor = fn(&dyn Any) -> Option<Value>;
+
+thread_local! {
+    static REDACTORS: RefCell<HashMap<TypeId, Redactor>> = RefCell::default();
+}
+
+/// Redact `T` wherever the crate shows it.
+pub fn register<T: Redact + Serialize + 'static>() {
+    fn show<T: Redact + Serialize + 'static>(state: &dyn Any) -> Option<Value> {
+        let mut json = sealed(|| serde_json::to_value(state.downcast_ref::<T>()?).ok())?;
+        T::redact(&mut json);
+        Some(json)
+    }
+
+    REDACTORS.with(|redactors| redactors.borrow_mut().insert(TypeId::of::<T>(), show::<T>));
+}
+
+/// Redact a value entirely.
+pub fn hide(json: &mut Value) {
+    *json = Value::String(REDACTED.to_string());
+}
+
+/// Keep the first character of a string and the domain of an email, as in `a***@example.com`.
+/// Anything but a string is hidden.
+pub fn mask(json: &mut Value) {
+    let text = match json {
+        Value::String(text) => text,
+        _ => return hide(json),
+    };
+    let (name, domain) = match text.split_once('@') {
+        Some((name, domain)) => (name, format!("@{}", domain)),
+        None => (text.as_str(), String::new()),
+    };
+    let first: String = name.chars().take(1).collect();
+    *json = Value::String(format!("{}***{}", first, domain));
+}
+
+/// Cut strings and arrays down to `max` characters or items, noting how much was left out.
+pub fn truncate(json: &mut Value, max: usize) {
+    match json {
+        Value::String(text) => {
+            if let Some((end, _)) = text.char_indices().nth(max) {
+                *text = format!("{}… ({} bytes)", &text[..end], text.len());
+            }
+        }
+        Value::Array(items) if items.len() > max => {
+            let len = items.len();
+            items.truncate(max);
+            items.push(Value::String(format!("… ({} items)", len)));
+        }
+        _ => {}
+    }
+}
+
+/// Cut arrays and objects at every depth down to `max` items, noting how many were left out.
+pub(crate) fn sample(json: &mut Value, max: usize) {
+    match json {
+        Value::Array(items) => {
+            let len = items.len();
+            items.truncate(max);
+            items.iter_mut().for_each(|item| sample(item, max));
+            if len > max {
+                items.push(Value::String(format!("… ({} items)", len)));
+            }
+        }
+        Value::Object(map) => {
+            let len = map.len();
+            let mut kept: serde_json::Map<_, _> =
+                std::mem::take(map).into_iter().take(max).collect();
+            kept.values_mut().for_each(|value| sample(value, max));
+            if len > max {
+                kept.insert("…".to_string(), Value::String(format!("{} entries", len)));
+            }
+            *map = kept;
+        }
+        _ => {}
+    }
+}
+
+/// `state` redacted, if its type is registered.
+pub(crate) fn show<T: 'static>(state: &T) -> Option<Value> {
+    let show = REDACTORS.with(|redactors| redactors.borrow().get(&TypeId::of::<T>()).copied())?;
+    show(state)
+}
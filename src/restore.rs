@@ -0,0 +1,145 @@
+//! UI state restored when going back to a page, such as scroll positions and expanded sections.
+//!
+//! State of a `SessionRestoreHandle` is kept per route (path and query) in session storage. It's
+//! saved after every change, and replaced with the state saved for the new route when the user
+//! goes back or forward:
+//!
+//! ```ignore
+//! #[derive(Clone, Default, Serialize, Deserialize)]
+//! struct PageUi {
+//!     scroll: f64,
+//!     expanded: HashSet<String>,
+//! }
+//!
+//! type Handle = SessionRestoreHandle<PageUi>;
+//!
+//! self.handle.reduce(move |ui| ui.scroll = scroll_top);
+//! ```
+//!
+//! Routers changing the URL with `history.pushState` don't tell the page, so call `navigated`
+//! after they do, for the new route to start from its own state.
+use std::any::{type_name, TypeId};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast};
+use yew::{format::Json, services::storage::Area};
+
+use crate::config;
+use crate::dispatch::Dispatch;
+use crate::handle::SessionRestoreHandle;
+use crate::handler::{Handler, Reduction, ReductionOnce};
+use crate::storage::WebStorage;
+
+thread_local! {
+    /// Models whose state is replaced on navigation.
+    static LISTENING: RefCell<HashSet<TypeId>> = Default::default();
+}
+
+/// Handler for UI state kept per route in session storage. See the [`restore`](crate::restore)
+/// module.
+#[derive(Default, Clone)]
+pub struct SessionRestoreHandler<T> {
+    state: Rc<T>,
+}
+
+impl<T> SessionRestoreHandler<T>
+where
+    T: Serialize,
+{
+    fn save(&self) {
+        if let Some(mut storage) = storage() {
+            storage.store(&key::<T>(&route()), Json(&*self.state));
+        }
+    }
+}
+
+impl<T> Handler for SessionRestoreHandler<T>
+where
+    T: Default + Clone + Serialize + DeserializeOwned + 'static,
+{
+    type Model = T;
+
+    fn new() -> Self {
+        listen::<T>();
+        Self {
+            state: Rc::new(load(&route())),
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.save();
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.save();
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+/// Replace state of `T` with the state saved for the current route, or default state if none
+/// is. Call after changing the URL with `history.pushState`.
+pub fn navigated<T>()
+where
+    T: Default + Clone + Serialize + DeserializeOwned + 'static,
+{
+    let state: T = load(&route());
+    Dispatch::<SessionRestoreHandle<T>>::connect().reduce(move |current| *current = state);
+}
+
+fn storage() -> Option<WebStorage> {
+    WebStorage::new(Area::Session).ok()
+}
+
+/// Path and query of the current page.
+fn route() -> String {
+    web_sys::window()
+        .map(|window| window.location())
+        .and_then(|location| Some(location.pathname().ok()? + &location.search().ok()?))
+        .unwrap_or_default()
+}
+
+fn key<T>(route: &str) -> String {
+    format!(
+        "{}restore.{}:{}",
+        config::key_prefix(),
+        type_name::<T>(),
+        route
+    )
+}
+
+/// State saved for `route`, or default state.
+fn load<T: Default + DeserializeOwned>(route: &str) -> T {
+    storage()
+        .and_then(|storage| match storage.restore(&key::<T>(route)) {
+            Json(Ok(state)) => Some(state),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Replace state of `T` when the user goes back or forward. Listening starts when state is first
+/// created, and lasts as long as the page.
+fn listen<T>()
+where
+    T: Default + Clone + Serialize + DeserializeOwned + 'static,
+{
+    if !LISTENING.with(|listening| listening.borrow_mut().insert(TypeId::of::<T>())) {
+        return;
+    }
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return,
+    };
+    let onpopstate = Closure::wrap(Box::new(navigated::<T>) as Box<dyn FnMut()>);
+    let _ =
+        window.add_event_listener_with_callback("popstate", onpopstate.as_ref().unchecked_ref());
+    onpopstate.forget();
+}
@@ -0,0 +1,152 @@
+//! Changes applied after a delay, or every so often.
+//!
+//! `reduce_after` hands a change to the shared state agent, which applies it once the delay has
+//! passed. Unlike a `TimeoutService` task held by a component, it still applies after the
+//! component that scheduled it is gone, for as long as the scope's state is alive:
+//!
+//! ```ignore
+//! // Dismiss the toast after five seconds, unless it's hovered.
+//! self.dismiss = Some(self.handle.reduce_after(Duration::from_secs(5), |toasts| toasts.pop()));
+//!
+//! fn update(&mut self, msg: Self::Message) -> ShouldRender {
+//!     match msg {
+//!         Msg::Hovered => self.dismiss.as_ref().map(ScheduledHandle::cancel),
+//!         Msg::Left => self.dismiss.as_ref().map(|dismiss| dismiss.reschedule(Duration::from_secs(2))),
+//!     };
+//!     false
+//! }
+//! ```
+//!
+//! `every` has the agent apply a change every so often, for upkeep such as expiring cache entries
+//! or refreshing tokens:
+//!
+//! ```ignore
+//! let expiry = dispatch.every(Duration::from_secs(60), |cache| cache.expire(clock::now()));
+//! // No need while the tab is hidden.
+//! expiry.pause();
+//! expiry.resume();
+//! ```
+//!
+//! Timers only run in the browser. Elsewhere (as in tests), changes scheduled with no delay apply
+//! right away, others wait until rescheduled with none, and recurring changes apply with
+//! `run_now`.
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use yew::Callback;
+
+/// When a scheduled change applies.
+pub(crate) enum Timing {
+    /// After a delay from now, or every so often from now if recurring.
+    After(Duration),
+    /// Right away, and still every so often if recurring.
+    Now,
+    /// Not until resumed.
+    Pause,
+    /// Every so often again.
+    Resume,
+    /// Never.
+    Cancel,
+}
+
+/// Connects a `ScheduledHandle` or `RecurringHandle` to the agent holding its change.
+#[doc(hidden)]
+#[derive(Default)]
+pub struct Control {
+    agent: RefCell<Option<Callback<Timing>>>,
+    /// Sent before the agent got the change.
+    early: RefCell<Option<Timing>>,
+}
+
+impl Control {
+    fn send(&self, timing: Timing) {
+        match &*self.agent.borrow() {
+            Some(agent) => agent.emit(timing),
+            None => *self.early.borrow_mut() = Some(timing),
+        }
+    }
+}
+
+/// Handle to a change made with `reduce_after`. Dropping it leaves the change scheduled.
+#[derive(Clone, Default)]
+pub struct ScheduledHandle {
+    control: Rc<Control>,
+}
+
+impl ScheduledHandle {
+    /// Don't apply the change. Does nothing if it's applied already.
+    pub fn cancel(&self) {
+        self.control.send(Timing::Cancel)
+    }
+
+    /// Apply the change after `delay` from now, instead of when it was scheduled for. Does nothing
+    /// if it's applied already.
+    pub fn reschedule(&self, delay: Duration) {
+        self.control.send(Timing::After(delay))
+    }
+
+    pub(crate) fn control(&self) -> Rc<Control> {
+        self.control.clone()
+    }
+}
+
+impl fmt::Debug for ScheduledHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScheduledHandle")
+            .field("scheduled", &self.control.agent.borrow().is_some())
+            .finish()
+    }
+}
+
+/// Handle to a change made with `every`. Dropping it leaves the change recurring.
+#[derive(Clone, Default)]
+pub struct RecurringHandle {
+    control: Rc<Control>,
+}
+
+impl RecurringHandle {
+    /// Stop applying the change until resumed.
+    pub fn pause(&self) {
+        self.control.send(Timing::Pause)
+    }
+
+    /// Apply the change every so often again, starting one interval from now.
+    pub fn resume(&self) {
+        self.control.send(Timing::Resume)
+    }
+
+    /// Apply the change every `interval` from now on.
+    pub fn set_interval(&self, interval: Duration) {
+        self.control.send(Timing::After(interval))
+    }
+
+    /// Apply the change right away, even if paused, without waiting for the next interval.
+    pub fn run_now(&self) {
+        self.control.send(Timing::Now)
+    }
+
+    /// Stop applying the change for good.
+    pub fn cancel(&self) {
+        self.control.send(Timing::Cancel)
+    }
+
+    pub(crate) fn control(&self) -> Rc<Control> {
+        self.control.clone()
+    }
+}
+
+impl fmt::Debug for RecurringHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecurringHandle")
+            .field("scheduled", &self.control.agent.borrow().is_some())
+            .finish()
+    }
+}
+
+/// Give a handle's control the agent holding its change, returning any timing sent before.
+pub(crate) fn connect(control: &Control, agent: Callback<Timing>) -> Option<Timing> {
+    *control.agent.borrow_mut() = Some(agent);
+    control.early.borrow_mut().take()
+}
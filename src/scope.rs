@@ -0,0 +1,204 @@
+//! Types for scoping shared state.
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::handler::Handler;
+
+/// Scope identified by a number, so independent instances of state don't each need a unit struct.
+///
+/// ```ignore
+/// pub type LeftPanel = SharedStateComponent<PanelModel, Scope<0>>;
+/// pub type RightPanel = SharedStateComponent<PanelModel, Scope<1>>;
+/// ```
+pub struct Scope<const ID: usize>;
+
+/// Scope derived from another scope and a number. Useful for keeping numbered scopes of unrelated
+/// components apart.
+///
+/// ```ignore
+/// pub struct Panel;
+/// pub type LeftPanel = SharedStateComponent<PanelModel, Indexed<Panel, 0>>;
+/// pub type RightPanel = SharedStateComponent<PanelModel, Indexed<Panel, 1>>;
+/// ```
+pub struct Indexed<SCOPE, const ID: usize>(PhantomData<SCOPE>);
+
+/// State forced by a `TestProvider`.
+#[cfg(feature = "test-utils")]
+type Forced = Rc<dyn Any>;
+
+thread_local! {
+    /// Parent of every child scope.
+    static PARENTS: RefCell<HashMap<TypeId, TypeId>> = Default::default();
+    /// Current state of every running service, keyed by handler and scope.
+    static STATES: RefCell<HashMap<(TypeId, TypeId), Rc<dyn Any>>> = Default::default();
+    /// State forced by every mounted `TestProvider`, innermost last, keyed by handler and scope.
+    /// Global to the thread rather than to the provider's children, which yew has no way to tell
+    /// apart from anything else created meanwhile.
+    #[cfg(feature = "test-utils")]
+    static OVERRIDES: RefCell<HashMap<(TypeId, TypeId), Vec<Forced>>> = Default::default();
+}
+
+/// Make `CHILD` a child scope of `PARENT`.
+///
+/// When state is first created for a child scope it is seeded with the parent's state. If the
+/// parent scope doesn't have any state yet, its own parent is tried, and so on. Changes made in
+/// the child scope don't affect the parent.
+///
+/// ```ignore
+/// struct Defaults;
+/// struct Project;
+///
+/// scope::inherit::<Project, Defaults>();
+/// ```
+pub fn inherit<CHILD: 'static, PARENT: 'static>() {
+    PARENTS.with(|parents| {
+        parents
+            .borrow_mut()
+            .insert(TypeId::of::<CHILD>(), TypeId::of::<PARENT>())
+    });
+}
+
+/// Record current state of a scope, allowing child scopes to inherit it.
+pub(crate) fn publish<H, SCOPE>(state: Rc<H::Model>)
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    STATES.with(|states| states.borrow_mut().insert(key, state));
+}
+
+/// Forget state of a scope that is no longer running.
+pub(crate) fn unpublish<H, SCOPE>()
+where
+    H: Handler + 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    STATES.with(|states| states.borrow_mut().remove(&key));
+}
+
+/// Current state of a running scope.
+#[cfg(feature = "clipboard")]
+pub(crate) fn current<H, SCOPE>() -> Option<Rc<H::Model>>
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    state_of::<H>(TypeId::of::<SCOPE>())
+}
+
+/// State of the nearest ancestor scope that has any.
+pub(crate) fn inherited<H, SCOPE>() -> Option<Rc<H::Model>>
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let mut scope = TypeId::of::<SCOPE>();
+    // Guards against cycles.
+    let mut visited = HashSet::new();
+    while visited.insert(scope) {
+        scope = PARENTS.with(|parents| parents.borrow().get(&scope).cloned())?;
+        if let Some(state) = state_of::<H>(scope) {
+            return Some(state);
+        }
+    }
+
+    None
+}
+
+/// Force state of a scope for components connecting from now on, until it's restored.
+#[cfg(feature = "test-utils")]
+pub(crate) fn force<H, SCOPE>(state: Rc<H::Model>)
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    OVERRIDES.with(|overrides| overrides.borrow_mut().entry(key).or_default().push(state));
+}
+
+/// Stop forcing `state`. Other states forced since are kept, in case providers aren't destroyed
+/// in the order they were created.
+#[cfg(feature = "test-utils")]
+pub(crate) fn restore<H, SCOPE>(state: &Rc<H::Model>)
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    OVERRIDES.with(|overrides| {
+        let mut overrides = overrides.borrow_mut();
+        if let Some(forced) = overrides.get_mut(&key) {
+            let state: Forced = state.clone();
+            if let Some(index) = forced.iter().rposition(|other| Rc::ptr_eq(other, &state)) {
+                forced.remove(index);
+            }
+            if forced.is_empty() {
+                overrides.remove(&key);
+            }
+        }
+    });
+}
+
+/// Name of a scope for diagnostics: the model, and the scope type unless it's the default.
+#[cfg(any(feature = "metrics", feature = "profiler"))]
+pub(crate) fn name<H, SCOPE>() -> String
+where
+    H: Handler + 'static,
+    SCOPE: 'static,
+{
+    if TypeId::of::<H>() == TypeId::of::<SCOPE>() {
+        std::any::type_name::<H::Model>().to_string()
+    } else {
+        format!(
+            "{} in {}",
+            std::any::type_name::<H::Model>(),
+            std::any::type_name::<SCOPE>()
+        )
+    }
+}
+
+/// State forced for a scope, if any.
+#[cfg(feature = "test-utils")]
+pub(crate) fn forced<H, SCOPE>() -> Option<Rc<H::Model>>
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    let key = (TypeId::of::<H>(), TypeId::of::<SCOPE>());
+    let state = OVERRIDES.with(|overrides| overrides.borrow().get(&key)?.last().cloned())?;
+    state.downcast().ok()
+}
+
+/// State is never forced without `TestProvider`. Generic all the same, as the wrapper keeps it as
+/// a function pointer per scope.
+#[cfg(not(feature = "test-utils"))]
+#[allow(clippy::extra_unused_type_parameters)]
+pub(crate) fn forced<H, SCOPE>() -> Option<Rc<H::Model>>
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+    SCOPE: 'static,
+{
+    None
+}
+
+fn state_of<H>(scope: TypeId) -> Option<Rc<H::Model>>
+where
+    H: Handler + 'static,
+    H::Model: 'static,
+{
+    let key = (TypeId::of::<H>(), scope);
+    let state = STATES.with(|states| states.borrow().get(&key).cloned())?;
+    state.downcast().ok()
+}
@@ -0,0 +1,140 @@
+//! Values kept plain in memory, but never written out plainly.
+use std::cell::Cell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::ser::{self, SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config;
+
+/// Key of encrypted values.
+const ENCRYPTED: &str = "$encrypted";
+/// Written in place of values while no cipher is set.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+thread_local! {
+    /// How many writes out of the app are in progress, sealing sensitive values they serialize.
+    static SEALING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Run `write`, which writes state out of the app (to storage, an export, or the inspector),
+/// sealing sensitive values it serializes: encrypted, or redacted while no cipher is set.
+pub(crate) fn sealed<R>(write: impl FnOnce() -> R) -> R {
+    /// Unseals once the write is done, even if it panics.
+    struct Seal;
+
+    impl Drop for Seal {
+        fn drop(&mut self) {
+            SEALING.with(|sealing| sealing.set(sealing.get() - 1));
+        }
+    }
+
+    SEALING.with(|sealing| sealing.set(sealing.get() + 1));
+    let _seal = Seal;
+    write()
+}
+
+/// A value, such as an access token, that's plain in memory but never written out plainly.
+///
+/// Wherever the crate writes state out of the app (storage, session exports, files, the
+/// clipboard, links, snapshots and the inspector), it's encrypted with the cipher set by
+/// `config().cipher()`, or written as `"<redacted>"` while none is set. `Debug` redacts it too,
+/// and so do logs. Elsewhere it serializes as the value itself, so state synced between tabs and
+/// peers, or sent to workers, keeps it, in any format.
+///
+/// Redacted values deserialize as default, as do values the cipher can't decrypt. Reading sealed
+/// values needs a self-describing format, such as JSON.
+///
+/// ```ignore
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// struct Session {
+///     user: String,
+///     token: Sensitive<String>,
+/// }
+///
+/// let header = format!("Bearer {}", *session.token);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Sensitive<T>(T);
+
+impl<T> Sensitive<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Sensitive<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Deref for Sensitive<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Sensitive<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for Sensitive<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sensitive({})", REDACTED)
+    }
+}
+
+impl<T: Serialize> Serialize for Sensitive<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if SEALING.with(Cell::get) == 0 {
+            return self.0.serialize(serializer);
+        }
+        let cipher = match config::cipher() {
+            Some(cipher) => cipher,
+            None => return serializer.serialize_str(REDACTED),
+        };
+        let plain = serde_json::to_string(&self.0).map_err(ser::Error::custom)?;
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(ENCRYPTED, &cipher.encrypt(&plain))?;
+        map.end()
+    }
+}
+
+impl<'de, T: DeserializeOwned + Default> Deserialize<'de> for Sensitive<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Formats like bincode are never sealed.
+        if !deserializer.is_human_readable() {
+            return T::deserialize(deserializer).map(Self);
+        }
+        let value = Value::deserialize(deserializer)?;
+        let encrypted = match &value {
+            Value::String(text) if text == REDACTED => return Ok(Self::default()),
+            Value::Object(map) if map.len() == 1 => map.get(ENCRYPTED).and_then(Value::as_str),
+            _ => None,
+        };
+        let encrypted = match encrypted {
+            Some(encrypted) => encrypted,
+            // Written by hand, as in fixtures.
+            None => return T::deserialize(value).map(Self).map_err(de::Error::custom),
+        };
+        let plain = config::cipher().and_then(|cipher| cipher.decrypt(encrypted));
+        match plain {
+            Some(plain) => serde_json::from_str(&plain)
+                .map(Self)
+                .map_err(de::Error::custom),
+            None => Ok(Self::default()),
+        }
+    }
+}
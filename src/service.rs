@@ -0,0 +1,125 @@
+//! Service objects (API clients, socket wrappers) shared through state handles, instead of global
+//! statics.
+//!
+//! Services aren't state: they're never persisted or cloned, only shared behind an `Rc`. Provide
+//! one at startup, and keep the returned dispatch alive for as long as it should be available:
+//!
+//! ```ignore
+//! let _api = service::provide(ApiClient::new("https://api.example.com"));
+//!
+//! // In a component with a `ServiceHandle<ApiClient>`.
+//! if let Some(api) = self.handle.state().get() {
+//!     api.fetch_orders(self.link.callback(Msg::Orders));
+//! }
+//! ```
+use std::fmt;
+use std::rc::Rc;
+
+use crate::dispatch::Dispatch;
+use crate::handle::ServiceHandle;
+use crate::handler::{Handler, Reduction, ReductionOnce};
+
+/// Shared instance of a service, if one was provided.
+pub struct Service<T> {
+    instance: Option<Rc<T>>,
+}
+
+impl<T> Service<T> {
+    /// The service, unless none was provided yet.
+    pub fn get(&self) -> Option<&T> {
+        self.instance.as_deref()
+    }
+
+    /// Shared reference to the service, for keeping it past this borrow (say in a callback).
+    pub fn shared(&self) -> Option<Rc<T>> {
+        self.instance.clone()
+    }
+
+    /// Replace the service with `instance`.
+    pub fn provide(&mut self, instance: T) {
+        self.instance = Some(Rc::new(instance));
+    }
+
+    /// Stop providing the service. Only dropped once nothing else holds it.
+    pub fn remove(&mut self) {
+        self.instance = None;
+    }
+}
+
+// Services needn't implement these themselves, as only the `Rc` is cloned or compared.
+
+impl<T> Clone for Service<T> {
+    fn clone(&self) -> Self {
+        Self {
+            instance: self.instance.clone(),
+        }
+    }
+}
+
+impl<T> Default for Service<T> {
+    fn default() -> Self {
+        Self { instance: None }
+    }
+}
+
+impl<T> PartialEq for Service<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.instance, &other.instance) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Service<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Service")
+            .field("service", &std::any::type_name::<T>())
+            .field("provided", &self.instance.is_some())
+            .finish()
+    }
+}
+
+/// Handler sharing a service. Never persists, and never clones the service itself.
+pub struct ServiceHandler<T> {
+    state: Rc<Service<T>>,
+}
+
+impl<T> Clone for ServiceHandler<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> Handler for ServiceHandler<T> {
+    type Model = Service<T>;
+
+    fn new() -> Self {
+        Self {
+            state: Default::default(),
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+/// Provide `instance` to every `ServiceHandle<T>`, replacing any service provided before. It's
+/// available for as long as the returned dispatch (or anything else connected) is alive.
+pub fn provide<T: 'static>(instance: T) -> Dispatch<ServiceHandle<T>> {
+    let mut dispatch = Dispatch::<ServiceHandle<T>>::connect();
+    dispatch.reduce(move |service: &mut Service<T>| service.provide(instance));
+    dispatch
+}
@@ -0,0 +1,295 @@
+//! Sharing part of shared state with a service worker, so background logic (push notifications,
+//! background sync) can take part in it.
+//!
+//! The bridge posts the selected part of state to the controlling service worker whenever it
+//! changes, and applies updates the service worker posts back. Messages are plain objects,
+//! `{ channel, state }`, where `state` is the selected part as JSON:
+//!
+//! ```js
+//! self.addEventListener("message", (event) => {
+//!     if (event.data.channel === "inbox") {
+//!         unread = event.data.state.unread;
+//!     }
+//! });
+//!
+//! // Later, on a push:
+//! for (const client of await self.clients.matchAll()) {
+//!     client.postMessage({ channel: "inbox", state: { unread: unread + 1 } });
+//! }
+//! ```
+//!
+//! `on_push` turns push messages into actions on shared state. The service worker forwards each
+//! push to the page as `{ channel, action }`:
+//!
+//! ```js
+//! self.addEventListener("push", (event) => {
+//!     const action = event.data.json();
+//!     event.waitUntil(self.clients.matchAll().then((clients) => {
+//!         for (const client of clients) {
+//!             client.postMessage({ channel: "push", action });
+//!         }
+//!     }));
+//! });
+//! ```
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{MessageEvent, ServiceWorkerContainer};
+use yew::Callback;
+
+use crate::dispatch::Dispatch;
+use crate::handle::Handle;
+use crate::handler::Handler;
+use crate::worker::Action;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<C, S> {
+    channel: C,
+    state: S,
+}
+
+#[derive(Deserialize)]
+struct PushEnvelope<A> {
+    channel: String,
+    action: A,
+}
+
+fn container() -> Result<ServiceWorkerContainer, JsValue> {
+    Ok(web_sys::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .navigator()
+        .service_worker())
+}
+
+/// Decode a message from the service worker.
+fn decode<T: DeserializeOwned>(event: &MessageEvent) -> Option<T> {
+    let json = js_sys::JSON::stringify(&event.data()).ok()?.as_string()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Event listener on the service worker container, removed when dropped.
+struct Listener<F: ?Sized> {
+    container: ServiceWorkerContainer,
+    event: &'static str,
+    closure: Closure<F>,
+}
+
+impl<F: ?Sized> Listener<F> {
+    fn new(container: &ServiceWorkerContainer, event: &'static str, closure: Closure<F>) -> Self {
+        let _ = container.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+        Self {
+            container: container.clone(),
+            event,
+            closure,
+        }
+    }
+}
+
+impl<F: ?Sized> Drop for Listener<F> {
+    fn drop(&mut self) {
+        let _ = self
+            .container
+            .remove_event_listener_with_callback(self.event, self.closure.as_ref().unchecked_ref());
+    }
+}
+
+struct Shared<S> {
+    channel: String,
+    container: ServiceWorkerContainer,
+    /// Last part sent or received.
+    synced: Option<S>,
+}
+
+impl<S: Serialize> Shared<S> {
+    /// Post the last part to the controlling service worker. Without one (as on the first visit,
+    /// before the service worker claims the page) it's posted once one takes control.
+    fn post(&self) {
+        let (controller, state) = match (self.container.controller(), &self.synced) {
+            (Some(controller), Some(state)) => (controller, state),
+            _ => return,
+        };
+        let envelope = Envelope {
+            channel: self.channel.as_str(),
+            state,
+        };
+        let message = serde_json::to_string(&envelope)
+            .ok()
+            .and_then(|json| js_sys::JSON::parse(&json).ok());
+        if let Some(message) = message {
+            let _ = controller.post_message(&message);
+        }
+    }
+}
+
+/// Bridge made by `bridge`. Dropping it stops sharing.
+pub struct ServiceWorkerBridge {
+    _dispatch: Rc<dyn std::any::Any>,
+    _onmessage: Listener<dyn FnMut(MessageEvent)>,
+    _oncontrollerchange: Listener<dyn FnMut()>,
+}
+
+/// Share the part of state of handle type `H` chosen by `select` with the service worker, on
+/// `channel`. Updates from the service worker are applied with `apply`.
+///
+/// ```ignore
+/// self.bridge = service_worker::bridge::<SharedHandle<App>, _>(
+///     "inbox",
+///     |app| app.inbox.clone(),
+///     |app, inbox| app.inbox = inbox,
+/// )
+/// .ok();
+/// ```
+pub fn bridge<H, S>(
+    channel: &str,
+    select: impl Fn(&Model<H>) -> S + 'static,
+    apply: impl Fn(&mut Model<H>, S) + 'static,
+) -> Result<ServiceWorkerBridge, JsValue>
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    S: Clone + PartialEq + Serialize + DeserializeOwned + 'static,
+{
+    bridge_in::<H, StateHandler<H>, S>(channel, select, apply)
+}
+
+/// Like `bridge`, for shared state in `SCOPE`.
+pub fn bridge_in<H, SCOPE, S>(
+    channel: &str,
+    select: impl Fn(&Model<H>) -> S + 'static,
+    apply: impl Fn(&mut Model<H>, S) + 'static,
+) -> Result<ServiceWorkerBridge, JsValue>
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    S: Clone + PartialEq + Serialize + DeserializeOwned + 'static,
+    SCOPE: 'static,
+{
+    let container = container()?;
+    let shared = Rc::new(RefCell::new(Shared {
+        channel: channel.to_string(),
+        container: container.clone(),
+        synced: None,
+    }));
+
+    // Post local changes to the selected part.
+    let changed = {
+        let shared = shared.clone();
+        Callback::from(move |state: Rc<Model<H>>| {
+            let part = select(&state);
+            let mut shared = shared.borrow_mut();
+            if shared.synced.as_ref() != Some(&part) {
+                shared.synced = Some(part);
+                shared.post();
+            }
+        })
+    };
+    let dispatch = Rc::new(RefCell::new(Dispatch::<H, SCOPE>::new(changed)));
+
+    // Apply updates from the service worker.
+    let apply = Rc::new(apply);
+    let receiver: Weak<RefCell<Shared<S>>> = Rc::downgrade(&shared);
+    let reducer: Weak<RefCell<Dispatch<H, SCOPE>>> = Rc::downgrade(&dispatch);
+    let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let envelope: Option<Envelope<String, S>> = decode(&event);
+        let (shared, dispatch) = match (receiver.upgrade(), reducer.upgrade()) {
+            (Some(shared), Some(dispatch)) => (shared, dispatch),
+            _ => return,
+        };
+        let part = match envelope {
+            Some(envelope) if envelope.channel == shared.borrow().channel => envelope.state,
+            _ => return,
+        };
+        // Recorded first, so the change isn't posted back.
+        shared.borrow_mut().synced = Some(part.clone());
+        let apply = apply.clone();
+        dispatch
+            .borrow_mut()
+            .reduce(move |state| apply(state, part));
+    }) as Box<dyn FnMut(_)>);
+    let onmessage = Listener::new(&container, "message", onmessage);
+
+    // Catch up a service worker that took control since.
+    let controlled: Weak<RefCell<Shared<S>>> = Rc::downgrade(&shared);
+    let oncontrollerchange = Closure::wrap(Box::new(move || {
+        if let Some(shared) = controlled.upgrade() {
+            shared.borrow().post();
+        }
+    }) as Box<dyn FnMut()>);
+    let oncontrollerchange = Listener::new(&container, "controllerchange", oncontrollerchange);
+
+    Ok(ServiceWorkerBridge {
+        _dispatch: dispatch,
+        _onmessage: onmessage,
+        _oncontrollerchange: oncontrollerchange,
+    })
+}
+
+/// Listener made by `on_push`. Dropping it stops applying actions.
+pub struct PushListener {
+    _dispatch: Rc<dyn std::any::Any>,
+    _onmessage: Listener<dyn FnMut(MessageEvent)>,
+}
+
+/// Apply actions the service worker forwards from push messages on `channel` to shared state of
+/// handle type `H`.
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// enum InboxAction {
+///     Received(Message),
+/// }
+///
+/// impl Action<App> for InboxAction {
+///     fn apply(self, app: &mut App) {
+///         match self {
+///             InboxAction::Received(message) => app.inbox.push(message),
+///         }
+///     }
+/// }
+///
+/// self.push = service_worker::on_push::<SharedHandle<App>, InboxAction>("push").ok();
+/// ```
+pub fn on_push<H, A>(channel: &str) -> Result<PushListener, JsValue>
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    A: Action<Model<H>> + 'static,
+{
+    on_push_in::<H, StateHandler<H>, A>(channel)
+}
+
+/// Like `on_push`, for shared state in `SCOPE`.
+pub fn on_push_in<H, SCOPE, A>(channel: &str) -> Result<PushListener, JsValue>
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    A: Action<Model<H>> + 'static,
+    SCOPE: 'static,
+{
+    let container = container()?;
+    let dispatch = Rc::new(RefCell::new(Dispatch::<H, SCOPE>::connect()));
+
+    let channel = channel.to_string();
+    let reducer: Weak<RefCell<Dispatch<H, SCOPE>>> = Rc::downgrade(&dispatch);
+    let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let action = match decode::<PushEnvelope<A>>(&event) {
+            Some(envelope) if envelope.channel == channel => envelope.action,
+            _ => return,
+        };
+        if let Some(dispatch) = reducer.upgrade() {
+            dispatch
+                .borrow_mut()
+                .reduce(move |state| action.apply(state));
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    Ok(PushListener {
+        _dispatch: dispatch,
+        _onmessage: Listener::new(&container, "message", onmessage),
+    })
+}
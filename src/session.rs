@@ -0,0 +1,268 @@
+//! Recording user sessions for bug reports.
+//!
+//! A `SessionRecorder` keeps the last actions dispatched to shared state, along with a snapshot of
+//! state from before the oldest of them. Exported sessions can be replayed later to reproduce the
+//! state a user ended up in, or played back against a live app at the pace they were recorded.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use yew::services::{timeout::TimeoutTask, TimeoutService};
+
+use crate::clock;
+use crate::dispatch::Dispatch;
+use crate::error::Error;
+use crate::handle::{Handle, StateHandle};
+use crate::handler::Handler;
+use crate::sensitive::sealed;
+use crate::worker::Action;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+/// A recorded session.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Session<T, A> {
+    /// State before the first recorded action.
+    pub initial: T,
+    /// Recorded actions, oldest first.
+    pub actions: Vec<A>,
+    /// When each action was recorded, in milliseconds since the Unix epoch. Empty for sessions
+    /// exported before timestamps were recorded.
+    #[serde(default)]
+    pub times: Vec<f64>,
+    /// Errors reported during the session, oldest first.
+    pub errors: Vec<String>,
+}
+
+impl<T, A> Session<T, A>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+    A: Action<T>,
+{
+    /// Load a session exported by `SessionRecorder::export`.
+    pub fn import(session: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(session)
+    }
+
+    /// Apply every recorded action, returning the state the session ended in.
+    pub fn replay(self) -> T {
+        let mut state = self.initial;
+        for action in self.actions {
+            action.apply(&mut state);
+        }
+        state
+    }
+
+    /// Reset shared state of `H` to the initial state, then dispatch every recorded action at
+    /// the pace it was recorded, `speed` times faster. An infinite speed dispatches them all right
+    /// away. Playback stops when the returned `Replay` is dropped.
+    ///
+    /// ```ignore
+    /// let replay = session.play::<SharedHandle<Count>>(4.0);
+    /// ```
+    pub fn play<H>(self, speed: f64) -> Replay
+    where
+        H: Handle + 'static,
+        StateHandler<H>: Handler<Model = T> + Clone + 'static,
+        T: Clone + 'static,
+        A: 'static,
+    {
+        self.play_in::<H, StateHandler<H>>(speed)
+    }
+
+    /// Like `play`, for `SCOPE`.
+    pub fn play_in<H, SCOPE>(self, speed: f64) -> Replay
+    where
+        H: Handle + 'static,
+        StateHandler<H>: Handler<Model = T> + Clone + 'static,
+        T: Clone + 'static,
+        A: 'static,
+        SCOPE: 'static,
+    {
+        let mut dispatch = Dispatch::<H, SCOPE>::connect();
+        let initial = self.initial;
+        dispatch.reduce(move |state| *state = initial);
+
+        // Sessions without timestamps play back with no delay between actions.
+        let mut times = self.times;
+        times.resize(
+            self.actions.len(),
+            times.last().copied().unwrap_or_default(),
+        );
+        let player = Rc::new(RefCell::new(Player {
+            dispatch,
+            actions: times.into_iter().zip(self.actions).collect(),
+            previous: None,
+            speed,
+            next: None,
+        }));
+        Player::advance(&player);
+        Replay { player }
+    }
+}
+
+/// Playback started by `Session::play`. Dropping it stops playback.
+pub struct Replay {
+    player: Rc<RefCell<dyn Playing>>,
+}
+
+impl Replay {
+    /// Whether every action has been dispatched.
+    pub fn is_finished(&self) -> bool {
+        self.player.borrow().is_finished()
+    }
+}
+
+trait Playing {
+    fn is_finished(&self) -> bool;
+}
+
+struct Player<H, SCOPE, A>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    dispatch: Dispatch<H, SCOPE>,
+    /// Actions left, with when they were recorded.
+    actions: VecDeque<(f64, A)>,
+    /// When the last dispatched action was recorded.
+    previous: Option<f64>,
+    speed: f64,
+    next: Option<TimeoutTask>,
+}
+
+impl<H, SCOPE, A> Playing for Player<H, SCOPE, A>
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+{
+    fn is_finished(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+impl<H, SCOPE, A> Player<H, SCOPE, A>
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    SCOPE: 'static,
+    A: Action<Model<H>> + 'static,
+{
+    /// Dispatch actions that are due, then wait for the next one.
+    fn advance(player: &Rc<RefCell<Self>>) {
+        let mut this = player.borrow_mut();
+        while let Some(&(time, _)) = this.actions.front() {
+            let wait = this
+                .previous
+                .map_or(0.0, |previous| (time - previous) / this.speed);
+            if wait >= 1.0 {
+                let weak: Weak<RefCell<Self>> = Rc::downgrade(player);
+                this.next = Some(TimeoutService::spawn(
+                    Duration::from_millis(wait as u64),
+                    (move |_| {
+                        if let Some(player) = weak.upgrade() {
+                            player.borrow_mut().previous = Some(time);
+                            Self::advance(&player);
+                        }
+                    })
+                    .into(),
+                ));
+                return;
+            }
+            if let Some((time, action)) = this.actions.pop_front() {
+                this.previous = Some(time);
+                this.dispatch.reduce(move |state| action.apply(state));
+            }
+        }
+        this.next = None;
+    }
+}
+
+/// Records the last actions dispatched to shared state.
+///
+/// Recorders are cheap to clone, and clones record to the same session.
+#[derive(Clone)]
+pub struct SessionRecorder<T, A> {
+    session: Rc<RefCell<Recording<T, A>>>,
+}
+
+struct Recording<T, A> {
+    initial: T,
+    actions: VecDeque<(f64, A)>,
+    errors: VecDeque<String>,
+    capacity: usize,
+}
+
+impl<T, A> SessionRecorder<T, A>
+where
+    T: Clone + Serialize + for<'a> Deserialize<'a> + 'static,
+    A: Action<T> + Clone + 'static,
+{
+    /// Start recording from `initial` state, keeping at most `capacity` actions and errors.
+    pub fn new(initial: T, capacity: usize) -> Self {
+        Self {
+            session: Rc::new(RefCell::new(Recording {
+                initial,
+                actions: Default::default(),
+                errors: Default::default(),
+                capacity,
+            })),
+        }
+    }
+
+    /// Record an action without applying it.
+    pub fn record(&self, action: &A) {
+        let mut session = self.session.borrow_mut();
+        session.actions.push_back((clock::now(), action.clone()));
+        // Roll the oldest action into the snapshot, so replaying still ends in the same state.
+        if session.actions.len() > session.capacity {
+            if let Some((_, oldest)) = session.actions.pop_front() {
+                oldest.apply(&mut session.initial);
+            }
+        }
+    }
+
+    /// Record an error reported by shared state.
+    pub fn record_error(&self, error: &Error) {
+        let mut session = self.session.borrow_mut();
+        session.errors.push_back(error.to_string());
+        if session.errors.len() > session.capacity {
+            session.errors.pop_front();
+        }
+    }
+
+    /// Record an action, then apply it to shared state.
+    pub fn dispatch<H>(&self, handle: &StateHandle<T, H>, action: A)
+    where
+        T: Default,
+        H: Handler<Model = T>,
+    {
+        self.record(&action);
+        handle.reduce(move |state| action.apply(state));
+    }
+
+    /// Current recording.
+    pub fn session(&self) -> Session<T, A> {
+        let session = self.session.borrow();
+        Session {
+            initial: session.initial.clone(),
+            actions: session
+                .actions
+                .iter()
+                .map(|(_, action)| action.clone())
+                .collect(),
+            times: session.actions.iter().map(|(time, _)| *time).collect(),
+            errors: session.errors.iter().cloned().collect(),
+        }
+    }
+
+    /// Serialize current recording, for attaching to bug reports.
+    pub fn export(&self) -> String {
+        sealed(|| serde_json::to_string(&self.session())).expect("can't serialize session")
+    }
+}
@@ -0,0 +1,256 @@
+//! Keyboard shortcuts as shared state.
+//!
+//! Components bind key chords to named actions, and a single document-level `keydown` listener
+//! (attached while shortcut state is alive) triggers them. Components then react to the action
+//! they care about, instead of each attaching its own listener:
+//!
+//! ```ignore
+//! // In a component with `handle: ShortcutHandle`.
+//! fn rendered(&mut self, first_render: bool) {
+//!     if first_render {
+//!         self.handle.reduce(|shortcuts| shortcuts.bind("ctrl+s", "save"));
+//!     }
+//! }
+//!
+//! fn change(&mut self, handle: Self::Properties) -> ShouldRender {
+//!     if handle.state().take_new(&mut self.seen) == Some("save") {
+//!         self.save();
+//!     }
+//!     ...
+//! }
+//!
+//! fn destroy(&mut self) {
+//!     self.handle.reduce(|shortcuts| shortcuts.unbind("ctrl+s", "save"));
+//! }
+//! ```
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast};
+
+#[cfg(target_arch = "wasm32")]
+use crate::dispatch::Dispatch;
+#[cfg(target_arch = "wasm32")]
+use crate::handle::ShortcutHandle;
+use crate::handler::{Handler, Reduction, ReductionOnce};
+
+/// Modifiers in the order they're written in chords.
+const MODIFIERS: [&str; 4] = ["ctrl", "alt", "shift", "meta"];
+
+/// Normalize a chord like `"Shift+Ctrl+S"` to `"ctrl+shift+s"`. `control`, `option`, `cmd` and
+/// `super` are accepted for `ctrl`, `alt` and `meta`.
+pub fn chord(keys: &str) -> String {
+    let mut modifiers = [false; 4];
+    let mut key = String::new();
+    for part in keys.split('+').map(|part| part.trim().to_lowercase()) {
+        let modifier = match part.as_str() {
+            "ctrl" | "control" => 0,
+            "alt" | "option" => 1,
+            "shift" => 2,
+            "meta" | "cmd" | "super" => 3,
+            _ => {
+                key = part;
+                continue;
+            }
+        };
+        modifiers[modifier] = true;
+    }
+    let mut chord: Vec<&str> = MODIFIERS
+        .iter()
+        .zip(modifiers.iter())
+        .filter(|(_, held)| **held)
+        .map(|(name, _)| *name)
+        .collect();
+    chord.push(&key);
+    chord.join("+")
+}
+
+/// An action triggered by a shortcut.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triggered {
+    pub action: String,
+    /// Shortcuts triggered so far, so the same action triggered twice can be told apart.
+    pub count: u64,
+}
+
+/// Bound shortcuts, and the action triggered last.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Shortcuts {
+    /// Actions bound to each chord, with the one bound last winning.
+    bindings: BTreeMap<String, Vec<String>>,
+    triggered: Option<Triggered>,
+}
+
+impl Shortcuts {
+    /// Bind `keys` (see `chord`) to `action`. Binding a chord that's bound already overrides it
+    /// until this binding is removed.
+    pub fn bind(&mut self, keys: &str, action: &str) {
+        self.bindings
+            .entry(chord(keys))
+            .or_default()
+            .push(action.to_string());
+    }
+
+    /// Remove the binding of `keys` to `action`.
+    pub fn unbind(&mut self, keys: &str, action: &str) {
+        let chord = chord(keys);
+        if let Some(actions) = self.bindings.get_mut(&chord) {
+            if let Some(index) = actions.iter().rposition(|bound| bound == action) {
+                actions.remove(index);
+            }
+            if actions.is_empty() {
+                self.bindings.remove(&chord);
+            }
+        }
+    }
+
+    /// Action `keys` triggers, if any.
+    pub fn action(&self, keys: &str) -> Option<&str> {
+        self.bindings.get(&chord(keys))?.last().map(String::as_str)
+    }
+
+    /// Bound chords, with the action each triggers.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.bindings
+            .iter()
+            .filter_map(|(chord, actions)| Some((chord.as_str(), actions.last()?.as_str())))
+    }
+
+    /// Trigger the action bound to `keys`, as if they were pressed. Returns whether one is bound.
+    pub fn trigger(&mut self, keys: &str) -> bool {
+        let action = match self.action(keys) {
+            Some(action) => action.to_string(),
+            None => return false,
+        };
+        let count = self.triggered.as_ref().map_or(0, |last| last.count) + 1;
+        self.triggered = Some(Triggered { action, count });
+        true
+    }
+
+    /// The action triggered last, if any.
+    pub fn triggered(&self) -> Option<&Triggered> {
+        self.triggered.as_ref()
+    }
+
+    /// The action triggered last, if it was triggered after `seen`, which is then updated. Keep
+    /// `seen` (starting from 0) in each component to handle every action once.
+    pub fn take_new(&self, seen: &mut u64) -> Option<&str> {
+        let triggered = self.triggered.as_ref().filter(|last| last.count > *seen)?;
+        *seen = triggered.count;
+        Some(&triggered.action)
+    }
+}
+
+/// Document-level `keydown` listener, removed when dropped.
+#[cfg(target_arch = "wasm32")]
+struct Listener(Closure<dyn Fn(web_sys::KeyboardEvent)>);
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            let _ = document
+                .remove_event_listener_with_callback("keydown", self.0.as_ref().unchecked_ref());
+        }
+    }
+}
+
+/// Chord of a key press, like `"ctrl+s"`. `None` for modifiers pressed on their own.
+#[cfg(target_arch = "wasm32")]
+fn pressed(event: &web_sys::KeyboardEvent) -> Option<String> {
+    let key = event.key().to_lowercase();
+    if matches!(key.as_str(), "control" | "alt" | "shift" | "meta") {
+        return None;
+    }
+    let held = [
+        event.ctrl_key(),
+        event.alt_key(),
+        event.shift_key(),
+        event.meta_key(),
+    ];
+    let mut chord: Vec<&str> = MODIFIERS
+        .iter()
+        .zip(held.iter())
+        .filter(|(_, held)| **held)
+        .map(|(name, _)| *name)
+        .collect();
+    chord.push(&key);
+    Some(chord.join("+"))
+}
+
+/// Handler for keyboard shortcuts. Listens for key presses while it's alive, and prevents the
+/// browser's default for bound chords only.
+pub struct ShortcutHandler {
+    state: Rc<Shortcuts>,
+    /// Bound chords, read by the listener to decide whether to prevent the default.
+    bound: Rc<RefCell<HashSet<String>>>,
+    #[cfg(target_arch = "wasm32")]
+    _listener: Option<Listener>,
+}
+
+impl ShortcutHandler {
+    fn update_bound(&self) {
+        *self.bound.borrow_mut() = self.state.bindings.keys().cloned().collect();
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn listen(bound: Rc<RefCell<HashSet<String>>>) -> Option<Listener> {
+        let document = web_sys::window()?.document()?;
+        let listener = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            let chord = match pressed(&event) {
+                Some(chord) if bound.borrow().contains(&chord) => chord,
+                _ => return,
+            };
+            event.prevent_default();
+            Dispatch::<ShortcutHandle>::connect().reduce(move |shortcuts| {
+                shortcuts.trigger(&chord);
+            });
+        }) as Box<dyn Fn(web_sys::KeyboardEvent)>);
+        document
+            .add_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref())
+            .ok()?;
+        Some(Listener(listener))
+    }
+}
+
+impl Handler for ShortcutHandler {
+    type Model = Shortcuts;
+
+    fn new() -> Self {
+        let bound = Rc::new(RefCell::new(HashSet::new()));
+        Self {
+            state: Default::default(),
+            #[cfg(target_arch = "wasm32")]
+            _listener: Self::listen(bound.clone()),
+            bound,
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.update_bound();
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+        self.update_bound();
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+impl Clone for ShortcutHandler {
+    /// Clones share shortcuts, but don't listen for key presses.
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            bound: Default::default(),
+            #[cfg(target_arch = "wasm32")]
+            _listener: None,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Golden-state snapshot assertions, for regression tests of reducer sequences.
+//!
+//! Snapshots are JSON files under `tests/snapshots` in the crate being tested. Run tests with
+//! `UPDATE_SNAPSHOTS=1` to write new snapshots, or replace ones that no longer match:
+//!
+//! ```ignore
+//! #[test]
+//! fn checkout_flow() {
+//!     let mut dispatch = Dispatch::<SharedHandle<Cart>>::connect();
+//!     dispatch.reduce(|cart| cart.add(Item::new("tea", 2)));
+//!     dispatch.reduce(|cart| cart.apply_coupon("HALF"));
+//!     assert_state_snapshot!(dispatch, "cart_with_coupon");
+//! }
+//! ```
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::dispatch::Dispatch;
+use crate::handle::Handle;
+use crate::handler::Handler;
+use crate::sensitive::sealed;
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+/// Environment variable that turns on update mode.
+const UPDATE: &str = "UPDATE_SNAPSHOTS";
+
+/// Assert current state of `dispatch` matches snapshot `name` in `dir`, or write it in update
+/// mode. Use `assert_state_snapshot!` rather than calling this directly.
+///
+/// Only works natively, where changes are applied before `reduce` returns.
+pub fn assert_snapshot<H, SCOPE>(dispatch: &mut Dispatch<H, SCOPE>, dir: &Path, name: &str)
+where
+    H: Handle,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: Serialize,
+    SCOPE: 'static,
+{
+    let state = dispatch
+        .await_change(|_| true)
+        .ready()
+        .expect("state isn't available right away; snapshots only work natively");
+    let actual = sealed(|| serde_json::to_value(&*state)).expect("can't serialize state");
+    let path = dir.join(format!("{}.json", name));
+
+    if env::var_os(UPDATE).is_some_and(|update| update != "0") {
+        let text = serde_json::to_string_pretty(&actual).expect("can't serialize state");
+        fs::create_dir_all(dir).expect("can't create snapshot directory");
+        fs::write(&path, text + "\n").expect("can't write snapshot");
+        return;
+    }
+
+    let text = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "no snapshot at {}; run with {}=1 to write it",
+            path.display(),
+            UPDATE
+        )
+    });
+    let expected: serde_json::Value = serde_json::from_str(&text)
+        .unwrap_or_else(|err| panic!("snapshot at {} isn't JSON: {}", path.display(), err));
+    assert!(
+        actual == expected,
+        "state doesn't match snapshot {} (run with {}=1 to update it)\nexpected: {}\n  actual: {}",
+        path.display(),
+        UPDATE,
+        expected,
+        actual
+    );
+}
+
+/// Assert current state of a dispatch matches a stored JSON snapshot. See the
+/// [`snapshot`](crate::snapshot) module.
+///
+/// ```ignore
+/// assert_state_snapshot!(dispatch, "empty_cart");
+/// ```
+#[macro_export]
+macro_rules! assert_state_snapshot {
+    ($dispatch:expr, $name:expr $(,)?) => {
+        $crate::snapshot::assert_snapshot(
+            &mut $dispatch,
+            &::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots"),
+            $name,
+        )
+    };
+}
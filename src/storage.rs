@@ -0,0 +1,729 @@
+//! Persistent storage of shared state.
+//!
+//! Along with storage itself, other ways of keeping state are gathered here: cookies, files, and
+//! state restored per route.
+use std::cell::RefCell;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(not(target_arch = "wasm32"))]
+use yew::format::Nothing;
+use yew::format::{Json, Text};
+#[cfg(target_arch = "wasm32")]
+use yew::services::StorageService;
+
+use crate::config;
+use crate::error::Error;
+use crate::lz;
+use crate::sensitive::sealed;
+use crate::telemetry;
+
+pub use yew::services::storage::Area;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod memory;
+
+#[cfg(feature = "cookies")]
+pub use crate::cookie;
+#[cfg(feature = "files")]
+pub use crate::file;
+pub use crate::handle::StorageHandle;
+pub use crate::handler::{SavePolicy, Storable};
+#[cfg(feature = "session-restore")]
+pub use crate::restore;
+
+/// Encrypts state fields marked `#[storable(encrypt)]`, set with `config().cipher()`.
+///
+/// Fields marked for encryption aren't stored at all until a cipher is set.
+pub trait Cipher {
+    fn encrypt(&self, data: &str) -> String;
+    /// Returns `None` if `data` can't be decrypted.
+    fn decrypt(&self, data: &str) -> Option<String>;
+}
+
+/// Persisted state, along with a checksum for detecting corruption.
+#[derive(Serialize, Deserialize)]
+struct Persisted {
+    checksum: u64,
+    state: String,
+}
+
+/// Result of restoring state from storage.
+pub(crate) enum Restored<T> {
+    /// Nothing is stored.
+    Missing,
+    Found(T),
+    /// Stored data is corrupt.
+    Corrupt(String),
+}
+
+/// Where part of state is stored.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub enum Part {
+    /// In `Storable::area()`, at the state's key.
+    Main,
+    /// In session storage, at `<key>.session`.
+    Session,
+    /// Encrypted in `Storable::area()`, at `<key>.encrypted`.
+    Encrypted,
+}
+
+/// State split into the parts that are stored separately. Unless it's derived, `Storable` stores
+/// state whole as the main part.
+#[doc(hidden)]
+#[derive(Debug, Default, PartialEq)]
+pub struct Parts {
+    main: Value,
+    session: Option<Value>,
+    encrypted: Option<Value>,
+    /// Why part of state couldn't be serialized, if it couldn't.
+    unserializable: Option<String>,
+}
+
+impl Parts {
+    /// Parts with all of `state` in the main part.
+    pub(crate) fn whole<T: Serialize>(state: &T) -> Self {
+        match sealed(|| serde_json::to_value(state)) {
+            Ok(main) => Self {
+                main,
+                ..Default::default()
+            },
+            Err(error) => Self {
+                unserializable: Some(error.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// State stored whole, leniently if `T::lenient()`.
+    pub(crate) fn to_whole<T: Storable + Default>(&self) -> Option<T> {
+        serde_json::from_value(self.main.clone()).ok().or_else(|| {
+            if !T::lenient() {
+                return None;
+            }
+            let default = serde_json::to_value(T::default()).ok()?;
+            if kind(&self.main) != kind(&default) {
+                return None;
+            }
+            // Unknown fields are kept at first, in case they were skipped when serializing the
+            // default.
+            let state = serde_json::from_value(merge(default.clone(), self.main.clone(), true))
+                .or_else(|_| {
+                    serde_json::from_value(merge(default.clone(), self.main.clone(), false))
+                })
+                .ok()?;
+            #[cfg(debug_assertions)]
+            warn_drift::<T>(&default, &self.main);
+            Some(state)
+        })
+    }
+
+    fn part_mut(&mut self, part: Part) -> &mut Value {
+        match part {
+            Part::Main => &mut self.main,
+            Part::Session => self.session.get_or_insert(Value::Null),
+            Part::Encrypted => self.encrypted.get_or_insert(Value::Null),
+        }
+    }
+
+    /// Store field `name` in `part`.
+    pub fn insert<V: Serialize>(&mut self, part: Part, name: &str, value: &V) {
+        let value = match sealed(|| serde_json::to_value(value)) {
+            Ok(value) => value,
+            Err(error) => {
+                let reason = format!("{}: {}", name, error);
+                self.unserializable.get_or_insert(reason);
+                return;
+            }
+        };
+        let part = self.part_mut(part);
+        if !part.is_object() {
+            *part = Value::Object(Default::default());
+        }
+        if let Value::Object(fields) = part {
+            fields.insert(name.to_string(), value);
+        }
+    }
+
+    /// Field `name` of `part`, if it's stored and still deserializes.
+    pub fn get<V: DeserializeOwned>(&self, part: Part, name: &str) -> Option<V> {
+        let part = match part {
+            Part::Main => Some(&self.main),
+            Part::Session => self.session.as_ref(),
+            Part::Encrypted => self.encrypted.as_ref(),
+        };
+        let value = part?.get(name)?.clone();
+        serde_json::from_value(value).ok()
+    }
+}
+
+/// Kind of JSON value, ignoring its contents.
+fn kind(value: &Value) -> std::mem::Discriminant<Value> {
+    std::mem::discriminant(value)
+}
+
+/// Merge stored state into default state. Where they differ in kind, the default is kept.
+fn merge(default: Value, stored: Value, keep_unknown: bool) -> Value {
+    match (default, stored) {
+        (Value::Object(mut default), Value::Object(mut stored)) => {
+            for (key, value) in default.iter_mut() {
+                if let Some(stored) = stored.remove(key) {
+                    *value = merge(value.take(), stored, keep_unknown);
+                }
+            }
+            if keep_unknown {
+                default.extend(stored);
+            }
+            Value::Object(default)
+        }
+        // Missing values, such as `None`, accept anything.
+        (Value::Null, stored) => stored,
+        (default, stored) if kind(&default) == kind(&stored) => stored,
+        (default, _) => default,
+    }
+}
+
+/// Fields of stored state that don't match the model.
+#[cfg(debug_assertions)]
+#[derive(Debug, Default, Serialize)]
+struct Drift {
+    /// Fields of the model missing from stored state.
+    missing: Vec<String>,
+    /// Fields of stored state the model doesn't have.
+    unknown: Vec<String>,
+    /// Fields whose stored value has the wrong type.
+    mistyped: Vec<String>,
+}
+
+#[cfg(debug_assertions)]
+impl Drift {
+    fn between(default: &Value, stored: &Value) -> Self {
+        let mut drift = Self::default();
+        drift.compare(default, stored, "");
+        drift
+    }
+
+    fn compare(&mut self, default: &Value, stored: &Value, path: &str) {
+        let (default, stored) = match (default, stored) {
+            (Value::Object(default), Value::Object(stored)) => (default, stored),
+            (Value::Null, _) => return,
+            (default, stored) => {
+                if kind(default) != kind(stored) {
+                    self.mistyped.push(path.to_string());
+                }
+                return;
+            }
+        };
+        let field = |key: &str| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", path, key)
+            }
+        };
+        for (key, value) in default {
+            match stored.get(key) {
+                Some(stored) => self.compare(value, stored, &field(key)),
+                None => self.missing.push(field(key)),
+            }
+        }
+        for key in stored.keys().filter(|key| !default.contains_key(*key)) {
+            self.unknown.push(field(key));
+        }
+    }
+}
+
+/// Warn that stored state of `T` was only restored leniently, so developers notice the model
+/// drifted before shipping without a migration.
+#[cfg(debug_assertions)]
+fn warn_drift<T>(default: &Value, stored: &Value) {
+    let drift = Drift::between(default, stored);
+    let warning = format!(
+        "yew-state: stored `{}` doesn't match the model, and was restored leniently: {}",
+        std::any::type_name::<T>(),
+        serde_json::to_string(&drift).unwrap_or_default(),
+    );
+    crate::telemetry::warn(&warning);
+}
+
+/// FNV-1a hash of `data`.
+pub(crate) fn checksum(data: &str) -> u64 {
+    data.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Decode persisted state. Returns `None` if it's corrupt.
+fn decode(data: &str) -> Option<Value> {
+    match serde_json::from_str::<Persisted>(data) {
+        Ok(persisted) if persisted.checksum == checksum(&persisted.state) => {
+            serde_json::from_str(&persisted.state).ok()
+        }
+        Ok(_) => None,
+        // State saved without a checksum.
+        Err(_) => serde_json::from_str(data).ok(),
+    }
+}
+
+/// Encode state for storage, along with its checksum.
+fn encode(state: &Value, compressed: bool) -> Result<String, serde_json::Error> {
+    let state = serde_json::to_string(state)?;
+    let persisted = Persisted {
+        checksum: checksum(&state),
+        state,
+    };
+    let persisted = serde_json::to_string(&persisted)?;
+    if compressed {
+        Ok(lz::compress_to_utf16(&persisted))
+    } else {
+        Ok(persisted)
+    }
+}
+
+/// Decode stored state, compressed or not, regardless of the current setting.
+fn decode_stored(data: &str) -> Option<Value> {
+    decode(data).or_else(|| decode(&lz::decompress_from_utf16(data)?))
+}
+
+fn session_key(key: &str) -> String {
+    format!("{}.session", key)
+}
+
+fn encrypted_key(key: &str) -> String {
+    format!("{}.encrypted", key)
+}
+
+/// Store `state` at `key`, along with its checksum.
+pub(crate) fn store<T: Storable>(
+    storage: &mut WebStorage,
+    key: &str,
+    state: &T,
+) -> Result<(), Error> {
+    store_parts::<T>(storage, key, &state.to_parts())
+}
+
+/// Store `parts` of state at `key`, along with their checksums. Nothing is stored if any part
+/// can't be serialized.
+pub(crate) fn store_parts<T: Storable>(
+    storage: &mut WebStorage,
+    key: &str,
+    parts: &Parts,
+) -> Result<(), Error> {
+    let unstorable = |reason: String| Error::Unstorable {
+        key: key.to_string(),
+        reason,
+    };
+    if let Some(reason) = &parts.unserializable {
+        return Err(unstorable(reason.clone()));
+    }
+    let encoded = |part: &Value, compressed| {
+        encode(part, compressed).map_err(|error| unstorable(error.to_string()))
+    };
+    let main = encoded(&parts.main, T::compressed())?;
+    let session = parts
+        .session
+        .as_ref()
+        .map(|session| encoded(session, false))
+        .transpose()?;
+    let encrypted = parts
+        .encrypted
+        .as_ref()
+        .map(|encrypted| encoded(encrypted, false))
+        .transpose()?;
+
+    #[cfg(feature = "metrics")]
+    crate::metrics::stored::<T>();
+    storage.store(key, Text::Ok(main));
+    if let Some(session) = session {
+        if let Ok(mut session_storage) = WebStorage::new(Area::Session) {
+            session_storage.store(&session_key(key), Text::Ok(session));
+        }
+    }
+    if let Some(encrypted) = encrypted {
+        if let Some(cipher) = config::cipher() {
+            storage.store(&encrypted_key(key), Text::Ok(cipher.encrypt(&encrypted)));
+        }
+    }
+    Ok(())
+}
+
+/// Restore state stored at `key`. Corrupt session or encrypted parts are left out, rather than
+/// making all of it corrupt.
+pub(crate) fn restore<T>(storage: &WebStorage, key: &str) -> Restored<T>
+where
+    T: Storable + Default,
+{
+    let data = match storage.restore::<Text>(key) {
+        Ok(data) => data,
+        Err(_) => return Restored::Missing,
+    };
+    let main = match decode_stored(&data) {
+        Some(main) => main,
+        None => return Restored::Corrupt(data),
+    };
+    let session = WebStorage::new(Area::Session)
+        .ok()
+        .and_then(|storage| storage.restore::<Text>(&session_key(key)).ok())
+        .and_then(|data| decode(&data));
+    let encrypted = config::cipher()
+        .zip(storage.restore::<Text>(&encrypted_key(key)).ok())
+        .and_then(|(cipher, data)| decode(&cipher.decrypt(&data)?));
+
+    let parts = Parts {
+        main,
+        session,
+        encrypted,
+        unserializable: None,
+    };
+    match T::from_parts(&parts) {
+        Some(state) => Restored::Found(state),
+        None => Restored::Corrupt(data),
+    }
+}
+
+/// Assert `state` is restored unchanged however it's stored: with or without compression, and as
+/// saved by earlier versions (without a checksum). Also asserts that storing restored state again
+/// gives the same data, so stored state doesn't drift each time it's saved.
+///
+/// State that isn't stored whole (derived with `#[storable(skip)]` fields and such) only needs
+/// to be stored the same way again.
+///
+/// Used by `assert_storable_roundtrip!`.
+#[cfg(feature = "test-utils")]
+#[doc(hidden)]
+pub fn assert_roundtrip<T>(state: &T)
+where
+    T: Storable + Default + PartialEq + std::fmt::Debug,
+{
+    let parts = state.to_parts();
+    if let Some(reason) = &parts.unserializable {
+        panic!("state can't be serialized: {}", reason);
+    }
+    let whole = parts == Parts::whole(state);
+    let reencode = |part: &Value, compressed| {
+        decode_stored(&encode(part, compressed).expect("can't serialize state"))
+    };
+
+    for &compressed in &[false, true] {
+        let stored = Parts {
+            main: reencode(&parts.main, compressed).expect("stored state is corrupt"),
+            session: parts
+                .session
+                .as_ref()
+                .and_then(|part| reencode(part, false)),
+            encrypted: parts
+                .encrypted
+                .as_ref()
+                .and_then(|part| reencode(part, false)),
+            unserializable: None,
+        };
+        let restored = T::from_parts(&stored);
+        assert!(
+            restored.is_some(),
+            "state can't be restored (compressed: {})",
+            compressed
+        );
+        let restored = restored.unwrap();
+        if whole {
+            assert_eq!(
+                &restored, state,
+                "state changed when restored (compressed: {})",
+                compressed
+            );
+        }
+        assert_eq!(
+            restored.to_parts(),
+            parts,
+            "stored data changed when stored again (compressed: {})",
+            compressed
+        );
+    }
+
+    if whole {
+        let legacy = sealed(|| serde_json::to_string(state)).expect("can't serialize state");
+        let restored = decode_stored(&legacy).and_then(|main| {
+            T::from_parts(&Parts {
+                main,
+                ..Default::default()
+            })
+        });
+        assert_eq!(
+            restored.as_ref(),
+            Some(state),
+            "state changed when restored from data without a checksum"
+        );
+    }
+}
+
+/// Assert that state survives being stored and restored, for the default state or each of the
+/// given states. Use it in tests of your own models.
+///
+/// ```ignore
+/// #[test]
+/// fn todos_roundtrip() {
+///     assert_storable_roundtrip!(Todos);
+///     assert_storable_roundtrip!(Todos, Todos::sample(), Todos::with_unicode());
+/// }
+/// ```
+#[cfg(feature = "test-utils")]
+#[macro_export]
+macro_rules! assert_storable_roundtrip {
+    ($model:ty) => {
+        $crate::assert_storable_roundtrip!($model, <$model as ::std::default::Default>::default())
+    };
+    ($model:ty, $($state:expr),+ $(,)?) => {
+        $( $crate::storage::assert_roundtrip::<$model>(&$state); )+
+    };
+}
+
+/// Key state is stored at, including the configured prefix.
+pub(crate) fn key<T: Storable>() -> String {
+    format!("{}{}", config::key_prefix(), T::key())
+}
+
+/// Key of a named snapshot slot.
+fn slot_key<T: Storable>(name: &str) -> String {
+    format!("{}.slot.{}", key::<T>(), name)
+}
+
+/// Key of the list of snapshot slot names.
+fn slots_key<T: Storable>() -> String {
+    format!("{}.slots", key::<T>())
+}
+
+impl<T> StorageHandle<T>
+where
+    T: Default + Clone + Storable + 'static,
+{
+    fn storage() -> Option<WebStorage> {
+        WebStorage::new(T::area()).ok()
+    }
+
+    fn set_slots(storage: &mut WebStorage, slots: &[String]) {
+        storage.store(&slots_key::<T>(), Json(&slots));
+    }
+
+    /// Save current state as a named snapshot, replacing any snapshot with the same name.
+    pub fn save_slot(&self, name: &str) {
+        if let Some(mut storage) = Self::storage() {
+            if let Err(error) = store(&mut storage, &slot_key::<T>(name), self.state()) {
+                return self.report(error);
+            }
+
+            let mut slots = self.list_slots();
+            if !slots.iter().any(|slot| slot == name) {
+                slots.push(name.to_string());
+                Self::set_slots(&mut storage, &slots);
+            }
+        }
+    }
+
+    /// Replace shared state with a named snapshot. Returns `false` if the snapshot doesn't exist or
+    /// is corrupt.
+    pub fn load_slot(&self, name: &str) -> bool {
+        let storage = match Self::storage() {
+            Some(storage) => storage,
+            None => return false,
+        };
+
+        match restore::<T>(&storage, &slot_key::<T>(name)) {
+            Restored::Found(snapshot) => {
+                self.reduce(move |state| *state = snapshot);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Delete a named snapshot.
+    pub fn delete_slot(&self, name: &str) {
+        if let Some(mut storage) = Self::storage() {
+            storage.remove(&slot_key::<T>(name));
+
+            let mut slots = self.list_slots();
+            slots.retain(|slot| slot != name);
+            Self::set_slots(&mut storage, &slots);
+        }
+    }
+
+    /// Names of saved snapshots, in the order they were first saved.
+    pub fn list_slots(&self) -> Vec<String> {
+        let slots = Self::storage().map(|storage| storage.restore(&slots_key::<T>()));
+        match slots {
+            Some(Json(Ok(slots))) => slots,
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Keys the crate keeps things other than state at in local storage, such as the client id.
+const RESERVED: &[&str] = &["client-id"];
+/// Endings of keys the crate keeps things other than state at, such as leader leases of sync.
+const RESERVED_SUFFIXES: &[&str] = &[".leader"];
+
+thread_local! {
+    /// Keys reserved with `reserve`.
+    static RESERVED_KEYS: RefCell<Vec<String>> = Default::default();
+}
+
+/// Keep entries at `key` (before the prefix is prepended) from being collected by `gc`, for
+/// entries the crate keeps in local storage besides state, such as cached flags.
+#[cfg(any(feature = "flags", feature = "experiments"))]
+pub(crate) fn reserve(key: &str) {
+    RESERVED_KEYS.with(|reserved| {
+        let mut reserved = reserved.borrow_mut();
+        if !reserved.iter().any(|other| other == key) {
+            reserved.push(key.to_string());
+        }
+    });
+}
+
+/// Whether `key` (without prefix) is, or is derived from, `known`: a backup or snapshot slot of it.
+fn derived(key: &str, known: &str) -> bool {
+    match key.strip_prefix(known) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+/// Keys in local storage under the configured prefix that don't belong to any of `known_keys`.
+///
+/// `known_keys` are `Storable::key()` values of every persistent type still in use. Entries
+/// derived from a known key (backups and snapshot slots) aren't reported, and neither are entries
+/// the crate keeps besides state: the client id, leader leases, and cached flags and experiment
+/// assignments. Without a configured prefix nothing is reported, since keys of other scripts on
+/// the origin can't be told apart.
+pub fn orphans(known_keys: &[&str]) -> Vec<String> {
+    let prefix = config::key_prefix();
+    if prefix.is_empty() {
+        telemetry::warn("storage keys have no prefix, so orphaned entries can't be told apart");
+        return Vec::new();
+    }
+    let storage = match WebStorage::new(Area::Local) {
+        Ok(storage) => storage,
+        Err(_) => return Vec::new(),
+    };
+    let reserved = RESERVED_KEYS.with(|reserved| reserved.borrow().clone());
+    let is_known = |key: &str| {
+        known_keys
+            .iter()
+            .copied()
+            .chain(RESERVED.iter().copied())
+            .chain(reserved.iter().map(String::as_str))
+            .any(|known| derived(key, known))
+            || RESERVED_SUFFIXES.iter().any(|suffix| key.ends_with(suffix))
+    };
+
+    storage
+        .keys()
+        .into_iter()
+        .filter(|key| match key.strip_prefix(prefix.as_str()) {
+            Some(key) => !is_known(key),
+            None => false,
+        })
+        .collect()
+}
+
+/// Remove keys found by `orphans` from local storage, returning the keys removed. Does nothing
+/// without a configured prefix.
+///
+/// ```ignore
+/// yew_state::config().key_prefix("myapp:");
+/// storage::gc(&[Settings::key(), Document::key()]);
+/// ```
+pub fn gc(known_keys: &[&str]) -> Vec<String> {
+    let orphans = orphans(known_keys);
+    if let Ok(mut storage) = WebStorage::new(Area::Local) {
+        for key in &orphans {
+            storage.remove(key);
+        }
+    }
+    orphans
+}
+
+/// Local or session storage of the browser, or `memory` outside of browsers.
+pub(crate) struct WebStorage {
+    #[cfg(target_arch = "wasm32")]
+    service: StorageService,
+    area: Area,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebStorage {
+    pub(crate) fn new(area: Area) -> Result<Self, &'static str> {
+        Ok(Self {
+            service: StorageService::new(copy(&area))?,
+            area,
+        })
+    }
+
+    pub(crate) fn store<T: Into<Text>>(&mut self, key: &str, value: T) {
+        sealed(|| self.service.store(key, value));
+    }
+
+    pub(crate) fn restore<T: From<Text>>(&self, key: &str) -> T {
+        self.service.restore(key)
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        self.service.remove(key);
+    }
+
+    /// Every key with data stored.
+    pub(crate) fn keys(&self) -> Vec<String> {
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => return Vec::new(),
+        };
+        let storage = match self.area {
+            Area::Local => window.local_storage(),
+            Area::Session => window.session_storage(),
+        };
+        let storage = match storage.ok().flatten() {
+            Some(storage) => storage,
+            None => return Vec::new(),
+        };
+        let len = storage.length().unwrap_or(0);
+        (0..len)
+            .filter_map(|index| storage.key(index).ok().flatten())
+            .collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WebStorage {
+    pub(crate) fn new(area: Area) -> Result<Self, &'static str> {
+        Ok(Self { area })
+    }
+
+    pub(crate) fn store<T: Into<Text>>(&mut self, key: &str, value: T) {
+        if let Ok(data) = sealed(|| value.into()) {
+            memory::set(copy(&self.area), key, &data);
+        }
+    }
+
+    pub(crate) fn restore<T: From<Text>>(&self, key: &str) -> T {
+        match memory::get(copy(&self.area), key) {
+            Some(data) => T::from(Ok(data)),
+            None => T::from(Nothing.into()),
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        memory::with(&self.area, |entries| entries.remove(key));
+    }
+
+    /// Every key with data stored.
+    pub(crate) fn keys(&self) -> Vec<String> {
+        memory::keys(copy(&self.area))
+    }
+}
+
+/// `Area` isn't `Copy`.
+fn copy(area: &Area) -> Area {
+    match area {
+        Area::Local => Area::Local,
+        Area::Session => Area::Session,
+    }
+}
@@ -0,0 +1,51 @@
+//! Local and session storage kept in memory, standing in for web storage outside of browsers so
+//! persistence can be tested natively. Each thread has storage of its own, which lasts as long as
+//! the thread does:
+//!
+//! ```ignore
+//! storage::memory::set(Area::Local, "settings", "not json");
+//! let _dispatch = Dispatch::<StorageHandle<Settings>>::new(callback);
+//! assert!(storage::memory::get(Area::Local, "settings.backup").is_some());
+//! ```
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use super::Area;
+
+type Entries = BTreeMap<String, String>;
+
+thread_local! {
+    static LOCAL: RefCell<Entries> = Default::default();
+    static SESSION: RefCell<Entries> = Default::default();
+}
+
+pub(crate) fn with<R>(area: &Area, f: impl FnOnce(&mut Entries) -> R) -> R {
+    let entries = match area {
+        Area::Local => &LOCAL,
+        Area::Session => &SESSION,
+    };
+    entries.with(|entries| f(&mut entries.borrow_mut()))
+}
+
+/// Data stored at `key`, if any.
+pub fn get(area: Area, key: &str) -> Option<String> {
+    with(&area, |entries| entries.get(key).cloned())
+}
+
+/// Store `data` at `key`, as anything else stored there from outside of the crate would be.
+pub fn set(area: Area, key: &str, data: &str) {
+    with(&area, |entries| {
+        entries.insert(key.to_string(), data.to_string())
+    });
+}
+
+/// Every key with data stored, in order.
+pub fn keys(area: Area) -> Vec<String> {
+    with(&area, |entries| entries.keys().cloned().collect())
+}
+
+/// Remove everything stored, in both areas.
+pub fn clear() {
+    with(&Area::Local, Entries::clear);
+    with(&Area::Session, Entries::clear);
+}
@@ -0,0 +1,41 @@
+//! A single place to declare every shared model of an app.
+//!
+//! `#[derive(Store)]` (with the `derive` feature) on a struct whose fields are models gives each
+//! field its own scope, so components still subscribe to just the slice they use:
+//!
+//! ```ignore
+//! #[derive(Clone, Default, Store)]
+//! struct AppStore {
+//!     cart: Cart,
+//!     #[store(storage)]
+//!     settings: Settings,
+//! }
+//!
+//! // One scope per field, in a module named after the store.
+//! type CartBadge = SharedStateComponent<Badge, app_store::Cart>;
+//!
+//! // A dispatch per field, connected in one go.
+//! let mut store = AppStore::dispatch();
+//! store.cart.reduce(|cart| cart.items.clear());
+//!
+//! // Or the whole store, after a change to any field.
+//! let _all = AppStore::subscribe(Callback::from(|store: Rc<AppStore>| log_store(&store)));
+//! ```
+//!
+//! Fields are shared with `SharedHandle`, or `StorageHandle` when marked `#[store(storage)]`.
+use std::rc::Rc;
+
+use yew::Callback;
+
+/// Models declared together, each shared in a scope of its own. Derive it with `#[derive(Store)]`.
+pub trait Store: Clone + Default + 'static {
+    /// A `Dispatch` per field, as public fields of the same names.
+    type Dispatch;
+
+    /// Connect to every field, without receiving changes.
+    fn dispatch() -> Self::Dispatch;
+
+    /// Connect to every field. `callback` receives the whole store on connect, and after a change
+    /// to any field.
+    fn subscribe(callback: Callback<Rc<Self>>) -> Self::Dispatch;
+}
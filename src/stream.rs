@@ -0,0 +1,65 @@
+//! Streams of changes to shared state.
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use yew::Callback;
+
+struct Queue<T> {
+    states: VecDeque<Rc<T>>,
+    waker: Option<Waker>,
+}
+
+/// Stream of shared state: current state, then state after every change. See `Dispatch::stream`.
+///
+/// States are queued until they're read, so a slow reader sees every one of them.
+pub struct StateStream<T> {
+    queue: Rc<RefCell<Queue<T>>>,
+    _dispatch: Box<dyn Any>,
+}
+
+impl<T> StateStream<T> {
+    /// Stream of states passed to the callback, which is given to `connect`.
+    pub(crate) fn new<D: 'static>(connect: impl FnOnce(Callback<Rc<T>>) -> D) -> Self
+    where
+        T: 'static,
+    {
+        let queue = Rc::new(RefCell::new(Queue {
+            states: VecDeque::new(),
+            waker: None,
+        }));
+        let sender = Rc::downgrade(&queue);
+        let dispatch = connect(Callback::from(move |state| {
+            if let Some(queue) = sender.upgrade() {
+                let mut queue = queue.borrow_mut();
+                queue.states.push_back(state);
+                if let Some(waker) = queue.waker.take() {
+                    waker.wake();
+                }
+            }
+        }));
+
+        Self {
+            queue,
+            _dispatch: Box::new(dispatch),
+        }
+    }
+}
+
+impl<T> futures_core::Stream for StateStream<T> {
+    type Item = Rc<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Rc<T>>> {
+        let mut queue = self.queue.borrow_mut();
+        match queue.states.pop_front() {
+            Some(state) => Poll::Ready(Some(state)),
+            None => {
+                queue.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,649 @@
+//! Syncing shared state with peers, such as other browsers.
+//!
+//! Every change is sent to peers over a `SyncTransport` as entire state. Conflicting changes are
+//! settled by last writer wins: each change is versioned, and the highest version (ties broken by
+//! peer id) ends up everywhere.
+//!
+//! Transports for a server (`websocket`), other tabs (`broadcast`), other browsers (`webrtc`), and
+//! tests (`memory`) are included, each behind the feature of the same name (`memory` is always
+//! available).
+//!
+//! Changes held while the transport is closed can be listed, retried, or discarded with
+//! `SyncConnection`, and are shared state of an `OutboxHandle` in the scope of the synced state,
+//! for showing "3 changes pending sync".
+//!
+//! `presence` tracks which peers are connected, as shared state of its own. `leader` elects one tab
+//! to hold the connection to a server, with other tabs relaying through it.
+//!
+//! ```ignore
+//! let transport = websocket::WebSocketTransport::new("wss://example.com/board")?;
+//! self.sync = Some(sync::connect::<SharedHandle<Board>>(transport));
+//! ```
+#[cfg(feature = "broadcast")]
+pub mod broadcast;
+pub mod leader;
+pub mod memory;
+pub mod presence;
+#[cfg(feature = "webrtc")]
+pub mod webrtc;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use yew::Callback;
+
+use crate::dispatch::Dispatch;
+use crate::error::Error;
+use crate::handle::{Handle, SharedHandle};
+use crate::handler::{Handler, Request};
+
+type StateHandler<H> = <H as Handle>::Handler;
+type Model<H> = <StateHandler<H> as Handler>::Model;
+
+/// State of a transport's connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    Connecting,
+    Open,
+    Closed,
+}
+
+/// Carries messages between peers.
+pub trait SyncTransport: 'static {
+    /// Send a message to peers. Messages sent before the connection opens should be held until it
+    /// does.
+    fn send(&self, message: Vec<u8>);
+    /// Receive messages from peers with `callback`.
+    fn on_message(&self, callback: Callback<Vec<u8>>);
+    /// Current state of the connection.
+    fn state(&self) -> ConnectionState;
+    /// Be notified with `callback` whenever the connection state changes.
+    fn on_state_change(&self, callback: Callback<ConnectionState>);
+    /// Bytes sent but not yet transmitted, for transports that buffer. Messages are held back while
+    /// this is over `SyncOptions::max_buffered`.
+    fn buffered(&self) -> usize {
+        0
+    }
+}
+
+/// How a connection holds outgoing messages while its transport is closed or can't keep up.
+///
+/// Held messages are sent once the transport opens again, or with the next change after it catches
+/// up. When more than `queue_limit` messages pile up, the connection reports
+/// `Error::SyncBackpressure` to subscribers of the shared state, once until the queue drains.
+///
+/// ```ignore
+/// let options = SyncOptions::new().queue_limit(4).max_buffered(16 * 1024);
+/// self.sync = Some(sync::connect_with::<SharedHandle<Board>>(transport, options));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    queue_limit: usize,
+    coalesce: bool,
+    max_buffered: usize,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            queue_limit: 16,
+            coalesce: true,
+            max_buffered: 64 * 1024,
+        }
+    }
+}
+
+impl SyncOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages that may pile up before backpressure is reported. Without coalescing, this is also
+    /// the most messages held at once. 16 by default.
+    pub fn queue_limit(mut self, limit: usize) -> Self {
+        self.queue_limit = limit.max(1);
+        self
+    }
+
+    /// Hold only the newest message, instead of keeping up to `queue_limit` and dropping the oldest.
+    /// Every message carries entire state, so nothing is lost by coalescing. On by default.
+    pub fn coalesce(mut self, coalesce: bool) -> Self {
+        self.coalesce = coalesce;
+        self
+    }
+
+    /// Hold messages back while the transport has more than `bytes` buffered. 64 KiB by default.
+    pub fn max_buffered(mut self, bytes: usize) -> Self {
+        self.max_buffered = bytes;
+        self
+    }
+}
+
+/// A change held in the outbox, waiting to be sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pending {
+    pub id: u64,
+    /// Version of state it carries. Newer changes have higher versions.
+    pub version: u64,
+    message: Rc<[u8]>,
+}
+
+impl Pending {
+    /// State it carries, as `T`.
+    pub fn state<T: DeserializeOwned>(&self) -> Option<T> {
+        let message: Message<T> = serde_json::from_slice(&self.message).ok()?;
+        Some(message.state)
+    }
+
+    /// Size of the message in bytes.
+    pub fn size(&self) -> usize {
+        self.message.len()
+    }
+}
+
+/// Changes held by a connection while its transport can't take them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutboxState {
+    pending: Vec<Pending>,
+    dropped: usize,
+}
+
+impl OutboxState {
+    /// Held changes, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Pending> {
+        self.pending.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Changes dropped since the outbox last drained, either coalesced into newer ones or over
+    /// `SyncOptions::queue_limit`.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+/// Handle for the outbox of a sync connection. Shared in the scope of the synced state, so for
+/// `SharedHandle<Board>` in the default scope, connect with
+/// `SharedStateComponent<Indicator, SharedHandler<Board>>`.
+pub type OutboxHandle = SharedHandle<OutboxState>;
+
+/// Version in a message, read without its state.
+#[derive(Deserialize)]
+struct Header {
+    version: u64,
+}
+
+/// Outgoing messages of a connection.
+struct Outbox {
+    transport: Weak<dyn SyncTransport>,
+    options: SyncOptions,
+    held: VecDeque<Pending>,
+    next_id: u64,
+    /// Messages dropped since the queue last drained.
+    dropped: usize,
+    /// Whether backpressure was reported since the queue last drained.
+    reported: bool,
+    /// Shares the outbox as state.
+    publish: Rc<dyn Fn(OutboxState)>,
+}
+
+impl Outbox {
+    /// Share current outbox, once it's no longer borrowed.
+    fn publish(outbox: &RefCell<Self>) {
+        let (state, publish) = {
+            let outbox = outbox.borrow();
+            let state = OutboxState {
+                pending: outbox.held.iter().cloned().collect(),
+                dropped: outbox.dropped,
+            };
+            (state, outbox.publish.clone())
+        };
+        publish(state);
+    }
+
+    /// The transport, if it can take messages now.
+    fn ready(&self) -> Option<Rc<dyn SyncTransport>> {
+        let transport = self.transport.upgrade()?;
+        let ready = transport.state() == ConnectionState::Open
+            && transport.buffered() <= self.options.max_buffered;
+        ready.then_some(transport)
+    }
+
+    /// Send `message`, or hold it if the transport can't take it. Returns an error when the queue
+    /// first overflows.
+    fn send(outbox: &RefCell<Self>, message: Vec<u8>) -> Option<Error> {
+        let (transport, held) = {
+            let mut this = outbox.borrow_mut();
+            match this.ready() {
+                Some(transport) => {
+                    this.dropped = 0;
+                    this.reported = false;
+                    let held: Vec<Pending> = this.held.drain(..).collect();
+                    (transport, held)
+                }
+                None => {
+                    this.transport.upgrade()?;
+                    let error = this.hold(message);
+                    drop(this);
+                    Outbox::publish(outbox);
+                    return error;
+                }
+            }
+        };
+        if !held.is_empty() {
+            Outbox::publish(outbox);
+        }
+        // Transports may deliver right away, and answers come back here.
+        for pending in held {
+            transport.send(pending.message.to_vec());
+        }
+        transport.send(message);
+        None
+    }
+
+    /// Send held messages, if there are any.
+    fn flush(outbox: &RefCell<Self>) -> bool {
+        let last = outbox.borrow_mut().held.pop_back();
+        match last {
+            Some(last) => {
+                Outbox::send(outbox, last.message.to_vec());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Send the held message `id` now, if the transport can take it.
+    fn retry(outbox: &RefCell<Self>, id: u64) -> bool {
+        let (transport, pending) = {
+            let mut this = outbox.borrow_mut();
+            let transport = match this.ready() {
+                Some(transport) => transport,
+                None => return false,
+            };
+            match this.take(id) {
+                Some(pending) => (transport, pending),
+                None => return false,
+            }
+        };
+        Outbox::publish(outbox);
+        transport.send(pending.message.to_vec());
+        true
+    }
+
+    /// Drop the held message `id` without sending it.
+    fn discard(outbox: &RefCell<Self>, id: u64) -> bool {
+        let discarded = outbox.borrow_mut().take(id).is_some();
+        if discarded {
+            Outbox::publish(outbox);
+        }
+        discarded
+    }
+
+    fn take(&mut self, id: u64) -> Option<Pending> {
+        let index = self.held.iter().position(|pending| pending.id == id)?;
+        self.held.remove(index)
+    }
+
+    fn hold(&mut self, message: Vec<u8>) -> Option<Error> {
+        if self.options.coalesce {
+            self.dropped += self.held.len();
+            self.held.clear();
+        } else if self.held.len() == self.options.queue_limit {
+            self.held.pop_front();
+            self.dropped += 1;
+        }
+        self.next_id += 1;
+        let version = serde_json::from_slice::<Header>(&message).map_or(0, |header| header.version);
+        self.held.push_back(Pending {
+            id: self.next_id,
+            version,
+            message: message.into(),
+        });
+
+        let backed_up = self.held.len() + self.dropped > self.options.queue_limit;
+        if backed_up && !self.reported {
+            self.reported = true;
+            Some(Error::SyncBackpressure {
+                held: self.held.len(),
+                dropped: self.dropped,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Bookkeeping shared by the included transports.
+#[derive(Default)]
+pub(crate) struct Endpoint {
+    receiver: RefCell<Option<Callback<Vec<u8>>>>,
+    watcher: RefCell<Option<Callback<ConnectionState>>>,
+    state: Cell<ConnectionState>,
+    /// Messages sent before the connection opened.
+    pending: RefCell<Vec<Vec<u8>>>,
+}
+
+impl Endpoint {
+    pub(crate) fn deliver(&self, message: Vec<u8>) {
+        let receiver = self.receiver.borrow().clone();
+        if let Some(receiver) = receiver {
+            receiver.emit(message);
+        }
+    }
+
+    pub(crate) fn state(&self) -> ConnectionState {
+        self.state.get()
+    }
+
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        if self.state.replace(state) != state {
+            let watcher = self.watcher.borrow().clone();
+            if let Some(watcher) = watcher {
+                watcher.emit(state);
+            }
+        }
+    }
+
+    /// Hold `message` until the connection opens.
+    pub(crate) fn hold(&self, message: Vec<u8>) {
+        self.pending.borrow_mut().push(message);
+    }
+
+    /// Messages held until the connection opened.
+    pub(crate) fn take_pending(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.pending.borrow_mut())
+    }
+
+    pub(crate) fn on_message(&self, callback: Callback<Vec<u8>>) {
+        *self.receiver.borrow_mut() = Some(callback);
+    }
+
+    pub(crate) fn on_state_change(&self, callback: Callback<ConnectionState>) {
+        *self.watcher.borrow_mut() = Some(callback);
+    }
+}
+
+/// A version of state sent between peers.
+#[derive(Serialize, Deserialize)]
+struct Message<S> {
+    version: u64,
+    /// Peer that made this version.
+    origin: u64,
+    state: S,
+}
+
+/// What a peer knows of synced state.
+struct Peer<T> {
+    id: u64,
+    version: u64,
+    origin: u64,
+    /// Last state sent or received.
+    synced: Option<Rc<T>>,
+}
+
+impl<T: Serialize> Peer<T> {
+    /// Message with the last synced state.
+    fn message(&self) -> Option<Result<Vec<u8>, Error>> {
+        let message = Message {
+            version: self.version,
+            origin: self.origin,
+            state: self.synced.as_deref()?,
+        };
+        Some(serde_json::to_vec(&message).map_err(|error| Error::Encode(error.to_string())))
+    }
+}
+
+/// Random id for this peer.
+fn peer_id() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Math::random() * u64::MAX as f64) as u64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        thread_local! {
+            static NEXT: std::cell::Cell<u64> = const { std::cell::Cell::new(1) };
+        }
+        NEXT.with(|next| next.replace(next.get() + 1))
+    }
+}
+
+/// Connection made by `connect`. Dropping it stops syncing.
+pub struct SyncConnection {
+    _transport: Rc<dyn SyncTransport>,
+    _dispatch: Rc<dyn std::any::Any>,
+    _reporter: Rc<dyn std::any::Any>,
+    _status: Rc<dyn std::any::Any>,
+    #[cfg(feature = "wake")]
+    _woke: crate::wake::WakeGuard,
+    outbox: Rc<RefCell<Outbox>>,
+}
+
+impl SyncConnection {
+    /// Changes held while the transport can't take them, oldest first.
+    pub fn pending(&self) -> Vec<Pending> {
+        self.outbox.borrow().held.iter().cloned().collect()
+    }
+
+    /// Send the held change `id` now. Returns whether it was sent, which it isn't while the
+    /// transport still can't take it.
+    pub fn retry(&self, id: u64) -> bool {
+        Outbox::retry(&self.outbox, id)
+    }
+
+    /// Drop the held change `id` without sending it. Peers still get newer changes, and catch up
+    /// with current state when the connection opens again.
+    pub fn discard(&self, id: u64) -> bool {
+        Outbox::discard(&self.outbox, id)
+    }
+}
+
+/// Sync shared state of handle type `H` with peers reachable through `transport`.
+pub fn connect<H>(transport: impl SyncTransport) -> SyncConnection
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: PartialEq + Serialize + DeserializeOwned,
+{
+    connect_in_with::<H, StateHandler<H>>(transport, SyncOptions::default())
+}
+
+/// Like `connect`, holding outgoing messages as set by `options`.
+pub fn connect_with<H>(transport: impl SyncTransport, options: SyncOptions) -> SyncConnection
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: PartialEq + Serialize + DeserializeOwned,
+{
+    connect_in_with::<H, StateHandler<H>>(transport, options)
+}
+
+/// Like `connect`, for shared state in `SCOPE`.
+pub fn connect_in<H, SCOPE>(transport: impl SyncTransport) -> SyncConnection
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: PartialEq + Serialize + DeserializeOwned,
+    SCOPE: 'static,
+{
+    connect_in_with::<H, SCOPE>(transport, SyncOptions::default())
+}
+
+/// Like `connect_with`, for shared state in `SCOPE`.
+pub fn connect_in_with<H, SCOPE>(
+    transport: impl SyncTransport,
+    options: SyncOptions,
+) -> SyncConnection
+where
+    H: Handle + 'static,
+    StateHandler<H>: Clone + 'static,
+    Model<H>: PartialEq + Serialize + DeserializeOwned,
+    SCOPE: 'static,
+{
+    let transport: Rc<dyn SyncTransport> = Rc::new(transport);
+    let peer = Rc::new(RefCell::new(Peer {
+        id: peer_id(),
+        version: 0,
+        origin: 0,
+        synced: None,
+    }));
+
+    // Report queue pressure to subscribers.
+    let reporter = Rc::new(RefCell::new(Dispatch::<H, SCOPE>::connect()));
+    let status = Rc::new(RefCell::new(Dispatch::<OutboxHandle, SCOPE>::connect()));
+    let publish = {
+        let status = Rc::downgrade(&status);
+        Rc::new(move |state: OutboxState| {
+            if let Some(status) = status.upgrade() {
+                status.borrow_mut().reduce(move |outbox| *outbox = state);
+            }
+        })
+    };
+    let outbox = Rc::new(RefCell::new(Outbox {
+        transport: Rc::downgrade(&transport),
+        options,
+        held: VecDeque::new(),
+        next_id: 0,
+        dropped: 0,
+        reported: false,
+        publish,
+    }));
+    let report = {
+        let reporter = Rc::downgrade(&reporter);
+        Rc::new(move |error: Error| {
+            if let Some(reporter) = reporter.upgrade() {
+                reporter.borrow_mut().send(Request::Report(error));
+            }
+        })
+    };
+    // Messages that couldn't be encoded are reported instead.
+    let send = {
+        let outbox = Rc::downgrade(&outbox);
+        let report = report.clone();
+        Rc::new(move |message: Result<Vec<u8>, Error>| {
+            let error = match message {
+                Ok(message) => outbox
+                    .upgrade()
+                    .and_then(|outbox| Outbox::send(&outbox, message)),
+                Err(error) => Some(error),
+            };
+            if let Some(error) = error {
+                report(error);
+            }
+        })
+    };
+
+    // Send local changes.
+    let changed = {
+        let peer = peer.clone();
+        let send = send.clone();
+        Callback::from(move |state: Rc<Model<H>>| {
+            let message = {
+                let mut peer = peer.borrow_mut();
+                if peer.synced.as_ref() == Some(&state) {
+                    return;
+                }
+                peer.version += 1;
+                peer.origin = peer.id;
+                peer.synced = Some(state);
+                peer.message()
+            };
+            if let Some(message) = message {
+                send(message);
+            }
+        })
+    };
+    let dispatch = Rc::new(RefCell::new(Dispatch::<H, SCOPE>::new(changed)));
+
+    // Apply newer changes from peers, and answer older ones with ours.
+    let received = {
+        let peer = peer.clone();
+        let dispatch: Weak<RefCell<Dispatch<H, SCOPE>>> = Rc::downgrade(&dispatch);
+        let send = send.clone();
+        let report = report.clone();
+        Callback::from(move |message: Vec<u8>| {
+            let message: Message<Model<H>> = match serde_json::from_slice(&message) {
+                Ok(message) => message,
+                Err(error) => return report(Error::InvalidSyncMessage(error.to_string())),
+            };
+            let mut local = peer.borrow_mut();
+            let theirs = (message.version, message.origin);
+            let ours = (local.version, local.origin);
+            if theirs > ours {
+                let state = Rc::new(message.state);
+                local.version = message.version;
+                local.origin = message.origin;
+                local.synced = Some(state.clone());
+                // Release the peer first, since the change comes back to `changed`.
+                drop(local);
+                if let Some(dispatch) = dispatch.upgrade() {
+                    dispatch
+                        .borrow_mut()
+                        .reduce(move |local| *local = (*state).clone());
+                }
+            } else if theirs < ours {
+                let answer = local.message();
+                drop(local);
+                if let Some(answer) = answer {
+                    send(answer);
+                }
+            }
+        })
+    };
+    transport.on_message(received);
+
+    // Catch up peers that connected (or reconnected) since. Held messages end with current state.
+    let opened = {
+        let peer = Rc::downgrade(&peer);
+        let outbox = Rc::downgrade(&outbox);
+        Callback::from(move |state| {
+            if state != ConnectionState::Open {
+                return;
+            }
+            let flushed = outbox
+                .upgrade()
+                .is_some_and(|outbox| Outbox::flush(&outbox));
+            if flushed {
+                return;
+            }
+            if let Some(message) = peer.upgrade().and_then(|peer| peer.borrow().message()) {
+                send(message);
+            }
+        })
+    };
+    transport.on_state_change(opened.clone());
+
+    // Peers may have missed changes while away, so catch them up as if the connection reopened.
+    #[cfg(feature = "wake")]
+    let woke = {
+        let transport = Rc::downgrade(&transport);
+        crate::wake::on_wake(move |_| {
+            if let Some(transport) = transport.upgrade() {
+                opened.emit(transport.state());
+            }
+        })
+    };
+
+    SyncConnection {
+        _transport: transport,
+        _dispatch: dispatch,
+        _reporter: reporter,
+        _status: status,
+        #[cfg(feature = "wake")]
+        _woke: woke,
+        outbox,
+    }
+}
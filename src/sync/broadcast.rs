@@ -0,0 +1,61 @@
+//! Transport over a `BroadcastChannel`, for syncing tabs of the same origin.
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{BroadcastChannel, MessageEvent};
+use yew::Callback;
+
+use super::{ConnectionState, Endpoint, SyncTransport};
+
+/// Transport to every other tab with a channel of the same name. Always open.
+pub struct BroadcastTransport {
+    channel: BroadcastChannel,
+    endpoint: Rc<Endpoint>,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl BroadcastTransport {
+    pub fn new(name: &str) -> Result<Self, JsValue> {
+        let channel = BroadcastChannel::new(name)?;
+        let endpoint = Rc::new(Endpoint::default());
+        endpoint.set_state(ConnectionState::Open);
+
+        let receiver: Weak<Endpoint> = Rc::downgrade(&endpoint);
+        let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+            if let Some(endpoint) = receiver.upgrade() {
+                endpoint.deliver(js_sys::Uint8Array::new(&event.data()).to_vec());
+            }
+        }) as Box<dyn FnMut(_)>);
+        channel.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            channel,
+            endpoint,
+            _onmessage: onmessage,
+        })
+    }
+}
+
+impl SyncTransport for BroadcastTransport {
+    fn send(&self, message: Vec<u8>) {
+        let message = js_sys::Uint8Array::from(message.as_slice());
+        let _ = self.channel.post_message(&message);
+    }
+
+    fn on_message(&self, callback: Callback<Vec<u8>>) {
+        self.endpoint.on_message(callback);
+    }
+
+    fn state(&self) -> ConnectionState {
+        ConnectionState::Open
+    }
+
+    fn on_state_change(&self, _callback: Callback<ConnectionState>) {}
+}
+
+impl Drop for BroadcastTransport {
+    fn drop(&mut self) {
+        self.channel.set_onmessage(None);
+        self.channel.close();
+    }
+}
@@ -0,0 +1,288 @@
+//! Electing one tab as leader, to own resources only one tab should, like the WebSocket to a server
+//! or a polling loop.
+//!
+//! Tabs compete for a lease in local storage, which the leader renews every 2 seconds. When the
+//! leader closes, another tab takes over within two renewals; when it stops renewing, once the lease
+//! runs out after 10 seconds.
+//! Browsers throttle timers of hidden tabs, so leadership tends to move to tabs that are visible.
+//!
+//! `LeaderTransport` syncs every tab through the leader: the leader holds the connection to the
+//! server, and relays between it and the other tabs.
+//!
+//! ```ignore
+//! let transport = LeaderTransport::new(
+//!     Election::new("board"),
+//!     BroadcastTransport::new("board")?,
+//!     || WebSocketTransport::new("wss://example.com/board").ok(),
+//! );
+//! self.sync = Some(sync::connect::<SharedHandle<Board>>(transport));
+//! ```
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use yew::{
+    format::Json,
+    services::{interval::IntervalTask, storage::Area, IntervalService},
+    Callback,
+};
+
+use super::{peer_id, ConnectionState, Endpoint, SyncTransport};
+use crate::clock::now;
+use crate::config;
+use crate::storage::WebStorage;
+
+/// Milliseconds between lease renewals.
+const RENEW: u64 = 2_000;
+/// Milliseconds a lease lasts without renewal.
+const LEASE: f64 = 10_000.0;
+
+/// Lease stored in local storage.
+#[derive(Serialize, Deserialize)]
+struct Lease {
+    holder: u64,
+    expires: f64,
+}
+
+struct Candidate {
+    key: String,
+    id: u64,
+    leader: Cell<bool>,
+    watcher: RefCell<Option<Callback<bool>>>,
+}
+
+impl Candidate {
+    fn storage() -> Option<WebStorage> {
+        WebStorage::new(Area::Local).ok()
+    }
+
+    /// Renew the lease if this tab holds it, or claim it if it's free. A claim is confirmed on the
+    /// next renewal, if no other tab claimed it since.
+    fn renew(&self) {
+        let mut storage = match Self::storage() {
+            Some(storage) => storage,
+            None => return,
+        };
+        let now = now();
+        let lease: Option<Lease> = match storage.restore(&self.key) {
+            Json(Ok(lease)) => Some(lease),
+            _ => None,
+        };
+        let leader = match lease {
+            Some(lease) if lease.expires > now && lease.holder != self.id => false,
+            lease => {
+                let held = lease.is_some_and(|lease| lease.holder == self.id);
+                let lease = Lease {
+                    holder: self.id,
+                    expires: now + LEASE,
+                };
+                storage.store(&self.key, Json(&lease));
+                held
+            }
+        };
+        self.set_leader(leader);
+    }
+
+    fn set_leader(&self, leader: bool) {
+        if self.leader.replace(leader) != leader {
+            let watcher = self.watcher.borrow().clone();
+            if let Some(watcher) = watcher {
+                watcher.emit(leader);
+            }
+        }
+    }
+
+    /// Give up the lease, so another tab can take over without waiting for it to run out.
+    fn resign(&self) {
+        if !self.leader.get() {
+            return;
+        }
+        if let Some(mut storage) = Self::storage() {
+            storage.remove(&self.key);
+        }
+    }
+}
+
+/// Participation of this tab in electing a leader. Dropping it gives up leadership.
+pub struct Election {
+    candidate: Rc<Candidate>,
+    _renew: IntervalTask,
+}
+
+impl Election {
+    /// Take part in electing the leader of tabs using `name`. The first election takes a renewal,
+    /// so no tab leads for the first 2 seconds.
+    pub fn new(name: &str) -> Self {
+        let candidate = Rc::new(Candidate {
+            key: format!("{}{}.leader", config::key_prefix(), name),
+            id: peer_id(),
+            leader: Cell::new(false),
+            watcher: RefCell::new(None),
+        });
+        candidate.renew();
+
+        let renewing: Weak<Candidate> = Rc::downgrade(&candidate);
+        let renew = IntervalService::spawn(
+            Duration::from_millis(RENEW),
+            Callback::from(move |_| {
+                if let Some(candidate) = renewing.upgrade() {
+                    candidate.renew();
+                }
+            }),
+        );
+
+        Self {
+            candidate,
+            _renew: renew,
+        }
+    }
+
+    /// Whether this tab is the leader.
+    pub fn is_leader(&self) -> bool {
+        self.candidate.leader.get()
+    }
+
+    /// Be notified with `callback` whenever this tab becomes leader (`true`), or stops being it
+    /// (`false`).
+    pub fn on_change(&self, callback: Callback<bool>) {
+        *self.candidate.watcher.borrow_mut() = Some(callback);
+    }
+}
+
+impl Drop for Election {
+    fn drop(&mut self) {
+        self.candidate.resign();
+    }
+}
+
+struct Relay {
+    election: Election,
+    tabs: Box<dyn SyncTransport>,
+    connect: Box<dyn Fn() -> Option<Box<dyn SyncTransport>>>,
+    /// Connection to the server, while this tab is the leader.
+    upstream: RefCell<Option<Rc<dyn SyncTransport>>>,
+    endpoint: Endpoint,
+}
+
+impl Relay {
+    fn upstream(&self) -> Option<Rc<dyn SyncTransport>> {
+        self.upstream.borrow().clone()
+    }
+
+    fn update_state(&self) {
+        let state = match self.upstream() {
+            Some(upstream) => upstream.state(),
+            None => self.tabs.state(),
+        };
+        self.endpoint.set_state(state);
+    }
+
+    /// Connect to the server on becoming leader, and disconnect on stepping down.
+    fn lead(relay: &Rc<Self>, leader: bool) {
+        let upstream = if leader { (relay.connect)() } else { None };
+        let upstream: Option<Rc<dyn SyncTransport>> = upstream.map(Rc::from);
+        if let Some(upstream) = &upstream {
+            let receiver = Rc::downgrade(relay);
+            upstream.on_message(Callback::from(move |message: Vec<u8>| {
+                if let Some(relay) = receiver.upgrade() {
+                    relay.tabs.send(message.clone());
+                    relay.endpoint.deliver(message);
+                }
+            }));
+            let watcher = Rc::downgrade(relay);
+            upstream.on_state_change(Callback::from(move |_| {
+                if let Some(relay) = watcher.upgrade() {
+                    relay.update_state();
+                }
+            }));
+        }
+        *relay.upstream.borrow_mut() = upstream;
+        relay.update_state();
+    }
+}
+
+/// Transport reaching the server through whichever tab is leader. The leader connects with
+/// `connect`, and relays between the server and `tabs`, a transport between tabs (usually a
+/// `BroadcastTransport`). Other tabs only use `tabs`.
+pub struct LeaderTransport {
+    relay: Rc<Relay>,
+}
+
+impl LeaderTransport {
+    pub fn new<T, F>(election: Election, tabs: impl SyncTransport, connect: F) -> Self
+    where
+        T: SyncTransport,
+        F: Fn() -> Option<T> + 'static,
+    {
+        let relay = Rc::new(Relay {
+            election,
+            tabs: Box::new(tabs),
+            connect: Box::new(move || {
+                connect().map(|transport| Box::new(transport) as Box<dyn SyncTransport>)
+            }),
+            upstream: RefCell::new(None),
+            endpoint: Endpoint::default(),
+        });
+
+        let receiver = Rc::downgrade(&relay);
+        relay
+            .tabs
+            .on_message(Callback::from(move |message: Vec<u8>| {
+                if let Some(relay) = receiver.upgrade() {
+                    if let Some(upstream) = relay.upstream() {
+                        upstream.send(message.clone());
+                    }
+                    relay.endpoint.deliver(message);
+                }
+            }));
+        let watcher = Rc::downgrade(&relay);
+        relay.tabs.on_state_change(Callback::from(move |_| {
+            if let Some(relay) = watcher.upgrade() {
+                relay.update_state();
+            }
+        }));
+        let elected = Rc::downgrade(&relay);
+        relay.election.on_change(Callback::from(move |leader| {
+            if let Some(relay) = elected.upgrade() {
+                Relay::lead(&relay, leader);
+            }
+        }));
+        Relay::lead(&relay, relay.election.is_leader());
+
+        Self { relay }
+    }
+
+    /// Whether this tab is the leader.
+    pub fn is_leader(&self) -> bool {
+        self.relay.election.is_leader()
+    }
+}
+
+impl SyncTransport for LeaderTransport {
+    fn send(&self, message: Vec<u8>) {
+        if let Some(upstream) = self.relay.upstream() {
+            upstream.send(message.clone());
+        }
+        self.relay.tabs.send(message);
+    }
+
+    fn on_message(&self, callback: Callback<Vec<u8>>) {
+        self.relay.endpoint.on_message(callback);
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.relay.endpoint.state()
+    }
+
+    fn on_state_change(&self, callback: Callback<ConnectionState>) {
+        self.relay.endpoint.on_state_change(callback);
+    }
+
+    fn buffered(&self) -> usize {
+        self.relay
+            .upstream()
+            .map_or(0, |upstream| upstream.buffered())
+            .max(self.relay.tabs.buffered())
+    }
+}
@@ -0,0 +1,98 @@
+//! In-memory transport, for tests.
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use yew::Callback;
+
+use super::{ConnectionState, Endpoint, SyncTransport};
+
+/// Connects in-memory transports to each other. Messages are delivered right away.
+///
+/// ```ignore
+/// let hub = MemoryHub::new();
+/// let _alice = sync::connect_in::<SharedHandle<Board>, Alice>(hub.connect());
+/// let _bob = sync::connect_in::<SharedHandle<Board>, Bob>(hub.connect());
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryHub {
+    endpoints: Rc<RefCell<Vec<Weak<Endpoint>>>>,
+}
+
+impl MemoryHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A transport to every other transport of this hub.
+    pub fn connect(&self) -> MemoryTransport {
+        let endpoint = Rc::new(Endpoint::default());
+        endpoint.set_state(ConnectionState::Open);
+        self.endpoints.borrow_mut().push(Rc::downgrade(&endpoint));
+        MemoryTransport {
+            endpoint,
+            hub: self.clone(),
+        }
+    }
+}
+
+/// Transport made by `MemoryHub::connect`. Clones share one connection, so a test can keep a clone
+/// to `disconnect` it.
+#[derive(Clone)]
+pub struct MemoryTransport {
+    endpoint: Rc<Endpoint>,
+    hub: MemoryHub,
+}
+
+impl MemoryTransport {
+    /// Simulate losing the connection. Messages sent meanwhile are held until `reconnect`, and
+    /// messages from peers are missed.
+    pub fn disconnect(&self) {
+        self.endpoint.set_state(ConnectionState::Closed);
+    }
+
+    /// Restore the connection after `disconnect`, sending held messages.
+    pub fn reconnect(&self) {
+        for message in self.endpoint.take_pending() {
+            self.broadcast(message);
+        }
+        self.endpoint.set_state(ConnectionState::Open);
+    }
+
+    fn broadcast(&self, message: Vec<u8>) {
+        let peers: Vec<Rc<Endpoint>> = {
+            let mut endpoints = self.hub.endpoints.borrow_mut();
+            endpoints.retain(|endpoint| endpoint.strong_count() > 0);
+            endpoints
+                .iter()
+                .filter_map(Weak::upgrade)
+                .filter(|endpoint| !Rc::ptr_eq(endpoint, &self.endpoint))
+                .collect()
+        };
+        for peer in peers {
+            if peer.state() == ConnectionState::Open {
+                peer.deliver(message.clone());
+            }
+        }
+    }
+}
+
+impl SyncTransport for MemoryTransport {
+    fn send(&self, message: Vec<u8>) {
+        match self.endpoint.state() {
+            ConnectionState::Open => self.broadcast(message),
+            _ => self.endpoint.hold(message),
+        }
+    }
+
+    fn on_message(&self, callback: Callback<Vec<u8>>) {
+        self.endpoint.on_message(callback);
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.endpoint.state()
+    }
+
+    fn on_state_change(&self, callback: Callback<ConnectionState>) {
+        self.endpoint.on_state_change(callback);
+    }
+}
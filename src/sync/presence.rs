@@ -0,0 +1,344 @@
+//! Presence of peers in a shared scope, such as other tabs or people viewing the same board.
+//!
+//! Presence is ordinary shared state of a `PresenceHandle`, so components render it like any other:
+//!
+//! ```ignore
+//! type Handle = PresenceHandle<Cursor>;
+//!
+//! // Once, somewhere that lives as long as the app.
+//! let transport = BroadcastTransport::new("board.presence")?;
+//! self.presence = Some(presence::connect::<Cursor>(transport));
+//!
+//! // In a component.
+//! html! { <p>{ format!("{} people viewing", self.handle.state().len()) }</p> }
+//! self.handle.reduce(move |presence| presence.set_meta(cursor));
+//! ```
+//!
+//! A transport's messages go to a single receiver, so presence needs a transport of its own,
+//! separate from the one syncing state.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::{Rc, Weak};
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use yew::services::{interval::IntervalTask, IntervalService};
+use yew::Callback;
+
+use super::{peer_id, ConnectionState, SyncTransport};
+use crate::clock::now;
+use crate::dispatch::Dispatch;
+use crate::handle::StateHandle;
+use crate::handler::{Handler, Reduction, ReductionOnce};
+
+/// Milliseconds between heartbeats.
+const HEARTBEAT: f64 = 5_000.0;
+/// Milliseconds after which a silent peer is considered gone.
+const TIMEOUT: f64 = 3.0 * HEARTBEAT;
+
+/// Handle for presence of peers, with metadata `M` for each (a cursor position, a name, or `()`).
+pub type PresenceHandle<M> = StateHandle<Presence<M>, PresenceHandler<M>>;
+
+/// A peer that's present.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerInfo<M> {
+    pub id: u64,
+    /// When the peer was last heard from, in milliseconds since the Unix epoch.
+    pub last_seen: f64,
+    pub meta: M,
+}
+
+/// Peers present in a scope, this one included. Empty until a handle has loaded state.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Presence<M> {
+    id: u64,
+    peers: BTreeMap<u64, PeerInfo<M>>,
+}
+
+impl<M: Default> Presence<M> {
+    fn new(id: u64) -> Self {
+        let mut peers = BTreeMap::new();
+        peers.insert(
+            id,
+            PeerInfo {
+                id,
+                last_seen: now(),
+                meta: M::default(),
+            },
+        );
+        Self { id, peers }
+    }
+}
+
+impl<M> Presence<M> {
+    /// Id of this peer.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// This peer.
+    pub fn me(&self) -> Option<&PeerInfo<M>> {
+        self.peers.get(&self.id)
+    }
+
+    /// Metadata of this peer.
+    pub fn meta(&self) -> Option<&M> {
+        self.me().map(|me| &me.meta)
+    }
+
+    /// Change metadata of this peer. Peers are told right away.
+    pub fn set_meta(&mut self, meta: M) {
+        if let Some(me) = self.peers.get_mut(&self.id) {
+            me.meta = meta;
+        }
+    }
+
+    /// Number of peers present, this one included.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Whether no peers are present, which is only the case before a handle has loaded state.
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Peer with `id`, if it's present.
+    pub fn get(&self, id: u64) -> Option<&PeerInfo<M>> {
+        self.peers.get(&id)
+    }
+
+    /// Every peer present, this one included, ordered by id.
+    pub fn peers(&self) -> impl Iterator<Item = &PeerInfo<M>> {
+        self.peers.values()
+    }
+
+    /// Every other peer present, ordered by id.
+    pub fn others(&self) -> impl Iterator<Item = &PeerInfo<M>> {
+        let id = self.id;
+        self.peers.values().filter(move |peer| peer.id != id)
+    }
+
+    fn seen(&mut self, mut peer: PeerInfo<M>) {
+        peer.last_seen = now();
+        self.peers.insert(peer.id, peer);
+    }
+
+    fn gone(&mut self, id: u64) {
+        if id != self.id {
+            self.peers.remove(&id);
+        }
+    }
+
+    /// Mark this peer seen, and drop peers that went silent.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    fn beat(&mut self) {
+        let now = now();
+        let id = self.id;
+        self.peers
+            .retain(|_, peer| peer.id == id || now - peer.last_seen < TIMEOUT);
+        if let Some(me) = self.peers.get_mut(&id) {
+            me.last_seen = now;
+        }
+    }
+}
+
+/// Handler for presence of peers. State starts with only this peer present.
+#[derive(Clone)]
+pub struct PresenceHandler<M> {
+    state: Rc<Presence<M>>,
+}
+
+impl<M> Handler for PresenceHandler<M>
+where
+    M: Clone + Default,
+{
+    type Model = Presence<M>;
+
+    fn new() -> Self {
+        Self {
+            state: Rc::new(Presence::new(peer_id())),
+        }
+    }
+
+    fn apply(&mut self, f: Reduction<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+    }
+
+    fn apply_once(&mut self, f: ReductionOnce<Self::Model>) {
+        f(Rc::make_mut(&mut self.state));
+    }
+
+    fn state(&self) -> Rc<Self::Model> {
+        Rc::clone(&self.state)
+    }
+}
+
+/// Message sent between peers.
+#[derive(Serialize, Deserialize)]
+enum Beat<M> {
+    Here(PeerInfo<M>),
+    Gone(u64),
+}
+
+fn send<M: Serialize>(transport: &Weak<dyn SyncTransport>, beat: &Beat<M>) {
+    if let (Some(transport), Ok(message)) = (transport.upgrade(), serde_json::to_vec(beat)) {
+        transport.send(message);
+    }
+}
+
+/// This peer, as of the latest state.
+fn me<M: Clone>(latest: &RefCell<Option<Rc<Presence<M>>>>) -> Option<PeerInfo<M>> {
+    latest.borrow().as_ref()?.me().cloned()
+}
+
+/// Connection made by `connect`. Dropping it tells peers this one is gone.
+pub struct PresenceConnection {
+    transport: Rc<dyn SyncTransport>,
+    id: u64,
+    _dispatch: Rc<dyn std::any::Any>,
+    #[cfg(target_arch = "wasm32")]
+    _heartbeat: IntervalTask,
+    #[cfg(feature = "wake")]
+    _woke: crate::wake::WakeGuard,
+}
+
+impl Drop for PresenceConnection {
+    fn drop(&mut self) {
+        if let Ok(message) = serde_json::to_vec(&Beat::<()>::Gone(self.id)) {
+            self.transport.send(message);
+        }
+    }
+}
+
+/// Announce this peer, and track peers reachable through `transport`, in the state of
+/// `PresenceHandle<M>`. A heartbeat is sent every 5 seconds, and peers silent for 15 are dropped.
+pub fn connect<M>(transport: impl SyncTransport) -> PresenceConnection
+where
+    M: Clone + Default + PartialEq + Serialize + DeserializeOwned + 'static,
+{
+    connect_in::<M, PresenceHandler<M>>(transport)
+}
+
+/// Like `connect`, for presence in `SCOPE`.
+pub fn connect_in<M, SCOPE>(transport: impl SyncTransport) -> PresenceConnection
+where
+    M: Clone + Default + PartialEq + Serialize + DeserializeOwned + 'static,
+    SCOPE: 'static,
+{
+    let transport: Rc<dyn SyncTransport> = Rc::new(transport);
+    let latest: Rc<RefCell<Option<Rc<Presence<M>>>>> = Default::default();
+
+    // Tell peers when metadata of this peer changes.
+    let changed = {
+        let latest = latest.clone();
+        let transport = Rc::downgrade(&transport);
+        Callback::from(move |presence: Rc<Presence<M>>| {
+            let previous = latest.replace(Some(presence.clone()));
+            if previous.is_none_or(|previous| previous.meta() != presence.meta()) {
+                if let Some(me) = presence.me() {
+                    send(&transport, &Beat::Here(me.clone()));
+                }
+            }
+        })
+    };
+    let dispatch = Rc::new(RefCell::new(Dispatch::<PresenceHandle<M>, SCOPE>::new(
+        changed,
+    )));
+    let id = latest.borrow().as_ref().map_or(0, |presence| presence.id());
+
+    let received = {
+        let latest = latest.clone();
+        let dispatch: Weak<RefCell<Dispatch<PresenceHandle<M>, SCOPE>>> = Rc::downgrade(&dispatch);
+        let transport = Rc::downgrade(&transport);
+        Callback::from(move |message: Vec<u8>| {
+            let beat: Beat<M> = match serde_json::from_slice(&message) {
+                Ok(beat) => beat,
+                Err(_) => return,
+            };
+            let dispatch = match dispatch.upgrade() {
+                Some(dispatch) => dispatch,
+                None => return,
+            };
+            match beat {
+                Beat::Here(peer) => {
+                    // Greet newcomers, so they don't wait a heartbeat to see this peer.
+                    let known = latest
+                        .borrow()
+                        .as_ref()
+                        .is_none_or(|presence| presence.get(peer.id).is_some());
+                    let me = if known { None } else { me(&latest) };
+                    dispatch
+                        .borrow_mut()
+                        .reduce(move |presence| presence.seen(peer));
+                    if let Some(me) = me {
+                        send(&transport, &Beat::Here(me));
+                    }
+                }
+                Beat::Gone(id) => dispatch
+                    .borrow_mut()
+                    .reduce(move |presence| presence.gone(id)),
+            }
+        })
+    };
+    transport.on_message(received);
+
+    // Announce this peer whenever the connection (re)opens.
+    let opened = {
+        let latest = Rc::downgrade(&latest);
+        let transport = Rc::downgrade(&transport);
+        Callback::from(move |state| {
+            if state != ConnectionState::Open {
+                return;
+            }
+            if let Some(me) = latest.upgrade().and_then(|latest| me(&latest)) {
+                send(&transport, &Beat::Here(me));
+            }
+        })
+    };
+    transport.on_state_change(opened.clone());
+
+    // Heartbeats were held up while away: drop peers gone meanwhile, and announce this one again.
+    #[cfg(feature = "wake")]
+    let woke = {
+        let dispatch = Rc::downgrade(&dispatch);
+        let transport = Rc::downgrade(&transport);
+        crate::wake::on_wake(move |_| {
+            if let Some(dispatch) = dispatch.upgrade() {
+                dispatch.borrow_mut().reduce(Presence::beat);
+            }
+            if let Some(transport) = transport.upgrade() {
+                opened.emit(transport.state());
+            }
+        })
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    let heartbeat = {
+        let dispatch = Rc::downgrade(&dispatch);
+        let transport = Rc::downgrade(&transport);
+        IntervalService::spawn(
+            Duration::from_millis(HEARTBEAT as u64),
+            Callback::from(move |_| {
+                if let Some(dispatch) = dispatch.upgrade() {
+                    dispatch.borrow_mut().reduce(Presence::beat);
+                }
+                if let Some(me) = me(&latest) {
+                    send(&transport, &Beat::Here(me));
+                }
+            }),
+        )
+    };
+
+    PresenceConnection {
+        transport,
+        id,
+        _dispatch: dispatch,
+        #[cfg(target_arch = "wasm32")]
+        _heartbeat: heartbeat,
+        #[cfg(feature = "wake")]
+        _woke: woke,
+    }
+}
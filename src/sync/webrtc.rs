@@ -0,0 +1,284 @@
+//! Peer-to-peer transport over a WebRTC data channel.
+//!
+//! Connecting two browsers takes an exchange of offers, answers, and ICE candidates through some
+//! other channel (a server, a QR code, copy and paste). That's left to a `Signaling`
+//! implementation.
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{
+    MessageEvent, RtcDataChannel, RtcDataChannelEvent, RtcDataChannelState, RtcDataChannelType,
+    RtcIceCandidateInit, RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType,
+    RtcSessionDescriptionInit,
+};
+use yew::Callback;
+
+use super::{ConnectionState, Endpoint, SyncTransport};
+
+/// Label of the data channel state is synced over.
+const LABEL: &str = "yew-state";
+
+/// Signaling message between two peers setting up a connection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Signal {
+    Offer(String),
+    Answer(String),
+    Candidate {
+        candidate: String,
+        sdp_mid: Option<String>,
+        sdp_m_line_index: Option<u16>,
+    },
+}
+
+/// Delivers signals to the other peer, and signals from it to `on_signal`.
+pub trait Signaling: 'static {
+    fn send(&self, signal: Signal);
+    fn on_signal(&self, callback: Callback<Signal>);
+}
+
+#[derive(Default)]
+struct Channel {
+    channel: RefCell<Option<RtcDataChannel>>,
+    endpoint: Endpoint,
+    /// Event handlers of the channel.
+    closures: RefCell<Vec<Box<dyn Any>>>,
+}
+
+impl Channel {
+    fn send(&self, message: Vec<u8>) {
+        let sent = match &*self.channel.borrow() {
+            Some(channel) if channel.ready_state() == RtcDataChannelState::Open => {
+                channel.send_with_u8_array(&message).is_ok()
+            }
+            _ => false,
+        };
+        if !sent {
+            self.endpoint.hold(message);
+        }
+    }
+}
+
+/// Start using `channel`.
+fn attach(state: &Rc<Channel>, channel: RtcDataChannel) {
+    channel.set_binary_type(RtcDataChannelType::Arraybuffer);
+
+    let receiver: Weak<Channel> = Rc::downgrade(state);
+    let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+        if let Some(state) = receiver.upgrade() {
+            let message = js_sys::Uint8Array::new(&event.data()).to_vec();
+            state.endpoint.deliver(message);
+        }
+    }) as Box<dyn FnMut(_)>);
+    channel.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+    let opened: Weak<Channel> = Rc::downgrade(state);
+    let onopen = Closure::wrap(Box::new(move || {
+        if let Some(state) = opened.upgrade() {
+            for message in state.endpoint.take_pending() {
+                state.send(message);
+            }
+            state.endpoint.set_state(ConnectionState::Open);
+        }
+    }) as Box<dyn FnMut()>);
+    channel.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+
+    let closed: Weak<Channel> = Rc::downgrade(state);
+    let onclose = Closure::wrap(Box::new(move || {
+        if let Some(state) = closed.upgrade() {
+            state.endpoint.set_state(ConnectionState::Closed);
+        }
+    }) as Box<dyn FnMut()>);
+    channel.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+
+    let mut closures = state.closures.borrow_mut();
+    closures.push(Box::new(onmessage));
+    closures.push(Box::new(onopen));
+    closures.push(Box::new(onclose));
+    *state.channel.borrow_mut() = Some(channel);
+}
+
+/// SDP of a session description returned by `createOffer` or `createAnswer`.
+fn sdp(description: &JsValue) -> Result<String, JsValue> {
+    js_sys::Reflect::get(description, &"sdp".into())?
+        .as_string()
+        .ok_or_else(|| "session description has no SDP".into())
+}
+
+fn description(kind: RtcSdpType, sdp: &str) -> RtcSessionDescriptionInit {
+    let description = RtcSessionDescriptionInit::new(kind);
+    description.set_sdp(sdp);
+    description
+}
+
+async fn handle_signal(
+    connection: &RtcPeerConnection,
+    signaling: &dyn Signaling,
+    signal: Signal,
+) -> Result<(), JsValue> {
+    match signal {
+        Signal::Offer(offer) => {
+            let offer = description(RtcSdpType::Offer, &offer);
+            JsFuture::from(connection.set_remote_description(&offer)).await?;
+            let answer = sdp(&JsFuture::from(connection.create_answer()).await?)?;
+            let local = description(RtcSdpType::Answer, &answer);
+            JsFuture::from(connection.set_local_description(&local)).await?;
+            signaling.send(Signal::Answer(answer));
+        }
+        Signal::Answer(answer) => {
+            let answer = description(RtcSdpType::Answer, &answer);
+            JsFuture::from(connection.set_remote_description(&answer)).await?;
+        }
+        Signal::Candidate {
+            candidate,
+            sdp_mid,
+            sdp_m_line_index,
+        } => {
+            let init = RtcIceCandidateInit::new(&candidate);
+            init.set_sdp_mid(sdp_mid.as_deref());
+            init.set_sdp_m_line_index(sdp_m_line_index);
+            JsFuture::from(
+                connection.add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init)),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Transport over a WebRTC data channel. Messages sent before the channel opens are held until it
+/// does.
+///
+/// ```ignore
+/// // In one browser:
+/// let transport = RtcTransport::offer(MySignaling::new(room))?;
+/// // In the other:
+/// let transport = RtcTransport::answer(MySignaling::new(room))?;
+///
+/// self.sync = Some(sync::connect::<SharedHandle<Board>>(transport));
+/// ```
+pub struct RtcTransport {
+    connection: RtcPeerConnection,
+    channel: Rc<Channel>,
+    _onicecandidate: Closure<dyn FnMut(RtcPeerConnectionIceEvent)>,
+    _ondatachannel: Option<Closure<dyn FnMut(RtcDataChannelEvent)>>,
+}
+
+impl RtcTransport {
+    /// Start connecting by sending an offer. The other peer calls `answer`.
+    pub fn offer(signaling: impl Signaling) -> Result<Self, JsValue> {
+        let signaling: Rc<dyn Signaling> = Rc::new(signaling);
+        let transport = Self::new(signaling.clone())?;
+        attach(
+            &transport.channel,
+            transport.connection.create_data_channel(LABEL),
+        );
+
+        let connection = transport.connection.clone();
+        spawn_local(async move {
+            let offer = async {
+                let offer = sdp(&JsFuture::from(connection.create_offer()).await?)?;
+                let local = description(RtcSdpType::Offer, &offer);
+                JsFuture::from(connection.set_local_description(&local)).await?;
+                Ok::<_, JsValue>(offer)
+            };
+            match offer.await {
+                Ok(offer) => signaling.send(Signal::Offer(offer)),
+                Err(error) => web_sys::console::error_2(&"can't make WebRTC offer".into(), &error),
+            }
+        });
+        Ok(transport)
+    }
+
+    /// Wait for an offer from the other peer, and answer it.
+    pub fn answer(signaling: impl Signaling) -> Result<Self, JsValue> {
+        let mut transport = Self::new(Rc::new(signaling))?;
+        let channel: Weak<Channel> = Rc::downgrade(&transport.channel);
+        let ondatachannel = Closure::wrap(Box::new(move |event: RtcDataChannelEvent| {
+            if let Some(channel) = channel.upgrade() {
+                attach(&channel, event.channel());
+            }
+        }) as Box<dyn FnMut(_)>);
+        transport
+            .connection
+            .set_ondatachannel(Some(ondatachannel.as_ref().unchecked_ref()));
+        transport._ondatachannel = Some(ondatachannel);
+        Ok(transport)
+    }
+
+    fn new(signaling: Rc<dyn Signaling>) -> Result<Self, JsValue> {
+        let connection = RtcPeerConnection::new()?;
+
+        // Trickle ICE candidates to the other peer.
+        let sender = signaling.clone();
+        let onicecandidate = Closure::wrap(Box::new(move |event: RtcPeerConnectionIceEvent| {
+            if let Some(candidate) = event.candidate() {
+                sender.send(Signal::Candidate {
+                    candidate: candidate.candidate(),
+                    sdp_mid: candidate.sdp_mid(),
+                    sdp_m_line_index: candidate.sdp_m_line_index(),
+                });
+            }
+        }) as Box<dyn FnMut(_)>);
+        connection.set_onicecandidate(Some(onicecandidate.as_ref().unchecked_ref()));
+
+        let receiver = connection.clone();
+        let responder = Rc::downgrade(&signaling);
+        signaling.on_signal(Callback::from(move |signal| {
+            let connection = receiver.clone();
+            let signaling = match responder.upgrade() {
+                Some(signaling) => signaling,
+                None => return,
+            };
+            spawn_local(async move {
+                if let Err(error) = handle_signal(&connection, &*signaling, signal).await {
+                    web_sys::console::error_2(&"can't handle WebRTC signal".into(), &error);
+                }
+            });
+        }));
+
+        Ok(Self {
+            connection,
+            channel: Default::default(),
+            _onicecandidate: onicecandidate,
+            _ondatachannel: None,
+        })
+    }
+}
+
+impl SyncTransport for RtcTransport {
+    fn send(&self, message: Vec<u8>) {
+        self.channel.send(message);
+    }
+
+    fn on_message(&self, callback: Callback<Vec<u8>>) {
+        self.channel.endpoint.on_message(callback);
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.channel.endpoint.state()
+    }
+
+    fn on_state_change(&self, callback: Callback<ConnectionState>) {
+        self.channel.endpoint.on_state_change(callback);
+    }
+
+    fn buffered(&self) -> usize {
+        match &*self.channel.channel.borrow() {
+            Some(channel) => channel.buffered_amount() as usize,
+            None => 0,
+        }
+    }
+}
+
+impl Drop for RtcTransport {
+    fn drop(&mut self) {
+        if let Some(channel) = &*self.channel.channel.borrow() {
+            channel.close();
+        }
+        self.connection.close();
+    }
+}
@@ -0,0 +1,101 @@
+//! Transport over a WebSocket, for syncing through a server.
+//!
+//! The server relays every message it receives to its other clients.
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+use yew::Callback;
+
+use super::{ConnectionState, Endpoint, SyncTransport};
+
+/// Transport over a WebSocket. Messages sent before the socket opens are held until it does.
+///
+/// It doesn't reconnect by itself: once closed, make a new transport and connect again.
+pub struct WebSocketTransport {
+    socket: WebSocket,
+    endpoint: Rc<Endpoint>,
+    _onopen: Closure<dyn FnMut()>,
+    _onclose: Closure<dyn FnMut()>,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl WebSocketTransport {
+    /// Connect to `url`.
+    pub fn new(url: &str) -> Result<Self, JsValue> {
+        let socket = WebSocket::new(url)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let endpoint = Rc::new(Endpoint::default());
+
+        let opened: Weak<Endpoint> = Rc::downgrade(&endpoint);
+        let sender = socket.clone();
+        let onopen = Closure::wrap(Box::new(move || {
+            if let Some(endpoint) = opened.upgrade() {
+                for message in endpoint.take_pending() {
+                    let _ = sender.send_with_u8_array(&message);
+                }
+                endpoint.set_state(ConnectionState::Open);
+            }
+        }) as Box<dyn FnMut()>);
+        socket.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+
+        let closed: Weak<Endpoint> = Rc::downgrade(&endpoint);
+        let onclose = Closure::wrap(Box::new(move || {
+            if let Some(endpoint) = closed.upgrade() {
+                endpoint.set_state(ConnectionState::Closed);
+            }
+        }) as Box<dyn FnMut()>);
+        socket.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+
+        let receiver: Weak<Endpoint> = Rc::downgrade(&endpoint);
+        let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+            if let Some(endpoint) = receiver.upgrade() {
+                endpoint.deliver(js_sys::Uint8Array::new(&event.data()).to_vec());
+            }
+        }) as Box<dyn FnMut(_)>);
+        socket.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            endpoint,
+            _onopen: onopen,
+            _onclose: onclose,
+            _onmessage: onmessage,
+        })
+    }
+}
+
+impl SyncTransport for WebSocketTransport {
+    fn send(&self, message: Vec<u8>) {
+        if self.endpoint.state() != ConnectionState::Open
+            || self.socket.send_with_u8_array(&message).is_err()
+        {
+            self.endpoint.hold(message);
+        }
+    }
+
+    fn on_message(&self, callback: Callback<Vec<u8>>) {
+        self.endpoint.on_message(callback);
+    }
+
+    fn state(&self) -> ConnectionState {
+        self.endpoint.state()
+    }
+
+    fn on_state_change(&self, callback: Callback<ConnectionState>) {
+        self.endpoint.on_state_change(callback);
+    }
+
+    fn buffered(&self) -> usize {
+        self.socket.buffered_amount() as usize
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onclose(None);
+        self.socket.set_onmessage(None);
+        let _ = self.socket.close();
+    }
+}
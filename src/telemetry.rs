@@ -0,0 +1,31 @@
+//! Diagnostics: warnings, and with the `tracing` feature, spans for changes to shared state.
+//!
+//! With `tracing`, every change is a `dispatch` span at debug level, with `handler`, `scope`, and
+//! `label` fields, and `changed` and `duration_ms` recorded once it's applied. Warnings are
+//! tracing events instead of console messages, so apps already collecting traces (say with
+//! tracing-wasm) get them in the same pipeline.
+#[cfg(feature = "tracing")]
+use std::any::type_name;
+
+/// Warn developers of a likely mistake.
+pub(crate) fn warn(message: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!("{}", message);
+    #[cfg(all(not(feature = "tracing"), target_arch = "wasm32"))]
+    yew::services::ConsoleService::warn(message);
+    #[cfg(all(not(feature = "tracing"), not(target_arch = "wasm32")))]
+    eprintln!("{}", message);
+}
+
+/// Span for a change to shared state of handler `H` in `SCOPE`.
+#[cfg(feature = "tracing")]
+pub(crate) fn span<H: 'static, SCOPE: 'static>(label: Option<&str>) -> tracing::Span {
+    tracing::debug_span!(
+        "dispatch",
+        handler = type_name::<H>(),
+        scope = type_name::<SCOPE>(),
+        label = label.unwrap_or_default(),
+        changed = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    )
+}
@@ -0,0 +1,167 @@
+//! Undoable changes, as in "Deleted — Undo".
+//!
+//! `reduce_undoable` changes state right away and offers to undo it for a while. The irreversible
+//! part (deleting on the server, say) only runs once that window has passed without an undo. The
+//! change returns the change undoing it, so undoing reverses just that one, leaving changes made
+//! since alone. Render `UndoToasts` once to show what can be undone:
+//!
+//! ```ignore
+//! self.handle.reduce_undoable(
+//!     "Message deleted",
+//!     move |inbox| {
+//!         let message = inbox.remove(id);
+//!         move |inbox| inbox.insert(id, message)
+//!     },
+//!     Duration::from_secs(5),
+//!     move || api::delete_message(id),
+//! );
+//!
+//! html! {
+//!     <>
+//!         <App />
+//!         <UndoToasts />
+//!     </>
+//! }
+//! ```
+//!
+//! Outside the browser there are no timers, and changes wait to be committed or undone by hand.
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+use yew::services::{timeout::TimeoutTask, TimeoutService};
+
+use crate::dispatch::Dispatch;
+use crate::handle::UndoHandle;
+
+/// A change that can still be undone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pending {
+    pub id: u64,
+    /// What the change did, for the toast.
+    pub message: String,
+}
+
+/// Changes that can still be undone, oldest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Undoables {
+    pending: Vec<Pending>,
+}
+
+impl Undoables {
+    pub fn iter(&self) -> impl Iterator<Item = &Pending> {
+        self.pending.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// A change made with `reduce_undoable`. Undoing or committing it after the other does nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Undoable {
+    id: u64,
+}
+
+impl Undoable {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The change listed as `id` in `Undoables`.
+    pub fn from_id(id: u64) -> Self {
+        Self { id }
+    }
+
+    /// Apply the change undoing it, skipping the commit.
+    pub fn undo(self) {
+        if let Some(action) = take(self.id) {
+            (action.undo)();
+        }
+    }
+
+    /// Commit the change now, without waiting for the window to pass.
+    pub fn commit(self) {
+        if let Some(action) = take(self.id) {
+            (action.commit)();
+        }
+    }
+
+    /// Whether the change can still be undone.
+    pub fn is_pending(self) -> bool {
+        ACTIONS.with(|actions| actions.borrow().contains_key(&self.id))
+    }
+}
+
+struct Action {
+    undo: Box<dyn FnOnce()>,
+    commit: Box<dyn FnOnce()>,
+    #[cfg(target_arch = "wasm32")]
+    _timer: Option<TimeoutTask>,
+}
+
+thread_local! {
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+    static ACTIONS: RefCell<HashMap<u64, Action>> = RefCell::default();
+}
+
+/// Commit every pending change, as when the page is about to unload.
+pub fn commit_all() {
+    let mut ids: Vec<u64> = ACTIONS.with(|actions| actions.borrow().keys().copied().collect());
+    ids.sort_unstable();
+    for id in ids {
+        Undoable { id }.commit();
+    }
+}
+
+/// Offer to undo a change that was just made, committing it after `window`.
+pub(crate) fn offer(
+    message: String,
+    window: Duration,
+    undo: impl FnOnce() + 'static,
+    commit: impl FnOnce() + 'static,
+) -> Undoable {
+    let id = NEXT_ID.with(|next| {
+        next.set(next.get() + 1);
+        next.get()
+    });
+    let undoable = Undoable { id };
+    if window.is_zero() {
+        commit();
+        return undoable;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    let _timer = Some(TimeoutService::spawn(
+        window,
+        (move |_| undoable.commit()).into(),
+    ));
+    ACTIONS.with(|actions| {
+        actions.borrow_mut().insert(
+            id,
+            Action {
+                undo: Box::new(undo),
+                commit: Box::new(commit),
+                #[cfg(target_arch = "wasm32")]
+                _timer,
+            },
+        )
+    });
+    Dispatch::<UndoHandle>::connect()
+        .reduce(move |undoables| undoables.pending.push(Pending { id, message }));
+    undoable
+}
+
+/// Stop offering to undo a change, returning what to do with it.
+fn take(id: u64) -> Option<Action> {
+    let action = ACTIONS.with(|actions| actions.borrow_mut().remove(&id))?;
+    Dispatch::<UndoHandle>::connect()
+        .reduce(move |undoables| undoables.pending.retain(|pending| pending.id != id));
+    Some(action)
+}
@@ -0,0 +1,120 @@
+//! Scroll offsets, selections and focus of named views, for keeping split panes in sync or
+//! restoring where the user was.
+//!
+//! Views with the same name share their state, so binding two panes to one name scrolls them
+//! together:
+//!
+//! ```ignore
+//! // In a component with `handle: ViewSyncHandle` and `pane: NodeRef`.
+//! fn view(&self) -> Html {
+//!     html! {
+//!         <div ref=self.pane.clone() onscroll=view_sync::on_scroll(&self.handle, "diff")>
+//!             { self.lines() }
+//!         </div>
+//!     }
+//! }
+//!
+//! fn rendered(&mut self, _first_render: bool) {
+//!     view_sync::restore_scroll(self.handle.state(), "diff", &self.pane);
+//! }
+//! ```
+use std::collections::HashMap;
+
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event};
+use yew::{Callback, NodeRef};
+
+use crate::handle::ViewSyncHandle;
+
+/// State of one view.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ViewState {
+    pub scroll_top: f64,
+    pub scroll_left: f64,
+    /// Selected range, from start to end.
+    pub selection: Option<(u32, u32)>,
+}
+
+/// State of every named view.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Views {
+    views: HashMap<String, ViewState>,
+    focused: Option<String>,
+}
+
+impl Views {
+    /// State of `view`, which is the default until it's changed.
+    pub fn get(&self, view: &str) -> ViewState {
+        self.views.get(view).cloned().unwrap_or_default()
+    }
+
+    /// Name of the focused view, if any.
+    pub fn focused(&self) -> Option<&str> {
+        self.focused.as_deref()
+    }
+
+    pub fn set_scroll(&mut self, view: &str, top: f64, left: f64) {
+        let state = self.views.entry(view.to_string()).or_default();
+        state.scroll_top = top;
+        state.scroll_left = left;
+    }
+
+    /// Select from `start` to `end` in `view`, or clear the selection with `None`.
+    pub fn select(&mut self, view: &str, selection: Option<(u32, u32)>) {
+        self.views.entry(view.to_string()).or_default().selection = selection;
+    }
+
+    pub fn focus(&mut self, view: &str) {
+        self.focused = Some(view.to_string());
+    }
+
+    /// Unfocus `view`, if it's focused.
+    pub fn blur(&mut self, view: &str) {
+        if self.focused.as_deref() == Some(view) {
+            self.focused = None;
+        }
+    }
+}
+
+/// Callback for `onscroll` of a scrollable element, keeping scroll offsets of `view` up to date.
+pub fn on_scroll(handle: &ViewSyncHandle, view: &'static str) -> Callback<Event> {
+    handle.reduce_callback_with(move |views, event: Event| {
+        let element = event
+            .target()
+            .and_then(|target| target.dyn_into::<Element>().ok());
+        if let Some(element) = element {
+            views.set_scroll(
+                view,
+                element.scroll_top() as f64,
+                element.scroll_left() as f64,
+            );
+        }
+    })
+}
+
+/// Callback for `onfocus`, marking `view` as focused.
+pub fn on_focus<E: 'static>(handle: &ViewSyncHandle, view: &'static str) -> Callback<E> {
+    handle.reduce_callback(move |views| views.focus(view))
+}
+
+/// Callback for `onblur`, unfocusing `view`.
+pub fn on_blur<E: 'static>(handle: &ViewSyncHandle, view: &'static str) -> Callback<E> {
+    handle.reduce_callback(move |views| views.blur(view))
+}
+
+/// Scroll the element of `node` to the offsets of `view`, if it isn't there already. Call it from
+/// `rendered`, so panes follow each other.
+pub fn restore_scroll(views: &Views, view: &str, node: &NodeRef) {
+    let element = match node.cast::<Element>() {
+        Some(element) => element,
+        None => return,
+    };
+    let state = views.get(view);
+    let (top, left) = (state.scroll_top as i32, state.scroll_left as i32);
+    if element.scroll_top() != top {
+        element.set_scroll_top(top);
+    }
+    if element.scroll_left() != left {
+        element.set_scroll_left(left);
+    }
+}
@@ -0,0 +1,127 @@
+//! Catching up after the page was hidden or the machine slept.
+//!
+//! Browsers throttle timers of background tabs and stop them while a laptop sleeps, so after hours
+//! away, timers and connections can be quietly out of date. When the page becomes visible again,
+//! or timers show the machine slept, every scope catches up: handlers refresh what they load
+//! (feature flags are fetched again), recurring changes made with `every` run right away, and
+//! sync and presence connections send current state to peers.
+//!
+//! Transports don't reconnect by themselves, so reconnect them in `on_wake`:
+//!
+//! ```ignore
+//! self.wake = Some(wake::on_wake(move |away| {
+//!     if away > 60_000.0 {
+//!         reconnect();
+//!     }
+//! }));
+//! ```
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast};
+
+/// Milliseconds between checks for sleep.
+#[cfg(target_arch = "wasm32")]
+const TICK: f64 = 10_000.0;
+/// Milliseconds a check may be late before it's taken as the machine having slept.
+#[cfg(target_arch = "wasm32")]
+const SLEPT: f64 = 20_000.0;
+
+type Subscriber = Rc<dyn Fn(f64)>;
+
+thread_local! {
+    static SUBSCRIBERS: RefCell<Vec<(u64, Subscriber)>> = Default::default();
+    static NEXT: Cell<u64> = const { Cell::new(0) };
+    #[cfg(target_arch = "wasm32")]
+    static LISTENING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Subscription made by `on_wake`. Dropping it unsubscribes.
+#[derive(Debug)]
+pub struct WakeGuard {
+    id: u64,
+}
+
+impl Drop for WakeGuard {
+    fn drop(&mut self) {
+        SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().retain(|(id, _)| *id != self.id));
+    }
+}
+
+/// Call `f` with the milliseconds the page was away whenever it wakes, until the returned guard
+/// is dropped.
+pub fn on_wake(f: impl Fn(f64) + 'static) -> WakeGuard {
+    #[cfg(target_arch = "wasm32")]
+    listen();
+    let id = NEXT.with(|next| next.replace(next.get() + 1));
+    SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push((id, Rc::new(f))));
+    WakeGuard { id }
+}
+
+/// Catch up as if the page just woke after `away` milliseconds, for wake-ups noticed some other
+/// way (such as going back online), and for tests.
+pub fn woke(away: f64) {
+    // Subscribers may subscribe or unsubscribe while notified.
+    let subscribers: Vec<Subscriber> = SUBSCRIBERS.with(|subscribers| {
+        subscribers
+            .borrow()
+            .iter()
+            .map(|(_, f)| f.clone())
+            .collect()
+    });
+    for f in subscribers {
+        f(away);
+    }
+}
+
+/// Watch for the page becoming visible, and for timers running late after sleep. Listening
+/// starts with the first subscriber, and lasts as long as the page.
+#[cfg(target_arch = "wasm32")]
+fn listen() {
+    if LISTENING.with(|listening| listening.replace(true)) {
+        return;
+    }
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return,
+    };
+    let document = match window.document() {
+        Some(document) => document,
+        None => return,
+    };
+
+    let hidden_at = Rc::new(Cell::new(None));
+    let onvisibilitychange = {
+        let document = document.clone();
+        Closure::wrap(Box::new(move || {
+            let now = crate::clock::now();
+            if document.hidden() {
+                hidden_at.set(Some(now));
+            } else if let Some(hidden_at) = hidden_at.take() {
+                woke(now - hidden_at);
+            }
+        }) as Box<dyn FnMut()>)
+    };
+    let _ = document.add_event_listener_with_callback(
+        "visibilitychange",
+        onvisibilitychange.as_ref().unchecked_ref(),
+    );
+    onvisibilitychange.forget();
+
+    // Timers stop while the machine sleeps, so a check far later than expected means it slept.
+    // Checks in hidden tabs are throttled, and becoming visible covers those.
+    let last = Cell::new(crate::clock::now());
+    let tick = Closure::wrap(Box::new(move || {
+        let now = crate::clock::now();
+        let away = now - last.replace(now);
+        if away > TICK + SLEPT && !document.hidden() {
+            woke(away);
+        }
+    }) as Box<dyn FnMut()>);
+    let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
+        tick.as_ref().unchecked_ref(),
+        TICK as i32,
+    );
+    tick.forget();
+}
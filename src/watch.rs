@@ -0,0 +1,148 @@
+//! Futures resolving with shared state once it changes, or once a change is applied.
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+use crate::handler::Request;
+
+/// Called with state after every change until it returns `true`.
+pub(crate) type Watcher<T> = Box<dyn FnMut(&Rc<T>) -> bool>;
+
+/// Slot of an `Applied` future: state after the change, or `None` if it wasn't applied.
+type Outcome<T> = RefCell<Slot<Option<Rc<T>>>>;
+
+struct Slot<V> {
+    state: Option<V>,
+    waker: Option<Waker>,
+}
+
+impl<V> Slot<V> {
+    fn new() -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self {
+            state: None,
+            waker: None,
+        }))
+    }
+
+    fn resolve(&mut self, state: V) {
+        self.state = Some(state);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<V> {
+        match self.state.take() {
+            Some(state) => Poll::Ready(state),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Future resolving with the next state that matches. See `StateHandle::next_state` and
+/// `StateHandle::await_change`.
+///
+/// Dropping it stops watching.
+pub struct Change<T> {
+    slot: Rc<RefCell<Slot<Rc<T>>>>,
+}
+
+impl<T> Change<T> {
+    /// State it resolved with, if it already has, without waiting.
+    #[cfg(feature = "test-utils")]
+    pub(crate) fn ready(&self) -> Option<Rc<T>> {
+        self.slot.borrow_mut().state.take()
+    }
+}
+
+impl<T> Future for Change<T> {
+    type Output = Rc<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Rc<T>> {
+        self.slot.borrow_mut().poll(cx)
+    }
+}
+
+/// Future resolving once a change is applied and every subscriber notified, with state after it.
+/// Resolves with `None` if the change is never applied: when it isn't authorized, is dropped by a
+/// rate limit, or fails. See `StateHandle::reduce_awaitable`.
+pub struct Applied<T> {
+    slot: Rc<Outcome<T>>,
+}
+
+impl<T> Future for Applied<T> {
+    type Output = Option<Rc<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Rc<T>>> {
+        self.slot.borrow_mut().poll(cx)
+    }
+}
+
+/// Resolves an `Applied` future, carried along with the change it waits for. Dropped without
+/// completing, as when the change is rejected, it resolves with `None`.
+#[doc(hidden)]
+pub struct Completion<T> {
+    slot: Option<Weak<Outcome<T>>>,
+}
+
+impl<T> Completion<T> {
+    /// The change was applied, leaving `state`.
+    pub(crate) fn complete(mut self, state: Rc<T>) {
+        self.resolve(Some(state));
+    }
+
+    fn resolve(&mut self, state: Option<Rc<T>>) {
+        // Gone if the future was dropped.
+        if let Some(slot) = self.slot.take().and_then(|slot| slot.upgrade()) {
+            slot.borrow_mut().resolve(state);
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        self.resolve(None);
+    }
+}
+
+/// Carry `request` along with the completion of the future it returns, resolving once it's
+/// applied.
+pub(crate) fn awaited<T>(request: Request<T>) -> (Request<T>, Applied<T>) {
+    let slot = Slot::new();
+    let completion = Completion {
+        slot: Some(Rc::downgrade(&slot)),
+    };
+    (
+        Request::Awaited(Box::new(request), completion),
+        Applied { slot },
+    )
+}
+
+/// Request watching for state matching `selector`, along with the future it resolves. If `now`,
+/// current state is checked too.
+pub(crate) fn watch<T: 'static>(
+    selector: impl Fn(&T) -> bool + 'static,
+    now: bool,
+) -> (Request<T>, Change<T>) {
+    let slot = Slot::new();
+    let weak: Weak<RefCell<Slot<Rc<T>>>> = Rc::downgrade(&slot);
+    let watcher: Watcher<T> = Box::new(move |state| {
+        let slot = match weak.upgrade() {
+            Some(slot) => slot,
+            // The future was dropped.
+            None => return true,
+        };
+        if !selector(state) {
+            return false;
+        }
+        slot.borrow_mut().resolve(Rc::clone(state));
+        true
+    });
+
+    (Request::Watch(watcher, now), Change { slot })
+}
@@ -0,0 +1,255 @@
+//! Shared state running in a web worker.
+//!
+//! Closures can't cross the worker boundary, so state living in a worker is changed with
+//! serializable actions instead of reductions. Every message is encoded with a `Codec` (`Bincode`
+//! by default) before it is sent. yew's worker transport only frames the encoded bytes, rather
+//! than encoding them again.
+//!
+//! Messages that can't be encoded aren't sent. The error is passed to the callback given to
+//! `WorkerBridge::with_errors`, or logged as a warning.
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use serde::{
+    de::{self, Deserializer, Visitor},
+    Deserialize, Serialize, Serializer,
+};
+use yew::{
+    agent::{Agent, AgentLink, Bridge, Bridged, HandlerId, Public},
+    Callback,
+};
+
+use crate::error::Error;
+use crate::telemetry;
+
+/// A serializable change to state.
+pub trait Action<T>: Serialize + for<'a> Deserialize<'a> {
+    /// Apply changes to state.
+    fn apply(self, state: &mut T);
+}
+
+/// Allows state to live in a web worker.
+pub trait WorkerState: Default + Serialize + for<'a> Deserialize<'a> + 'static {
+    /// Script that registers the worker.
+    fn name_of_resource() -> &'static str {
+        "worker.js"
+    }
+}
+
+/// Serialization strategy for messages crossing the worker boundary.
+pub trait Codec: 'static {
+    /// Encode a value to bytes. Returns `Error::Encode` if it can't be serialized.
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error>;
+    /// Decode a value from bytes. Returns `None` if the bytes are invalid.
+    fn decode<T: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Option<T>;
+}
+
+/// Compact binary encoding. This is the default.
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+        bincode::serialize(value).map_err(|error| Error::Encode(error.to_string()))
+    }
+
+    fn decode<T: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Option<T> {
+        bincode::deserialize(bytes).ok()
+    }
+}
+
+/// JSON encoding. Slower than `Bincode`, but readable when debugging.
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(value).map_err(|error| Error::Encode(error.to_string()))
+    }
+
+    fn decode<T: for<'a> Deserialize<'a>>(bytes: &[u8]) -> Option<T> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// Encoded message sent to or from the worker. Serialized as a byte string, so yew's transport
+/// copies it as is instead of encoding every byte.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct Packet(Vec<u8>);
+
+impl Serialize for Packet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Packet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Bytes;
+
+        impl<'de> Visitor<'de> for Bytes {
+            type Value = Packet;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("encoded message")
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Packet, E> {
+                Ok(Packet(bytes.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Packet, E> {
+                Ok(Packet(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(Bytes)
+    }
+}
+
+/// Message sent from the worker to its bridges.
+#[doc(hidden)]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Reply {
+    /// Current state.
+    State(Packet),
+    /// State couldn't be encoded, for this reason.
+    Error(String),
+}
+
+/// Public agent for managing state in a web worker. In charge of applying actions to state then
+/// notifying subscribers of new state.
+///
+/// The worker script must register it before use:
+/// ```ignore
+/// StateWorker::<MyState, MyAction>::register();
+/// ```
+pub struct StateWorker<T, A, C = Bincode>
+where
+    T: WorkerState,
+    A: Action<T> + 'static,
+    C: Codec,
+{
+    state: T,
+    subscriptions: HashSet<HandlerId>,
+    link: AgentLink<Self>,
+    _mark: PhantomData<(A, C)>,
+}
+
+impl<T, A, C> StateWorker<T, A, C>
+where
+    T: WorkerState,
+    A: Action<T> + 'static,
+    C: Codec,
+{
+    fn reply(&self) -> Reply {
+        match C::encode(&self.state) {
+            Ok(bytes) => Reply::State(Packet(bytes)),
+            Err(Error::Encode(reason)) => Reply::Error(reason),
+            Err(error) => Reply::Error(error.to_string()),
+        }
+    }
+}
+
+impl<T, A, C> Agent for StateWorker<T, A, C>
+where
+    T: WorkerState,
+    A: Action<T> + 'static,
+    C: Codec,
+{
+    type Message = ();
+    type Reach = Public<Self>;
+    type Input = Packet;
+    type Output = Reply;
+
+    fn create(link: AgentLink<Self>) -> Self {
+        Self {
+            state: Default::default(),
+            subscriptions: Default::default(),
+            link,
+            _mark: PhantomData,
+        }
+    }
+
+    fn update(&mut self, _msg: Self::Message) {}
+
+    fn handle_input(&mut self, msg: Self::Input, _who: HandlerId) {
+        let action = match C::decode::<A>(&msg.0) {
+            Some(action) => action,
+            None => return,
+        };
+        action.apply(&mut self.state);
+
+        // Encode once for all subscribers.
+        let reply = self.reply();
+        for who in self.subscriptions.iter().cloned() {
+            self.link.respond(who, reply.clone());
+        }
+    }
+
+    fn connected(&mut self, who: HandlerId) {
+        self.subscriptions.insert(who);
+        self.link.respond(who, self.reply());
+    }
+
+    fn disconnected(&mut self, who: HandlerId) {
+        self.subscriptions.remove(&who);
+    }
+
+    fn name_of_resource() -> &'static str {
+        T::name_of_resource()
+    }
+}
+
+/// Typed bridge to a `StateWorker`. Takes care of encoding actions and decoding state.
+pub struct WorkerBridge<T, A, C = Bincode>
+where
+    T: WorkerState,
+    A: Action<T> + 'static,
+    C: Codec,
+{
+    bridge: Box<dyn Bridge<StateWorker<T, A, C>>>,
+    on_error: Callback<Error>,
+}
+
+impl<T, A, C> WorkerBridge<T, A, C>
+where
+    T: WorkerState,
+    A: Action<T> + 'static,
+    C: Codec,
+{
+    /// Connect to the worker. `callback` receives current state on connect, and after every
+    /// action. Messages that can't be encoded are logged as warnings.
+    pub fn new(callback: Callback<Rc<T>>) -> Self {
+        let warn = Callback::from(|error: Error| telemetry::warn(&format!("yew-state: {}", error)));
+        Self::with_errors(callback, warn)
+    }
+
+    /// Like `new`, passing messages that can't be encoded (actions here, or state in the worker)
+    /// to `on_error` instead.
+    pub fn with_errors(callback: Callback<Rc<T>>, on_error: Callback<Error>) -> Self {
+        let errors = on_error.clone();
+        let callback = Callback::from(move |reply: Reply| match reply {
+            Reply::State(packet) => {
+                if let Some(state) = C::decode(&packet.0) {
+                    callback.emit(Rc::new(state));
+                }
+            }
+            Reply::Error(reason) => errors.emit(Error::Encode(reason)),
+        });
+
+        Self {
+            bridge: StateWorker::bridge(callback),
+            on_error,
+        }
+    }
+
+    /// Send an action to be applied to state, unless it can't be encoded.
+    pub fn send(&mut self, action: A) {
+        match C::encode(&action) {
+            Ok(bytes) => self.bridge.send(Packet(bytes)),
+            Err(error) => self.on_error.emit(error),
+        }
+    }
+}
@@ -0,0 +1,1227 @@
+//! Shared state agents, exercised through `Dispatch`.
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use yew::Callback;
+use yew_state::dispatch::{self, In};
+use yew_state::{
+    authorize, batch, path, scope, Diff, DiffHandle, Dispatch, Error, Priority, SharedHandle,
+};
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct Count(u32);
+
+impl Diff for Count {
+    type Patch = u32;
+
+    fn diff(&self, new: &Self) -> Option<u32> {
+        if self == new {
+            None
+        } else {
+            Some(new.0)
+        }
+    }
+
+    fn patch(&mut self, patch: &u32) {
+        self.0 = *patch;
+    }
+}
+
+#[derive(Clone, Default, PartialEq)]
+struct Flag(bool);
+
+type Log<T> = Rc<RefCell<Vec<T>>>;
+
+/// Callback recording every state it receives.
+fn record(log: &Log<u32>) -> Callback<Rc<Count>> {
+    let log = log.clone();
+    Callback::from(move |count: Rc<Count>| log.borrow_mut().push(count.0))
+}
+
+type Latest<T> = Rc<RefCell<Rc<T>>>;
+
+/// Callback keeping the latest state it receives in `latest`.
+// Only tests of optional features use it.
+#[allow(dead_code)]
+fn keep<T: 'static>(latest: &Latest<T>) -> Callback<Rc<T>> {
+    let latest = latest.clone();
+    Callback::from(move |state| *latest.borrow_mut() = state)
+}
+
+#[test]
+fn receives_state_on_connect_and_change() {
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&log));
+    dispatch.reduce(|count| count.0 += 1);
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![0, 1, 2]);
+}
+
+#[test]
+fn shares_state_within_scope() {
+    let log = Log::default();
+    let mut writer = Dispatch::<SharedHandle<Count>>::connect();
+    writer.reduce(|count| count.0 = 5);
+    let _reader = Dispatch::<SharedHandle<Count>>::new(record(&log));
+
+    assert_eq!(*log.borrow(), vec![5]);
+}
+
+#[test]
+fn scopes_are_independent() {
+    struct Other;
+
+    let log = Log::default();
+    let mut writer = Dispatch::<SharedHandle<Count>>::connect();
+    let _reader = Dispatch::<SharedHandle<Count>, Other>::new(record(&log));
+    writer.reduce(|count| count.0 = 5);
+
+    assert_eq!(*log.borrow(), vec![0]);
+}
+
+#[test]
+fn child_scope_inherits_parent_state() {
+    struct Parent;
+    struct Child;
+    scope::inherit::<Child, Parent>();
+
+    let log = Log::default();
+    let mut parent = Dispatch::<SharedHandle<Count>, Parent>::connect();
+    parent.reduce(|count| count.0 = 3);
+    let mut child = Dispatch::<SharedHandle<Count>, Child>::new(record(&log));
+    child.reduce(|count| count.0 += 1);
+
+    let parent_log = Log::default();
+    let _parent = Dispatch::<SharedHandle<Count>, Parent>::new(record(&parent_log));
+    assert_eq!(*log.borrow(), vec![3, 4]);
+    assert_eq!(*parent_log.borrow(), vec![3]);
+}
+
+#[test]
+fn diff_handle_patches_local_state() {
+    let log = Log::default();
+    let mut dispatch = Dispatch::<DiffHandle<Count>>::new(record(&log));
+    dispatch.reduce(|count| count.0 = 7);
+    // Unchanged state isn't sent.
+    dispatch.reduce(|count| count.0 = 7);
+    dispatch.reduce(|count| count.0 = 9);
+
+    assert_eq!(*log.borrow(), vec![0, 7, 9]);
+}
+
+#[test]
+fn notifies_in_priority_order() {
+    let log: Log<&str> = Default::default();
+    let low = log.clone();
+    let _low = Dispatch::<SharedHandle<Count>>::with_priority(
+        Priority::Low,
+        Callback::from(move |_| low.borrow_mut().push("low")),
+    );
+    let normal = log.clone();
+    let _normal = Dispatch::<SharedHandle<Count>>::new(Callback::from(move |_| {
+        normal.borrow_mut().push("normal")
+    }));
+    let high = log.clone();
+    let mut high = Dispatch::<SharedHandle<Count>>::with_priority(
+        Priority::High,
+        Callback::from(move |_| high.borrow_mut().push("high")),
+    );
+    log.borrow_mut().clear();
+
+    high.reduce(|count| count.0 += 1);
+    assert_eq!(*log.borrow(), vec!["high", "normal", "low"]);
+}
+
+#[test]
+fn atomic_batch_notifies_after_every_change() {
+    struct Totals;
+
+    let log: Log<&str> = Default::default();
+    let notified = log.clone();
+    let mut items = Dispatch::<SharedHandle<Count>>::new(Callback::from(move |_| {
+        notified.borrow_mut().push("notify items")
+    }));
+    let notified = log.clone();
+    let mut totals = Dispatch::<SharedHandle<Count>, Totals>::new(Callback::from(move |_| {
+        notified.borrow_mut().push("notify totals")
+    }));
+    log.borrow_mut().clear();
+
+    let (applied_items, applied_totals) = (log.clone(), log.clone());
+    batch::atomic(|batch| {
+        batch.dispatch(&mut items, move |count| {
+            applied_items.borrow_mut().push("apply items");
+            count.0 += 1;
+        });
+        batch.dispatch(&mut totals, move |count| {
+            applied_totals.borrow_mut().push("apply totals");
+            count.0 += 1;
+        });
+    });
+
+    assert_eq!(
+        *log.borrow(),
+        vec![
+            "apply items",
+            "apply totals",
+            "notify items",
+            "notify totals"
+        ]
+    );
+}
+
+#[test]
+fn atomic_batch_notifies_even_if_a_change_is_dropped() {
+    struct Totals;
+
+    let log: Log<&str> = Default::default();
+    let notified = log.clone();
+    let mut items = Dispatch::<SharedHandle<Count>>::new(Callback::from(move |_| {
+        notified.borrow_mut().push("notify items")
+    }));
+    // Standalone state drops unauthorized changes without handling them.
+    yew_state::config().standalone(true);
+    authorize::authorize_in::<SharedHandle<Count>, Totals>(|_, _| false);
+    let mut totals = Dispatch::<SharedHandle<Count>, Totals>::connect();
+    log.borrow_mut().clear();
+
+    batch::atomic(|batch| {
+        batch.dispatch(&mut items, |count| count.0 += 1);
+        batch.dispatch(&mut totals, |count| count.0 += 1);
+    });
+
+    assert_eq!(*log.borrow(), vec!["notify items"]);
+}
+
+#[test]
+fn get_many_reads_every_scope_once() {
+    struct Other;
+
+    let mut first = Dispatch::<SharedHandle<Count>>::connect();
+    first.reduce(|count| count.0 = 1);
+    let mut second = Dispatch::<SharedHandle<Count>, Other>::connect();
+    second.reduce(|count| count.0 = 2);
+
+    let log: Log<(u32, u32)> = Default::default();
+    let states = log.clone();
+    let _pending = dispatch::get_many::<(In<SharedHandle<Count>>, In<SharedHandle<Count>, Other>)>(
+        Callback::from(move |(first, second): (Rc<Count>, Rc<Count>)| {
+            states.borrow_mut().push((first.0, second.0))
+        }),
+    );
+    first.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![(1, 2)]);
+}
+
+/// Poll `future` once, returning its output if it's ready.
+fn poll_once<F: Future + Unpin>(future: &mut F) -> Option<F::Output> {
+    fn noop(_: *const ()) {}
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+    match Pin::new(future).poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+#[test]
+fn next_state_resolves_on_change() {
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::connect();
+    let mut next = dispatch.next_state();
+    assert!(poll_once(&mut next).is_none());
+
+    dispatch.reduce(|count| count.0 = 4);
+    assert_eq!(poll_once(&mut next).map(|count| count.0), Some(4));
+}
+
+#[test]
+fn await_change_resolves_when_state_matches() {
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::connect();
+    dispatch.reduce(|count| count.0 = 1);
+    let mut already = dispatch.await_change(|count| count.0 == 1);
+    let mut later = dispatch.await_change(|count| count.0 == 3);
+    assert_eq!(poll_once(&mut already).map(|count| count.0), Some(1));
+
+    dispatch.reduce(|count| count.0 += 1);
+    assert!(poll_once(&mut later).is_none());
+    dispatch.reduce(|count| count.0 += 1);
+    assert_eq!(poll_once(&mut later).map(|count| count.0), Some(3));
+}
+
+#[test]
+fn observers_unsubscribe_when_guard_drops() {
+    let log = Log::default();
+    let observed = log.clone();
+    let guard =
+        yew_state::observe::<SharedHandle<Count>>(move |count| observed.borrow_mut().push(count.0));
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::connect();
+    dispatch.reduce(|count| count.0 = 1);
+    drop(guard);
+    dispatch.reduce(|count| count.0 = 2);
+
+    assert_eq!(*log.borrow(), vec![0, 1]);
+}
+
+#[test]
+fn unauthorized_changes_are_not_applied() {
+    struct Guarded;
+
+    authorize::authorize_in::<SharedHandle<Count>, Guarded>(|count, label| {
+        label != Some("reset") && count.0 < 2
+    });
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Guarded>::new(record(&log));
+    dispatch.reduce(|count| count.0 += 1);
+    dispatch.reduce_labeled("reset", |count| count.0 = 0);
+    dispatch.reduce_labeled("increment", |count| count.0 += 1);
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![0, 1, 2]);
+}
+
+#[test]
+fn failed_changes_leave_nothing_behind() {
+    let log = Log::default();
+    let mut dispatch = Dispatch::<DiffHandle<Count>>::new(record(&log));
+    dispatch.try_reduce(|count| {
+        count.0 = 100;
+        Err(Error::InvalidFile("not a count".into()))
+    });
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![0, 1]);
+}
+
+#[test]
+fn paths_set_deeply_nested_fields() {
+    #[derive(Clone, Default, PartialEq)]
+    struct Notifications {
+        email: bool,
+        push: bool,
+    }
+
+    #[derive(Clone, Default, PartialEq)]
+    struct Settings {
+        notifications: Notifications,
+    }
+
+    #[derive(Clone, Default, PartialEq)]
+    struct App {
+        settings: Settings,
+    }
+
+    struct Paths;
+
+    let email = path!(App.settings.notifications.email);
+    let push = path!(App.settings.notifications.push);
+    assert_eq!(email.name(), "settings.notifications.email");
+
+    let labels = Log::<String>::default();
+    authorize::authorize_in::<SharedHandle<App>, Paths>({
+        let labels = labels.clone();
+        move |_, label| {
+            labels.borrow_mut().extend(label.map(str::to_string));
+            true
+        }
+    });
+    let states = Rc::new(RefCell::new(Vec::<Rc<App>>::new()));
+    let mut dispatch = Dispatch::<SharedHandle<App>, Paths>::new({
+        let states = states.clone();
+        Callback::from(move |app| states.borrow_mut().push(app))
+    });
+    dispatch.set(email, true);
+
+    let states = states.borrow();
+    assert!(states[1].settings.notifications.email);
+    assert!(email.changed(&states[0], &states[1]));
+    assert!(!push.changed(&states[0], &states[1]));
+    assert_eq!(*labels.borrow(), vec!["settings.notifications.email"]);
+}
+
+#[test]
+fn path_helpers_change_options_and_lists() {
+    #[derive(Clone, Default, PartialEq, Debug)]
+    struct Todos {
+        items: Vec<(u32, bool)>,
+        selected: Option<u32>,
+        dark: bool,
+    }
+
+    let items = path!(Todos.items);
+    let selected = path!(Todos.selected);
+    let mut todos = Todos::default();
+    for id in 0..4 {
+        items.push((id, false))(&mut todos);
+    }
+    items.update_where(|(id, _)| id % 2 == 0, |(_, done)| *done = true)(&mut todos);
+    items.retain(|(id, _)| *id != 3)(&mut todos);
+    selected.set_some(2)(&mut todos);
+    path!(Todos.dark).toggle()(&mut todos);
+
+    assert_eq!(todos.items, vec![(0, true), (1, false), (2, true)]);
+    assert_eq!(todos.selected, Some(2));
+    assert!(todos.dark);
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut dispatch = Dispatch::<SharedHandle<Todos>>::new({
+        let log = log.clone();
+        Callback::from(move |todos: Rc<Todos>| log.borrow_mut().push(todos.selected))
+    });
+    dispatch.reduce(selected.set_some(1));
+    dispatch.reduce(selected.take());
+
+    assert_eq!(*log.borrow(), vec![None, Some(1), None]);
+}
+
+#[cfg(feature = "rate-limit")]
+#[test]
+fn changes_over_the_rate_limit_are_dropped() {
+    use yew_state::rate_limit::{self, RateLimit};
+
+    struct Limited;
+
+    rate_limit::limit_in::<SharedHandle<Count>, Limited>(RateLimit::new(2, 0.001).per_label());
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Limited>::new(record(&log));
+    for _ in 0..3 {
+        dispatch.reduce_labeled("render", |count| count.0 += 1);
+    }
+    dispatch.reduce_labeled("click", |count| count.0 += 10);
+
+    assert_eq!(*log.borrow(), vec![0, 1, 2, 12]);
+}
+
+#[cfg(feature = "rate-limit")]
+#[test]
+fn rate_limits_refill_as_time_passes() {
+    use std::time::Duration;
+    use yew_state::clock::TestClock;
+    use yew_state::rate_limit::{self, RateLimit};
+
+    struct Refilled;
+
+    let clock = TestClock::new(0.0);
+    yew_state::config().clock(clock.clone());
+    rate_limit::limit_in::<SharedHandle<Count>, Refilled>(RateLimit::new(1, 1.0));
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Refilled>::new(record(&log));
+    dispatch.reduce(|count| count.0 += 1);
+    dispatch.reduce(|count| count.0 += 1);
+    clock.advance(Duration::from_secs(1));
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![0, 1, 2]);
+}
+
+#[cfg(feature = "rate-limit")]
+#[test]
+fn deferred_changes_are_dropped_once_the_limit_drops_them() {
+    use std::time::Duration;
+    use yew_state::clock::TestClock;
+    use yew_state::rate_limit::{self, RateLimit};
+
+    struct Deferred;
+
+    let clock = TestClock::new(0.0);
+    yew_state::config().clock(clock.clone());
+    rate_limit::limit_in::<SharedHandle<Count>, Deferred>(RateLimit::new(1, 1.0).defer());
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Deferred>::new(record(&log));
+    for _ in 0..3 {
+        dispatch.reduce(|count| count.0 += 1);
+    }
+    // Room for one more change, with the rest over the limit dropped.
+    rate_limit::limit_in::<SharedHandle<Count>, Deferred>(RateLimit::new(1, 0.001));
+    clock.advance(Duration::from_secs(1));
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![0, 1, 2]);
+}
+
+#[cfg(feature = "rate-limit")]
+#[test]
+fn batched_changes_are_rate_limited() {
+    use yew_state::rate_limit::{self, RateLimit};
+
+    struct Limited;
+    struct Totals;
+
+    rate_limit::limit_in::<SharedHandle<Count>, Limited>(RateLimit::new(1, 0.001));
+    let log = Log::default();
+    let mut limited = Dispatch::<SharedHandle<Count>, Limited>::new(record(&log));
+    let totals_log = Log::default();
+    let mut totals = Dispatch::<SharedHandle<Count>, Totals>::new(record(&totals_log));
+    for _ in 0..2 {
+        batch::atomic(|batch| {
+            batch.dispatch(&mut limited, |count| count.0 += 1);
+            batch.dispatch(&mut totals, |count| count.0 += 1);
+        });
+    }
+
+    assert_eq!(*log.borrow(), vec![0, 1]);
+    assert_eq!(*totals_log.borrow(), vec![0, 1, 2]);
+}
+
+/// Dispatch shared between a test and the callbacks that use it.
+type Shared<H, SCOPE> = Rc<RefCell<Option<Dispatch<H, SCOPE>>>>;
+
+#[test]
+fn changes_made_while_notifying_wait_for_every_subscriber() {
+    struct Nested;
+
+    let log: Log<String> = Default::default();
+    let first: Shared<SharedHandle<Count>, Nested> = Default::default();
+    let (notified, reducer) = (log.clone(), first.clone());
+    let dispatch = Dispatch::new(Callback::from(move |count: Rc<Count>| {
+        notified.borrow_mut().push(format!("first {}", count.0));
+        if count.0 == 1 {
+            if let Some(dispatch) = &mut *reducer.borrow_mut() {
+                dispatch.reduce(|count| count.0 = 2);
+            }
+        }
+    }));
+    *first.borrow_mut() = Some(dispatch);
+    let notified = log.clone();
+    let mut second =
+        Dispatch::<SharedHandle<Count>, Nested>::new(Callback::from(move |count: Rc<Count>| {
+            notified.borrow_mut().push(format!("second {}", count.0))
+        }));
+    log.borrow_mut().clear();
+    second.reduce(|count| count.0 = 1);
+
+    // Subscribers of the same priority are notified in no particular order.
+    let mut log = log.borrow().clone();
+    log[..2].sort();
+    log[2..].sort();
+    assert_eq!(log, vec!["first 1", "second 1", "first 2", "second 2"]);
+}
+
+#[test]
+fn changes_made_by_a_reducer_apply_after_it() {
+    struct Reentrant;
+
+    let log = Log::default();
+    let shared: Shared<SharedHandle<Count>, Reentrant> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Reentrant>::new(record(&log));
+    *shared.borrow_mut() = Some(Dispatch::connect());
+
+    let nested = shared.clone();
+    dispatch.reduce(move |count| {
+        if let Some(dispatch) = &mut *nested.borrow_mut() {
+            // Sees this change applied, not the state it was made from.
+            dispatch.reduce(|count| count.0 *= 10);
+        }
+        count.0 = 1;
+    });
+
+    assert_eq!(*log.borrow(), vec![0, 1, 10]);
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn state_matches_its_snapshot() {
+    use yew_state::assert_state_snapshot;
+
+    #[derive(Clone, Default, serde::Serialize)]
+    struct Cart {
+        items: Vec<String>,
+        coupon: Option<String>,
+    }
+
+    let mut dispatch = Dispatch::<SharedHandle<Cart>>::connect();
+    dispatch.reduce(|cart| cart.items.push("tea".into()));
+    dispatch.reduce(|cart| cart.items.push("milk".into()));
+    dispatch.reduce(|cart| cart.coupon = Some("HALF".into()));
+
+    assert_state_snapshot!(dispatch, "cart_with_coupon");
+}
+
+#[cfg(feature = "session-recorder")]
+#[test]
+fn sessions_play_back_against_shared_state() {
+    use yew_state::session::Session;
+    use yew_state::worker::Action;
+
+    #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+    struct Total(u32);
+
+    #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    struct Add(u32);
+
+    impl Action<Total> for Add {
+        fn apply(self, total: &mut Total) {
+            total.0 += self.0;
+        }
+    }
+
+    let log = Log::default();
+    let _reader = Dispatch::<SharedHandle<Total>>::new(Callback::from({
+        let log = log.clone();
+        move |total: Rc<Total>| log.borrow_mut().push(total.0)
+    }));
+    let session = Session {
+        initial: Total(10),
+        actions: vec![Add(1), Add(2)],
+        times: vec![0.0, 5_000.0],
+        errors: vec![],
+    };
+    let replay = session.play::<SharedHandle<Total>>(f64::INFINITY);
+
+    assert!(replay.is_finished());
+    assert_eq!(*log.borrow(), vec![0, 10, 11, 13]);
+}
+
+#[test]
+fn debug_output_cuts_off_large_state() {
+    use yew_state::handle::Handle;
+
+    let mut handle = SharedHandle::<Vec<u32>>::default();
+    handle.set_local_state(Rc::new((0..10_000).collect()));
+    let output = format!("{:?}", handle);
+
+    assert!(output.starts_with("StateHandle { handler: \"yew_state::handler::SharedHandler<"));
+    assert!(output.len() < 500, "{}", output);
+    let dispatch = Dispatch::<SharedHandle<Count>>::connect();
+    assert!(format!("{:?}", dispatch).contains("agent::Count"));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_are_sent_to_the_sink() {
+    use yew_state::metrics::{Counter, Gauge, MetricsSink};
+
+    struct Measured;
+
+    #[derive(Clone, Default)]
+    struct Sink(Log<String>);
+
+    impl MetricsSink for Sink {
+        fn increment(&self, counter: Counter, scope: &str) {
+            if scope.ends_with("Measured") {
+                self.0.borrow_mut().push(counter.name().to_string());
+            }
+        }
+
+        fn gauge(&self, gauge: Gauge, scope: &str, value: f64) {
+            if scope.ends_with("Measured") && gauge == Gauge::Subscribers {
+                self.0
+                    .borrow_mut()
+                    .push(format!("{} {}", gauge.name(), value));
+            }
+        }
+    }
+
+    let sink = Sink::default();
+    yew_state::config().metrics(sink.clone());
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Measured>::connect();
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(
+        *sink.0.borrow(),
+        vec!["yew_state.subscribers 1", "yew_state.dispatches"]
+    );
+}
+
+#[test]
+fn services_are_shared_without_cloning() {
+    use yew_state::service::{self, Service};
+    use yew_state::ServiceHandle;
+
+    // Neither `Clone` nor `Default`.
+    struct Api {
+        base: &'static str,
+    }
+
+    let seen: Log<Option<Rc<Api>>> = Default::default();
+    let record = seen.clone();
+    let _reader =
+        Dispatch::<ServiceHandle<Api>>::new(Callback::from(move |api: Rc<Service<Api>>| {
+            record.borrow_mut().push(api.shared())
+        }));
+    let _provided = service::provide(Api {
+        base: "https://api.example.com",
+    });
+
+    let seen = seen.borrow();
+    assert!(seen[0].is_none());
+    assert_eq!(
+        seen[1].as_ref().map(|api| api.base),
+        Some("https://api.example.com")
+    );
+}
+
+#[test]
+fn restarting_the_handler_keeps_subscribers() {
+    struct Restarted;
+
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Restarted>::new(record(&log));
+    dispatch.reduce(|count| count.0 = 7);
+    dispatch.restart_handler();
+    dispatch.reduce(|count| count.0 += 1);
+
+    assert_eq!(*log.borrow(), vec![0, 7, 0, 1]);
+}
+
+#[test]
+fn restarting_the_handler_is_authorized_like_changes() {
+    struct Locked;
+
+    authorize::authorize_in::<SharedHandle<Count>, Locked>(|_, label| label.is_some());
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Locked>::new(record(&log));
+    dispatch.reduce_labeled("set", |count| count.0 = 7);
+    dispatch.restart_handler();
+
+    assert_eq!(*log.borrow(), vec![0, 7]);
+}
+
+#[test]
+fn notify_policy_decides_which_changes_are_sent() {
+    use yew_state::notify::{self, NotifyPolicy};
+
+    struct OnChange;
+    struct Even;
+
+    notify::policy_in::<SharedHandle<Count>, OnChange>(NotifyPolicy::OnChange);
+    notify::policy_in::<SharedHandle<Count>, Even>(NotifyPolicy::Custom(|_, new| new.0 % 2 == 0));
+    let (changed, even) = (Log::default(), Log::default());
+    let mut on_change = Dispatch::<SharedHandle<Count>, OnChange>::new(record(&changed));
+    let mut on_even = Dispatch::<SharedHandle<Count>, Even>::new(record(&even));
+    for value in [1, 1, 2, 3, 4] {
+        on_change.reduce(move |count| count.0 = value);
+        on_even.reduce(move |count| count.0 = value);
+    }
+
+    assert_eq!(*changed.borrow(), vec![0, 1, 2, 3, 4]);
+    assert_eq!(*even.borrow(), vec![0, 2, 4]);
+}
+
+#[test]
+fn watchers_see_changes_the_notify_policy_holds_back() {
+    use yew_state::notify::{self, NotifyPolicy};
+
+    struct Even;
+
+    notify::policy_in::<SharedHandle<Count>, Even>(NotifyPolicy::Custom(|_, new| new.0 % 2 == 0));
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Even>::new(record(&log));
+    let mut odd = dispatch.await_change(|count| count.0 % 2 == 1);
+    dispatch.reduce(|count| count.0 = 1);
+
+    assert_eq!(poll_once(&mut odd).map(|count| count.0), Some(1));
+    assert_eq!(*log.borrow(), vec![0]);
+}
+
+#[test]
+fn patches_held_back_by_the_notify_policy_are_caught_up_on() {
+    use yew_state::notify::{self, NotifyPolicy};
+
+    /// Patched by how much it changed, so skipping a patch leaves subscribers behind.
+    #[derive(Clone, Default, PartialEq)]
+    struct Total(u32);
+
+    enum Change {
+        Grew(u32),
+        Shrank(u32),
+    }
+
+    impl Diff for Total {
+        type Patch = Change;
+
+        fn diff(&self, new: &Self) -> Option<Change> {
+            match new.0.checked_sub(self.0) {
+                Some(0) => None,
+                Some(grown) => Some(Change::Grew(grown)),
+                None => Some(Change::Shrank(self.0 - new.0)),
+            }
+        }
+
+        fn patch(&mut self, change: &Change) {
+            match change {
+                Change::Grew(grown) => self.0 += grown,
+                Change::Shrank(shrunk) => self.0 -= shrunk,
+            }
+        }
+    }
+
+    notify::policy::<DiffHandle<Total>>(NotifyPolicy::Custom(|_, new| new.0 % 2 == 0));
+    let log = Log::default();
+    let recorded = log.clone();
+    let mut dispatch =
+        Dispatch::<DiffHandle<Total>>::new(Callback::from(move |total: Rc<Total>| {
+            recorded.borrow_mut().push(total.0)
+        }));
+    for _ in 0..4 {
+        dispatch.reduce(|total| total.0 += 1);
+    }
+    dispatch.reduce(|total| total.0 += 2);
+    assert_eq!(*log.borrow(), vec![0, 2, 4, 6]);
+
+    // Shrinking while a patch is held back.
+    dispatch.reduce(|total| total.0 -= 1);
+    dispatch.reduce(|total| total.0 -= 3);
+    assert_eq!(*log.borrow(), vec![0, 2, 4, 6, 2]);
+}
+
+#[cfg(feature = "shortcuts")]
+#[test]
+fn shortcuts_trigger_the_action_bound_last() {
+    use yew_state::handle::ShortcutHandle;
+    use yew_state::shortcuts::{self, Shortcuts};
+
+    assert_eq!(shortcuts::chord("Shift + Ctrl+S"), "ctrl+shift+s");
+
+    let latest: Latest<Shortcuts> = Default::default();
+    let mut dispatch = Dispatch::<ShortcutHandle>::new(keep(&latest));
+    dispatch.reduce(|shortcuts| shortcuts.bind("ctrl+s", "save"));
+    dispatch.reduce(|shortcuts| shortcuts.bind("control+s", "save-draft"));
+    dispatch.reduce(|shortcuts| {
+        shortcuts.trigger("ctrl+s");
+    });
+
+    let mut seen = 0;
+    assert_eq!(latest.borrow().take_new(&mut seen), Some("save-draft"));
+    assert_eq!(latest.borrow().take_new(&mut seen), None);
+    dispatch.reduce(|shortcuts| shortcuts.unbind("ctrl+s", "save-draft"));
+    assert_eq!(latest.borrow().action("ctrl+s"), Some("save"));
+}
+
+#[cfg(feature = "drag")]
+#[test]
+fn drops_reach_only_their_target() {
+    use yew_state::drag::Drag;
+    use yew_state::handle::DragHandle;
+
+    let latest: Latest<Drag<u32>> = Default::default();
+    let mut dispatch = Dispatch::<DragHandle<u32>>::new(keep(&latest));
+    dispatch.reduce(|drag| drag.start("backlog", 7));
+    dispatch.reduce(|drag| drag.hover("doing"));
+    dispatch.reduce(|drag| drag.hover("done"));
+    dispatch.reduce(|drag| drag.leave("doing"));
+    assert!(latest.borrow().is_hovered("done"));
+
+    dispatch.reduce(|drag| {
+        drag.drop_on("done");
+    });
+    let drag = latest.borrow().clone();
+    assert!(drag.dragging().is_none());
+    assert_eq!(drag.hovered(), None);
+
+    let (mut doing, mut done) = (0, 0);
+    assert!(drag.take_dropped("doing", &mut doing).is_none());
+    let dropped = drag.take_dropped("done", &mut done).unwrap();
+    assert_eq!((dropped.payload, dropped.source.as_str()), (7, "backlog"));
+    assert!(drag.take_dropped("done", &mut done).is_none());
+
+    // Nothing is dragged, so nothing drops.
+    dispatch.reduce(|drag| assert!(!drag.drop_on("done")));
+}
+
+#[cfg(feature = "announcer")]
+#[test]
+fn announcements_keep_the_last_few_of_each_politeness() {
+    use yew_state::announce::{self, Announcements, Politeness};
+    use yew_state::handle::AnnouncerHandle;
+
+    let latest: Latest<Announcements> = Default::default();
+    let _dispatch = Dispatch::<AnnouncerHandle>::new(keep(&latest));
+    announce::assertive("Payment failed");
+    for _ in 0..7 {
+        announce::polite("Item added to cart");
+    }
+
+    let queued = latest.borrow().clone();
+    let polite: Vec<u64> = queued.iter(Politeness::Polite).map(|a| a.id).collect();
+    assert_eq!(polite, vec![4, 5, 6, 7, 8]);
+    let assertive: Vec<&str> = queued
+        .iter(Politeness::Assertive)
+        .map(|a| a.message.as_str())
+        .collect();
+    assert_eq!(assertive, vec!["Payment failed"]);
+}
+
+#[cfg(feature = "breadcrumbs")]
+#[test]
+fn pushing_a_crumb_on_the_trail_goes_back_to_it() {
+    use yew_state::breadcrumbs::Trail;
+    use yew_state::handle::BreadcrumbHandle;
+
+    let latest: Latest<Trail> = Default::default();
+    let mut dispatch = Dispatch::<BreadcrumbHandle>::new(keep(&latest));
+    dispatch.reduce(|trail| trail.reset("Home", "/"));
+    dispatch.reduce(|trail| trail.push("Orders", "/orders"));
+    dispatch.reduce(|trail| trail.push("Order 7", "/orders/7"));
+    dispatch.reduce(|trail| trail.push("All orders", "/orders"));
+
+    let trail = latest.borrow().clone();
+    let labels: Vec<&str> = trail.iter().map(|crumb| crumb.label.as_str()).collect();
+    assert_eq!(labels, vec!["Home", "All orders"]);
+    assert_eq!(
+        trail.current().map(|crumb| crumb.href.as_str()),
+        Some("/orders")
+    );
+}
+
+#[cfg(feature = "undo")]
+#[test]
+fn undoable_changes_commit_only_if_not_undone() {
+    use std::time::Duration;
+    use yew_state::handle::UndoHandle;
+    use yew_state::undo::Undoables;
+
+    let listed: Latest<Undoables> = Default::default();
+    let _toasts = Dispatch::<UndoHandle>::new(keep(&listed));
+    let latest: Latest<Count> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(keep(&latest));
+    let committed = Rc::new(RefCell::new(Vec::new()));
+
+    let log = committed.clone();
+    let first = dispatch.reduce_undoable(
+        "Added one",
+        |count| {
+            count.0 += 1;
+            |count: &mut Count| count.0 -= 1
+        },
+        Duration::from_secs(5),
+        move || log.borrow_mut().push("first"),
+    );
+    let log = committed.clone();
+    let second = dispatch.reduce_undoable(
+        "Added ten",
+        |count| {
+            count.0 += 10;
+            |count: &mut Count| count.0 -= 10
+        },
+        Duration::from_secs(5),
+        move || log.borrow_mut().push("second"),
+    );
+    assert_eq!(latest.borrow().0, 11);
+    assert_eq!(listed.borrow().len(), 2);
+
+    // Only the first change is undone, keeping the second and its commit.
+    first.undo();
+    assert_eq!(latest.borrow().0, 10);
+    assert_eq!(listed.borrow().len(), 1);
+    second.commit();
+    assert_eq!(*committed.borrow(), vec!["second"]);
+    assert!(listed.borrow().is_empty());
+
+    // Already undone.
+    first.commit();
+    assert_eq!(*committed.borrow(), vec!["second"]);
+}
+
+/// Agent pushing into shared state, as one handling a websocket would, and telling its own
+/// subscribers whenever shared state changes.
+struct Socket {
+    link: yew::agent::AgentLink<Self>,
+    dispatch: Dispatch<SharedHandle<Count>>,
+    subscribers: std::collections::HashSet<yew::agent::HandlerId>,
+}
+
+enum SocketMsg {
+    Changed(Rc<Count>),
+}
+
+impl yew::agent::Agent for Socket {
+    type Reach = yew::agent::Context<Self>;
+    type Message = SocketMsg;
+    /// A count received over the socket.
+    type Input = u32;
+    type Output = u32;
+
+    fn create(link: yew::agent::AgentLink<Self>) -> Self {
+        Self {
+            dispatch: Dispatch::from_agent(&link, SocketMsg::Changed),
+            link,
+            subscribers: Default::default(),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) {
+        match msg {
+            SocketMsg::Changed(count) => {
+                for who in &self.subscribers {
+                    self.link.respond(*who, count.0);
+                }
+            }
+        }
+    }
+
+    fn handle_input(&mut self, count: Self::Input, _who: yew::agent::HandlerId) {
+        self.dispatch.reduce(move |state| state.0 = count);
+    }
+
+    fn connected(&mut self, id: yew::agent::HandlerId) {
+        self.subscribers.insert(id);
+    }
+
+    fn disconnected(&mut self, id: yew::agent::HandlerId) {
+        self.subscribers.remove(&id);
+    }
+}
+
+#[test]
+fn agents_push_into_shared_state_and_hear_of_changes() {
+    use yew::agent::Bridged;
+
+    let seen: Log<u32> = Default::default();
+    let _component = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+    let heard: Log<u32> = Default::default();
+    let log = heard.clone();
+    let mut socket = Socket::bridge(Callback::from(move |count| log.borrow_mut().push(count)));
+
+    socket.send(3);
+    Dispatch::<SharedHandle<Count>>::connect().reduce(|count| count.0 += 1);
+
+    assert_eq!(*seen.borrow(), vec![0, 3, 4]);
+    // Initial state arrives while the socket is being created, before anyone is subscribed.
+    assert_eq!(*heard.borrow(), vec![3, 4]);
+}
+
+#[test]
+fn subscribers_connecting_together_get_initial_state_together() {
+    yew_state::config().batch_bootstrap(true);
+    let first: Log<u32> = Default::default();
+    let second: Log<u32> = Default::default();
+    let kept: Rc<RefCell<Vec<Dispatch<SharedHandle<Count>>>>> = Default::default();
+
+    let (log_first, log_second, keep) = (first.clone(), second.clone(), kept.clone());
+    // Connections made while the scheduler runs, as when a page mounts several components.
+    let _page = Dispatch::<SharedHandle<Flag>>::new(Callback::from(move |_: Rc<Flag>| {
+        let mut kept = keep.borrow_mut();
+        kept.push(Dispatch::new(record(&log_first)));
+        kept[0].reduce(|count| count.0 = 1);
+        kept.push(Dispatch::new(record(&log_second)));
+    }));
+    yew_state::config().batch_bootstrap(false);
+
+    assert_eq!(*first.borrow(), vec![1]);
+    assert_eq!(*second.borrow(), vec![1]);
+}
+
+#[test]
+fn diff_handles_only_diff_changes_someone_is_notified_of() {
+    use std::cell::Cell;
+
+    thread_local! {
+        static DIFFS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Clone, Default, PartialEq)]
+    struct Counted(u32);
+
+    impl Diff for Counted {
+        type Patch = u32;
+
+        fn diff(&self, new: &Self) -> Option<u32> {
+            DIFFS.with(|diffs| diffs.set(diffs.get() + 1));
+            Some(new.0).filter(|_| self != new)
+        }
+
+        fn patch(&mut self, patch: &u32) {
+            self.0 = *patch;
+        }
+    }
+
+    yew_state::config().batch_bootstrap(true);
+    let log: Log<u32> = Default::default();
+    let kept: Rc<RefCell<Option<Dispatch<DiffHandle<Counted>>>>> = Default::default();
+    let (recorded, keep) = (log.clone(), kept.clone());
+    let _page = Dispatch::<SharedHandle<Flag>>::new(Callback::from(move |_: Rc<Flag>| {
+        let recorded = recorded.clone();
+        let mut dispatch =
+            Dispatch::<DiffHandle<Counted>>::new(Callback::from(move |counted: Rc<Counted>| {
+                recorded.borrow_mut().push(counted.0)
+            }));
+        // Applied while the only subscriber waits for initial state, so there's nobody to patch.
+        dispatch.reduce(|counted| counted.0 = 1);
+        *keep.borrow_mut() = Some(dispatch);
+    }));
+    yew_state::config().batch_bootstrap(false);
+    assert_eq!(DIFFS.with(Cell::get), 0);
+
+    kept.borrow_mut()
+        .as_mut()
+        .unwrap()
+        .reduce(|counted| counted.0 = 2);
+    assert_eq!(DIFFS.with(Cell::get), 1);
+    assert_eq!(*log.borrow(), vec![1, 2]);
+}
+
+#[test]
+fn bursts_of_coalesced_changes_apply_only_the_last() {
+    use yew_state::coalesce;
+
+    struct Coalesced;
+
+    coalesce::labels_in::<SharedHandle<Count>, Coalesced>(&["resize"]);
+    let seen: Log<u32> = Default::default();
+    let kept: Log<Dispatch<SharedHandle<Count>, Coalesced>> = Default::default();
+
+    let (log, keep) = (seen.clone(), kept.clone());
+    // Changes made while the scheduler runs, as in a burst of events.
+    let _burst = Dispatch::<SharedHandle<Flag>>::new(Callback::from(move |_: Rc<Flag>| {
+        let mut dispatch = Dispatch::<SharedHandle<Count>, Coalesced>::new(record(&log));
+        for width in 1..=3 {
+            dispatch.reduce_labeled("resize", move |count| count.0 = width * 10);
+        }
+        dispatch.reduce_labeled("click", |count| count.0 += 1);
+        for width in 4..=5 {
+            dispatch.reduce_labeled("resize", move |count| count.0 = width * 10);
+        }
+        keep.borrow_mut().push(dispatch);
+    }));
+
+    assert_eq!(*seen.borrow(), vec![0, 30, 31, 50]);
+}
+
+#[test]
+fn awaiting_a_reduction_resolves_with_the_applied_state() {
+    let seen: Log<u32> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+
+    let mut applied = dispatch.reduce_awaitable(|count| count.0 += 2);
+    assert_eq!(
+        poll_once(&mut applied).flatten().map(|count| count.0),
+        Some(2)
+    );
+    assert_eq!(*seen.borrow(), vec![0, 2]);
+}
+
+#[test]
+fn awaiting_a_rejected_reduction_resolves_with_nothing() {
+    struct Locked;
+
+    authorize::authorize_in::<SharedHandle<Count>, Locked>(|count, _| count.0 == 0);
+    let mut dispatch = Dispatch::<SharedHandle<Count>, Locked>::connect();
+    let mut applied = dispatch.reduce_awaitable(|count| count.0 += 1);
+    assert_eq!(
+        poll_once(&mut applied).flatten().map(|count| count.0),
+        Some(1)
+    );
+
+    let mut rejected = dispatch.reduce_awaitable(|count| count.0 += 1);
+    assert!(matches!(poll_once(&mut rejected), Some(None)));
+}
+
+#[test]
+fn retried_changes_with_the_same_key_apply_once() {
+    let seen: Log<u32> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+
+    for _ in 0..3 {
+        dispatch.reduce_idempotent("order-17", |count| count.0 += 1);
+    }
+    dispatch.reduce_idempotent("order-18", |count| count.0 += 10);
+
+    assert_eq!(*seen.borrow(), vec![0, 1, 11]);
+}
+
+#[test]
+fn scheduled_changes_can_be_cancelled_or_rescheduled() {
+    use std::time::Duration;
+
+    let seen: Log<u32> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+
+    dispatch.reduce_after(Duration::ZERO, |count| count.0 += 1);
+    let cancelled = dispatch.reduce_after(Duration::from_secs(5), |count| count.0 += 10);
+    let sooner = dispatch.reduce_after(Duration::from_secs(5), |count| count.0 += 100);
+    cancelled.cancel();
+    // Timers only run in the browser, so apply it now.
+    sooner.reschedule(Duration::ZERO);
+    cancelled.reschedule(Duration::ZERO);
+
+    assert_eq!(*seen.borrow(), vec![0, 1, 101]);
+}
+
+#[test]
+fn recurring_changes_run_until_cancelled() {
+    use std::time::Duration;
+
+    let seen: Log<u32> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+
+    let recurring = dispatch.every(Duration::from_secs(60), |count| count.0 += 1);
+    // Timers only run in the browser, so run it by hand.
+    recurring.run_now();
+    recurring.pause();
+    recurring.run_now();
+    recurring.cancel();
+    recurring.run_now();
+
+    assert_eq!(*seen.borrow(), vec![0, 1, 2]);
+}
+
+#[test]
+fn low_power_mode_leaves_recurring_changes_in_place() {
+    use std::time::Duration;
+    use yew_state::power;
+
+    let seen: Log<u32> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+    let recurring = dispatch.every(Duration::from_secs(60), |count| count.0 += 1);
+
+    yew_state::config().low_power(true);
+    assert!(power::is_low_power());
+    recurring.run_now();
+    yew_state::config().low_power(false);
+    recurring.run_now();
+
+    assert!(!power::is_low_power());
+    assert_eq!(*seen.borrow(), vec![0, 1, 2]);
+}
+
+#[cfg(feature = "wake")]
+#[test]
+fn waking_runs_recurring_changes_that_were_held_up() {
+    use std::time::Duration;
+    use yew_state::wake;
+
+    let seen: Log<u32> = Default::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&seen));
+    let _expiry = dispatch.every(Duration::from_secs(60), |count| count.0 += 1);
+    let paused = dispatch.every(Duration::from_secs(60), |count| count.0 += 10);
+    paused.pause();
+
+    let away: Log<u32> = Default::default();
+    let _guard = {
+        let away = away.clone();
+        wake::on_wake(move |ms| away.borrow_mut().push(ms as u32))
+    };
+    wake::woke(3_600_000.0);
+
+    assert_eq!(*away.borrow(), vec![3_600_000]);
+    assert_eq!(*seen.borrow(), vec![0, 1]);
+}
+
+#[test]
+fn state_is_kept_standalone_if_the_service_cant_be_bridged() {
+    yew_state::config().standalone(true);
+    let log = Log::default();
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::new(record(&log));
+    dispatch.reduce(|count| count.0 += 1);
+    let other = Log::default();
+    let _other = Dispatch::<SharedHandle<Count>>::new(record(&other));
+
+    assert_eq!(*log.borrow(), vec![0, 1]);
+    // Not shared.
+    assert_eq!(*other.borrow(), vec![0]);
+
+    let mut change = dispatch.reduce_awaitable(|count| count.0 += 1);
+    assert_eq!(
+        poll_once(&mut change).flatten().map(|count| count.0),
+        Some(2)
+    );
+}
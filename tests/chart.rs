@@ -0,0 +1,96 @@
+//! `#[derive(Chart)]` and `#[derive(Events)]`.
+#![cfg(feature = "derive")]
+use yew_state::machine::{self, Chart, Events};
+use yew_state::Machine;
+
+#[derive(Clone, Debug, Default, PartialEq, Chart)]
+enum Door {
+    Open,
+    #[default]
+    Closed,
+    Locked,
+}
+
+#[derive(Debug, PartialEq, Events)]
+enum DoorEvent {
+    Push,
+    Pull,
+    Lock,
+}
+
+impl Machine for Door {
+    type Event = DoorEvent;
+
+    fn transition(&self, event: &Self::Event) -> Option<Self> {
+        match (self, event) {
+            (Door::Closed, DoorEvent::Push) => Some(Door::Open),
+            (Door::Open, DoorEvent::Pull) => Some(Door::Closed),
+            (Door::Closed, DoorEvent::Lock) => Some(Door::Locked),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn variants_are_listed() {
+    assert_eq!(Door::states(), vec![Door::Open, Door::Closed, Door::Locked]);
+    assert_eq!(
+        DoorEvent::all(),
+        vec![DoorEvent::Push, DoorEvent::Pull, DoorEvent::Lock]
+    );
+}
+
+#[test]
+fn derived_charts_export_transitions() {
+    let dot = machine::to_dot::<Door>();
+    assert!(dot.contains(r#""Closed" [shape=doublecircle];"#));
+    assert!(dot.contains(r#""Closed" -> "Open" [label="Push"];"#));
+    assert!(dot.contains(r#""Closed" -> "Locked" [label="Lock"];"#));
+    assert!(!dot.contains(r#""Locked" ->"#));
+
+    let xstate: serde_json::Value =
+        serde_json::from_str(&machine::to_xstate::<Door>("door")).unwrap();
+    assert_eq!(xstate["initial"], "Closed");
+    assert_eq!(xstate["states"]["Open"]["on"]["Pull"], "Closed");
+}
+
+/// Chart that forgets to list one of its states.
+#[derive(Clone, Debug, Default, PartialEq)]
+enum Light {
+    #[default]
+    Off,
+    On,
+    Broken,
+}
+
+impl Machine for Light {
+    type Event = DoorEvent;
+
+    fn transition(&self, event: &Self::Event) -> Option<Self> {
+        match (self, event) {
+            (Light::Off, DoorEvent::Push) => Some(Light::On),
+            (Light::On, DoorEvent::Pull) => Some(Light::Off),
+            (Light::On, DoorEvent::Lock) => Some(Light::Broken),
+            _ => None,
+        }
+    }
+}
+
+impl Chart for Light {
+    fn states() -> Vec<Self> {
+        vec![Light::Off, Light::On]
+    }
+
+    fn events() -> Vec<DoorEvent> {
+        DoorEvent::all()
+    }
+}
+
+#[test]
+fn transitions_to_undeclared_states_are_left_out() {
+    let xstate: serde_json::Value =
+        serde_json::from_str(&machine::to_xstate::<Light>("light")).unwrap();
+    assert_eq!(xstate["states"]["On"]["on"]["Pull"], "Off");
+    assert!(xstate["states"]["On"]["on"].get("Lock").is_none());
+    assert!(xstate["states"].get("Broken").is_none());
+}
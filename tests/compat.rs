@@ -0,0 +1,79 @@
+//! Bridging shared state with other store crates.
+#![cfg(feature = "compat")]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use yew::Callback;
+use yew_state::compat::{self, Reducer, ScopeStore, Store, StoreGuard};
+use yew_state::{Dispatch, SharedHandle};
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct Cart(Vec<&'static str>);
+
+type Subscribers = Rc<RefCell<Vec<Callback<Rc<Cart>>>>>;
+
+/// Store of another crate, keeping state itself.
+#[derive(Default)]
+struct OtherStore {
+    state: RefCell<Rc<Cart>>,
+    subscribers: Subscribers,
+}
+
+impl Store for OtherStore {
+    type Model = Cart;
+
+    fn state(&self) -> Rc<Cart> {
+        self.state.borrow().clone()
+    }
+
+    fn reduce(&self, f: Reducer<Cart>) {
+        f(Rc::make_mut(&mut self.state.borrow_mut()));
+        let state = self.state();
+        let subscribers = self.subscribers.borrow().clone();
+        for subscriber in subscribers {
+            subscriber.emit(state.clone());
+        }
+    }
+
+    fn subscribe(&self, callback: Callback<Rc<Cart>>) -> StoreGuard {
+        callback.emit(self.state());
+        self.subscribers.borrow_mut().push(callback);
+        StoreGuard::new(())
+    }
+}
+
+#[test]
+fn scopes_back_stores_of_other_crates() {
+    let store = ScopeStore::<SharedHandle<Cart>>::new();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let _guard = {
+        let log = log.clone();
+        store.subscribe(Callback::from(move |cart: Rc<Cart>| {
+            log.borrow_mut().push(cart.0.len())
+        }))
+    };
+    store.reduce(Box::new(|cart| cart.0.push("apple")));
+    Dispatch::<SharedHandle<Cart>>::connect().reduce(|cart| cart.0.push("pear"));
+
+    assert_eq!(*log.borrow(), vec![0, 1, 2]);
+    assert_eq!(store.state().0, vec!["apple", "pear"]);
+}
+
+#[test]
+fn mirrors_keep_scopes_in_step_with_other_stores() {
+    let other = Rc::new(OtherStore::default());
+    other.reduce(Box::new(|cart| cart.0.push("apple")));
+    let scope = ScopeStore::<SharedHandle<Cart>, Mirrored>::new();
+    let _mirror = compat::mirror_in::<SharedHandle<Cart>, Mirrored, _>(other.clone());
+
+    // State starts from the other store's.
+    assert_eq!(scope.state().0, vec!["apple"]);
+
+    other.reduce(Box::new(|cart| cart.0.push("pear")));
+    assert_eq!(scope.state().0, vec!["apple", "pear"]);
+
+    scope.reduce(Box::new(|cart| cart.0.clear()));
+    assert_eq!(*other.state(), Cart::default());
+}
+
+struct Mirrored;
@@ -0,0 +1,180 @@
+//! `#[derive(Storable)]`.
+#![cfg(feature = "derive")]
+use serde::{Deserialize, Serialize};
+use yew_state::storage::{Part, Parts};
+use yew_state::{Area, Storable};
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize, Storable)]
+#[storable(key = "account", area = "session", compressed)]
+struct Account {
+    name: String,
+    count: u32,
+    #[storable(skip)]
+    draft: String,
+    #[storable(session)]
+    tab: u8,
+    #[storable(encrypt)]
+    token: Option<String>,
+}
+
+fn account() -> Account {
+    Account {
+        name: "Ferris".to_string(),
+        count: 3,
+        draft: "unsent".to_string(),
+        tab: 2,
+        token: Some("secret".to_string()),
+    }
+}
+
+#[test]
+fn container_attributes() {
+    assert_eq!(Account::key(), "account");
+    assert!(matches!(Account::area(), Area::Session));
+    assert!(Account::compressed());
+}
+
+#[test]
+fn fields_are_stored_in_their_part() {
+    let parts = account().to_parts();
+
+    let mut expected = Parts::default();
+    expected.insert(Part::Main, "name", &"Ferris");
+    expected.insert(Part::Main, "count", &3);
+    expected.insert(Part::Session, "tab", &2);
+    expected.insert(Part::Encrypted, "token", &Some("secret"));
+    assert_eq!(parts, expected);
+}
+
+#[test]
+fn skipped_fields_are_restored_as_default() {
+    let restored = Account::from_parts(&account().to_parts()).unwrap();
+
+    assert_eq!(
+        restored,
+        Account {
+            draft: String::new(),
+            ..account()
+        }
+    );
+}
+
+#[test]
+fn fields_are_restored_one_by_one() {
+    let mut parts = account().to_parts();
+    parts.insert(Part::Main, "count", &"no longer a number");
+    let restored = Account::from_parts(&parts).unwrap();
+
+    assert_eq!(restored.count, 0);
+    assert_eq!(restored.name, "Ferris");
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn derived_state_roundtrips() {
+    use yew_state::assert_storable_roundtrip;
+
+    assert_storable_roundtrip!(Account);
+    assert_storable_roundtrip!(Account, account());
+}
+
+mod store {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use yew::Callback;
+    use yew_state::{Dispatch, SharedHandle, Store};
+
+    #[derive(Clone, Default, PartialEq, Debug)]
+    struct Count(u32);
+
+    #[derive(Clone, Default, Store)]
+    struct AppStore {
+        clicks: Count,
+        page_views: Count,
+    }
+
+    #[test]
+    fn fields_are_shared_in_their_own_scopes() {
+        let log: Rc<RefCell<Vec<(u32, u32)>>> = Default::default();
+        let recorded = log.clone();
+        let mut store = AppStore::subscribe(Callback::from(move |store: Rc<AppStore>| {
+            recorded
+                .borrow_mut()
+                .push((store.clicks.0, store.page_views.0))
+        }));
+        store.clicks.reduce(|clicks| clicks.0 += 1);
+        let mut views = Dispatch::<SharedHandle<Count>, app_store::PageViews>::connect();
+        views.reduce(|views| views.0 = 5);
+
+        assert_eq!(log.borrow().last(), Some(&(1, 5)));
+        // The default scope of the model isn't touched.
+        let unscoped = Rc::new(RefCell::new(None));
+        let seen = unscoped.clone();
+        let _default =
+            Dispatch::<SharedHandle<Count>>::new(Callback::from(move |count: Rc<Count>| {
+                *seen.borrow_mut() = Some(count.0)
+            }));
+        assert_eq!(*unscoped.borrow(), Some(0));
+    }
+}
+
+mod redact {
+    use std::rc::Rc;
+
+    use serde::{Deserialize, Serialize};
+    use yew_state::handle::Handle;
+    use yew_state::{redact, Redact, SharedHandle};
+
+    #[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize, Redact)]
+    struct Profile {
+        #[redact(mask)]
+        email: String,
+        #[redact(hide)]
+        api_key: String,
+        #[redact(truncate = 4)]
+        avatar: String,
+        #[redact(nested)]
+        billing: Billing,
+        name: String,
+    }
+
+    #[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize, Redact)]
+    struct Billing {
+        #[redact(mask)]
+        card: String,
+    }
+
+    #[test]
+    fn registered_state_is_redacted_in_debug_output() {
+        let profile = Profile {
+            email: "ada@example.com".into(),
+            api_key: "key-123".into(),
+            avatar: "data:image/png;base64".into(),
+            billing: Billing {
+                card: "4242424242424242".into(),
+            },
+            name: "Ada".into(),
+        };
+        let mut handle = SharedHandle::<Profile>::default();
+        handle.set_local_state(Rc::new(profile.clone()));
+        assert!(format!("{:?}", handle).contains("key-123"));
+
+        redact::register::<Profile>();
+        let output = format!("{:?}", handle);
+        for shown in &[
+            "a***@example.com",
+            "<redacted>",
+            "data… (21 bytes)",
+            "4***",
+            "Ada",
+        ] {
+            assert!(output.contains(shown), "{} not in {}", shown, output);
+        }
+        for hidden in &["ada@", "key-123", "base64", "4242"] {
+            assert!(!output.contains(hidden), "{} in {}", hidden, output);
+        }
+        // State itself is untouched.
+        assert_eq!(*handle.state(), profile);
+    }
+}
@@ -0,0 +1,44 @@
+//! Handles held by components.
+use std::cell::Cell;
+use std::rc::Rc;
+
+use yew::{Callback, Properties};
+use yew_state::{handle::Handle, SharedHandle};
+
+/// State that can't be compared.
+#[derive(Clone, Default)]
+struct Canvas {
+    _pixels: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+struct Props {
+    handle: SharedHandle<Canvas>,
+}
+
+#[test]
+fn properties_with_handles_derive_partial_eq() {
+    let props = Props {
+        handle: Default::default(),
+    };
+    assert!(props == props.clone());
+
+    let mut changed = props.clone();
+    changed.handle.set_local_state(Rc::new(Canvas::default()));
+    assert!(props != changed);
+}
+
+#[test]
+fn reading_state_first_is_called_back_once() {
+    let reads = Rc::new(Cell::new(0));
+    let mut handle = SharedHandle::<Canvas>::default();
+    let counted = reads.clone();
+    handle.set_local_read(Callback::from(move |_| counted.set(counted.get() + 1)));
+    assert_eq!(reads.get(), 0);
+
+    let child = handle.clone();
+    let _ = child.state();
+    let _ = handle.state();
+    let _ = handle.map(|canvas| canvas._pixels.len());
+    assert_eq!(reads.get(), 1);
+}
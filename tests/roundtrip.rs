@@ -0,0 +1,290 @@
+//! Randomized round-trip and determinism checks.
+//!
+//! Cases come from a small seeded generator, so failures are reproducible: the failing seed is in
+//! the panic message. Failing cases aren't shrunk.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use yew::Callback;
+#[cfg(feature = "test-utils")]
+use yew_state::assert_storable_roundtrip;
+use yew_state::{BoundedLog, Dispatch, Intern, SharedHandle, Storable};
+
+const CASES: u64 = 256;
+
+/// xorshift64*, good enough for generating test cases.
+struct Gen(u64);
+
+impl Gen {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn string(&mut self) -> String {
+        // Mostly ASCII, with some characters that need escaping or surrogate pairs.
+        const SPECIAL: &[char] = &['"', '\\', '\n', '\u{0}', 'é', '€', '中', '😀', '\u{10ffff}'];
+        (0..self.below(24))
+            .map(|_| match self.below(4) {
+                0 => SPECIAL[self.below(SPECIAL.len() as u64) as usize],
+                _ => (b' ' + self.below(95) as u8) as char,
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+struct Item {
+    name: String,
+    count: u32,
+    tags: Vec<Intern<String>>,
+    note: Option<String>,
+}
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+struct Model {
+    id: i64,
+    done: bool,
+    items: Vec<Item>,
+    log: BoundedLog<String, 8>,
+}
+
+impl Storable for Model {}
+
+// Only round-trip checks start from generated models.
+#[cfg(feature = "test-utils")]
+impl Model {
+    fn generate(gen: &mut Gen) -> Self {
+        let mut model = Model {
+            id: gen.next() as i64,
+            done: gen.below(2) == 0,
+            ..Default::default()
+        };
+        for _ in 0..gen.below(6) {
+            model.items.push(Item {
+                name: gen.string(),
+                count: gen.next() as u32,
+                tags: (0..gen.below(4))
+                    .map(|_| Intern::new(gen.string()))
+                    .collect(),
+                note: if gen.below(2) == 0 {
+                    Some(gen.string())
+                } else {
+                    None
+                },
+            });
+        }
+        model.log.extend((0..gen.below(12)).map(|_| gen.string()));
+        model
+    }
+}
+
+/// A change to `Model`.
+#[derive(Clone, Debug)]
+enum Op {
+    Add(String),
+    Remove(usize),
+    Increment(usize, u32),
+    Toggle,
+    Log(String),
+}
+
+impl Op {
+    fn generate(gen: &mut Gen) -> Self {
+        match gen.below(5) {
+            0 => Op::Add(gen.string()),
+            1 => Op::Remove(gen.below(8) as usize),
+            2 => Op::Increment(gen.below(8) as usize, gen.next() as u32),
+            3 => Op::Toggle,
+            _ => Op::Log(gen.string()),
+        }
+    }
+
+    fn apply(&self, model: &mut Model) {
+        match self {
+            Op::Add(name) => model.items.push(Item {
+                name: name.clone(),
+                ..Default::default()
+            }),
+            Op::Remove(index) => {
+                if *index < model.items.len() {
+                    model.items.remove(*index);
+                }
+            }
+            Op::Increment(index, by) => {
+                if let Some(item) = model.items.get_mut(*index) {
+                    item.count = item.count.wrapping_add(*by);
+                }
+            }
+            Op::Toggle => model.done = !model.done,
+            Op::Log(entry) => model.log.push(entry.clone()),
+        }
+    }
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn default_state_roundtrips() {
+    assert_storable_roundtrip!(Model);
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn generated_state_roundtrips() {
+    for seed in 0..CASES {
+        let model = Model::generate(&mut Gen::new(seed));
+        let result = std::panic::catch_unwind(|| assert_storable_roundtrip!(Model, model));
+        assert!(result.is_ok(), "round-trip failed for seed {}", seed);
+    }
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn reduced_state_roundtrips() {
+    for seed in 0..CASES {
+        let mut gen = Gen::new(seed);
+        let mut model = Model::generate(&mut gen);
+        for _ in 0..gen.below(32) {
+            Op::generate(&mut gen).apply(&mut model);
+        }
+        let result = std::panic::catch_unwind(|| assert_storable_roundtrip!(Model, model));
+        assert!(result.is_ok(), "round-trip failed for seed {}", seed);
+    }
+}
+
+type Latest = Rc<RefCell<Option<Rc<Model>>>>;
+
+/// Callback keeping the latest state it receives.
+fn latest(latest: &Latest) -> Callback<Rc<Model>> {
+    let latest = latest.clone();
+    Callback::from(move |model| *latest.borrow_mut() = Some(model))
+}
+
+#[test]
+fn reductions_are_deterministic() {
+    struct First;
+    struct Second;
+
+    for seed in 0..CASES {
+        let mut gen = Gen::new(seed);
+        let ops: Vec<Op> = (0..gen.below(32)).map(|_| Op::generate(&mut gen)).collect();
+
+        let mut expected = Model::default();
+        for op in &ops {
+            op.apply(&mut expected);
+        }
+
+        let (first_state, second_state) = (Latest::default(), Latest::default());
+        let mut first = Dispatch::<SharedHandle<Model>, First>::new(latest(&first_state));
+        let mut second = Dispatch::<SharedHandle<Model>, Second>::new(latest(&second_state));
+        // Agents outlive a single case, so start from scratch.
+        first.reduce(|model| *model = Model::default());
+        second.reduce(|model| *model = Model::default());
+        for op in ops {
+            let other = op.clone();
+            first.reduce(move |model| op.apply(model));
+            second.reduce(move |model| other.apply(model));
+        }
+
+        assert_eq!(
+            first_state.borrow().as_deref(),
+            Some(&expected),
+            "seed {}",
+            seed
+        );
+        assert_eq!(
+            second_state.borrow().as_deref(),
+            Some(&expected),
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn lz_roundtrips() {
+    use yew_state::lz;
+
+    for seed in 0..CASES {
+        let mut gen = Gen::new(seed);
+        let input: String = (0..gen.below(8)).map(|_| gen.string()).collect();
+        assert_eq!(
+            lz::decompress_from_encoded_uri_component(&lz::compress_to_encoded_uri_component(
+                &input
+            )),
+            Some(input.clone()),
+            "seed {}",
+            seed
+        );
+        assert_eq!(
+            lz::decompress_from_base64(&lz::compress_to_base64(&input)),
+            Some(input.clone()),
+            "seed {}",
+            seed
+        );
+        assert_eq!(
+            lz::decompress_from_utf16(&lz::compress_to_utf16(&input)),
+            Some(input.clone()),
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn sensitive_values_serialize_plainly_outside_of_writes() {
+    use yew_state::storage::Cipher;
+    use yew_state::Sensitive;
+
+    /// Reverses text, which is plenty to tell encrypted from plain.
+    struct Reverse;
+
+    impl Cipher for Reverse {
+        fn encrypt(&self, data: &str) -> String {
+            data.chars().rev().collect()
+        }
+
+        fn decrypt(&self, data: &str) -> Option<String> {
+            Some(data.chars().rev().collect())
+        }
+    }
+
+    #[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+    struct Session {
+        user: String,
+        token: Sensitive<String>,
+    }
+
+    let session = Session {
+        user: "ada".into(),
+        token: Sensitive::new("s3cret".into()),
+    };
+    assert!(!format!("{:?}", session).contains("s3cret"));
+
+    // As when syncing, or sending to a worker.
+    let json = serde_json::to_string(&session).unwrap();
+    assert_eq!(json, r#"{"user":"ada","token":"s3cret"}"#);
+    let restored: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, session);
+    let restored: Session = bincode::deserialize(&bincode::serialize(&session).unwrap()).unwrap();
+    assert_eq!(restored, session);
+
+    // As written out by the crate.
+    let redacted: Session = serde_json::from_str(r#"{"user":"ada","token":"<redacted>"}"#).unwrap();
+    assert_eq!(*redacted.token, "");
+    yew_state::config().cipher(Reverse);
+    let encrypted = r#"{"user":"ada","token":{"$encrypted":"\"terc3s\""}}"#;
+    let restored: Session = serde_json::from_str(encrypted).unwrap();
+    assert_eq!(restored, session);
+}
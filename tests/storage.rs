@@ -0,0 +1,209 @@
+//! Persistent state, stored in the in-memory stand-in for web storage.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use yew::Callback;
+use yew_state::storage::{self, memory};
+use yew_state::{config, Area, Dispatch, Sensitive, Storable, StorageHandle};
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+struct Settings {
+    volume: u8,
+}
+
+impl Storable for Settings {
+    fn key() -> &'static str {
+        "settings"
+    }
+}
+
+/// Callback recording every volume it receives.
+fn record(log: &Rc<RefCell<Vec<u8>>>) -> Callback<Rc<Settings>> {
+    let log = log.clone();
+    Callback::from(move |settings: Rc<Settings>| log.borrow_mut().push(settings.volume))
+}
+
+#[test]
+fn state_is_loaded_as_it_was_saved() {
+    let mut dispatch = Dispatch::<StorageHandle<Settings>>::connect();
+    dispatch.reduce(|settings| settings.volume = 7);
+    drop(dispatch);
+    assert!(memory::get(Area::Local, "settings").is_some());
+
+    let log = Rc::default();
+    let _dispatch = Dispatch::<StorageHandle<Settings>>::new(record(&log));
+    assert_eq!(*log.borrow(), vec![7]);
+}
+
+#[test]
+fn corrupt_state_is_backed_up_and_starts_from_default() {
+    memory::set(Area::Local, "settings", "not json");
+
+    let log = Rc::default();
+    let _dispatch = Dispatch::<StorageHandle<Settings>>::new(record(&log));
+    assert_eq!(*log.borrow(), vec![0]);
+    assert_eq!(
+        memory::get(Area::Local, "settings.backup").as_deref(),
+        Some("not json")
+    );
+}
+
+#[test]
+fn gc_removes_orphans_under_the_prefix() {
+    config().key_prefix("app:");
+    memory::set(Area::Local, "app:settings", "{}");
+    memory::set(Area::Local, "app:settings.backup", "{}");
+    memory::set(Area::Local, "app:removed", "{}");
+
+    assert_eq!(storage::orphans(&["settings"]), vec!["app:removed"]);
+    assert_eq!(storage::gc(&["settings"]), vec!["app:removed"]);
+    assert_eq!(
+        memory::keys(Area::Local),
+        vec!["app:settings", "app:settings.backup"]
+    );
+}
+
+#[test]
+fn gc_leaves_other_prefixes_and_entries_of_the_crate_alone() {
+    config().key_prefix("app:");
+    memory::set(Area::Local, "app:client-id", "\"id\"");
+    memory::set(Area::Local, "app:tabs.leader", "{}");
+    memory::set(Area::Local, "app:removed", "{}");
+    memory::set(Area::Local, "other:removed", "{}");
+    memory::set(Area::Local, "removed", "{}");
+
+    assert_eq!(storage::gc(&["settings"]), vec!["app:removed"]);
+    assert_eq!(
+        memory::keys(Area::Local),
+        vec![
+            "app:client-id",
+            "app:tabs.leader",
+            "other:removed",
+            "removed"
+        ]
+    );
+}
+
+#[test]
+fn gc_does_nothing_without_a_prefix() {
+    memory::set(Area::Local, "removed", "{}");
+
+    assert!(storage::gc(&["settings"]).is_empty());
+    assert_eq!(memory::keys(Area::Local), vec!["removed"]);
+}
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+struct Account {
+    user: String,
+    token: Sensitive<String>,
+}
+
+impl Storable for Account {
+    fn key() -> &'static str {
+        "account"
+    }
+}
+
+#[test]
+fn sensitive_values_are_redacted_when_stored_without_a_cipher() {
+    let mut dispatch = Dispatch::<StorageHandle<Account>>::connect();
+    dispatch.reduce(|account| *account.token = "s3cret".into());
+
+    let stored = memory::get(Area::Local, "account").unwrap();
+    assert!(stored.contains("<redacted>"));
+    assert!(!stored.contains("s3cret"));
+}
+
+#[test]
+fn sensitive_values_are_stored_encrypted_and_restored() {
+    /// Reverses text, which is plenty to tell encrypted from plain.
+    struct Reverse;
+
+    impl storage::Cipher for Reverse {
+        fn encrypt(&self, data: &str) -> String {
+            data.chars().rev().collect()
+        }
+
+        fn decrypt(&self, data: &str) -> Option<String> {
+            Some(data.chars().rev().collect())
+        }
+    }
+
+    config().cipher(Reverse);
+    let latest: Rc<RefCell<Option<Rc<Account>>>> = Rc::default();
+    let recorded = latest.clone();
+    let mut dispatch =
+        Dispatch::<StorageHandle<Account>>::new(Callback::from(move |account: Rc<Account>| {
+            *recorded.borrow_mut() = Some(account)
+        }));
+    dispatch.reduce(|account| *account.token = "s3cret".into());
+    assert!(!memory::get(Area::Local, "account")
+        .unwrap()
+        .contains("s3cret"));
+
+    // Loaded from storage again.
+    dispatch.restart_handler();
+    assert_eq!(
+        latest
+            .borrow()
+            .as_ref()
+            .map(|account| account.token.as_str()),
+        Some("s3cret")
+    );
+}
+
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+struct Grid {
+    // JSON maps only have string keys.
+    cells: std::collections::BTreeMap<(u8, u8), bool>,
+}
+
+impl Storable for Grid {
+    fn key() -> &'static str {
+        "grid"
+    }
+}
+
+#[test]
+fn state_that_cant_be_serialized_is_reported_and_not_stored() {
+    let errors: Rc<RefCell<Vec<String>>> = Rc::default();
+    let reported = errors.clone();
+    let log: Rc<RefCell<Vec<usize>>> = Rc::default();
+    let recorded = log.clone();
+    let mut dispatch = Dispatch::<StorageHandle<Grid>>::with_errors(
+        Callback::from(move |grid: Rc<Grid>| recorded.borrow_mut().push(grid.cells.len())),
+        Callback::from(move |error: Rc<yew_state::Error>| {
+            reported.borrow_mut().push(error.to_string())
+        }),
+    );
+    dispatch.reduce(|grid| {
+        grid.cells.insert((0, 0), true);
+    });
+    dispatch.reduce(|grid| {
+        grid.cells.insert((0, 1), true);
+    });
+
+    // Still shared, just not stored.
+    assert_eq!(*log.borrow(), vec![0, 1, 2]);
+    assert_eq!(memory::get(Area::Local, "grid"), None);
+    assert_eq!(errors.borrow().len(), 2);
+    assert!(errors.borrow()[0].starts_with("can't store state at grid"));
+}
+
+#[test]
+fn restarting_saves_changes_waiting_for_the_autosave_interval() {
+    use std::time::Duration;
+    use yew_state::{HandlerSettings, SavePolicy};
+
+    let log = Rc::default();
+    let mut dispatch = Dispatch::<StorageHandle<Settings>>::new(record(&log));
+    let every_minute = SavePolicy::Interval(Duration::from_secs(60));
+    dispatch.configure(HandlerSettings::new().save_policy(every_minute));
+    dispatch.reduce(|settings| settings.volume = 3);
+    assert_eq!(memory::get(Area::Local, "settings"), None);
+
+    dispatch.restart_handler();
+    assert!(memory::get(Area::Local, "settings").is_some());
+    assert_eq!(*log.borrow(), vec![0, 3, 3]);
+}
@@ -0,0 +1,38 @@
+//! Streams of state changes.
+#![cfg(feature = "stream")]
+use futures::stream::StreamExt;
+use futures::FutureExt;
+use yew_state::{Dispatch, SharedHandle};
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct Count(u32);
+
+#[test]
+fn streams_current_state_then_changes() {
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::connect();
+    dispatch.reduce(|count| count.0 = 1);
+    let mut stream = dispatch.stream();
+    dispatch.reduce(|count| count.0 += 1);
+    dispatch.reduce(|count| count.0 += 1);
+
+    let counts: Vec<u32> = (0..3)
+        .map(|_| stream.next().now_or_never().flatten().unwrap().0)
+        .collect();
+    assert_eq!(counts, vec![1, 2, 3]);
+    assert!(stream.next().now_or_never().is_none());
+}
+
+#[test]
+fn composes_with_combinators() {
+    let mut dispatch = Dispatch::<SharedHandle<Count>>::connect();
+    let evens = dispatch
+        .stream()
+        .map(|count| count.0)
+        .filter(|count| futures::future::ready(count % 2 == 0));
+    for _ in 0..5 {
+        dispatch.reduce(|count| count.0 += 1);
+    }
+
+    let evens = evens.take(3).collect::<Vec<_>>();
+    assert_eq!(futures::executor::block_on(evens), vec![0, 2, 4]);
+}
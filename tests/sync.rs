@@ -0,0 +1,258 @@
+//! Syncing state between peers.
+#![cfg(feature = "sync")]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use yew::Callback;
+use yew_state::sync::presence::{self, PresenceHandle};
+use yew_state::sync::{self, memory::MemoryHub, SyncOptions, SyncTransport};
+use yew_state::{Dispatch, Error, SharedHandle};
+
+#[derive(Clone, Default, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+struct Board(Vec<String>);
+
+type Latest = Rc<RefCell<Option<Rc<Board>>>>;
+
+fn latest(log: &Latest) -> Option<Board> {
+    log.borrow().as_deref().cloned()
+}
+
+/// Callback keeping the latest board in `log`.
+fn watch(log: &Latest) -> Callback<Rc<Board>> {
+    let log = log.clone();
+    Callback::from(move |board| *log.borrow_mut() = Some(board))
+}
+
+fn board(items: &[&str]) -> Board {
+    Board(items.iter().map(|item| item.to_string()).collect())
+}
+
+/// Callback keeping every error it receives in `log`.
+fn errors(log: &Rc<RefCell<Vec<Error>>>) -> Callback<Rc<Error>> {
+    let log = log.clone();
+    Callback::from(move |error: Rc<Error>| log.borrow_mut().push((*error).clone()))
+}
+
+#[test]
+fn peers_converge() {
+    struct Alice;
+    struct Bob;
+
+    let mut alice = Dispatch::<SharedHandle<Board>, Alice>::connect();
+    alice.reduce(|board| board.0.push("from alice".into()));
+    let mut bob = Dispatch::<SharedHandle<Board>, Bob>::connect();
+    bob.reduce(|board| board.0.push("from bob".into()));
+
+    let hub = MemoryHub::new();
+    let _alice_sync = sync::connect_in::<SharedHandle<Board>, Alice>(hub.connect());
+    let _bob_sync = sync::connect_in::<SharedHandle<Board>, Bob>(hub.connect());
+
+    let (alice_log, bob_log) = (Rc::default(), Rc::default());
+    let _alice_view = Dispatch::<SharedHandle<Board>, Alice>::new(watch(&alice_log));
+    let _bob_view = Dispatch::<SharedHandle<Board>, Bob>::new(watch(&bob_log));
+    assert_eq!(latest(&alice_log), latest(&bob_log));
+
+    alice.reduce(|board| board.0.push("later".into()));
+    assert_eq!(latest(&bob_log), latest(&alice_log));
+    assert!(latest(&bob_log).unwrap().0.contains(&"later".to_string()));
+
+    bob.reduce(|state| *state = board(&["reset"]));
+    assert_eq!(latest(&alice_log), Some(board(&["reset"])));
+}
+
+#[test]
+fn sensitive_values_are_synced_as_they_are() {
+    use yew_state::Sensitive;
+
+    #[derive(Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Session {
+        token: Sensitive<String>,
+    }
+
+    struct Alice;
+    struct Bob;
+
+    let hub = MemoryHub::new();
+    let _alice_sync = sync::connect_in::<SharedHandle<Session>, Alice>(hub.connect());
+    let _bob_sync = sync::connect_in::<SharedHandle<Session>, Bob>(hub.connect());
+    let latest: Rc<RefCell<Option<Rc<Session>>>> = Rc::default();
+    let recorded = latest.clone();
+    let _bob = Dispatch::<SharedHandle<Session>, Bob>::new(Callback::from(move |session| {
+        *recorded.borrow_mut() = Some(session)
+    }));
+
+    let mut alice = Dispatch::<SharedHandle<Session>, Alice>::connect();
+    alice.reduce(|session| *session.token = "s3cret".into());
+    let token = latest
+        .borrow()
+        .as_ref()
+        .map(|session| session.token.to_string());
+    assert_eq!(token.as_deref(), Some("s3cret"));
+}
+
+#[test]
+fn reconnected_peers_catch_up() {
+    struct Alice;
+    struct Bob;
+
+    let hub = MemoryHub::new();
+    let mut alice = Dispatch::<SharedHandle<Board>, Alice>::connect();
+    let _alice_sync = sync::connect_in::<SharedHandle<Board>, Alice>(hub.connect());
+    let bob_log = Latest::default();
+    let _bob_view = Dispatch::<SharedHandle<Board>, Bob>::new(watch(&bob_log));
+    let bob_transport = hub.connect();
+    let _bob_sync = sync::connect_in::<SharedHandle<Board>, Bob>(bob_transport.clone());
+
+    bob_transport.disconnect();
+    alice.reduce(|state| *state = board(&["while away"]));
+    assert_eq!(latest(&bob_log), Some(Board::default()));
+
+    bob_transport.reconnect();
+    assert_eq!(latest(&bob_log), Some(board(&["while away"])));
+}
+
+#[test]
+fn held_changes_coalesce_while_disconnected() {
+    struct Alice;
+    struct Bob;
+
+    let hub = MemoryHub::new();
+    let mut alice = Dispatch::<SharedHandle<Board>, Alice>::connect();
+    let alice_transport = hub.connect();
+    let options = SyncOptions::new().queue_limit(2);
+    let _alice_sync =
+        sync::connect_in_with::<SharedHandle<Board>, Alice>(alice_transport.clone(), options);
+    let seen: Rc<RefCell<Vec<Board>>> = Rc::default();
+    let log = seen.clone();
+    let _bob_view =
+        Dispatch::<SharedHandle<Board>, Bob>::new(Callback::from(move |board: Rc<Board>| {
+            log.borrow_mut().push((*board).clone())
+        }));
+    let _bob_sync = sync::connect_in::<SharedHandle<Board>, Bob>(hub.connect());
+
+    alice_transport.disconnect();
+    for item in &["one", "two", "three", "four"] {
+        alice.reduce(move |board| board.0.push(item.to_string()));
+    }
+    seen.borrow_mut().clear();
+    alice_transport.reconnect();
+
+    assert_eq!(
+        *seen.borrow(),
+        vec![board(&["one", "two", "three", "four"])]
+    );
+}
+
+#[test]
+fn peers_see_each_other_present() {
+    struct Alice;
+    struct Bob;
+
+    let hub = MemoryHub::new();
+    let alice_sync = presence::connect_in::<u32, Alice>(hub.connect());
+    let _bob_sync = presence::connect_in::<u32, Bob>(hub.connect());
+
+    let alice_log = Rc::default();
+    let watch = |log: &Rc<RefCell<Vec<(usize, u32)>>>| {
+        let log = log.clone();
+        Callback::from(move |presence: Rc<presence::Presence<u32>>| {
+            let others = presence.others().map(|peer| peer.meta).sum();
+            log.borrow_mut().push((presence.len(), others))
+        })
+    };
+    let mut alice = Dispatch::<PresenceHandle<u32>, Alice>::connect();
+    let bob_log = Rc::default();
+    let _bob_view = Dispatch::<PresenceHandle<u32>, Bob>::new(watch(&bob_log));
+    let _alice_view = Dispatch::<PresenceHandle<u32>, Alice>::new(watch(&alice_log));
+    assert_eq!(alice_log.borrow().last(), Some(&(2, 0)));
+
+    alice.reduce(|presence| presence.set_meta(7));
+    assert_eq!(bob_log.borrow().last(), Some(&(2, 7)));
+
+    drop(alice);
+    drop(alice_sync);
+    assert_eq!(bob_log.borrow().last(), Some(&(1, 0)));
+}
+
+#[test]
+fn held_changes_can_be_listed_and_discarded() {
+    use yew_state::sync::{OutboxHandle, OutboxState};
+
+    struct Alice;
+
+    let hub = MemoryHub::new();
+    let mut alice = Dispatch::<SharedHandle<Board>, Alice>::connect();
+    let alice_transport = hub.connect();
+    let options = SyncOptions::new().coalesce(false);
+    let alice_sync =
+        sync::connect_in_with::<SharedHandle<Board>, Alice>(alice_transport.clone(), options);
+    let lengths: Rc<RefCell<Vec<usize>>> = Rc::default();
+    let log = lengths.clone();
+    let _indicator =
+        Dispatch::<OutboxHandle, Alice>::new(Callback::from(move |outbox: Rc<OutboxState>| {
+            log.borrow_mut().push(outbox.len())
+        }));
+
+    alice_transport.disconnect();
+    for item in &["one", "two", "three"] {
+        alice.reduce(move |board| board.0.push(item.to_string()));
+    }
+    let pending = alice_sync.pending();
+    assert_eq!(pending.len(), 3);
+    assert_eq!(
+        pending[2].state::<Board>(),
+        Some(board(&["one", "two", "three"]))
+    );
+    assert!(pending[0].version < pending[1].version);
+
+    assert!(alice_sync.discard(pending[0].id));
+    assert!(!alice_sync.discard(pending[0].id));
+    // Still disconnected.
+    assert!(!alice_sync.retry(pending[1].id));
+    assert_eq!(lengths.borrow().last(), Some(&2));
+
+    alice_transport.reconnect();
+    assert!(alice_sync.pending().is_empty());
+    assert_eq!(*lengths.borrow(), vec![0, 1, 2, 3, 2, 0]);
+}
+
+#[test]
+fn messages_that_cant_be_decoded_are_reported() {
+    struct Alice;
+
+    let hub = MemoryHub::new();
+    let _alice_sync = sync::connect_in::<SharedHandle<Board>, Alice>(hub.connect());
+    let (log, reported) = (Rc::default(), Rc::default());
+    let _alice_view =
+        Dispatch::<SharedHandle<Board>, Alice>::with_errors(watch(&log), errors(&reported));
+
+    hub.connect().send(b"not a message".to_vec());
+    assert!(matches!(
+        reported.borrow().as_slice(),
+        [Error::InvalidSyncMessage(_)]
+    ));
+    assert_eq!(latest(&log), Some(Board::default()));
+}
+
+#[test]
+fn state_that_cant_be_encoded_is_reported() {
+    use std::collections::BTreeMap;
+
+    /// Keys that aren't strings can't be encoded as JSON.
+    #[derive(Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Grid(BTreeMap<(u32, u32), u32>);
+
+    struct Alice;
+
+    let hub = MemoryHub::new();
+    let mut alice = Dispatch::<SharedHandle<Grid>, Alice>::connect();
+    let _alice_sync = sync::connect_in::<SharedHandle<Grid>, Alice>(hub.connect());
+    let reported = Rc::default();
+    let _alice_view =
+        Dispatch::<SharedHandle<Grid>, Alice>::with_errors(Callback::noop(), errors(&reported));
+
+    alice.reduce(|grid| {
+        grid.0.insert((0, 0), 1);
+    });
+    assert!(matches!(reported.borrow().as_slice(), [Error::Encode(_)]));
+}
@@ -0,0 +1,30 @@
+//! Codecs of state living in a web worker.
+use serde::{ser, Deserialize, Serialize, Serializer};
+use yew_state::worker::{Bincode, Codec, Json};
+use yew_state::Error;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Count(u64);
+
+/// State some of which can't be serialized.
+struct Handle;
+
+impl Serialize for Handle {
+    fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("handles can't be serialized"))
+    }
+}
+
+#[test]
+fn codecs_roundtrip_state() {
+    let bytes = Bincode::encode(&Count(7)).unwrap();
+    assert_eq!(Bincode::decode::<Count>(&bytes), Some(Count(7)));
+    let bytes = Json::encode(&Count(7)).unwrap();
+    assert_eq!(Json::decode::<Count>(&bytes), Some(Count(7)));
+}
+
+#[test]
+fn state_that_cant_be_encoded_is_an_error() {
+    assert!(matches!(Bincode::encode(&Handle), Err(Error::Encode(_))));
+    assert!(matches!(Json::encode(&Handle), Err(Error::Encode(_))));
+}